@@ -0,0 +1,301 @@
+use crate::Version;
+use breezyshim::tree::{MutableTree, Tree};
+use chrono::NaiveDate;
+use std::path::Path;
+
+#[derive(Debug)]
+pub enum Error {
+    BrzError(breezyshim::error::Error),
+    IoError(std::io::Error),
+    InvalidData(String),
+    NoComponent,
+    ReleaseExists(String),
+    ValidationFailed(String),
+}
+
+impl From<breezyshim::error::Error> for Error {
+    fn from(e: breezyshim::error::Error) -> Self {
+        Error::BrzError(e)
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(e: std::io::Error) -> Self {
+        Error::IoError(e)
+    }
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match &self {
+            Error::BrzError(e) => write!(f, "TreeError: {}", e),
+            Error::IoError(e) => write!(f, "IoError: {}", e),
+            Error::InvalidData(e) => write!(f, "Invalid data: {}", e),
+            Error::NoComponent => write!(f, "No <component> element found"),
+            Error::ReleaseExists(v) => write!(f, "Release {} already listed", v),
+            Error::ValidationFailed(e) => write!(f, "appstreamcli validate failed: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Render release notes as the contents of an AppStream `<description>`.
+///
+/// Bulleted lines become list items; anything else becomes a paragraph.
+fn render_description(notes: &str, indent: &str) -> Vec<String> {
+    let mut paragraphs: Vec<String> = vec![];
+    let mut items: Vec<String> = vec![];
+    for line in notes.lines() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        if let Some(item) = line.strip_prefix("* ").or_else(|| line.strip_prefix("- ")) {
+            items.push(item.to_string());
+        } else if let Some(last) = items.last_mut() {
+            last.push(' ');
+            last.push_str(line);
+        } else {
+            paragraphs.push(line.to_string());
+        }
+    }
+
+    let mut ret = vec![];
+    for p in paragraphs {
+        ret.push(format!("{}<p>{}</p>", indent, escape_xml(&p)));
+    }
+    if !items.is_empty() {
+        ret.push(format!("{}<ul>", indent));
+        for item in items {
+            ret.push(format!("{}  <li>{}</li>", indent, escape_xml(&item)));
+        }
+        ret.push(format!("{}</ul>", indent));
+    }
+    ret
+}
+
+/// Insert a `<release>` element for a new version into metainfo XML.
+///
+/// The new entry is added at the top of the `<releases>` element, which is
+/// created if it doesn't exist yet.
+pub fn add_release_to_metainfo(
+    text: &str,
+    version: &Version,
+    release_date: NaiveDate,
+    notes: Option<&str>,
+) -> Result<String, Error> {
+    let version = version.to_string();
+    let version_attr = format!("version=\"{}\"", escape_xml(&version));
+    if text
+        .lines()
+        .any(|l| l.trim_start().starts_with("<release ") && l.contains(&version_attr))
+    {
+        return Err(Error::ReleaseExists(version));
+    }
+
+    let mut lines: Vec<String> = text.lines().map(|l| l.to_string()).collect();
+
+    let (insert_at, indent, wrap) = if let Some(i) = lines
+        .iter()
+        .position(|l| l.trim_start().starts_with("<releases"))
+    {
+        if lines[i].trim_end().ends_with("/>") {
+            let indent = leading_whitespace(&lines[i]).to_string();
+            lines[i] = format!("{}<releases>", indent);
+            lines.insert(i + 1, format!("{}</releases>", indent));
+        }
+        (i + 1, format!("{}  ", leading_whitespace(&lines[i])), false)
+    } else if let Some(i) = lines.iter().rposition(|l| l.trim() == "</component>") {
+        (i, "    ".to_string(), true)
+    } else {
+        return Err(Error::NoComponent);
+    };
+
+    let mut new_lines = vec![];
+    if wrap {
+        new_lines.push("  <releases>".to_string());
+    }
+    let description = notes
+        .map(|n| render_description(n, &format!("{}    ", indent)))
+        .unwrap_or_default();
+    let release_line = format!(
+        "{}<release version=\"{}\" date=\"{}\"",
+        indent,
+        escape_xml(&version),
+        release_date.format("%Y-%m-%d")
+    );
+    if description.is_empty() {
+        new_lines.push(format!("{}/>", release_line));
+    } else {
+        new_lines.push(format!("{}>", release_line));
+        new_lines.push(format!("{}  <description>", indent));
+        new_lines.extend(description);
+        new_lines.push(format!("{}  </description>", indent));
+        new_lines.push(format!("{}</release>", indent));
+    }
+    if wrap {
+        new_lines.push("  </releases>".to_string());
+    }
+
+    lines.splice(insert_at..insert_at, new_lines);
+
+    let mut ret = lines.join("\n");
+    if text.ends_with('\n') {
+        ret.push('\n');
+    }
+    Ok(ret)
+}
+
+fn leading_whitespace(line: &str) -> &str {
+    &line[..line.len() - line.trim_start().len()]
+}
+
+/// Run `appstreamcli validate` on a metainfo file, if appstreamcli is installed.
+pub fn validate_with_appstreamcli(path: &Path) -> Result<(), Error> {
+    match std::process::Command::new("appstreamcli")
+        .arg("validate")
+        .arg("--no-net")
+        .arg(path)
+        .output()
+    {
+        Ok(output) => {
+            if output.status.success() {
+                Ok(())
+            } else {
+                Err(Error::ValidationFailed(
+                    String::from_utf8_lossy(&output.stdout).trim().to_string(),
+                ))
+            }
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            log::debug!("appstreamcli not found; not validating {}", path.display());
+            Ok(())
+        }
+        Err(e) => Err(Error::IoError(e)),
+    }
+}
+
+/// Add a release entry to an AppStream metainfo file in a tree.
+pub fn update_metainfo(
+    tree: &breezyshim::tree::WorkingTree,
+    path: &Path,
+    new_version: &Version,
+    release_date: NaiveDate,
+    notes: Option<&str>,
+) -> Result<(), Error> {
+    let text = tree.get_file_text(path)?;
+    let text = String::from_utf8(text)
+        .map_err(|e| Error::InvalidData(format!("Invalid UTF-8 in {}: {}", path.display(), e)))?;
+    let updated = add_release_to_metainfo(&text, new_version, release_date, notes)?;
+    tree.put_file_bytes_non_atomic(path, updated.as_bytes())?;
+    validate_with_appstreamcli(&tree.abspath(path)?)
+}
+
+/// Validate that a metainfo file can be updated.
+pub fn validate_update_metainfo(
+    tree: &dyn breezyshim::tree::Tree,
+    path: &Path,
+) -> Result<(), Error> {
+    let text = tree.get_file_text(path)?;
+    let text = String::from_utf8(text)
+        .map_err(|e| Error::InvalidData(format!("Invalid UTF-8 in {}: {}", path.display(), e)))?;
+    if !text.contains("<component") {
+        return Err(Error::NoComponent);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_add_release_existing_releases() {
+        let text = r#"<?xml version="1.0" encoding="UTF-8"?>
+<component type="desktop-application">
+  <id>org.example.App</id>
+  <releases>
+    <release version="1.2.2" date="2023-01-01"/>
+  </releases>
+</component>
+"#;
+        let updated = add_release_to_metainfo(
+            text,
+            &"1.2.3".parse().unwrap(),
+            NaiveDate::from_ymd_opt(2024, 1, 2).unwrap(),
+            Some("  * Fix crash & burn.\n  * Add <feature>.\n"),
+        )
+        .unwrap();
+        assert_eq!(
+            updated,
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<component type="desktop-application">
+  <id>org.example.App</id>
+  <releases>
+    <release version="1.2.3" date="2024-01-02">
+      <description>
+        <ul>
+          <li>Fix crash &amp; burn.</li>
+          <li>Add &lt;feature&gt;.</li>
+        </ul>
+      </description>
+    </release>
+    <release version="1.2.2" date="2023-01-01"/>
+  </releases>
+</component>
+"#
+        );
+    }
+
+    #[test]
+    fn test_add_release_no_releases() {
+        let text =
+            "<?xml version=\"1.0\"?>\n<component>\n  <id>org.example.App</id>\n</component>\n";
+        let updated = add_release_to_metainfo(
+            text,
+            &"1.0".parse().unwrap(),
+            NaiveDate::from_ymd_opt(2024, 1, 2).unwrap(),
+            None,
+        )
+        .unwrap();
+        assert_eq!(
+            updated,
+            "<?xml version=\"1.0\"?>\n<component>\n  <id>org.example.App</id>\n  <releases>\n    <release version=\"1.0\" date=\"2024-01-02\"/>\n  </releases>\n</component>\n"
+        );
+    }
+
+    #[test]
+    fn test_add_release_exists() {
+        let text = "<component>\n  <releases>\n    <release version=\"0.1\"/>\n  </releases>\n</component>\n";
+        assert!(matches!(
+            add_release_to_metainfo(
+                text,
+                &"0.1".parse().unwrap(),
+                NaiveDate::from_ymd_opt(2024, 1, 2).unwrap(),
+                None,
+            ),
+            Err(Error::ReleaseExists(_))
+        ));
+    }
+
+    #[test]
+    fn test_add_release_no_component() {
+        assert!(matches!(
+            add_release_to_metainfo(
+                "<foo/>\n",
+                &"0.1".parse().unwrap(),
+                NaiveDate::from_ymd_opt(2024, 1, 2).unwrap(),
+                None,
+            ),
+            Err(Error::NoComponent)
+        ));
+    }
+}
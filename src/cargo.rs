@@ -1,13 +1,23 @@
+use crate::limits::Service;
 use breezyshim::tree::{MutableTree, Tree, WorkingTree};
 
-use std::path::Path;
+use std::collections::{BTreeMap, HashSet};
+use std::path::{Path, PathBuf};
 use std::process::Command;
 
+/// How long to wait for a published crate to show up in the index.
+pub const DEFAULT_INDEX_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(600);
+
+/// Sections of Cargo.toml that can contain dependencies.
+const DEPENDENCY_SECTIONS: &[&str] = &["dependencies", "build-dependencies", "dev-dependencies"];
+
 #[derive(Debug)]
 pub enum Error {
     BrzError(breezyshim::error::Error),
     CratesIoError(crates_io_api::Error),
     VersionError(String),
+    MissingToken,
+    NotAnOwner { krate: String, user: String },
     Other(String),
 }
 
@@ -29,6 +39,13 @@ impl std::fmt::Display for Error {
             Error::BrzError(e) => write!(f, "TreeError: {}", e),
             Error::CratesIoError(e) => write!(f, "CratesIoError: {}", e),
             Error::VersionError(e) => write!(f, "VersionError: {}", e),
+            Error::MissingToken => write!(
+                f,
+                "No crates.io token found; set CARGO_REGISTRY_TOKEN or run \"disperse login crates-io\""
+            ),
+            Error::NotAnOwner { krate, user } => {
+                write!(f, "{} is not an owner of the crate {}", user, krate)
+            }
             Error::Other(e) => write!(f, "Other: {}", e),
         }
     }
@@ -41,6 +58,7 @@ pub fn get_owned_crates(user: &str) -> Result<Vec<url::Url>, Error> {
         crates_io_api::SyncClient::new(crate::USER_AGENT, std::time::Duration::from_millis(1000))
             .map_err(|e| Error::Other(format!("Unable to create crates.io client: {}", e)))?;
 
+    let _permit = crate::limits::acquire_blocking(Service::CratesIo);
     let user = client.user(user)?;
 
     let query = crates_io_api::CratesQueryBuilder::new().user_id(user.id);
@@ -55,18 +73,675 @@ pub fn get_owned_crates(user: &str) -> Result<Vec<url::Url>, Error> {
         .collect::<Vec<url::Url>>())
 }
 
+fn token_entry() -> Result<keyring::Entry, Error> {
+    keyring::Entry::new("crates.io", &crate::profile::qualify("api_token"))
+        .map_err(|e| Error::Other(format!("Unable to access keyring: {}", e)))
+}
+
+/// Find the crates.io API token, from the environment, the token cache or the keyring.
+pub fn token() -> Option<String> {
+    match std::env::var("CARGO_REGISTRY_TOKEN") {
+        Ok(token) => return Some(token),
+        Err(std::env::VarError::NotPresent) => {}
+        Err(e) => {
+            log::error!("Unable to read crates.io token from environment: {}", e);
+        }
+    }
+    if let Some(token) = crate::token_cache::cached_token(crate::token_cache::Service::CratesIo) {
+        return Some(token);
+    }
+    match token_entry().ok()?.get_password() {
+        Ok(token) => Some(token),
+        Err(keyring::Error::NoEntry) => None,
+        Err(e) => {
+            log::error!("Unable to read crates.io token from keyring: {}", e);
+            None
+        }
+    }
+}
+
+/// Store a crates.io API token in the keyring.
+pub fn store_token(token: &str) -> Result<(), Error> {
+    token_entry()?
+        .set_password(token)
+        .map_err(|e| Error::Other(format!("Unable to store crates.io token: {}", e)))
+}
+
+#[derive(serde::Deserialize)]
+struct MeResponse {
+    user: MeUser,
+}
+
+#[derive(serde::Deserialize)]
+struct MeUser {
+    login: String,
+}
+
+/// Look up the login of the user that a crates.io token belongs to.
+pub async fn token_user(token: &str) -> Result<String, Error> {
+    let _permit = crate::limits::acquire(Service::CratesIo).await;
+    let response = reqwest::Client::new()
+        .get("https://crates.io/api/v1/me")
+        .header(reqwest::header::USER_AGENT, crate::USER_AGENT)
+        .header(reqwest::header::AUTHORIZATION, token)
+        .send()
+        .await
+        .map_err(|e| Error::Other(format!("Unable to contact crates.io: {}", e)))?;
+    if response.status() == reqwest::StatusCode::FORBIDDEN
+        || response.status() == reqwest::StatusCode::UNAUTHORIZED
+    {
+        return Err(Error::Other("crates.io token was rejected".to_string()));
+    }
+    let me: MeResponse = response
+        .error_for_status()
+        .map_err(|e| Error::Other(format!("Unable to look up crates.io user: {}", e)))?
+        .json()
+        .await
+        .map_err(|e| Error::Other(format!("Unable to parse crates.io response: {}", e)))?;
+    Ok(me.user.login)
+}
+
+/// crates.io endpoint that exchanges an OIDC token for a publish token.
+const TRUSTED_PUBLISHING_URL: &str = "https://crates.io/api/v1/trusted_publishing/tokens";
+
+/// Check whether to publish through crates.io trusted publishing.
+///
+/// This is the case when running in GitHub Actions with permission to
+/// request an OIDC token, unless a token is set in CARGO_REGISTRY_TOKEN.
+pub fn use_trusted_publishing() -> bool {
+    std::env::var_os("CARGO_REGISTRY_TOKEN").is_none()
+        && std::env::var("GITHUB_ACTIONS").as_deref() == Ok("true")
+        && std::env::var_os("ACTIONS_ID_TOKEN_REQUEST_URL").is_some()
+        && std::env::var_os("ACTIONS_ID_TOKEN_REQUEST_TOKEN").is_some()
+}
+
+#[derive(serde::Deserialize)]
+struct OidcTokenResponse {
+    value: String,
+}
+
+#[derive(serde::Deserialize)]
+struct TrustedPublishingResponse {
+    token: String,
+}
+
+/// Short-lived publish token obtained through crates.io trusted publishing.
+pub struct TrustedPublishingToken {
+    token: String,
+}
+
+impl TrustedPublishingToken {
+    /// Exchange a GitHub Actions OIDC token for a crates.io publish token.
+    pub async fn obtain() -> Result<Self, Error> {
+        let env = |name: &str| {
+            std::env::var(name).map_err(|_| Error::Other(format!("{} is not set", name)))
+        };
+        let mut request_url: url::Url = env("ACTIONS_ID_TOKEN_REQUEST_URL")?
+            .parse()
+            .map_err(|e| Error::Other(format!("Invalid OIDC token request URL: {}", e)))?;
+        request_url
+            .query_pairs_mut()
+            .append_pair("audience", "crates.io");
+        let client = reqwest::Client::new();
+        let _permit = crate::limits::acquire(Service::CratesIo).await;
+        let oidc: OidcTokenResponse = client
+            .get(request_url)
+            .bearer_auth(env("ACTIONS_ID_TOKEN_REQUEST_TOKEN")?)
+            .header(reqwest::header::USER_AGENT, crate::USER_AGENT)
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+            .map_err(|e| Error::Other(format!("Unable to obtain OIDC token: {}", e)))?
+            .json()
+            .await
+            .map_err(|e| Error::Other(format!("Unable to parse OIDC token response: {}", e)))?;
+        let response: TrustedPublishingResponse = client
+            .post(TRUSTED_PUBLISHING_URL)
+            .header(reqwest::header::USER_AGENT, crate::USER_AGENT)
+            .json(&serde_json::json!({ "jwt": oidc.value }))
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+            .map_err(|e| Error::Other(format!("crates.io rejected the OIDC token: {}", e)))?
+            .json()
+            .await
+            .map_err(|e| Error::Other(format!("Unable to parse crates.io response: {}", e)))?;
+        Ok(Self {
+            token: response.token,
+        })
+    }
+
+    pub fn token(&self) -> &str {
+        &self.token
+    }
+
+    /// Revoke the token, rather than waiting for it to expire.
+    pub async fn revoke(self) -> Result<(), Error> {
+        let _permit = crate::limits::acquire(Service::CratesIo).await;
+        reqwest::Client::new()
+            .delete(TRUSTED_PUBLISHING_URL)
+            .bearer_auth(&self.token)
+            .header(reqwest::header::USER_AGENT, crate::USER_AGENT)
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+            .map_err(|e| Error::Other(format!("Unable to revoke publish token: {}", e)))?;
+        Ok(())
+    }
+}
+
+/// Check that a token is available and that its user owns the publishable crates in the tree.
+///
+/// Crates that have not been published before can be published by anybody.
+/// With trusted publishing, crates.io checks that the workflow is allowed to
+/// publish the crates, so there is nothing to check up front.
+pub async fn preflight(tree: &WorkingTree) -> Result<(), Error> {
+    if use_trusted_publishing() {
+        log::info!("Publishing to crates.io through trusted publishing");
+        return Ok(());
+    }
+    let token = token().ok_or(Error::MissingToken)?;
+    let user = token_user(&token).await?;
+    let client =
+        crates_io_api::AsyncClient::new(crate::USER_AGENT, std::time::Duration::from_millis(1000))
+            .map_err(|e| Error::Other(format!("Unable to create crates.io client: {}", e)))?;
+    for krate in workspace_crates(tree)?.into_iter().filter(|c| c.publish) {
+        let owners = match client.crate_owners(&krate.name).await {
+            Ok(owners) => owners,
+            Err(crates_io_api::Error::NotFound(_)) => continue,
+            Err(e) => return Err(e.into()),
+        };
+        if owners.iter().any(|o| o.login == user) {
+            continue;
+        }
+        if owners.iter().any(|o| o.kind.as_deref() == Some("team")) {
+            log::warn!(
+                "{} is not a direct owner of {}; assuming ownership through a team",
+                user,
+                krate.name
+            );
+        } else {
+            return Err(Error::NotAnOwner {
+                krate: krate.name,
+                user,
+            });
+        }
+    }
+    Ok(())
+}
+
 // Define a function to publish a Rust package using Cargo
-pub fn publish(tree: &WorkingTree, subpath: &Path) -> Result<(), Error> {
-    Command::new("cargo")
-        .arg("publish")
+pub fn publish(tree: &WorkingTree, subpath: &Path, token: Option<&str>) -> Result<(), Error> {
+    let mut cmd = Command::new("cargo");
+    if let Some(token) = token {
+        cmd.env("CARGO_REGISTRY_TOKEN", token);
+    }
+    let status = crate::logging::status(cmd.arg("publish").current_dir(tree.abspath(subpath)?))
+        .map_err(|e| Error::Other(format!("Unable to run cargo publish: {}", e)))?;
+    if !status.success() {
+        return Err(Error::Other(format!(
+            "cargo publish in {} failed: {}",
+            subpath.display(),
+            status
+        )));
+    }
+    Ok(())
+}
+
+/// Run a cargo command in the tree, capturing its output.
+///
+/// On failure, the error includes the standard error output of cargo.
+fn run_cargo(tree: &WorkingTree, subpath: &Path, args: &[&str]) -> Result<String, Error> {
+    let output = Command::new("cargo")
+        .args(args)
         .current_dir(tree.abspath(subpath)?)
-        .spawn()
-        .map_err(|e| Error::Other(format!("Unable to spawn cargo publish: {}", e)))?
-        .wait()
-        .map_err(|e| Error::Other(format!("Unable to wait for cargo publish: {}", e)))?;
+        .stdin(std::process::Stdio::null())
+        .output()
+        .map_err(|e| Error::Other(format!("Unable to spawn cargo {}: {}", args[0], e)))?;
+    if !output.status.success() {
+        return Err(Error::Other(format!(
+            "cargo {} failed ({}): {}",
+            args.join(" "),
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// List the files that would be included in the package for a crate.
+pub fn package_list(tree: &WorkingTree, subpath: &Path) -> Result<Vec<PathBuf>, Error> {
+    Ok(
+        run_cargo(tree, subpath, &["package", "--list", "--allow-dirty"])?
+            .lines()
+            .map(PathBuf::from)
+            .collect(),
+    )
+}
+
+/// Check that a crate can be published, without actually uploading it.
+pub fn publish_dry_run(tree: &WorkingTree, subpath: &Path) -> Result<(), Error> {
+    run_cargo(tree, subpath, &["publish", "--dry-run", "--allow-dirty"])?;
+    Ok(())
+}
+
+/// Verify that the packages in the tree build from their packaged sources.
+///
+/// For workspaces, all members are packaged together, so that crates can
+/// depend on new versions of other crates in the same workspace.
+pub fn verify_package(tree: &WorkingTree) -> Result<(), Error> {
+    let mut args = vec!["package", "--allow-dirty"];
+    if workspace_member_paths(tree)?.len() > 1 {
+        args.push("--workspace");
+    }
+    run_cargo(tree, Path::new("."), &args)?;
+    Ok(())
+}
+
+/// Check that the crate in `subpath` builds from its packaged sources.
+pub fn verify_crate_package(tree: &WorkingTree, subpath: &Path) -> Result<(), Error> {
+    run_cargo(tree, subpath, &["package", "--allow-dirty"])?;
     Ok(())
 }
 
+/// Directory cargo writes build output to for the tree.
+fn target_directory(tree: &WorkingTree) -> Result<PathBuf, Error> {
+    let metadata: serde_json::Value = serde_json::from_str(&run_cargo(
+        tree,
+        Path::new("."),
+        &["metadata", "--format-version", "1", "--no-deps"],
+    )?)
+    .map_err(|e| Error::Other(format!("Invalid cargo metadata: {}", e)))?;
+    metadata
+        .get("target_directory")
+        .and_then(|d| d.as_str())
+        .map(PathBuf::from)
+        .ok_or_else(|| Error::Other("No target directory in cargo metadata".to_string()))
+}
+
+/// Package the publishable crates in the tree, and copy the `.crate` files
+/// to `artifact_dir`.
+pub fn package(tree: &WorkingTree, artifact_dir: &Path) -> Result<Vec<PathBuf>, Error> {
+    let crates = workspace_crates(tree)?
+        .into_iter()
+        .filter(|c| c.publish)
+        .collect::<Vec<_>>();
+    if crates.is_empty() {
+        return Ok(vec![]);
+    }
+    let mut args = vec!["package", "--allow-dirty", "--no-verify"];
+    for krate in &crates {
+        args.extend(["-p", krate.name.as_str()]);
+    }
+    run_cargo(tree, Path::new("."), &args)?;
+
+    let package_dir = target_directory(tree)?.join("package");
+    std::fs::create_dir_all(artifact_dir).map_err(|e| {
+        Error::Other(format!(
+            "Unable to create {}: {}",
+            artifact_dir.display(),
+            e
+        ))
+    })?;
+    let mut ret = vec![];
+    for krate in crates {
+        let version = krate.version.as_deref().ok_or_else(|| {
+            Error::VersionError(format!("Unable to determine version of {}", krate.name))
+        })?;
+        let file_name = format!("{}-{}.crate", krate.name, version);
+        let path = artifact_dir.join(&file_name);
+        std::fs::copy(package_dir.join(&file_name), &path)
+            .map_err(|e| Error::Other(format!("Unable to copy {}: {}", file_name, e)))?;
+        ret.push(path);
+    }
+    Ok(ret)
+}
+
+/// A crate in a Cargo workspace.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WorkspaceCrate {
+    pub name: String,
+
+    /// Directory of the crate, relative to the root of the tree.
+    pub path: PathBuf,
+
+    pub version: Option<String>,
+
+    /// Whether the crate can be published; false if `publish = false` is set.
+    pub publish: bool,
+
+    /// Names of other crates in the workspace that this crate depends on.
+    ///
+    /// Dev-dependencies are not included, since they don't need to be
+    /// published first.
+    pub dependencies: Vec<String>,
+}
+
+/// Iterate over all dependency tables in a manifest, including
+/// target-specific ones and `[workspace.dependencies]`.
+fn dependency_tables(
+    doc: &toml_edit::DocumentMut,
+) -> Vec<(&'static str, &dyn toml_edit::TableLike)> {
+    let mut items = vec![];
+    for section in DEPENDENCY_SECTIONS {
+        items.push((*section, doc.get(section)));
+    }
+    if let Some(targets) = doc.get("target").and_then(|t| t.as_table_like()) {
+        for (_, target) in targets.iter() {
+            for section in DEPENDENCY_SECTIONS {
+                items.push((*section, target.get(section)));
+            }
+        }
+    }
+    items.push((
+        "dependencies",
+        doc.get("workspace").and_then(|w| w.get("dependencies")),
+    ));
+    items
+        .into_iter()
+        .filter_map(|(section, item)| Some((section, item?.as_table_like()?)))
+        .collect()
+}
+
+fn dependency_tables_mut(doc: &mut toml_edit::DocumentMut) -> Vec<&mut dyn toml_edit::TableLike> {
+    let mut ret = vec![];
+    let root = doc.as_table_mut();
+    for (key, item) in root.iter_mut() {
+        match key.get() {
+            section if DEPENDENCY_SECTIONS.contains(&section) => {
+                if let Some(t) = item.as_table_like_mut() {
+                    ret.push(t);
+                }
+            }
+            "target" => {
+                if let Some(targets) = item.as_table_like_mut() {
+                    for (_, target) in targets.iter_mut() {
+                        if let Some(target) = target.as_table_like_mut() {
+                            for (key, item) in target.iter_mut() {
+                                if DEPENDENCY_SECTIONS.contains(&key.get()) {
+                                    if let Some(t) = item.as_table_like_mut() {
+                                        ret.push(t);
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+            "workspace" => {
+                if let Some(t) = item
+                    .get_mut("dependencies")
+                    .and_then(|d| d.as_table_like_mut())
+                {
+                    ret.push(t);
+                }
+            }
+            _ => {}
+        }
+    }
+    ret
+}
+
+/// Name of the package a dependency refers to, taking renames into account.
+fn dependency_package_name<'a>(key: &'a str, item: &'a toml_edit::Item) -> &'a str {
+    item.get("package").and_then(|p| p.as_str()).unwrap_or(key)
+}
+
+/// Find the dependencies of a crate on other crates in the same workspace.
+pub fn workspace_dependencies_in_toml(
+    doc: &toml_edit::DocumentMut,
+    members: &HashSet<String>,
+) -> Vec<String> {
+    let mut ret = vec![];
+    for (section, table) in dependency_tables(doc) {
+        if section == "dev-dependencies" {
+            continue;
+        }
+        for (key, item) in table.iter() {
+            let name = dependency_package_name(key, item);
+            if members.contains(name) && !ret.iter().any(|n| n == name) {
+                ret.push(name.to_string());
+            }
+        }
+    }
+    ret
+}
+
+/// Update the version requirements of path dependencies on workspace crates.
+///
+/// Any operator prefix (e.g. "=" or "^") in the existing requirement is preserved.
+/// Returns whether anything was changed.
+pub fn update_dependency_versions_in_toml(
+    doc: &mut toml_edit::DocumentMut,
+    members: &HashSet<String>,
+    new_version: &str,
+) -> bool {
+    let mut changed = false;
+    for table in dependency_tables_mut(doc) {
+        for (key, item) in table.iter_mut() {
+            if !members.contains(dependency_package_name(key.get(), item)) {
+                continue;
+            }
+            if item.get("path").is_none() {
+                continue;
+            }
+            let Some(version) = item.get_mut("version") else {
+                continue;
+            };
+            let Some(old) = version.as_str() else {
+                continue;
+            };
+            let prefix =
+                &old[..old.len() - old.trim_start_matches(|c: char| !c.is_ascii_digit()).len()];
+            let new = format!("{}{}", prefix, new_version);
+            if new != old {
+                *version = toml_edit::value(new);
+                changed = true;
+            }
+        }
+    }
+    changed
+}
+
+fn read_toml(tree: &dyn Tree, path: &Path) -> Result<toml_edit::DocumentMut, Error> {
+    let contents = tree.get_file_text(path)?;
+    String::from_utf8_lossy(contents.as_slice())
+        .parse()
+        .map_err(|e| Error::Other(format!("Unable to parse {}: {}", path.display(), e)))
+}
+
+/// Find the directories of the members of the workspace, relative to the root of the tree.
+///
+/// For a crate that is not a workspace, this just returns the root.
+pub fn workspace_member_paths(tree: &WorkingTree) -> Result<Vec<PathBuf>, Error> {
+    let root = read_toml(tree, Path::new("Cargo.toml"))?;
+    let mut ret = vec![];
+    if root.get("package").is_some() {
+        ret.push(PathBuf::new());
+    }
+    let Some(workspace) = root.get("workspace") else {
+        return Ok(ret);
+    };
+    let patterns = |key| {
+        workspace
+            .get(key)
+            .and_then(|m| m.as_array())
+            .map(|a| a.iter().filter_map(|v| v.as_str()).collect::<Vec<_>>())
+            .unwrap_or_default()
+    };
+    let exclude = patterns("exclude")
+        .into_iter()
+        .map(PathBuf::from)
+        .collect::<Vec<_>>();
+    for pattern in patterns("members") {
+        for path in crate::iter_glob(tree, pattern) {
+            if ret.contains(&path) || exclude.contains(&path) {
+                continue;
+            }
+            if tree.has_filename(&path.join("Cargo.toml")) {
+                ret.push(path);
+            }
+        }
+    }
+    Ok(ret)
+}
+
+/// Find all crates in the workspace in the tree.
+pub fn workspace_crates(tree: &WorkingTree) -> Result<Vec<WorkspaceCrate>, Error> {
+    let root = read_toml(tree, Path::new("Cargo.toml"))?;
+    let workspace_version = root
+        .get("workspace")
+        .and_then(|w| w.get("package"))
+        .and_then(|p| p.get("version"))
+        .and_then(|v| v.as_str())
+        .map(|v| v.to_string());
+
+    let mut manifests = vec![];
+    for path in workspace_member_paths(tree)? {
+        let doc = read_toml(tree, &path.join("Cargo.toml"))?;
+        if let Some(name) = doc
+            .get("package")
+            .and_then(|p| p.get("name"))
+            .and_then(|n| n.as_str())
+        {
+            manifests.push((name.to_string(), path, doc));
+        }
+    }
+    let members = manifests
+        .iter()
+        .map(|(name, _, _)| name.clone())
+        .collect::<HashSet<_>>();
+
+    Ok(manifests
+        .into_iter()
+        .map(|(name, path, doc)| {
+            let package = doc.get("package").unwrap();
+            let version = match package.get("version") {
+                Some(v) if v.get("workspace").is_some() => workspace_version.clone(),
+                Some(v) => v.as_str().map(|v| v.to_string()),
+                None => None,
+            };
+            let publish = match package.get("publish") {
+                Some(p) => p
+                    .as_bool()
+                    .unwrap_or_else(|| p.as_array().is_none_or(|a| !a.is_empty())),
+                None => true,
+            };
+            let mut dependencies = workspace_dependencies_in_toml(&doc, &members);
+            dependencies.retain(|d| d != &name);
+            WorkspaceCrate {
+                name,
+                path,
+                version,
+                publish,
+                dependencies,
+            }
+        })
+        .collect())
+}
+
+/// Sort workspace crates so that every crate comes after the crates it depends on.
+///
+/// Crates that don't depend on each other are sorted by name, so that the
+/// order is stable.
+pub fn publish_order(crates: &[WorkspaceCrate]) -> Result<Vec<&WorkspaceCrate>, Error> {
+    let mut pending = crates
+        .iter()
+        .map(|c| (c.name.as_str(), c))
+        .collect::<BTreeMap<_, _>>();
+    let mut ret: Vec<&WorkspaceCrate> = vec![];
+    while !pending.is_empty() {
+        let ready = pending
+            .values()
+            .find(|c| {
+                c.dependencies
+                    .iter()
+                    .all(|d| !pending.contains_key(d.as_str()))
+            })
+            .copied();
+        match ready {
+            Some(c) => {
+                pending.remove(c.name.as_str());
+                ret.push(c);
+            }
+            None => {
+                return Err(Error::Other(format!(
+                    "Dependency cycle between workspace crates: {}",
+                    pending.keys().copied().collect::<Vec<_>>().join(", ")
+                )));
+            }
+        }
+    }
+    Ok(ret)
+}
+
+/// Wait until a particular version of a crate is available on crates.io.
+pub async fn wait_for_crate_version(
+    name: &str,
+    version: &str,
+    timeout: std::time::Duration,
+) -> Result<(), Error> {
+    let client =
+        crates_io_api::AsyncClient::new(crate::USER_AGENT, std::time::Duration::from_millis(1000))
+            .map_err(|e| Error::Other(format!("Unable to create crates.io client: {}", e)))?;
+    let start = std::time::Instant::now();
+    loop {
+        let permit = crate::limits::acquire(Service::CratesIo).await;
+        match client.get_crate(name).await {
+            Ok(response) if response.versions.iter().any(|v| v.num == version) => {
+                return Ok(());
+            }
+            Ok(_) => {}
+            Err(crates_io_api::Error::NotFound(_)) => {}
+            Err(e) => return Err(e.into()),
+        }
+        drop(permit);
+        if start.elapsed() > timeout {
+            return Err(Error::Other(format!(
+                "Timed out waiting for {} {} to become available on crates.io",
+                name, version
+            )));
+        }
+        log::info!("Waiting for {} {} to appear on crates.io", name, version);
+        tokio::time::sleep(std::time::Duration::from_secs(10)).await;
+    }
+}
+
+/// Publish all publishable crates in a workspace, in dependency order.
+///
+/// After each crate is published, this waits for it to become available in
+/// the index so that crates depending on it can be published.
+///
+/// If `only` is set, just the crate in that directory is published.
+///
+/// Returns the names of the crates that were published.
+pub async fn publish_workspace(
+    tree: &WorkingTree,
+    only: Option<&Path>,
+    token: Option<&str>,
+    index_timeout: std::time::Duration,
+) -> Result<Vec<String>, Error> {
+    let crates = workspace_crates(tree)?;
+    let order = publish_order(&crates)?
+        .into_iter()
+        .filter(|c| c.publish && only.is_none_or(|p| c.path == p))
+        .collect::<Vec<_>>();
+    let mut published = vec![];
+    for (i, krate) in order.iter().enumerate() {
+        log::info!("Publishing {}", krate.name);
+        let permit = crate::limits::acquire(Service::CratesIo).await;
+        publish(tree, &krate.path, token)?;
+        drop(permit);
+        published.push(krate.name.clone());
+        if i + 1 < order.len() {
+            if let Some(version) = krate.version.as_ref() {
+                wait_for_crate_version(&krate.name, version, index_timeout).await?;
+            }
+        }
+    }
+    Ok(published)
+}
+
 pub fn update_version_in_toml(
     parsed_toml: &mut toml_edit::DocumentMut,
     new_version: &str,
@@ -110,7 +785,11 @@ pub fn update_version_in_toml(
 }
 
 // Define a function to update the version in the Cargo.toml file
-pub fn update_version(tree: &WorkingTree, new_version: &str) -> Result<(), Error> {
+//
+// For workspaces, the versions of all member crates and the version
+// requirements of path dependencies between them are updated as well.
+// Returns the manifests that were modified.
+pub fn update_version(tree: &WorkingTree, new_version: &str) -> Result<Vec<PathBuf>, Error> {
     // Read the Cargo.toml file
     let cargo_toml_contents = tree.get_file_text(Path::new("Cargo.toml"))?;
 
@@ -123,13 +802,96 @@ pub fn update_version(tree: &WorkingTree, new_version: &str) -> Result<(), Error
     // Update the version field
     update_version_in_toml(&mut parsed_toml, new_version)?;
 
+    let members = workspace_member_paths(tree)?
+        .into_iter()
+        .filter(|p| p != Path::new(""))
+        .collect::<Vec<_>>();
+    let mut member_manifests = vec![];
+    for path in members {
+        let path = path.join("Cargo.toml");
+        let doc = read_toml(tree, &path)?;
+        member_manifests.push((path, doc));
+    }
+    let names = std::iter::once(&parsed_toml)
+        .chain(member_manifests.iter().map(|(_, doc)| doc))
+        .filter_map(|doc| {
+            doc.get("package")
+                .and_then(|p| p.get("name"))
+                .and_then(|n| n.as_str())
+                .map(|n| n.to_string())
+        })
+        .collect::<HashSet<_>>();
+
+    update_dependency_versions_in_toml(&mut parsed_toml, &names, new_version);
+
     // Serialize the updated TOML back to a string
     let updated_cargo_toml = parsed_toml.to_string();
 
     // Write the updated TOML back to Cargo.toml
     tree.put_file_bytes_non_atomic(Path::new("Cargo.toml"), updated_cargo_toml.as_bytes())?;
 
-    // If there is a Cargo.lock file, then run `cargo update -w` to update the version in it
+    let mut modified = vec![PathBuf::from("Cargo.toml")];
+    for (path, mut doc) in member_manifests {
+        let mut changed = false;
+        if let Some(version) = doc.get_mut("package").and_then(|p| p.get_mut("version")) {
+            if version.is_str() {
+                *version = toml_edit::value(new_version);
+                changed = true;
+            }
+        }
+        changed |= update_dependency_versions_in_toml(&mut doc, &names, new_version);
+        if changed {
+            tree.put_file_bytes_non_atomic(&path, doc.to_string().as_bytes())?;
+            modified.push(path);
+        }
+    }
+
+    Ok(modified)
+}
+
+/// Update the version of a single crate in a workspace, e.g. in a repository
+/// with several independently released projects.
+///
+/// The version requirements of the other workspace crates on it are updated
+/// as well. Returns the manifests that were modified.
+pub fn update_crate_version(
+    tree: &WorkingTree,
+    subpath: &Path,
+    new_version: &str,
+) -> Result<Vec<PathBuf>, Error> {
+    let path = subpath.join("Cargo.toml");
+    let mut doc = read_toml(tree, &path)?;
+    update_version_in_toml(&mut doc, new_version)?;
+    let name = doc
+        .get("package")
+        .and_then(|p| p.get("name"))
+        .and_then(|n| n.as_str())
+        .ok_or_else(|| Error::Other(format!("No package name in {}", path.display())))?;
+    let names = HashSet::from([name.to_string()]);
+    tree.put_file_bytes_non_atomic(&path, doc.to_string().as_bytes())?;
+
+    let mut modified = vec![path.clone()];
+    if !tree.has_filename(Path::new("Cargo.toml")) {
+        return Ok(modified);
+    }
+    for member in workspace_member_paths(tree)? {
+        let member = member.join("Cargo.toml");
+        if member == path {
+            continue;
+        }
+        let mut doc = read_toml(tree, &member)?;
+        if update_dependency_versions_in_toml(&mut doc, &names, new_version) {
+            tree.put_file_bytes_non_atomic(&member, doc.to_string().as_bytes())?;
+            modified.push(member);
+        }
+    }
+    Ok(modified)
+}
+
+/// Run `cargo update -w` to update the versions of the workspace crates in Cargo.lock.
+///
+/// Returns whether there was a Cargo.lock file to update.
+pub fn update_lockfile(tree: &WorkingTree) -> Result<bool, Error> {
     if tree.has_filename(Path::new("Cargo.lock")) {
         Command::new("cargo")
             .arg("update")
@@ -142,9 +904,10 @@ pub fn update_version(tree: &WorkingTree, new_version: &str) -> Result<(), Error
             .map_err(|e| Error::Other(format!("Unable to spawn cargo update: {}", e)))?
             .wait()
             .map_err(|e| Error::Other(format!("Unable to wait for cargo update: {}", e)))?;
+        Ok(true)
+    } else {
+        Ok(false)
     }
-
-    Ok(())
 }
 
 pub fn find_version_in_toml(cargo_toml_contents: &str) -> Result<crate::version::Version, Error> {
@@ -187,6 +950,23 @@ pub fn find_version_in_toml(cargo_toml_contents: &str) -> Result<crate::version:
         .map_err(|e| Error::VersionError(format!("Unable to parse version: {}", e)))
 }
 
+pub fn find_name_in_toml(cargo_toml_contents: &str) -> Option<String> {
+    let parsed_toml: toml_edit::DocumentMut = cargo_toml_contents.parse().ok()?;
+
+    parsed_toml
+        .as_table()
+        .get("package")
+        .and_then(|p| p.get("name"))
+        .and_then(|n| n.as_str())
+        .map(|n| n.to_string())
+}
+
+pub fn find_name(tree: &dyn Tree) -> Option<String> {
+    let cargo_toml_contents = tree.get_file_text(Path::new("Cargo.toml")).ok()?;
+
+    find_name_in_toml(String::from_utf8_lossy(cargo_toml_contents.as_slice()).as_ref())
+}
+
 // Define a function to find the version in the Cargo.toml file
 pub fn find_version(tree: &dyn Tree) -> Result<crate::version::Version, Error> {
     // Read the Cargo.toml file
@@ -214,6 +994,18 @@ mod tests {
         assert_eq!(version, "0.2.0".parse().unwrap());
     }
 
+    #[test]
+    fn test_find_name_in_toml() {
+        assert_eq!(
+            super::find_name_in_toml("[package]\nname = \"foo\"\nversion = \"0.1.0\"\n"),
+            Some("foo".to_string())
+        );
+        assert_eq!(
+            super::find_name_in_toml("[workspace]\nmembers = [\"foo\"]\n"),
+            None
+        );
+    }
+
     #[test]
     fn test_find_version_in_toml_error() {
         let text = "[package]\nversion = 0.1.0\n";
@@ -271,6 +1063,101 @@ mod tests {
         );
     }
 
+    fn krate(name: &str, dependencies: &[&str]) -> super::WorkspaceCrate {
+        super::WorkspaceCrate {
+            name: name.to_string(),
+            path: name.into(),
+            version: Some("0.1.0".to_string()),
+            publish: true,
+            dependencies: dependencies.iter().map(|d| d.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn test_publish_order() {
+        let crates = vec![
+            krate("foo-cli", &["foo", "foo-derive"]),
+            krate("foo", &["foo-derive"]),
+            krate("foo-derive", &[]),
+            krate("bar", &[]),
+        ];
+        assert_eq!(
+            super::publish_order(&crates)
+                .unwrap()
+                .into_iter()
+                .map(|c| c.name.as_str())
+                .collect::<Vec<_>>(),
+            vec!["bar", "foo-derive", "foo", "foo-cli"]
+        );
+
+        let crates = vec![krate("a", &["b"]), krate("b", &["a"])];
+        assert!(super::publish_order(&crates).is_err());
+    }
+
+    #[test]
+    fn test_workspace_dependencies_in_toml() {
+        let members = ["foo", "foo-derive", "foo-testutils"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let doc: toml_edit::DocumentMut = r#"[package]
+name = "foo-cli"
+
+[dependencies]
+foo = { path = "../foo", version = "0.1.0" }
+serde = "1"
+
+[target.'cfg(unix)'.dependencies]
+derive = { package = "foo-derive", path = "../foo-derive", version = "0.1.0" }
+
+[dev-dependencies]
+foo-testutils = { path = "../foo-testutils" }
+"#
+        .parse()
+        .unwrap();
+        assert_eq!(
+            super::workspace_dependencies_in_toml(&doc, &members),
+            vec!["foo".to_string(), "foo-derive".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_update_dependency_versions_in_toml() {
+        let members = ["foo", "foo-derive"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let mut doc: toml_edit::DocumentMut = r#"[dependencies]
+foo = { path = "../foo", version = "0.1.0" }
+foo-derive = { path = "../foo-derive", version = "=0.1.0" }
+serde = { version = "1" }
+
+[workspace.dependencies.foo]
+path = "foo"
+version = "^0.1"
+"#
+        .parse()
+        .unwrap();
+        assert!(super::update_dependency_versions_in_toml(
+            &mut doc, &members, "0.2.0"
+        ));
+        assert_eq!(
+            doc.to_string(),
+            r#"[dependencies]
+foo = { path = "../foo", version = "0.2.0" }
+foo-derive = { path = "../foo-derive", version = "=0.2.0" }
+serde = { version = "1" }
+
+[workspace.dependencies.foo]
+path = "foo"
+version = "^0.2.0"
+"#
+        );
+        assert!(!super::update_dependency_versions_in_toml(
+            &mut doc, &members, "0.2.0"
+        ));
+    }
+
     #[test]
     fn test_update_version_in_toml_invalid() {
         let text = "";
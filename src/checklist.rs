@@ -0,0 +1,177 @@
+use crate::project_config::ChecklistItem;
+use std::io::{BufRead, IsTerminal, Write};
+
+#[derive(Debug)]
+pub enum Error {
+    IoError(std::io::Error),
+    /// Required items that were not confirmed.
+    Unconfirmed(Vec<String>),
+}
+
+impl From<std::io::Error> for Error {
+    fn from(e: std::io::Error) -> Self {
+        Error::IoError(e)
+    }
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match &self {
+            Error::IoError(e) => write!(f, "IoError: {}", e),
+            Error::Unconfirmed(items) => write!(
+                f,
+                "Required checklist items not confirmed: {}",
+                items.join(", ")
+            ),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// Whether an item on the release checklist was confirmed.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct ChecklistResult {
+    pub item: String,
+    pub required: bool,
+    pub confirmed: bool,
+}
+
+/// Go through the release checklist.
+///
+/// `confirm` asks whether an item has been taken care of, and returns None
+/// if there is nobody to ask. Fails if any required items were not confirmed.
+pub fn run_checklist(
+    items: &[ChecklistItem],
+    mut confirm: impl FnMut(&ChecklistItem) -> Result<Option<bool>, Error>,
+) -> Result<Vec<ChecklistResult>, Error> {
+    let mut results = vec![];
+    for item in items {
+        let confirmed = confirm(item)?.unwrap_or(false);
+        if !confirmed && !item.is_required() {
+            log::warn!("Checklist item not confirmed: {}", item.description);
+        }
+        results.push(ChecklistResult {
+            item: item.description.clone(),
+            required: item.is_required(),
+            confirmed,
+        });
+    }
+    let unconfirmed = results
+        .iter()
+        .filter(|r| r.required && !r.confirmed)
+        .map(|r| r.item.clone())
+        .collect::<Vec<_>>();
+    if !unconfirmed.is_empty() {
+        return Err(Error::Unconfirmed(unconfirmed));
+    }
+    Ok(results)
+}
+
+/// Ask a yes/no question on the terminal.
+///
+/// Returns None if standard input is not a terminal.
+pub fn confirm_terminal(question: &str) -> Result<Option<bool>, Error> {
+    let stdin = std::io::stdin();
+    if !stdin.is_terminal() {
+        return Ok(None);
+    }
+    print!("{} [y/N] ", question);
+    std::io::stdout().flush()?;
+    let mut answer = String::new();
+    stdin.lock().read_line(&mut answer)?;
+    Ok(Some(matches!(
+        answer.trim().to_lowercase().as_str(),
+        "y" | "yes"
+    )))
+}
+
+/// Ask for confirmation of a checklist item on the terminal.
+///
+/// Returns None if standard input is not a terminal.
+pub fn prompt_terminal(item: &ChecklistItem) -> Result<Option<bool>, Error> {
+    confirm_terminal(&format!(
+        "{}{} Done?",
+        item.description,
+        if item.is_required() {
+            " (required)"
+        } else {
+            ""
+        }
+    ))
+}
+
+/// Leave a checklist item unconfirmed, without asking anybody.
+pub fn unattended(_item: &ChecklistItem) -> Result<Option<bool>, Error> {
+    Ok(None)
+}
+
+/// How to confirm checklist items: on the terminal for interactive releases,
+/// and not at all otherwise.
+pub fn confirmer(interactive: bool) -> fn(&ChecklistItem) -> Result<Option<bool>, Error> {
+    if interactive {
+        prompt_terminal
+    } else {
+        unattended
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(description: &str, required: bool) -> ChecklistItem {
+        ChecklistItem {
+            description: description.to_string(),
+            required: Some(required),
+        }
+    }
+
+    #[test]
+    fn test_run_checklist() {
+        let items = vec![
+            item("Update translations", true),
+            item("Post to forum", false),
+        ];
+        assert_eq!(
+            run_checklist(&items, |i| Ok(Some(i.is_required()))).unwrap(),
+            vec![
+                ChecklistResult {
+                    item: "Update translations".to_string(),
+                    required: true,
+                    confirmed: true,
+                },
+                ChecklistResult {
+                    item: "Post to forum".to_string(),
+                    required: false,
+                    confirmed: false,
+                },
+            ]
+        );
+        match run_checklist(&items, |_| Ok(None)) {
+            Err(Error::Unconfirmed(items)) => assert_eq!(items, vec!["Update translations"]),
+            r => panic!("unexpected result: {:?}", r),
+        }
+        assert!(run_checklist(&items[1..], |_| Ok(None)).is_ok());
+    }
+
+    #[test]
+    fn test_run_checklist_non_interactive() {
+        let items = vec![
+            item("Update translations", true),
+            item("Post to forum", false),
+        ];
+        match run_checklist(&items, confirmer(false)) {
+            Err(Error::Unconfirmed(items)) => assert_eq!(items, vec!["Update translations"]),
+            r => panic!("unexpected result: {:?}", r),
+        }
+        assert_eq!(
+            run_checklist(&items[1..], confirmer(false)).unwrap(),
+            vec![ChecklistResult {
+                item: "Post to forum".to_string(),
+                required: false,
+                confirmed: false,
+            }]
+        );
+    }
+}
@@ -1,6 +1,8 @@
 use serde::Deserialize;
 
+use std::collections::BTreeMap;
 use std::fs;
+use std::path::Path;
 
 use toml;
 
@@ -13,6 +15,45 @@ const CONFIG_FILE_NAME: &str = "disperse.toml";
 ///
 /// [crates.io]
 /// username = "jelmer"
+///
+/// [prometheus]
+/// url = "https://pushgateway.example.com"
+/// job = "disperse"
+/// instance = "cron-host"
+/// bearer-token = "..."
+/// textfile-directory = "/var/lib/prometheus/node-exporter"
+///
+/// [prometheus.grouping]
+/// cluster = "home"
+///
+/// [history]
+/// path = "/var/lib/disperse/releases.jsonl"
+///
+/// [failure-issues]
+/// threshold = 3
+///
+/// [self-update]
+/// max-age = 90
+///
+/// [workspaces]
+/// max-age = 7
+///
+/// [defaults]
+/// tag-name = "v$VERSION"
+/// twine-upload = true
+/// ci-timeout = 3600
+/// signing-key = "0xDEADBEEF"
+///
+/// [project."https://github.com/jelmer/example"]
+/// snooze-until = "2025-09-01"
+///
+/// [profile.work]
+/// identity = "Jelmer Vernooij <jelmer@example.com>"
+/// signing-key = "0xDEADBEEF"
+/// env = { TWINE_USERNAME = "__token__" }
+///
+/// [profile.work.prometheus]
+/// url = "https://pushgateway.example.com"
 /// ```
 
 #[derive(Debug, serde::Deserialize, Default)]
@@ -21,6 +62,97 @@ pub struct Config {
     #[serde(rename = "crates.io")]
     pub crates_io: Option<CratesIoConfig>,
     pub repositories: Option<RepositoriesConfig>,
+    pub prometheus: Option<PrometheusConfig>,
+    pub history: Option<HistoryConfig>,
+    #[serde(rename = "failure-issues")]
+    pub failure_issues: Option<FailureIssuesConfig>,
+    #[serde(rename = "self-update")]
+    pub self_update: Option<SelfUpdateConfig>,
+    pub workspaces: Option<WorkspacesConfig>,
+    /// Project settings to use when a project's disperse.toml doesn't set them.
+    pub defaults: Option<toml::Table>,
+    /// Settings for individual projects, by URL.
+    #[serde(default, rename = "project")]
+    pub projects: BTreeMap<String, ProjectOverrides>,
+    /// Limits on requests to external services, by service.
+    #[serde(default)]
+    pub limits: BTreeMap<crate::limits::Service, crate::limits::ServiceLimits>,
+    /// Identities to release as, selected with --profile.
+    #[serde(default, rename = "profile")]
+    pub profiles: BTreeMap<String, Profile>,
+}
+
+/// Settings for releasing as a particular identity.
+///
+/// Credentials in the token cache and keyring are stored separately for
+/// each profile.
+#[derive(Debug, Deserialize, Default)]
+pub struct Profile {
+    /// Identity to commit and tag as, e.g. "Jane Doe <jane@example.com>".
+    pub identity: Option<String>,
+
+    /// OpenPGP key to sign tags and release manifests with.
+    #[serde(rename = "signing-key")]
+    pub signing_key: Option<String>,
+
+    /// Environment variables to set, e.g. for tools that read credentials
+    /// from the environment.
+    #[serde(default)]
+    pub env: BTreeMap<String, String>,
+
+    /// Replaces the top-level prometheus settings.
+    pub prometheus: Option<PrometheusConfig>,
+
+    /// Replaces the top-level failure-issues settings.
+    #[serde(rename = "failure-issues")]
+    pub failure_issues: Option<FailureIssuesConfig>,
+}
+
+#[derive(Debug, Deserialize, Default, Clone)]
+pub struct ProjectOverrides {
+    /// Never release the project.
+    pub disabled: Option<bool>,
+
+    /// Don't release the project before this date.
+    #[serde(rename = "snooze-until")]
+    pub snooze_until: Option<chrono::NaiveDate>,
+}
+
+impl ProjectOverrides {
+    /// Why the project shouldn't be released on `today`, if anything.
+    pub fn hold(&self, today: chrono::NaiveDate) -> Option<crate::project_config::Hold> {
+        crate::project_config::Hold::check(self.disabled, self.snooze_until, today)
+    }
+}
+
+#[derive(Debug, Deserialize, Default)]
+pub struct HistoryConfig {
+    /// File to record releases in; defaults to a file in the XDG state directory.
+    pub path: Option<std::path::PathBuf>,
+}
+
+#[derive(Debug, Deserialize, Default)]
+pub struct SelfUpdateConfig {
+    /// Warn when the running disperse was installed more than this many
+    /// days ago.
+    #[serde(rename = "max-age")]
+    pub max_age: Option<u64>,
+}
+
+#[derive(Debug, Deserialize, Default)]
+pub struct WorkspacesConfig {
+    /// Remove workspaces left behind by earlier runs once they are more than
+    /// this many days old.
+    #[serde(rename = "max-age")]
+    pub max_age: Option<u64>,
+}
+
+/// File issues on projects that discover repeatedly fails to release.
+#[derive(Debug, Deserialize, Default)]
+pub struct FailureIssuesConfig {
+    /// Number of consecutive failures with the same class of error after
+    /// which an issue is filed; defaults to 3.
+    pub threshold: Option<u32>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -28,6 +160,37 @@ pub struct RepositoriesConfig {
     pub owned: Option<Vec<url::Url>>,
 }
 
+#[derive(Debug, Deserialize, Default)]
+pub struct PrometheusConfig {
+    /// URL of the push gateway.
+    pub url: Option<String>,
+
+    /// Job label to push metrics under; defaults to "disperse".
+    pub job: Option<String>,
+
+    /// Instance label, so that multiple runners don't overwrite each other's metrics.
+    pub instance: Option<String>,
+
+    /// Additional grouping key labels. Metrics about a particular project
+    /// are grouped by its name as well, in the "project" label.
+    #[serde(default)]
+    pub grouping: BTreeMap<String, String>,
+
+    /// Username for basic authentication.
+    pub username: Option<String>,
+
+    /// Password for basic authentication.
+    pub password: Option<String>,
+
+    /// Token for bearer authentication.
+    #[serde(rename = "bearer-token")]
+    pub bearer_token: Option<String>,
+
+    /// Directory to write metrics to, for the node_exporter textfile collector.
+    #[serde(rename = "textfile-directory")]
+    pub textfile_directory: Option<std::path::PathBuf>,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct PypiConfig {
     pub username: String,
@@ -38,6 +201,12 @@ pub struct CratesIoConfig {
     pub username: String,
 }
 
+/// Load the configuration from a specific path.
+pub fn load_config_from(path: &Path) -> Result<Config, Box<dyn std::error::Error>> {
+    let contents = fs::read_to_string(path)?;
+    Ok(toml::from_str(&contents)?)
+}
+
 pub fn load_config() -> Result<Option<Config>, Box<dyn std::error::Error>> {
     let xdg = xdg::BaseDirectories::with_prefix("disperse")?;
 
@@ -48,9 +217,5 @@ pub fn load_config() -> Result<Option<Config>, Box<dyn std::error::Error>> {
         return Ok(None);
     }
 
-    // Read the file and parse the TOML
-    let contents = fs::read_to_string(config_file_path)?;
-    let config: Config = toml::from_str(&contents)?;
-
-    Ok(Some(config))
+    Ok(Some(load_config_from(&config_file_path)?))
 }
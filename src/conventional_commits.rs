@@ -0,0 +1,220 @@
+use crate::project_config::Bump;
+use lazy_regex::{regex_captures, regex_is_match};
+
+/// Order bumps by significance, so the largest one can be picked.
+fn significance(bump: Bump) -> u8 {
+    match bump {
+        Bump::Patch => 0,
+        Bump::Minor => 1,
+        Bump::Major => 2,
+    }
+}
+
+/// Work out which version component a Conventional Commits message calls for.
+///
+/// Breaking changes (a "!" after the type, or a BREAKING CHANGE footer) call
+/// for a major release, "feat" for a minor release and "fix" or "perf" for
+/// a patch release. Other types, and messages that don't follow the
+/// convention, don't call for a release.
+pub fn commit_bump(message: &str) -> Option<Bump> {
+    let header = message.lines().next().unwrap_or_default();
+    let (_, kind, breaking) = regex_captures!(r"^([A-Za-z]+)(?:\([^)]*\))?(!)?: ", header)?;
+    if !breaking.is_empty() || regex_is_match!(r"(?m)^BREAKING[ -]CHANGE: ", message) {
+        return Some(Bump::Major);
+    }
+    match kind.to_lowercase().as_str() {
+        "feat" => Some(Bump::Minor),
+        "fix" | "perf" => Some(Bump::Patch),
+        _ => None,
+    }
+}
+
+/// Return the Conventional Commits type of a message, in lower case.
+///
+/// Returns None for messages that don't follow the convention.
+pub fn commit_type(message: &str) -> Option<String> {
+    let header = message.lines().next().unwrap_or_default();
+    let (_, kind) = regex_captures!(r"^([A-Za-z]+)(?:\([^)]*\))?!?: ", header)?;
+    Some(kind.to_lowercase())
+}
+
+/// Section of the news that commits of a type are listed in.
+fn news_section(kind: &str) -> Option<&'static str> {
+    match kind.to_lowercase().as_str() {
+        "feat" => Some("Features"),
+        "fix" => Some("Bug fixes"),
+        "perf" => Some("Performance improvements"),
+        _ => None,
+    }
+}
+
+/// Generate news entries from commit messages, one per commit.
+///
+/// Only the first line of each message is used. With `conventional`,
+/// messages are grouped by their Conventional Commits type and those that
+/// are not worth mentioning in the news (e.g. "chore" or "docs") are left
+/// out. Returns the lines of the entries, with empty lines between groups.
+pub fn news_entries<'a>(
+    messages: impl IntoIterator<Item = &'a str>,
+    conventional: bool,
+) -> Vec<String> {
+    const BREAKING: &str = "Breaking changes";
+    let mut sections: Vec<(&str, Vec<String>)> = vec![];
+    for message in messages {
+        let header = message.lines().next().unwrap_or_default().trim();
+        if header.is_empty() {
+            continue;
+        }
+        let (section, summary) = if conventional {
+            let Some((_, kind, breaking, summary)) =
+                regex_captures!(r"^([A-Za-z]+)(?:\([^)]*\))?(!)?: (.*)$", header)
+            else {
+                continue;
+            };
+            let section =
+                if !breaking.is_empty() || regex_is_match!(r"(?m)^BREAKING[ -]CHANGE: ", message) {
+                    BREAKING
+                } else if let Some(section) = news_section(kind) {
+                    section
+                } else {
+                    continue;
+                };
+            (section, summary)
+        } else {
+            ("", header)
+        };
+        match sections.iter_mut().find(|(s, _)| *s == section) {
+            Some((_, entries)) => entries.push(summary.to_string()),
+            None => sections.push((section, vec![summary.to_string()])),
+        }
+    }
+    // Breaking changes go first.
+    sections.sort_by_key(|(s, _)| *s != BREAKING);
+    let mut lines = vec![];
+    for (section, entries) in sections {
+        if !lines.is_empty() {
+            lines.push(String::new());
+        }
+        if !section.is_empty() {
+            lines.push(format!("{}:", section));
+        }
+        lines.extend(entries.into_iter().map(|e| format!("* {}", e)));
+    }
+    lines
+}
+
+/// Work out which version component a set of commit messages calls for.
+///
+/// As is customary for 0.x versions, breaking changes only lead to a minor
+/// release if `major_zero` is set.
+pub fn bump_for_messages<'a>(
+    messages: impl IntoIterator<Item = &'a str>,
+    major_zero: bool,
+) -> Option<Bump> {
+    let bump = messages
+        .into_iter()
+        .filter_map(commit_bump)
+        .max_by_key(|b| significance(*b))?;
+    if major_zero && bump == Bump::Major {
+        Some(Bump::Minor)
+    } else {
+        Some(bump)
+    }
+}
+
+/// Work out which version component the commits since the last tag call for.
+pub fn bump_since_last_tag(
+    branch: &dyn breezyshim::branch::Branch,
+    major_zero: bool,
+) -> Result<Option<Bump>, Box<dyn std::error::Error>> {
+    let revisions = crate::revisions_since_last_tag(branch)?;
+    Ok(bump_for_messages(
+        revisions.iter().map(|r| r.message.as_str()),
+        major_zero,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_commit_bump() {
+        assert_eq!(commit_bump("feat: add frobnicator"), Some(Bump::Minor));
+        assert_eq!(commit_bump("feat(cli): add --frob"), Some(Bump::Minor));
+        assert_eq!(commit_bump("fix: don't crash"), Some(Bump::Patch));
+        assert_eq!(commit_bump("perf: go faster"), Some(Bump::Patch));
+        assert_eq!(commit_bump("feat!: drop Python 2"), Some(Bump::Major));
+        assert_eq!(
+            commit_bump("refactor: rework config\n\nBREAKING CHANGE: config moved"),
+            Some(Bump::Major)
+        );
+        assert_eq!(commit_bump("docs: fix typo"), None);
+        assert_eq!(commit_bump("Fix the frobnicator"), None);
+    }
+
+    #[test]
+    fn test_commit_type() {
+        assert_eq!(
+            commit_type("feat: add frobnicator"),
+            Some("feat".to_string())
+        );
+        assert_eq!(
+            commit_type("Fix(cli)!: drop --frob"),
+            Some("fix".to_string())
+        );
+        assert_eq!(commit_type("Fix the frobnicator"), None);
+    }
+
+    #[test]
+    fn test_news_entries() {
+        let messages = [
+            "fix: don't crash",
+            "chore: update dependencies",
+            "feat(cli): add --frob\n\nLonger description.",
+            "feat!: drop Python 2",
+            "Tidy up",
+        ];
+        assert_eq!(
+            news_entries(messages, false),
+            vec![
+                "* fix: don't crash",
+                "* chore: update dependencies",
+                "* feat(cli): add --frob",
+                "* feat!: drop Python 2",
+                "* Tidy up",
+            ]
+        );
+        assert_eq!(
+            news_entries(messages, true),
+            vec![
+                "Breaking changes:",
+                "* drop Python 2",
+                "",
+                "Bug fixes:",
+                "* don't crash",
+                "",
+                "Features:",
+                "* add --frob",
+            ]
+        );
+        assert!(news_entries(["chore: a"], true).is_empty());
+    }
+
+    #[test]
+    fn test_bump_for_messages() {
+        assert_eq!(
+            bump_for_messages(["fix: a", "feat: b", "docs: c"], false),
+            Some(Bump::Minor)
+        );
+        assert_eq!(
+            bump_for_messages(["fix: a", "feat!: b"], false),
+            Some(Bump::Major)
+        );
+        assert_eq!(
+            bump_for_messages(["fix: a", "feat!: b"], true),
+            Some(Bump::Minor)
+        );
+        assert_eq!(bump_for_messages(["chore: a"], false), None);
+    }
+}
@@ -0,0 +1,123 @@
+use breezyshim::branch::Branch;
+use lazy_regex::regex_captures;
+
+/// Extract the Co-authored-by trailers from a commit message.
+pub fn parse_co_authors(message: &str) -> Vec<String> {
+    message
+        .lines()
+        .filter_map(|l| regex_captures!(r"(?i)^\s*co-authored-by:\s*(.+?)\s*$", l))
+        .map(|(_, author)| author.to_string())
+        .collect()
+}
+
+/// Split "Jane Doe <jane@example.com>" into its name and email address.
+fn split_identity(identity: &str) -> (&str, Option<&str>) {
+    match regex_captures!(r"^(.*?)\s*<([^>]*)>\s*$", identity) {
+        Some((_, name, email)) => (name, Some(email)),
+        None => (identity.trim(), None),
+    }
+}
+
+/// Check whether an identity belongs to an automated account.
+pub fn is_bot(identity: &str) -> bool {
+    let (name, email) = split_identity(identity);
+    let name = name.to_lowercase();
+    name.ends_with("[bot]")
+        || email.is_some_and(|e| e.contains("[bot]"))
+        || ["dependabot", "renovate", "github-actions", "pre-commit-ci"]
+            .iter()
+            .any(|b| name.starts_with(b))
+}
+
+/// Turn a list of identities into a list of contributor names.
+///
+/// Bots are filtered out, and contributors are only listed once, in order
+/// of first appearance.
+pub fn contributor_names<'a>(identities: impl IntoIterator<Item = &'a str>) -> Vec<String> {
+    let mut ret: Vec<String> = vec![];
+    for identity in identities {
+        if is_bot(identity) {
+            continue;
+        }
+        let (name, email) = split_identity(identity);
+        let name = if name.is_empty() {
+            email.unwrap_or_default()
+        } else {
+            name
+        };
+        if !name.is_empty() && !ret.iter().any(|n| n.eq_ignore_ascii_case(name)) {
+            ret.push(name.to_string());
+        }
+    }
+    ret
+}
+
+/// Format a "Thanks to" paragraph for the release notes.
+pub fn thanks_paragraph(names: &[String]) -> Option<String> {
+    match names {
+        [] => None,
+        [name] => Some(format!("Thanks to {}.", name)),
+        [rest @ .., last] => Some(format!("Thanks to {} and {}.", rest.join(", "), last)),
+    }
+}
+
+/// Find the people who contributed to a branch since the last tagged revision.
+pub fn contributors_since_last_tag(
+    branch: &dyn Branch,
+) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    let mut identities = vec![];
+    for rev in crate::revisions_since_last_tag(branch)? {
+        identities.push(rev.committer.clone());
+        identities.extend(parse_co_authors(&rev.message));
+    }
+    // Oldest contributions first.
+    identities.reverse();
+    Ok(contributor_names(identities.iter().map(|i| i.as_str())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_co_authors() {
+        assert_eq!(
+            parse_co_authors(
+                "Fix the frobnicator\n\nCo-authored-by: Jane Doe <jane@example.com>\nco-authored-by: Joe <joe@example.com>\n"
+            ),
+            vec!["Jane Doe <jane@example.com>", "Joe <joe@example.com>"]
+        );
+        assert_eq!(
+            parse_co_authors("Fix the frobnicator"),
+            Vec::<String>::new()
+        );
+    }
+
+    #[test]
+    fn test_contributor_names() {
+        assert_eq!(
+            contributor_names([
+                "Jane Doe <jane@example.com>",
+                "dependabot[bot] <49699333+dependabot[bot]@users.noreply.github.com>",
+                "Joe <joe@example.com>",
+                "jane doe <jane@work.example.com>",
+                "<anonymous@example.com>",
+            ]),
+            vec!["Jane Doe", "Joe", "anonymous@example.com"]
+        );
+    }
+
+    #[test]
+    fn test_thanks_paragraph() {
+        let names = |n: &[&str]| n.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        assert_eq!(thanks_paragraph(&[]), None);
+        assert_eq!(
+            thanks_paragraph(&names(&["Jane"])),
+            Some("Thanks to Jane.".to_string())
+        );
+        assert_eq!(
+            thanks_paragraph(&names(&["Jane", "Joe", "Jim"])),
+            Some("Thanks to Jane, Joe and Jim.".to_string())
+        );
+    }
+}
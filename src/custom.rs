@@ -1,6 +1,6 @@
 use crate::{Status, Version};
 use maplit::hashmap;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
 fn status_tupled_version(v: &Version, s: Status) -> Option<String> {
     Some(format!(
@@ -58,21 +58,229 @@ lazy_static::lazy_static! {
     };
 }
 
+/// Variables for the release date, with their format and a pattern that
+/// matches any date.
+const DATE_VARIABLES: &[(&str, &str, &str)] = &[
+    ("DATE", "%Y-%m-%d", "[0-9]{4}-[0-9]{2}-[0-9]{2}"),
+    ("ISO_DATE", "%Y-%m-%d", "[0-9]{4}-[0-9]{2}-[0-9]{2}"),
+    ("YEAR", "%Y", "[0-9]{4}"),
+];
+
+fn date_variable(name: &str) -> Option<(&'static str, &'static str)> {
+    DATE_VARIABLES
+        .iter()
+        .find(|(n, _, _)| *n == name)
+        .map(|(_, format, pattern)| (*format, *pattern))
+}
+
+/// Values for the variables in update_version lines that don't come from
+/// the version.
+#[derive(Debug, Clone, Default)]
+pub struct VersionContext<'a> {
+    /// Variables defined in the project configuration.
+    pub custom: Option<&'a CustomVariables>,
+    /// Release date, for $DATE, $ISO_DATE and $YEAR.
+    pub date: Option<chrono::NaiveDate>,
+}
+
+/// Version variables defined in the project configuration, mapping names
+/// to format expressions such as "{major}_{minor}_{micro}".
+pub type CustomVariables = BTreeMap<String, String>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Radix {
+    Decimal,
+    LowerHex,
+    UpperHex,
+}
+
+/// A version component in a format expression, e.g. "{minor:02x}".
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Field {
+    component: usize,
+    width: usize,
+    zero_pad: bool,
+    radix: Radix,
+}
+
+impl Field {
+    fn format(&self, value: i32) -> String {
+        let digits = match self.radix {
+            Radix::Decimal => value.to_string(),
+            Radix::LowerHex => format!("{:x}", value),
+            Radix::UpperHex => format!("{:X}", value),
+        };
+        let pad = if self.zero_pad { "0" } else { " " };
+        format!(
+            "{}{}",
+            pad.repeat(self.width.saturating_sub(digits.len())),
+            digits
+        )
+    }
+
+    fn pattern(&self) -> String {
+        let class = match self.radix {
+            Radix::Decimal => "[0-9]",
+            Radix::LowerHex | Radix::UpperHex => "[0-9a-fA-F]",
+        };
+        if self.zero_pad && self.width > 0 {
+            // Fixed width, so that fields without separators can be told apart.
+            format!("({}{{{}}})", class, self.width)
+        } else {
+            format!(" *({}+)", class)
+        }
+    }
+
+    fn parse(&self, text: &str) -> Option<i32> {
+        let radix = match self.radix {
+            Radix::Decimal => 10,
+            Radix::LowerHex | Radix::UpperHex => 16,
+        };
+        i32::from_str_radix(text, radix).ok()
+    }
+}
+
+/// Split a format expression into literal text and version fields.
+fn parse_format(expr: &str) -> Result<Vec<Result<String, Field>>, String> {
+    let mut parts = vec![];
+    let mut rest = expr;
+    while let Some(start) = rest.find('{') {
+        let end = rest[start..]
+            .find('}')
+            .map(|e| start + e)
+            .ok_or_else(|| format!("unterminated field in {:?}", expr))?;
+        let (_, name, zero, width, radix) = lazy_regex::regex_captures!(
+            r"^\{(major|minor|micro)(?::(0?)([0-9]*)([xX]?))?\}$",
+            &rest[start..=end]
+        )
+        .ok_or_else(|| format!("invalid field {:?} in {:?}", &rest[start..=end], expr))?;
+        if start > 0 {
+            parts.push(Ok(rest[..start].to_string()));
+        }
+        parts.push(Err(Field {
+            component: match name {
+                "major" => 0,
+                "minor" => 1,
+                _ => 2,
+            },
+            width: width.parse().unwrap_or(0),
+            zero_pad: !zero.is_empty(),
+            radix: match radix {
+                "x" => Radix::LowerHex,
+                "X" => Radix::UpperHex,
+                _ => Radix::Decimal,
+            },
+        }));
+        rest = &rest[end + 1..];
+    }
+    if !rest.is_empty() {
+        parts.push(Ok(rest.to_string()));
+    }
+    Ok(parts)
+}
+
+/// Check that a custom version variable can be used.
+pub fn validate_custom_variable(name: &str, expr: &str) -> Result<(), String> {
+    if !lazy_regex::regex_is_match!(r"^[A-Z_]+$", name) {
+        return Err(format!(
+            "{} is not a valid variable name; use capital letters and underscores",
+            name
+        ));
+    }
+    if VERSION_VARIABLES.contains_key(name) || date_variable(name).is_some() {
+        return Err(format!("{} is a built-in variable", name));
+    }
+    parse_format(expr)?;
+    Ok(())
+}
+
+/// Expand a custom version variable; returns None if a component it uses
+/// is missing.
+fn format_custom(expr: &str, v: &Version) -> Option<String> {
+    let components = [Some(v.major()), v.minor(), v.micro()];
+    parse_format(expr)
+        .ok()?
+        .into_iter()
+        .map(|part| match part {
+            Ok(literal) => Some(literal),
+            Err(field) => components[field.component].map(|c| field.format(c)),
+        })
+        .collect()
+}
+
+/// Regular expression for the text a custom version variable expands to.
+fn custom_pattern(expr: &str) -> Option<(String, Vec<Field>)> {
+    let mut pattern = String::new();
+    let mut fields = vec![];
+    for part in parse_format(expr).ok()? {
+        match part {
+            Ok(literal) => pattern.push_str(&regex::escape(&literal)),
+            Err(field) => {
+                pattern.push_str(&field.pattern());
+                fields.push(field);
+            }
+        }
+    }
+    Some((pattern, fields))
+}
+
+/// Read the version components back from the expansion of a custom variable.
+fn parse_custom(expr: &str, text: &str) -> Option<[Option<i32>; 3]> {
+    let (pattern, fields) = custom_pattern(expr)?;
+    let captures = regex::Regex::new(&format!("^{}$", pattern))
+        .ok()?
+        .captures(text)?;
+    let mut components = [None; 3];
+    for (field, m) in fields.iter().zip(captures.iter().skip(1)) {
+        components[field.component] = Some(field.parse(m?.as_str())?);
+    }
+    Some(components)
+}
+
+/// Check whether an update_version line records the release status, and
+/// can therefore mark a version as being in development.
+pub fn has_status(new_line: &str) -> bool {
+    new_line.contains("$STATUS_TUPLED_VERSION")
+}
+
 pub fn expand_version_vars(
     text: &str,
     new_version: &Version,
     status: Status,
 ) -> Result<String, String> {
-    let mut text = text.to_owned();
-    for (k, vfn) in VERSION_VARIABLES.iter() {
-        let var = format!("${}", k);
-        if let Some(v) = vfn(new_version, status) {
-            text = text.replace(var.as_str(), v.as_str());
-        } else if text.contains(&var) {
-            return Err(format!("no expansion for variable ${} used in {}", k, text));
-        }
+    expand_version_vars_with(text, new_version, status, &VersionContext::default())
+}
+
+/// Expand built-in, date and custom version variables in `text`.
+pub fn expand_version_vars_with(
+    text: &str,
+    new_version: &Version,
+    status: Status,
+    context: &VersionContext,
+) -> Result<String, String> {
+    let mut missing = None;
+    let expanded = lazy_regex::regex_replace_all!(r"\$([A-Z_]+)", text, |var: &str, name: &str| {
+        let value = if let Some(vfn) = VERSION_VARIABLES.get(name) {
+            vfn(new_version, status)
+        } else if let Some((format, _)) = date_variable(name) {
+            context.date.map(|d| d.format(format).to_string())
+        } else if let Some(expr) = context.custom.and_then(|c| c.get(name)) {
+            format_custom(expr, new_version)
+        } else {
+            return var.to_string();
+        };
+        value.unwrap_or_else(|| {
+            missing.get_or_insert_with(|| name.to_string());
+            String::new()
+        })
+    });
+    match missing {
+        Some(name) => Err(format!(
+            "no expansion for variable ${} used in {}",
+            name, text
+        )),
+        None => Ok(expanded.into_owned()),
     }
-    Ok(text)
 }
 
 #[cfg(test)]
@@ -98,9 +306,68 @@ mod expand_version_vars_tests {
         let expanded = expand_version_vars(text, &new_version, status).unwrap();
         assert_eq!(expanded, "version = (1, 2, 3, \"dev\", 0)");
     }
+
+    #[test]
+    fn test_custom() {
+        let custom = maplit::btreemap! {
+            "VERSION_UNDERSCORED".to_string() => "{major}_{minor}_{micro}".to_string(),
+            "HEX_VERSION".to_string() => "0x{major:02x}{minor:02x}{micro:02X}".to_string(),
+        };
+        let new_version = Version::from_str("1.2.13").unwrap();
+        assert_eq!(
+            super::expand_version_vars_with(
+                "v$VERSION_UNDERSCORED $HEX_VERSION $VERSION $UNKNOWN",
+                &new_version,
+                Status::Final,
+                &super::VersionContext {
+                    custom: Some(&custom),
+                    date: None,
+                }
+            )
+            .unwrap(),
+            "v1_2_13 0x01020D 1.2.13 $UNKNOWN"
+        );
+        assert!(super::expand_version_vars_with(
+            "$VERSION_UNDERSCORED",
+            &Version::from_str("1").unwrap(),
+            Status::Final,
+            &super::VersionContext {
+                custom: Some(&custom),
+                date: None,
+            }
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_has_status() {
+        assert!(super::has_status("version_info = $STATUS_TUPLED_VERSION"));
+        assert!(!super::has_status("version_info = $TUPLED_VERSION"));
+    }
+
+    #[test]
+    fn test_date() {
+        let text = "__release__ = \"$VERSION ($DATE)\"  # $YEAR";
+        let new_version = Version::from_str("1.2.3").unwrap();
+        let context = super::VersionContext {
+            custom: None,
+            date: chrono::NaiveDate::from_ymd_opt(2024, 1, 2),
+        };
+        assert_eq!(
+            super::expand_version_vars_with(text, &new_version, Status::Final, &context).unwrap(),
+            "__release__ = \"1.2.3 (2024-01-02)\"  # 2024"
+        );
+        assert!(expand_version_vars(text, &new_version, Status::Final).is_err());
+    }
 }
 
 pub fn version_line_re(new_line: &str) -> regex::Regex {
+    version_line_re_with(new_line, &CustomVariables::new())
+}
+
+/// Build a regular expression that matches `new_line` with any version,
+/// taking custom version variables into account.
+pub fn version_line_re_with(new_line: &str, custom: &CustomVariables) -> regex::Regex {
     regex::Regex::new(
         lazy_regex::regex_replace_all!(
             r"\\\$([A-Z_]+)",
@@ -108,6 +375,10 @@ pub fn version_line_re(new_line: &str) -> regex::Regex {
             |_, var: &str| {
                 if VERSION_VARIABLES.contains_key(var) {
                     format!("(?P<{}>.*)", var.to_lowercase())
+                } else if let Some((_, pattern)) = date_variable(var) {
+                    format!("(?:{})", pattern)
+                } else if let Some((pattern, _)) = custom.get(var).and_then(|e| custom_pattern(e)) {
+                    format!("(?P<custom_{}>{})", var.to_lowercase(), pattern)
                 } else {
                     format!("\\${}", var)
                 }
@@ -139,9 +410,46 @@ mod version_line_re_tests {
         assert_eq!(v, Some(super::Version::from_str("1.2.3").unwrap()));
         assert_eq!(s, Some(super::Status::Dev));
     }
+
+    #[test]
+    fn test_custom() {
+        let custom = maplit::btreemap! {
+            "HEX_VERSION".to_string() => "0x{major:02x}{minor:02x}{micro:02x}".to_string(),
+        };
+        let re = super::version_line_re_with("#define FOO_VERSION $HEX_VERSION", &custom);
+        let cm = re.captures_iter("#define FOO_VERSION 0x01020d");
+        let (v, s) = super::version_from_capture_matches_with(cm, &custom);
+        assert_eq!(v, Some(super::Version::from_str("1.2.13").unwrap()));
+        assert_eq!(s, None);
+    }
+
+    #[test]
+    fn test_date() {
+        let re = super::version_line_re("__release__ = \"$VERSION ($DATE)\"");
+        assert!(!re.is_match("__release__ = \"1.2.3 (soon)\""));
+        let cm = re.captures_iter("__release__ = \"1.2.3 (2024-01-02)\"");
+        let (v, _) = super::version_from_capture_matches(cm);
+        assert_eq!(v, Some(super::Version::from_str("1.2.3").unwrap()));
+    }
+
+    #[test]
+    fn test_validate_custom_variable() {
+        assert!(super::validate_custom_variable("UNDERSCORED", "{major}_{minor}").is_ok());
+        assert!(super::validate_custom_variable("VERSION", "{major}").is_err());
+        assert!(super::validate_custom_variable("lower", "{major}").is_err());
+        assert!(super::validate_custom_variable("PATCH", "{patch}").is_err());
+        assert!(super::validate_custom_variable("OPEN", "{major").is_err());
+    }
 }
 
 fn version_from_capture_matches(cm: regex::CaptureMatches) -> (Option<Version>, Option<Status>) {
+    version_from_capture_matches_with(cm, &CustomVariables::new())
+}
+
+fn version_from_capture_matches_with(
+    cm: regex::CaptureMatches,
+    custom: &CustomVariables,
+) -> (Option<Version>, Option<Status>) {
     let mut major = None;
     let mut minor = None;
     let mut micro = None;
@@ -176,6 +484,16 @@ fn version_from_capture_matches(cm: regex::CaptureMatches) -> (Option<Version>,
                 status = Some(new_status);
             }
         }
+        for (name, expr) in custom {
+            let Some(v) = c.name(&format!("custom_{}", name.to_lowercase())) else {
+                continue;
+            };
+            if let Some([ma, mi, mc]) = parse_custom(expr, v.as_str()) {
+                major = ma.or(major);
+                minor = mi.or(minor);
+                micro = mc.or(micro);
+            }
+        }
     }
 
     if let Some(major) = major {
@@ -184,6 +502,11 @@ fn version_from_capture_matches(cm: regex::CaptureMatches) -> (Option<Version>,
                 major,
                 minor,
                 micro,
+                pre: None,
+                build: None,
+                epoch: None,
+                post: None,
+                dev: None,
             }),
             status,
         )
@@ -199,11 +522,15 @@ pub fn extract_version(line: &str) -> (Option<Version>, Option<Status>) {
     version_from_capture_matches(re.captures_iter(line))
 }
 
-pub fn reverse_version(new_line: &str, lines: &[&str]) -> (Option<Version>, Option<Status>) {
-    let re = version_line_re(new_line);
+pub fn reverse_version(
+    new_line: &str,
+    lines: &[&str],
+    custom: &CustomVariables,
+) -> (Option<Version>, Option<Status>) {
+    let re = version_line_re_with(new_line, custom);
     for line in lines {
         let cm = re.captures_iter(line);
-        let (v, s) = version_from_capture_matches(cm);
+        let (v, s) = version_from_capture_matches_with(cm, custom);
         if v.is_some() {
             return (v, s);
         }
@@ -220,6 +547,7 @@ mod reverse_version_tests {
         let (v, s) = super::reverse_version(
             "version = $VERSION",
             &["version = 1.2.3", "version = 1.2.4"],
+            &super::CustomVariables::new(),
         );
         assert_eq!(v, Some(super::Version::from_str("1.2.3").unwrap()));
         assert_eq!(s, None);
@@ -233,6 +561,7 @@ mod reverse_version_tests {
                 "version = (1, 2, 3, \"dev\", 0)",
                 "version = (1, 2, 3, \"final\", 0)",
             ],
+            &super::CustomVariables::new(),
         );
         assert_eq!(v, Some(super::Version::from_str("1.2.3").unwrap()));
         assert_eq!(s, Some(super::Status::Dev));
@@ -246,13 +575,14 @@ pub fn update_version_in_file(
     r#match: Option<&str>,
     new_version: &Version,
     status: Status,
+    context: &VersionContext,
 ) -> Result<(), String> {
     let mut lines = tree.get_file_lines(path).unwrap();
     let mut matches = 0;
     let r = if let Some(m) = r#match {
         regex::Regex::new(m).unwrap()
     } else {
-        version_line_re(new_line)
+        version_line_re_with(new_line, context.custom.unwrap_or(&CustomVariables::new()))
     };
     log::debug!("Expanding {:?} in {:?}", r, path);
     for oline in lines.iter_mut() {
@@ -263,7 +593,7 @@ pub fn update_version_in_file(
         if !r.is_match(line) {
             continue;
         }
-        let uline = expand_version_vars(new_line, new_version, status).unwrap();
+        let uline = expand_version_vars_with(new_line, new_version, status, context)?;
         let uline = format!("{}\n", uline);
         log::debug!("Expanded {:?} to {:?}", new_line, uline);
         *oline = uline.into_bytes();
@@ -305,8 +635,14 @@ mod tests {
                 major: 1,
                 minor: Some(2),
                 micro: Some(4),
+                pre: None,
+                build: None,
+                epoch: None,
+                post: None,
+                dev: None,
             },
             super::Status::Final,
+            &super::VersionContext::default(),
         )
         .unwrap();
         assert_eq!(tree.get_file_text(path).unwrap(), b"version = [1.2.4]\n");
@@ -316,6 +652,7 @@ mod tests {
 pub fn validate_update_version(
     wt: &dyn breezyshim::tree::Tree,
     update_version: &crate::project_config::UpdateVersion,
+    custom: &CustomVariables,
 ) -> Result<(), String> {
     let path = &update_version.path;
 
@@ -332,7 +669,7 @@ pub fn validate_update_version(
     let r = if let Some(m) = &update_version.r#match {
         regex::Regex::new(m).unwrap()
     } else {
-        version_line_re(new_line)
+        version_line_re_with(new_line, custom)
     };
     log::debug!("Expanding {:?} in {:?}", r, update_version.path);
     for oline in lines.iter_mut() {
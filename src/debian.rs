@@ -0,0 +1,530 @@
+use crate::Version;
+use breezyshim::tree::{MutableTree, Tree, WorkingTree};
+use chrono::{DateTime, FixedOffset};
+use debian_changelog::ChangeLog;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Distribution used for new entries if the changelog doesn't specify one.
+pub const DEFAULT_DISTRIBUTION: &str = "unstable";
+
+#[derive(Debug)]
+pub enum Error {
+    BrzError(breezyshim::error::Error),
+    ParseError(debian_changelog::ParseError),
+    VersionError(String),
+    NoEntries,
+    CommandFailed(String),
+    Other(String),
+}
+
+impl From<breezyshim::error::Error> for Error {
+    fn from(e: breezyshim::error::Error) -> Self {
+        Error::BrzError(e)
+    }
+}
+
+impl From<debian_changelog::ParseError> for Error {
+    fn from(e: debian_changelog::ParseError) -> Self {
+        Error::ParseError(e)
+    }
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match &self {
+            Error::BrzError(e) => write!(f, "TreeError: {}", e),
+            Error::ParseError(e) => write!(f, "Unable to parse debian/changelog: {}", e),
+            Error::VersionError(e) => write!(f, "VersionError: {}", e),
+            Error::NoEntries => write!(f, "debian/changelog has no entries"),
+            Error::CommandFailed(e) => write!(f, "Command failed: {}", e),
+            Error::Other(e) => write!(f, "Other: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// Determine the Debian version for a new upstream release.
+///
+/// The epoch is carried over from the previous version. Native packages
+/// (without a Debian revision) stay native; others get a "-1" revision.
+pub fn debian_version(
+    new_version: &Version,
+    previous: Option<&debversion::Version>,
+) -> debversion::Version {
+    debversion::Version {
+        epoch: previous.and_then(|p| p.epoch),
+        upstream_version: new_version.to_string(),
+        debian_revision: match previous {
+            Some(p) if p.debian_revision.is_none() => None,
+            _ => Some("1".to_string()),
+        },
+    }
+}
+
+/// Convert release notes to changelog lines.
+fn changelog_lines(notes: Option<&str>, native: bool) -> Vec<String> {
+    let mut ret = vec![];
+    for line in notes.unwrap_or("").lines() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        if let Some(item) = line.strip_prefix("* ").or_else(|| line.strip_prefix("- ")) {
+            ret.push(format!("* {}", item));
+        } else if ret.is_empty() {
+            ret.push(format!("* {}", line));
+        } else {
+            ret.push(format!("  {}", line));
+        }
+    }
+    if ret.is_empty() {
+        ret.push(if native {
+            "* New release.".to_string()
+        } else {
+            "* New upstream release.".to_string()
+        });
+    }
+    ret
+}
+
+/// Add a changelog entry for a new release.
+///
+/// If the topmost entry is still marked as UNRELEASED, it is finalized for
+/// the new version rather than adding another entry.
+///
+/// Returns the Debian version that was used.
+pub fn add_changelog_entry(
+    changelog: &mut ChangeLog,
+    new_version: &Version,
+    distribution: Option<&str>,
+    notes: Option<&str>,
+    maintainer: Option<(String, String)>,
+    datetime: DateTime<FixedOffset>,
+) -> Result<debversion::Version, Error> {
+    let entries = changelog.iter().collect::<Vec<_>>();
+    let mut entries = entries.into_iter();
+    let first = entries.next().ok_or(Error::NoEntries)?;
+
+    if first.is_unreleased() == Some(true) {
+        let last_released = entries.find(|e| e.is_unreleased() == Some(false));
+        let distribution = distribution
+            .map(|d| d.to_string())
+            .or_else(|| {
+                last_released
+                    .as_ref()
+                    .and_then(|e| e.distributions())
+                    .and_then(|ds| ds.into_iter().next())
+            })
+            .unwrap_or_else(|| DEFAULT_DISTRIBUTION.to_string());
+        let version = debian_version(
+            new_version,
+            last_released
+                .as_ref()
+                .and_then(|e| e.version())
+                .or_else(|| first.version())
+                .as_ref(),
+        );
+        let mut first = first;
+        first.set_version(&version);
+        first.set_distributions(vec![distribution]);
+        first.set_datetime(datetime);
+        if let Some(maintainer) = maintainer {
+            first.set_maintainer(maintainer);
+        }
+        return Ok(version);
+    }
+
+    let previous = first.version();
+    let package = first
+        .package()
+        .ok_or_else(|| Error::Other("No package name in debian/changelog".to_string()))?;
+    let distribution = distribution
+        .map(|d| d.to_string())
+        .or_else(|| first.distributions().and_then(|ds| ds.into_iter().next()))
+        .unwrap_or_else(|| DEFAULT_DISTRIBUTION.to_string());
+    let maintainer = maintainer
+        .or_else(debian_changelog::get_maintainer)
+        .ok_or_else(|| {
+            Error::Other("Unable to determine maintainer; set DEBFULLNAME and DEBEMAIL".to_string())
+        })?;
+    let version = debian_version(new_version, previous.as_ref());
+    let native = version.debian_revision.is_none();
+
+    let mut builder = changelog
+        .new_empty_entry()
+        .package(package)
+        .version(version.clone())
+        .distribution(distribution)
+        .urgency(debian_changelog::Urgency::default())
+        .maintainer(maintainer)
+        .datetime(datetime);
+    for line in changelog_lines(notes, native) {
+        builder = builder.change_line(line);
+    }
+    builder
+        .verify()
+        .map_err(|e| Error::Other(format!("Invalid changelog entry: {}", e)))?;
+    builder.finish();
+
+    Ok(version)
+}
+
+/// Whether a news file is a Debian changelog rather than a free-form news file.
+pub fn is_changelog_path(path: &Path) -> bool {
+    path.ends_with("debian/changelog")
+}
+
+/// Whether a change line is the empty placeholder of a new entry.
+fn is_placeholder_line(line: &str) -> bool {
+    matches!(line.trim(), "" | "*")
+}
+
+/// Return the first entry of a changelog, if it has not been released yet.
+fn pending_entry(changelog: &ChangeLog) -> Option<debian_changelog::Entry> {
+    changelog
+        .iter()
+        .next()
+        .filter(|e| e.is_unreleased() == Some(true))
+}
+
+/// Upstream version of the unreleased entry at the top of a changelog, if any.
+pub fn pending_version(changelog: &ChangeLog) -> Option<String> {
+    pending_entry(changelog)?
+        .version()
+        .map(|v| v.upstream_version)
+}
+
+/// Changes listed in the unreleased entry at the top of a changelog, if any.
+pub fn pending_changes(changelog: &ChangeLog) -> Option<String> {
+    Some(
+        pending_entry(changelog)?
+            .change_lines()
+            .filter(|l| !is_placeholder_line(l))
+            .map(|l| format!("{}\n", l))
+            .collect(),
+    )
+}
+
+/// Changes listed in the released entry for an upstream version, if there is one.
+pub fn released_changes(changelog: &ChangeLog, version: &str) -> Option<String> {
+    let entry = changelog.iter().find(|e| {
+        e.is_unreleased() != Some(true)
+            && e.version().is_some_and(|v| v.upstream_version == version)
+    })?;
+    Some(
+        entry
+            .change_lines()
+            .filter(|l| !is_placeholder_line(l))
+            .map(|l| format!("{}\n", l))
+            .collect(),
+    )
+}
+
+/// Fill the unreleased entry at the top of a changelog with changes, if it
+/// doesn't list any yet.
+///
+/// Returns whether the entry was filled.
+pub fn fill_pending_entry(changelog: &ChangeLog, changes: &[String]) -> Result<bool, Error> {
+    let entry = pending_entry(changelog).ok_or(Error::NoEntries)?;
+    if entry.change_lines().any(|l| !is_placeholder_line(&l)) {
+        return Ok(false);
+    }
+    let placeholders = entry
+        .change_lines()
+        .filter(|l| !l.trim().is_empty())
+        .count();
+    let native = entry.version().is_some_and(|v| v.debian_revision.is_none());
+    for line in changelog_lines(Some(&changes.join("\n")), native)
+        .iter()
+        .rev()
+    {
+        entry.prepend_change_line(line);
+    }
+    for _ in 0..placeholders {
+        entry.pop_change_line();
+    }
+    Ok(true)
+}
+
+/// Add an unreleased entry for the next version to the top of a changelog.
+pub fn add_pending_entry(
+    changelog: &mut ChangeLog,
+    new_version: &Version,
+    maintainer: Option<(String, String)>,
+    datetime: DateTime<FixedOffset>,
+) -> Result<debversion::Version, Error> {
+    let first = changelog.iter().next().ok_or(Error::NoEntries)?;
+    if first.is_unreleased() == Some(true) {
+        return Err(Error::Other(format!(
+            "debian/changelog already has an unreleased entry for {}",
+            first.version().map(|v| v.to_string()).unwrap_or_default()
+        )));
+    }
+    let package = first
+        .package()
+        .ok_or_else(|| Error::Other("No package name in debian/changelog".to_string()))?;
+    let version = debian_version(new_version, first.version().as_ref());
+    let maintainer = maintainer
+        .or_else(debian_changelog::get_maintainer)
+        .ok_or_else(|| {
+            Error::Other("Unable to determine maintainer; set DEBFULLNAME and DEBEMAIL".to_string())
+        })?;
+    changelog
+        .new_empty_entry()
+        .package(package)
+        .version(version.clone())
+        .distribution("UNRELEASED".to_string())
+        .urgency(debian_changelog::Urgency::default())
+        .maintainer(maintainer)
+        .datetime(datetime)
+        .change_line("* ".to_string())
+        .finish();
+    Ok(version)
+}
+
+/// Add an entry for a new release to debian/changelog in a tree.
+pub fn update_changelog(
+    tree: &WorkingTree,
+    new_version: &Version,
+    distribution: Option<&str>,
+    notes: Option<&str>,
+) -> Result<debversion::Version, Error> {
+    let path = Path::new("debian/changelog");
+    let text = tree.get_file_text(path)?;
+    let mut changelog: ChangeLog = String::from_utf8_lossy(&text).parse()?;
+    let version = add_changelog_entry(
+        &mut changelog,
+        new_version,
+        distribution,
+        notes,
+        debian_changelog::get_maintainer(),
+        chrono::Utc::now().into(),
+    )?;
+    tree.put_file_bytes_non_atomic(path, changelog.to_string().as_bytes())?;
+    Ok(version)
+}
+
+/// Build a source package, returning the path to the resulting .changes file.
+///
+/// Like dpkg-buildpackage itself, this writes its output to the parent directory of the tree.
+pub fn build_source_package(tree: &WorkingTree) -> Result<PathBuf, Error> {
+    let text = tree.get_file_text(Path::new("debian/changelog"))?;
+    let changelog: ChangeLog = String::from_utf8_lossy(&text).parse()?;
+    let first = changelog.iter().next().ok_or(Error::NoEntries)?;
+    let package = first
+        .package()
+        .ok_or_else(|| Error::Other("No package name in debian/changelog".to_string()))?;
+    let version = first
+        .version()
+        .ok_or_else(|| Error::VersionError("No version in debian/changelog".to_string()))?;
+
+    let basedir = tree.abspath(Path::new("."))?;
+    let status = Command::new("dpkg-buildpackage")
+        .arg("-S")
+        .arg("-d")
+        .current_dir(&basedir)
+        .status()
+        .map_err(|e| Error::Other(format!("Unable to spawn dpkg-buildpackage: {}", e)))?;
+    if !status.success() {
+        return Err(Error::CommandFailed(format!(
+            "dpkg-buildpackage -S -d: {}",
+            status
+        )));
+    }
+
+    // The .changes filename never includes the epoch.
+    let version = debversion::Version {
+        epoch: None,
+        ..version
+    };
+    let parent = basedir
+        .parent()
+        .ok_or_else(|| Error::Other("Tree has no parent directory".to_string()))?;
+    Ok(parent.join(format!("{}_{}_source.changes", package, version)))
+}
+
+/// Upload a source package to an archive using dput.
+pub fn upload(changes: &Path, target: &str) -> Result<(), Error> {
+    let status = Command::new("dput")
+        .arg(target)
+        .arg(changes)
+        .status()
+        .map_err(|e| Error::Other(format!("Unable to spawn dput: {}", e)))?;
+    if !status.success() {
+        return Err(Error::CommandFailed(format!(
+            "dput {} {}: {}",
+            target,
+            changes.display(),
+            status
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn datetime() -> DateTime<FixedOffset> {
+        DateTime::parse_from_rfc3339("2024-01-02T03:04:05+00:00").unwrap()
+    }
+
+    fn maintainer() -> Option<(String, String)> {
+        Some(("Jane Doe".to_string(), "jane@example.com".to_string()))
+    }
+
+    #[test]
+    fn test_debian_version() {
+        let v: Version = "1.2.3".parse().unwrap();
+        assert_eq!(debian_version(&v, None).to_string(), "1.2.3-1");
+        assert_eq!(
+            debian_version(&v, Some(&"1:1.2.2-3".parse().unwrap())).to_string(),
+            "1:1.2.3-1"
+        );
+        assert_eq!(
+            debian_version(&v, Some(&"1.2.2".parse().unwrap())).to_string(),
+            "1.2.3"
+        );
+    }
+
+    #[test]
+    fn test_add_changelog_entry() {
+        let mut changelog: ChangeLog = r#"foo (0.1-1) unstable; urgency=medium
+
+  * Initial release.
+
+ -- Jane Doe <jane@example.com>  Mon, 01 Jan 2024 00:00:00 +0000
+"#
+        .parse()
+        .unwrap();
+        let version = add_changelog_entry(
+            &mut changelog,
+            &"0.2".parse().unwrap(),
+            None,
+            Some("* Fix a bug.\n* Add a feature,\n  with a long description.\n"),
+            maintainer(),
+            datetime(),
+        )
+        .unwrap();
+        assert_eq!(version.to_string(), "0.2-1");
+        assert_eq!(
+            changelog.to_string(),
+            r#"foo (0.2-1) unstable; urgency=low
+
+  * Fix a bug.
+  * Add a feature,
+    with a long description.
+
+ -- Jane Doe <jane@example.com>  Tue, 02 Jan 2024 03:04:05 +0000
+
+foo (0.1-1) unstable; urgency=medium
+
+  * Initial release.
+
+ -- Jane Doe <jane@example.com>  Mon, 01 Jan 2024 00:00:00 +0000
+"#
+        );
+    }
+
+    #[test]
+    fn test_pending_entry() {
+        let mut changelog: ChangeLog = r#"foo (0.1) unstable; urgency=medium
+
+  * Initial release.
+
+ -- Jane Doe <jane@example.com>  Mon, 01 Jan 2024 00:00:00 +0000
+"#
+        .parse()
+        .unwrap();
+        assert_eq!(pending_version(&changelog), None);
+        assert_eq!(pending_changes(&changelog), None);
+        let version = add_pending_entry(
+            &mut changelog,
+            &"0.2".parse().unwrap(),
+            maintainer(),
+            datetime(),
+        )
+        .unwrap();
+        assert_eq!(version.to_string(), "0.2");
+        assert_eq!(pending_version(&changelog), Some("0.2".to_string()));
+        assert_eq!(pending_changes(&changelog), Some("".to_string()));
+        assert!(add_pending_entry(
+            &mut changelog,
+            &"0.3".parse().unwrap(),
+            maintainer(),
+            datetime()
+        )
+        .is_err());
+        assert!(fill_pending_entry(
+            &changelog,
+            &["* Fix a bug.".to_string(), "* Add a feature.".to_string()]
+        )
+        .unwrap());
+        assert!(!fill_pending_entry(&changelog, &["* Other.".to_string()]).unwrap());
+        assert_eq!(
+            changelog.to_string(),
+            r#"foo (0.2) UNRELEASED; urgency=low
+
+  * Fix a bug.
+  * Add a feature.
+
+ -- Jane Doe <jane@example.com>  Tue, 02 Jan 2024 03:04:05 +0000
+
+foo (0.1) unstable; urgency=medium
+
+  * Initial release.
+
+ -- Jane Doe <jane@example.com>  Mon, 01 Jan 2024 00:00:00 +0000
+"#
+        );
+        assert_eq!(
+            pending_changes(&changelog),
+            Some("* Fix a bug.\n* Add a feature.\n".to_string())
+        );
+        assert_eq!(
+            released_changes(&changelog, "0.1"),
+            Some("* Initial release.\n".to_string())
+        );
+        assert_eq!(released_changes(&changelog, "0.2"), None);
+    }
+
+    #[test]
+    fn test_add_changelog_entry_unreleased() {
+        let mut changelog: ChangeLog = r#"foo (0.2) UNRELEASED; urgency=medium
+
+  * Fix a bug.
+
+ -- Jane Doe <jane@example.com>  Mon, 01 Jan 2024 00:00:00 +0000
+
+foo (0.1) experimental; urgency=medium
+
+  * Initial release.
+
+ -- Jane Doe <jane@example.com>  Mon, 01 Jan 2024 00:00:00 +0000
+"#
+        .parse()
+        .unwrap();
+        let version = add_changelog_entry(
+            &mut changelog,
+            &"0.2.0".parse().unwrap(),
+            None,
+            None,
+            maintainer(),
+            datetime(),
+        )
+        .unwrap();
+        assert_eq!(version.to_string(), "0.2.0");
+        let first = changelog.iter().next().unwrap();
+        assert_eq!(first.version(), Some("0.2.0".parse().unwrap()));
+        assert_eq!(
+            first.distributions(),
+            Some(vec!["experimental".to_string()])
+        );
+        assert_eq!(
+            first.change_lines().collect::<Vec<_>>(),
+            vec!["* Fix a bug.".to_string()]
+        );
+    }
+}
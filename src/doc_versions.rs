@@ -0,0 +1,247 @@
+use crate::Version;
+use breezyshim::tree::{MutableTree, Tree, WorkingTree};
+use std::path::Path;
+
+/// Version as it appears in install instructions, e.g. "1.2.3", "1.2" or "1.2.3rc1".
+const VERSION: &str = r"[0-9]+(?:\.[0-9]+)*(?:[.+]?[A-Za-z]+[0-9]*)*";
+
+#[derive(Debug)]
+pub enum Error {
+    BrzError(breezyshim::error::Error),
+    InvalidData(String),
+    NoOccurrences(String),
+}
+
+impl From<breezyshim::error::Error> for Error {
+    fn from(e: breezyshim::error::Error) -> Self {
+        Error::BrzError(e)
+    }
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match &self {
+            Error::BrzError(e) => write!(f, "TreeError: {}", e),
+            Error::InvalidData(e) => write!(f, "Invalid data: {}", e),
+            Error::NoOccurrences(name) => {
+                write!(f, "No install instructions or badges for {} found", name)
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// Patterns for the places in documentation that mention the version of a
+/// package, each with a "version" group.
+///
+/// The patterns are anchored on the package name, so that versions of
+/// other packages and unrelated numbers are left alone.
+fn patterns(name: &str) -> Vec<regex::Regex> {
+    let name = regex::escape(name);
+    [
+        // pip install foo==1.2.3, pip install "foo[extra]==1.2.3"
+        format!(
+            r#"\bpip3? install\s+(?:-U\s+|--upgrade\s+)?["']?{}(?:\[[^\]]*\])?==(?P<version>{})"#,
+            name, VERSION
+        ),
+        // foo = "1.2" in Cargo.toml or pyproject.toml snippets
+        format!(
+            r#"(?m)^\s*{}\s*=\s*"[=^~]?(?P<version>{}(?:-[0-9A-Za-z.]+)?)""#,
+            name, VERSION
+        ),
+        // foo = { version = "1.2", features = [...] }
+        format!(
+            r#"(?m)^\s*{}\s*=\s*\{{[^}}\n]*\bversion\s*=\s*"[=^~]?(?P<version>{}(?:-[0-9A-Za-z.]+)?)""#,
+            name, VERSION
+        ),
+        // cargo add foo@1.2.3, npm install foo@1.2.3
+        format!(
+            r"\b(?:cargo add|cargo install|npm install|npm i|yarn add|pnpm add)\s+{}@(?P<version>{})",
+            name, VERSION
+        ),
+        // https://img.shields.io/badge/foo-1.2.3-blue
+        format!(
+            r"\bimg\.shields\.io/badge/(?:version|release|{})-v?(?P<version>{})-",
+            name, VERSION
+        ),
+    ]
+    .iter()
+    .map(|p| regex::Regex::new(p).unwrap())
+    .collect()
+}
+
+/// Find the versions of a package mentioned in a document.
+///
+/// Returns the byte ranges and the text of each mention, in order.
+pub fn find_versions(text: &str, name: &str) -> Vec<(std::ops::Range<usize>, String)> {
+    let mut ret = patterns(name)
+        .iter()
+        .flat_map(|re| {
+            re.captures_iter(text)
+                .filter_map(|c| c.name("version"))
+                .map(|m| (m.range(), m.as_str().to_string()))
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+    ret.sort_by_key(|(range, _)| range.start);
+    ret.dedup_by_key(|(range, _)| range.start);
+    ret
+}
+
+/// Format a new version with the same precision as an old one.
+///
+/// Install instructions often only pin the major and minor version, e.g.
+/// `foo = "1.2"`, which should become "1.3" rather than "1.3.0".
+pub fn with_precision_of(new_version: &Version, old: &str) -> String {
+    let full = new_version.to_string();
+    if !old.chars().all(|c| c.is_ascii_digit() || c == '.')
+        || full.contains(|c: char| !c.is_ascii_digit() && c != '.')
+    {
+        return full;
+    }
+    let components = old.split('.').count();
+    full.split('.')
+        .take(components)
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+/// Check whether a version mentioned in a document refers to `version`.
+pub fn documents_version(documented: &str, version: &Version) -> bool {
+    documented == with_precision_of(version, documented)
+}
+
+/// Replace the versions of a package mentioned in a document.
+pub fn update_versions(text: &str, name: &str, new_version: &Version) -> Result<String, Error> {
+    let occurrences = find_versions(text, name);
+    if occurrences.is_empty() {
+        return Err(Error::NoOccurrences(name.to_string()));
+    }
+    let mut ret = String::with_capacity(text.len());
+    let mut last = 0;
+    for (range, old) in occurrences {
+        ret.push_str(&text[last..range.start]);
+        ret.push_str(&with_precision_of(new_version, &old));
+        last = range.end;
+    }
+    ret.push_str(&text[last..]);
+    Ok(ret)
+}
+
+fn read_text(tree: &dyn Tree, path: &Path) -> Result<String, Error> {
+    String::from_utf8(tree.get_file_text(path)?)
+        .map_err(|e| Error::InvalidData(format!("Invalid UTF-8 in {}: {}", path.display(), e)))
+}
+
+/// Update the versions of a package mentioned in a document in a tree.
+pub fn update_doc_file(
+    tree: &WorkingTree,
+    path: &Path,
+    name: &str,
+    new_version: &Version,
+) -> Result<(), Error> {
+    let text = read_text(tree, path)?;
+    let updated = update_versions(&text, name, new_version)?;
+    if updated != text {
+        tree.put_file_bytes_non_atomic(path, updated.as_bytes())?;
+    }
+    Ok(())
+}
+
+/// Find the versions of a package mentioned in a document that don't refer
+/// to `current`.
+pub fn validate_update_docs(
+    tree: &dyn Tree,
+    path: &Path,
+    name: &str,
+    current: Option<&Version>,
+) -> Result<Vec<String>, Error> {
+    let text = read_text(tree, path)?;
+    let occurrences = find_versions(&text, name);
+    if occurrences.is_empty() {
+        return Err(Error::NoOccurrences(name.to_string()));
+    }
+    Ok(match current {
+        Some(current) => occurrences
+            .into_iter()
+            .map(|(_, v)| v)
+            .filter(|v| !documents_version(v, current))
+            .collect(),
+        None => vec![],
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_update_versions() {
+        let text = r#"Install with:
+
+    pip install foo==1.2.2
+    pip install "foo[cli]==1.2.2"
+    pip install foobar==0.1
+
+Or add this to Cargo.toml:
+
+    foo = "1.2"
+    foo = { version = "1.2.2", features = ["cli"] }
+    bar = "1.2.2"
+
+![version](https://img.shields.io/badge/version-1.2.2-blue)
+Released 1.2.2 in 2024.
+"#;
+        let updated = update_versions(text, "foo", &"1.3.0".parse().unwrap()).unwrap();
+        assert_eq!(
+            updated,
+            r#"Install with:
+
+    pip install foo==1.3.0
+    pip install "foo[cli]==1.3.0"
+    pip install foobar==0.1
+
+Or add this to Cargo.toml:
+
+    foo = "1.3"
+    foo = { version = "1.3.0", features = ["cli"] }
+    bar = "1.2.2"
+
+![version](https://img.shields.io/badge/version-1.3.0-blue)
+Released 1.2.2 in 2024.
+"#
+        );
+        assert!(matches!(
+            update_versions("pip install foo==1.2.2", "baz", &"1.3.0".parse().unwrap()),
+            Err(Error::NoOccurrences(_))
+        ));
+    }
+
+    #[test]
+    fn test_find_versions() {
+        assert_eq!(
+            find_versions("Run `npm install foo@1.2.3rc1`.", "foo")
+                .into_iter()
+                .map(|(_, v)| v)
+                .collect::<Vec<_>>(),
+            vec!["1.2.3rc1"]
+        );
+        assert_eq!(
+            find_versions("pip install foo==1.2.3.", "foo")
+                .into_iter()
+                .map(|(_, v)| v)
+                .collect::<Vec<_>>(),
+            vec!["1.2.3"]
+        );
+    }
+
+    #[test]
+    fn test_documents_version() {
+        let version: Version = "1.2.3".parse().unwrap();
+        assert!(documents_version("1.2.3", &version));
+        assert!(documents_version("1.2", &version));
+        assert!(!documents_version("1.2.2", &version));
+        assert!(!documents_version("1.2.3rc1", &version));
+    }
+}
@@ -0,0 +1,176 @@
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Branch that documentation is published to by default.
+pub const DEFAULT_BRANCH: &str = "gh-pages";
+
+/// Directory that always points at the documentation for the latest release.
+pub const LATEST_DIRECTORY: &str = "latest";
+
+#[derive(Debug)]
+pub enum Error {
+    IoError(std::io::Error),
+    CommandFailed(String),
+}
+
+impl From<std::io::Error> for Error {
+    fn from(e: std::io::Error) -> Self {
+        Error::IoError(e)
+    }
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match &self {
+            Error::IoError(e) => write!(f, "IoError: {}", e),
+            Error::CommandFailed(e) => write!(f, "Command failed: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+fn run(command: &mut Command) -> Result<(), Error> {
+    let status = crate::logging::status(command)?;
+    if !status.success() {
+        return Err(Error::CommandFailed(format!("{:?}: {}", command, status)));
+    }
+    Ok(())
+}
+
+fn git(dir: &Path) -> Command {
+    let mut c = Command::new("git");
+    c.arg("-C").arg(dir);
+    c
+}
+
+/// Build the documentation by running `command` in `dir`.
+pub fn build(dir: &Path, command: &str) -> Result<(), Error> {
+    run(Command::new("sh").arg("-c").arg(command).current_dir(dir))
+}
+
+fn copy_dir(from: &Path, to: &Path) -> Result<(), Error> {
+    std::fs::create_dir_all(to)?;
+    for entry in std::fs::read_dir(from)? {
+        let entry = entry?;
+        let target = to.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copy_dir(&entry.path(), &target)?;
+        } else {
+            std::fs::copy(entry.path(), target)?;
+        }
+    }
+    Ok(())
+}
+
+/// Page that redirects to the documentation for a specific version.
+///
+/// GitHub Pages doesn't reliably serve symlinks, so this is used for the
+/// "latest" pointer instead.
+pub fn redirect_page(version: &str) -> String {
+    format!(
+        r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<meta http-equiv="refresh" content="0; url=../{0}/">
+<link rel="canonical" href="../{0}/">
+<title>Redirecting to {0}</title>
+</head>
+<body><a href="../{0}/">Documentation for {0}</a></body>
+</html>
+"#,
+        version
+    )
+}
+
+/// Add the documentation in `output` to `branch` of the repository at `remote`.
+///
+/// The documentation ends up in a directory named after the version, and
+/// the "latest" directory is updated to redirect to it.
+pub fn publish(remote: &str, branch: &str, output: &Path, version: &str) -> Result<(), Error> {
+    let checkout: PathBuf =
+        std::env::temp_dir().join(format!("disperse-docs-{}-{}", version, std::process::id()));
+    let result = publish_in(&checkout, remote, branch, output, version);
+    if let Err(e) = std::fs::remove_dir_all(&checkout) {
+        log::warn!("Unable to remove {}: {}", checkout.display(), e);
+    }
+    result
+}
+
+fn publish_in(
+    checkout: &Path,
+    remote: &str,
+    branch: &str,
+    output: &Path,
+    version: &str,
+) -> Result<(), Error> {
+    run(Command::new("git").arg("init").arg("-q").arg(checkout))?;
+    if run(git(checkout).arg("fetch").arg("-q").arg(remote).arg(branch)).is_ok() {
+        run(git(checkout)
+            .arg("checkout")
+            .arg("-q")
+            .arg("-b")
+            .arg(branch)
+            .arg("FETCH_HEAD"))?;
+    } else {
+        log::info!("Creating new {} branch", branch);
+        run(git(checkout)
+            .arg("checkout")
+            .arg("-q")
+            .arg("--orphan")
+            .arg(branch))?;
+    }
+
+    let versioned = checkout.join(version);
+    if versioned.exists() {
+        std::fs::remove_dir_all(&versioned)?;
+    }
+    copy_dir(output, &versioned)?;
+
+    let latest = checkout.join(LATEST_DIRECTORY);
+    if latest.exists() {
+        std::fs::remove_dir_all(&latest)?;
+    }
+    std::fs::create_dir_all(&latest)?;
+    std::fs::write(latest.join("index.html"), redirect_page(version))?;
+    std::fs::write(checkout.join(".nojekyll"), "")?;
+
+    run(git(checkout).arg("add").arg("-A"))?;
+    run(git(checkout)
+        .arg("commit")
+        .arg("-q")
+        .arg("-m")
+        .arg(format!("Add documentation for {}", version)))?;
+    run(git(checkout)
+        .arg("push")
+        .arg("-q")
+        .arg(remote)
+        .arg(format!("HEAD:refs/heads/{}", branch)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redirect_page() {
+        let page = redirect_page("1.2.3");
+        assert!(page.contains(r#"content="0; url=../1.2.3/""#));
+    }
+
+    #[test]
+    fn test_copy_dir() {
+        let td = tempfile::tempdir().unwrap();
+        let from = td.path().join("from");
+        std::fs::create_dir_all(from.join("sub")).unwrap();
+        std::fs::write(from.join("index.html"), "index").unwrap();
+        std::fs::write(from.join("sub/page.html"), "page").unwrap();
+        let to = td.path().join("to");
+        copy_dir(&from, &to).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(to.join("sub/page.html")).unwrap(),
+            "page"
+        );
+    }
+}
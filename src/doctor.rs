@@ -0,0 +1,195 @@
+use crate::validate::Finding;
+use pyo3::prelude::*;
+
+/// Tools used while releasing, and what they are used for.
+const TOOLS: &[(&str, &str)] = &[
+    ("cargo", "packaging and publishing crates"),
+    ("twine", "uploading to PyPI"),
+    ("gpg", "signing tags and release manifests"),
+];
+
+/// Python modules used while releasing, and what they are used for.
+const PYTHON_MODULES: &[(&str, &str)] = &[("hatchling", "building hatch-based Python projects")];
+
+/// Breezy plugins used while releasing, and what they are used for.
+const BREEZY_PLUGINS: &[(&str, &str)] = &[
+    ("github", "proposing merges to protected GitHub branches"),
+    ("launchpad", "accessing lp: URLs"),
+];
+
+fn python_module_available(name: &str) -> bool {
+    std::process::Command::new("python3")
+        .arg("-c")
+        .arg(format!("import {}", name))
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .status()
+        .is_ok_and(|s| s.success())
+}
+
+/// Check that the tools used while releasing are installed.
+pub fn check_tools() -> Vec<Finding> {
+    let mut findings = vec![];
+    for (name, purpose) in TOOLS {
+        match crate::environment::tool_version(name, &["--version"]) {
+            Some(version) => findings.push(Finding::info(format!("{}: {}", name, version))),
+            None => findings.push(Finding::warning(format!(
+                "{} is not installed; it is needed for {}",
+                name, purpose
+            ))),
+        }
+    }
+    for (name, purpose) in PYTHON_MODULES {
+        if !python_module_available(name) {
+            findings.push(Finding::warning(format!(
+                "Python module {} is not installed; it is needed for {}",
+                name, purpose
+            )));
+        }
+    }
+    findings
+}
+
+/// Check that the breezy plugins used while releasing are loaded.
+pub fn check_breezy_plugins() -> Vec<Finding> {
+    let loaded = Python::with_gil(|py| -> PyResult<Vec<String>> {
+        let m = py.import_bound("breezy.plugin")?;
+        m.call_method0("plugins")?
+            .call_method0("keys")?
+            .iter()?
+            .map(|k| k?.extract::<String>())
+            .collect()
+    });
+    match loaded {
+        Ok(loaded) => BREEZY_PLUGINS
+            .iter()
+            .filter(|(name, _)| !loaded.iter().any(|l| l == name))
+            .map(|(name, purpose)| {
+                Finding::warning(format!(
+                    "Breezy plugin {} is not loaded; it is needed for {}",
+                    name, purpose
+                ))
+            })
+            .collect(),
+        Err(e) => vec![Finding::error(format!(
+            "Unable to list breezy plugins: {}",
+            e
+        ))],
+    }
+}
+
+/// Check that there are credentials for uploading to PyPI.
+///
+/// `env` looks up environment variables; `pypirc` is whether ~/.pypirc exists.
+pub fn check_pypi_credentials(env: impl Fn(&str) -> Option<String>, pypirc: bool) -> Vec<Finding> {
+    if let Some(password) = env("TWINE_PASSWORD") {
+        if env("TWINE_USERNAME").as_deref() == Some("__token__") && !password.starts_with("pypi-") {
+            return vec![Finding::error(
+                "TWINE_PASSWORD does not look like a PyPI API token; they start with \"pypi-\"",
+            )];
+        }
+        return vec![Finding::info("PyPI: credentials in TWINE_PASSWORD")];
+    }
+    if env("UV_PUBLISH_TOKEN").is_some() {
+        return vec![Finding::info("PyPI: token in UV_PUBLISH_TOKEN")];
+    }
+    if pypirc {
+        return vec![Finding::info("PyPI: credentials in ~/.pypirc")];
+    }
+    vec![Finding::warning(
+        "No PyPI credentials found; set TWINE_PASSWORD, UV_PUBLISH_TOKEN or add them to ~/.pypirc",
+    )]
+}
+
+/// Check that the credentials for GitHub, crates.io and PyPI are present and valid.
+pub async fn check_credentials() -> Vec<Finding> {
+    let mut findings = vec![];
+
+    match crate::github::token() {
+        Some(token) => match crate::github::token_user(&token).await {
+            Ok(login) => findings.push(Finding::info(format!("GitHub: authenticated as {}", login))),
+            Err(e) => findings.push(Finding::error(format!(
+                "GitHub token was rejected: {}",
+                e
+            ))),
+        },
+        None => findings.push(Finding::warning(
+            "No GitHub token found; set GITHUB_TOKEN or store one with \"disperse auth login github\"",
+        )),
+    }
+
+    if crate::cargo::use_trusted_publishing() {
+        findings.push(Finding::info("crates.io: using trusted publishing"));
+    } else {
+        match crate::cargo::token() {
+            Some(token) => match crate::cargo::token_user(&token).await {
+                Ok(login) => findings.push(Finding::info(format!(
+                    "crates.io: authenticated as {}",
+                    login
+                ))),
+                Err(e) => findings.push(Finding::error(format!("crates.io: {}", e))),
+            },
+            None => findings.push(Finding::warning(
+                crate::cargo::Error::MissingToken.to_string(),
+            )),
+        }
+    }
+
+    if crate::token_cache::cached_token(crate::token_cache::Service::PyPI).is_some() {
+        findings.push(Finding::info("PyPI: token in the token cache"));
+    } else {
+        let pypirc = std::env::var_os("HOME")
+            .is_some_and(|home| std::path::Path::new(&home).join(".pypirc").exists());
+        findings.extend(check_pypi_credentials(
+            |name| std::env::var(name).ok(),
+            pypirc,
+        ));
+    }
+
+    findings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::validate::Severity;
+
+    #[test]
+    fn test_check_pypi_credentials() {
+        let severities = |vars: &[(&str, &str)], pypirc| {
+            check_pypi_credentials(
+                |name| {
+                    vars.iter()
+                        .find(|(k, _)| *k == name)
+                        .map(|(_, v)| v.to_string())
+                },
+                pypirc,
+            )
+            .into_iter()
+            .map(|f| f.severity)
+            .collect::<Vec<_>>()
+        };
+        assert_eq!(severities(&[], false), vec![Severity::Warning]);
+        assert_eq!(severities(&[], true), vec![Severity::Info]);
+        assert_eq!(
+            severities(
+                &[
+                    ("TWINE_USERNAME", "__token__"),
+                    ("TWINE_PASSWORD", "pypi-abc")
+                ],
+                false
+            ),
+            vec![Severity::Info]
+        );
+        assert_eq!(
+            severities(
+                &[
+                    ("TWINE_USERNAME", "__token__"),
+                    ("TWINE_PASSWORD", "hunter2")
+                ],
+                false
+            ),
+            vec![Severity::Error]
+        );
+    }
+}
@@ -0,0 +1,160 @@
+use std::collections::BTreeMap;
+
+/// Tools whose versions can affect how a release is built or published.
+const TOOLS: &[(&str, &[&str])] = &[
+    ("cargo", &["--version"]),
+    ("python3", &["--version"]),
+    ("twine", &["--version"]),
+    ("gpg", &["--version"]),
+];
+
+/// Environment variables that can affect a release, by name or prefix.
+const ENV_PREFIXES: &[&str] = &[
+    "DISPERSE_",
+    "CARGO_",
+    "RUSTUP_",
+    "PYTHON",
+    "TWINE_",
+    "UV_",
+    "GNUPGHOME",
+    "SOURCE_DATE_EPOCH",
+    "LANG",
+    "LC_",
+    "TZ",
+];
+
+/// Parts of variable names that indicate a secret.
+const SECRET_MARKERS: &[&str] = &["TOKEN", "PASSWORD", "PASSPHRASE", "SECRET", "KEY"];
+
+/// Version of a tool, or None if it isn't installed.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct ToolVersion {
+    pub name: String,
+    pub version: Option<String>,
+}
+
+/// Description of the environment disperse runs in, so that runs on
+/// different hosts can be compared.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct Fingerprint {
+    pub disperse_version: String,
+    pub tools: Vec<ToolVersion>,
+    /// Relevant environment variables; values of secrets are redacted.
+    pub env: BTreeMap<String, String>,
+}
+
+impl std::fmt::Display for Fingerprint {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        writeln!(f, "disperse: {}", self.disperse_version)?;
+        for tool in &self.tools {
+            writeln!(
+                f,
+                "{}: {}",
+                tool.name,
+                tool.version.as_deref().unwrap_or("not found")
+            )?;
+        }
+        for (name, value) in &self.env {
+            writeln!(f, "{}={}", name, value)?;
+        }
+        Ok(())
+    }
+}
+
+/// Extract the version from the output of e.g. `gpg --version`.
+fn parse_version_output(output: &[u8]) -> Option<String> {
+    String::from_utf8_lossy(output)
+        .lines()
+        .map(str::trim)
+        .find(|l| !l.is_empty())
+        .map(str::to_string)
+}
+
+pub(crate) fn tool_version(name: &str, args: &[&str]) -> Option<String> {
+    let output = std::process::Command::new(name).args(args).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    // Older Pythons print their version to stderr.
+    parse_version_output(&output.stdout).or_else(|| parse_version_output(&output.stderr))
+}
+
+fn is_relevant(name: &str) -> bool {
+    ENV_PREFIXES.iter().any(|p| name.starts_with(p))
+}
+
+fn is_secret(name: &str) -> bool {
+    SECRET_MARKERS
+        .iter()
+        .any(|m| name.to_uppercase().contains(m))
+}
+
+/// Pick the relevant environment variables, redacting secrets.
+pub fn relevant_env(vars: impl IntoIterator<Item = (String, String)>) -> BTreeMap<String, String> {
+    vars.into_iter()
+        .filter(|(name, _)| is_relevant(name))
+        .map(|(name, value)| {
+            if is_secret(&name) {
+                (name, "<redacted>".to_string())
+            } else {
+                (name, value)
+            }
+        })
+        .collect()
+}
+
+/// Describe the environment disperse is running in.
+pub fn fingerprint() -> Fingerprint {
+    Fingerprint {
+        disperse_version: env!("CARGO_PKG_VERSION").to_string(),
+        tools: TOOLS
+            .iter()
+            .map(|(name, args)| ToolVersion {
+                name: name.to_string(),
+                version: tool_version(name, args),
+            })
+            .collect(),
+        env: relevant_env(std::env::vars()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_relevant_env() {
+        let env = relevant_env(
+            [
+                ("CARGO_HOME", "/home/user/.cargo"),
+                ("CARGO_REGISTRY_TOKEN", "cio_secret"),
+                ("DISPERSE_TOKEN_PASSPHRASE", "hunter2"),
+                ("HOME", "/home/user"),
+                ("LANG", "C.UTF-8"),
+            ]
+            .into_iter()
+            .map(|(k, v)| (k.to_string(), v.to_string())),
+        );
+        assert_eq!(
+            env.into_iter().collect::<Vec<_>>(),
+            vec![
+                ("CARGO_HOME".to_string(), "/home/user/.cargo".to_string()),
+                ("CARGO_REGISTRY_TOKEN".to_string(), "<redacted>".to_string()),
+                (
+                    "DISPERSE_TOKEN_PASSPHRASE".to_string(),
+                    "<redacted>".to_string()
+                ),
+                ("LANG".to_string(), "C.UTF-8".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_version_output() {
+        assert_eq!(
+            parse_version_output(b"gpg (GnuPG) 2.2.40\nlibgcrypt 1.10.1\n"),
+            Some("gpg (GnuPG) 2.2.40".to_string())
+        );
+        assert_eq!(parse_version_output(b"\n"), None);
+    }
+}
@@ -0,0 +1,215 @@
+use chrono::{DateTime, Utc};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// File name of the failure state, in the XDG state directory.
+const FAILURES_FILE_NAME: &str = "failures.json";
+
+#[derive(Debug)]
+pub enum Error {
+    IoError(std::io::Error),
+    JsonError(serde_json::Error),
+}
+
+impl From<std::io::Error> for Error {
+    fn from(e: std::io::Error) -> Self {
+        Error::IoError(e)
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(e: serde_json::Error) -> Self {
+        Error::JsonError(e)
+    }
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match &self {
+            Error::IoError(e) => write!(f, "IoError: {}", e),
+            Error::JsonError(e) => write!(f, "Invalid failure state: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// Consecutive failures to release a project with the same class of error.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct FailureRecord {
+    pub class: String,
+    /// Message of the most recent failure.
+    pub message: String,
+    pub count: u32,
+    pub first_failed: DateTime<Utc>,
+    pub last_failed: DateTime<Utc>,
+
+    /// Number of the issue filed about the failures, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub issue: Option<u64>,
+}
+
+/// Tracks failed releases across runs, so that repeated failures can be
+/// reported.
+pub struct FailureTracker {
+    path: PathBuf,
+}
+
+impl FailureTracker {
+    pub fn new(path: &Path) -> Self {
+        Self {
+            path: path.to_path_buf(),
+        }
+    }
+
+    /// Open the failure state in the default location.
+    pub fn open_default() -> Result<Self, Box<dyn std::error::Error>> {
+        let xdg = xdg::BaseDirectories::with_prefix("disperse")?;
+        Ok(Self::new(&xdg.place_state_file(FAILURES_FILE_NAME)?))
+    }
+
+    fn load(&self) -> Result<BTreeMap<String, FailureRecord>, Error> {
+        match std::fs::read(&self.path) {
+            Ok(contents) => Ok(serde_json::from_slice(&contents)?),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(BTreeMap::new()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn save(&self, records: &BTreeMap<String, FailureRecord>) -> Result<(), Error> {
+        let tmp = self.path.with_extension("tmp");
+        std::fs::write(&tmp, serde_json::to_vec_pretty(records)?)?;
+        std::fs::rename(&tmp, &self.path)?;
+        Ok(())
+    }
+
+    /// Look up the current run of failures for a project.
+    pub fn get(&self, project: &str) -> Result<Option<FailureRecord>, Error> {
+        Ok(self.load()?.remove(project))
+    }
+
+    /// Record a failure to release a project.
+    ///
+    /// A failure with a different class of error than the previous one
+    /// starts a new run of failures.
+    pub fn record_failure(
+        &self,
+        project: &str,
+        class: &str,
+        message: &str,
+        now: DateTime<Utc>,
+    ) -> Result<FailureRecord, Error> {
+        let mut records = self.load()?;
+        let record = match records.remove(project) {
+            Some(record) if record.class == class => FailureRecord {
+                message: message.to_string(),
+                count: record.count + 1,
+                last_failed: now,
+                ..record
+            },
+            _ => FailureRecord {
+                class: class.to_string(),
+                message: message.to_string(),
+                count: 1,
+                first_failed: now,
+                last_failed: now,
+                issue: None,
+            },
+        };
+        records.insert(project.to_string(), record.clone());
+        self.save(&records)?;
+        Ok(record)
+    }
+
+    /// Remember the issue that was filed about the failures of a project.
+    pub fn set_issue(&self, project: &str, issue: u64) -> Result<(), Error> {
+        let mut records = self.load()?;
+        if let Some(record) = records.get_mut(project) {
+            record.issue = Some(issue);
+            self.save(&records)?;
+        }
+        Ok(())
+    }
+
+    /// Forget the failures of a project, e.g. after it was released.
+    ///
+    /// Returns the run of failures that ended, if any.
+    pub fn record_success(&self, project: &str) -> Result<Option<FailureRecord>, Error> {
+        let mut records = self.load()?;
+        let record = records.remove(project);
+        if record.is_some() {
+            self.save(&records)?;
+        }
+        Ok(record)
+    }
+}
+
+/// Title for an issue about repeated release failures.
+pub fn issue_title(record: &FailureRecord) -> String {
+    format!("Automatic releases are failing ({})", record.class)
+}
+
+/// Body for an issue about repeated release failures.
+pub fn issue_body(project: &str, record: &FailureRecord, environment: &str) -> String {
+    format!(
+        "disperse has failed to release {} {} times in a row, most recently on {}. \
+         The first failure was on {}.\n\n\
+         The most recent error was:\n\n```\n{}\n```\n\n\
+         Environment:\n\n```\n{}```\n\n\
+         This issue is updated while the failures continue, and closed once \
+         a release succeeds.\n",
+        project,
+        record.count,
+        record.last_failed.format("%Y-%m-%d %H:%M UTC"),
+        record.first_failed.format("%Y-%m-%d %H:%M UTC"),
+        record.message.trim_end(),
+        environment,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_failure_tracker() {
+        let td = tempfile::tempdir().unwrap();
+        let tracker = FailureTracker::new(&td.path().join("failures.json"));
+        let t1: DateTime<Utc> = "2024-01-01T00:00:00Z".parse().unwrap();
+        let t2: DateTime<Utc> = "2024-01-02T00:00:00Z".parse().unwrap();
+        let t3: DateTime<Utc> = "2024-01-03T00:00:00Z".parse().unwrap();
+
+        assert_eq!(tracker.get("foo").unwrap(), None);
+        assert_eq!(
+            tracker
+                .record_failure("foo", "ci-failed", "CI failed", t1)
+                .unwrap()
+                .count,
+            1
+        );
+        tracker.set_issue("foo", 12).unwrap();
+        let record = tracker
+            .record_failure("foo", "ci-failed", "CI failed again", t2)
+            .unwrap();
+        assert_eq!(
+            record,
+            FailureRecord {
+                class: "ci-failed".to_string(),
+                message: "CI failed again".to_string(),
+                count: 2,
+                first_failed: t1,
+                last_failed: t2,
+                issue: Some(12),
+            }
+        );
+
+        // A different class of error starts a new run.
+        let record = tracker
+            .record_failure("foo", "upload-failed", "Upload failed", t3)
+            .unwrap();
+        assert_eq!((record.count, record.issue), (1, None));
+
+        assert_eq!(tracker.record_success("foo").unwrap(), Some(record));
+        assert_eq!(tracker.record_success("foo").unwrap(), None);
+    }
+}
@@ -5,8 +5,18 @@ use octocrab::Octocrab;
 use std::time::Duration;
 use url::Url;
 
+use crate::limits::Service;
+
 const DEFAULT_GITHUB_CI_TIMEOUT: u64 = 60 * 24;
 
+const DEFAULT_GITHUB_CI_POLL_INTERVAL: u64 = 30;
+
+/// Lower bound for the delay between two CI polls, in seconds.
+const MIN_GITHUB_CI_POLL_INTERVAL: u64 = 5;
+
+/// Upper bound for the delay between two CI polls, in seconds.
+const MAX_GITHUB_CI_POLL_INTERVAL: u64 = 60 * 10;
+
 #[derive(Debug)]
 pub enum Error {
     InvalidGitHubUrl(String, String),
@@ -50,6 +60,15 @@ impl GitHubCIStatus {
     pub fn is_ok(&self) -> bool {
         matches!(self, GitHubCIStatus::Ok)
     }
+
+    /// Short description of the state, e.g. "passing".
+    pub fn state(&self) -> &'static str {
+        match self {
+            GitHubCIStatus::Ok => "passing",
+            GitHubCIStatus::Failed { .. } => "failing",
+            GitHubCIStatus::Pending { .. } => "pending",
+        }
+    }
 }
 
 impl std::fmt::Display for GitHubCIStatus {
@@ -81,7 +100,8 @@ pub fn init_github() -> Result<Octocrab, Error> {
         Ok(token) => token,
         Err(_) => {
             debug!("GITHUB_TOKEN environment variable not set");
-            retrieve_github_token()
+            crate::token_cache::cached_token(crate::token_cache::Service::GitHub)
+                .unwrap_or_else(retrieve_github_token)
         }
     };
 
@@ -112,6 +132,7 @@ pub async fn get_github_repo(
     info!("Finding project {}/{} on GitHub", owner, repo_name);
 
     // Get the repository using octocrab
+    let _permit = crate::limits::acquire(Service::GitHub).await;
     Ok(instance.repos(owner, repo_name).get().await?)
 }
 
@@ -121,6 +142,7 @@ pub async fn check_gh_repo_action_status(
     committish: Option<&str>,
 ) -> Result<GitHubCIStatus, Error> {
     let committish = committish.unwrap_or("HEAD");
+    let _permit = crate::limits::acquire(Service::GitHub).await;
 
     let commit = instance
         .commits(&repo.owner.as_ref().unwrap().login, &repo.name)
@@ -169,29 +191,40 @@ pub async fn wait_for_gh_actions(
     repo: &octocrab::models::Repository,
     committish: Option<&str>,
     timeout: Option<u64>,
+    poll_interval: Option<u64>,
 ) -> Result<GitHubCIStatus, Error> {
     let timeout = timeout.unwrap_or(DEFAULT_GITHUB_CI_TIMEOUT);
-    info!(
-        "Waiting for CI for {} on {} to go green",
-        repo.name,
-        committish.unwrap_or("HEAD")
-    );
+    let poll_interval = poll_interval.unwrap_or(DEFAULT_GITHUB_CI_POLL_INTERVAL);
     let committish = committish.unwrap_or("HEAD");
+    let _progress = crate::progress::Progress::with_timeout(
+        format!(
+            "Waiting for CI for {} on {} to go green",
+            repo.name, committish
+        ),
+        Duration::from_secs(timeout),
+    );
 
-    let commit = instance
-        .commits(&repo.owner.as_ref().unwrap().login, &repo.name)
-        .get(committish)
-        .await?;
+    let commit = {
+        let _permit = crate::limits::acquire(Service::GitHub).await;
+        instance
+            .commits(&repo.owner.as_ref().unwrap().login, &repo.name)
+            .get(committish)
+            .await?
+    };
 
-    let start_time = std::time::Instant::now();
+    let deadline = std::time::Instant::now() + Duration::from_secs(timeout);
+    let mut attempt = 0;
 
-    while start_time.elapsed().as_secs() < timeout {
-        let check_runs = instance
-            .checks(&repo.owner.as_ref().unwrap().login, &repo.name)
-            .list_check_runs_for_git_ref(Commitish(commit.sha.clone()))
-            .send()
-            .await?
-            .check_runs;
+    while std::time::Instant::now() < deadline {
+        let check_runs = {
+            let _permit = crate::limits::acquire(Service::GitHub).await;
+            instance
+                .checks(&repo.owner.as_ref().unwrap().login, &repo.name)
+                .list_check_runs_for_git_ref(Commitish(commit.sha.clone()))
+                .send()
+                .await?
+                .check_runs
+        };
 
         match summarize_status(check_runs.as_slice()) {
             GitHubCIStatus::Ok => {
@@ -199,7 +232,11 @@ pub async fn wait_for_gh_actions(
                 return Ok(GitHubCIStatus::Ok);
             }
             GitHubCIStatus::Pending { .. } => {
-                std::thread::sleep(Duration::from_secs(30));
+                let delay = poll_delay(poll_interval, attempt)
+                    .min(deadline.saturating_duration_since(std::time::Instant::now()));
+                debug!("CI still pending; checking again in {:?}", delay);
+                tokio::time::sleep(delay).await;
+                attempt += 1;
             }
             GitHubCIStatus::Failed { html_url, sha } => {
                 return Ok(GitHubCIStatus::Failed { sha, html_url });
@@ -210,6 +247,23 @@ pub async fn wait_for_gh_actions(
     Err(Error::TimedOut)
 }
 
+/// Determine how long to wait before polling CI again.
+///
+/// The interval doubles with every attempt (up to `MAX_GITHUB_CI_POLL_INTERVAL`),
+/// with up to 10% of random jitter added so that concurrent runs don't poll in
+/// lockstep. Intervals shorter than `MIN_GITHUB_CI_POLL_INTERVAL` are raised to
+/// it, so that an interval of 0 doesn't turn into a busy loop.
+fn poll_delay(poll_interval: u64, attempt: u32) -> Duration {
+    use rand::Rng;
+    let poll_interval = poll_interval.max(MIN_GITHUB_CI_POLL_INTERVAL);
+    let base = poll_interval
+        .saturating_mul(1u64.checked_shl(attempt).unwrap_or(u64::MAX))
+        .min(MAX_GITHUB_CI_POLL_INTERVAL.max(poll_interval));
+    let base = Duration::from_secs(base);
+    let jitter = base.mul_f64(rand::thread_rng().gen_range(0.0..0.1));
+    base + jitter
+}
+
 fn summarize_status(check_runs: &[octocrab::models::checks::CheckRun]) -> GitHubCIStatus {
     for check in check_runs {
         match check.conclusion.as_deref() {
@@ -260,10 +314,11 @@ pub async fn create_github_release(
     tag_name: &str,
     version: &str,
     description: Option<&str>,
-) -> Result<(), Error> {
+) -> Result<url::Url, Error> {
     info!("Creating release on GitHub");
+    let _permit = crate::limits::acquire(Service::GitHub).await;
 
-    instance
+    let release = instance
         .repos(&repo.owner.as_ref().unwrap().login, &repo.name)
         .releases()
         .create(tag_name)
@@ -272,21 +327,88 @@ pub async fn create_github_release(
         .send()
         .await?;
 
+    Ok(release.html_url)
+}
+
+/// Attach a file to the release for a tag.
+///
+/// Returns the download URL of the file.
+pub async fn upload_release_asset(
+    instance: &Octocrab,
+    repo: &octocrab::models::Repository,
+    tag_name: &str,
+    name: &str,
+    contents: Vec<u8>,
+) -> Result<url::Url, Error> {
+    let _permit = crate::limits::acquire(Service::GitHub).await;
+    let repos = instance.repos(&repo.owner.as_ref().unwrap().login, &repo.name);
+    let releases = repos.releases();
+    let release = releases.get_by_tag(tag_name).await?;
+    let asset = releases
+        .upload_asset(release.id.into_inner(), name, contents.into())
+        .send()
+        .await?;
+    Ok(asset.browser_download_url)
+}
+
+/// File an issue on a repository, or update the body of an existing one.
+///
+/// Returns the number and URL of the issue.
+pub async fn file_or_update_issue(
+    instance: &Octocrab,
+    repo: &octocrab::models::Repository,
+    existing: Option<u64>,
+    title: &str,
+    body: &str,
+) -> Result<(u64, url::Url), Error> {
+    let _permit = crate::limits::acquire(Service::GitHub).await;
+    let issues = instance.issues(&repo.owner.as_ref().unwrap().login, &repo.name);
+    let issue = match existing {
+        Some(number) => issues.update(number).title(title).body(body).send().await?,
+        None => issues.create(title).body(body).send().await?,
+    };
+    Ok((issue.number, issue.html_url))
+}
+
+/// Close an issue, with a comment explaining why.
+pub async fn close_issue(
+    instance: &Octocrab,
+    repo: &octocrab::models::Repository,
+    number: u64,
+    comment: &str,
+) -> Result<(), Error> {
+    let _permit = crate::limits::acquire(Service::GitHub).await;
+    let issues = instance.issues(&repo.owner.as_ref().unwrap().login, &repo.name);
+    issues.create_comment(number, comment).await?;
+    issues
+        .update(number)
+        .state(octocrab::models::IssueState::Closed)
+        .send()
+        .await?;
     Ok(())
 }
 
-pub fn login() -> Result<Octocrab, Error> {
-    let entry = keyring::Entry::new("github.com", "personal_token").unwrap();
-    let token = match std::env::var("GITHUB_TOKEN") {
+fn token_entry() -> keyring::Entry {
+    keyring::Entry::new("github.com", &crate::profile::qualify("personal_token")).unwrap()
+}
+
+/// Find the GitHub personal token, from the environment, the token cache or the keyring.
+pub fn token() -> Option<String> {
+    match std::env::var("GITHUB_TOKEN") {
         Ok(token) => Some(token),
-        Err(std::env::VarError::NotPresent) => match entry.get_password() {
-            Ok(token) => Some(token),
-            Err(keyring::Error::NoEntry) => None,
-            Err(e) => {
-                log::error!("Unable to read GitHub personal token from keyring: {}", e);
-                None
+        Err(std::env::VarError::NotPresent) => {
+            match crate::token_cache::cached_token(crate::token_cache::Service::GitHub) {
+                Some(token) => Some(token),
+                None => match token_entry().get_password() {
+                    Ok(token) => Some(token),
+                    Err(keyring::Error::NoEntry) => None,
+                    Err(e) => {
+                        log::error!("Unable to read GitHub personal token from keyring: {}", e);
+                        None
+                    }
+                },
             }
-        },
+        }
         Err(e) => {
             log::error!(
                 "Unable to read GitHub personal token from environment: {}",
@@ -294,9 +416,20 @@ pub fn login() -> Result<Octocrab, Error> {
             );
             None
         }
-    };
+    }
+}
 
-    let builder = if let Some(token) = token {
+/// Look up the login of the user that a GitHub token belongs to.
+pub async fn token_user(token: &str) -> Result<String, Error> {
+    let instance = octocrab::OctocrabBuilder::new()
+        .personal_token(token.to_string())
+        .build()?;
+    let _permit = crate::limits::acquire(Service::GitHub).await;
+    Ok(instance.current().user().await?.login)
+}
+
+pub fn login() -> Result<Octocrab, Error> {
+    let builder = if let Some(token) = token() {
         log::info!("Using GitHub personal token from keyring");
         octocrab::OctocrabBuilder::new().personal_token(token)
     } else {
@@ -304,8 +437,30 @@ pub fn login() -> Result<Octocrab, Error> {
         let mut personal_token = String::new();
         std::io::stdin().read_line(&mut personal_token).unwrap();
         let personal_token = personal_token.trim();
-        entry.set_password(personal_token).unwrap();
+        token_entry().set_password(personal_token).unwrap();
         octocrab::OctocrabBuilder::new().personal_token(personal_token.to_string())
     };
     Ok(builder.build()?)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_poll_delay() {
+        let d = poll_delay(30, 0);
+        assert!(d >= Duration::from_secs(30) && d <= Duration::from_secs(33));
+        let d = poll_delay(30, 2);
+        assert!(d >= Duration::from_secs(120) && d <= Duration::from_secs(132));
+        let d = poll_delay(30, 10);
+        assert!(
+            d >= Duration::from_secs(MAX_GITHUB_CI_POLL_INTERVAL)
+                && d <= Duration::from_secs(MAX_GITHUB_CI_POLL_INTERVAL * 11 / 10)
+        );
+        let d = poll_delay(30, 100);
+        assert!(d <= Duration::from_secs(MAX_GITHUB_CI_POLL_INTERVAL * 11 / 10));
+        let d = poll_delay(0, 0);
+        assert!(d >= Duration::from_secs(MIN_GITHUB_CI_POLL_INTERVAL));
+    }
+}
@@ -0,0 +1,175 @@
+use chrono::{DateTime, Utc};
+use std::io::{BufRead, Write};
+use std::path::{Path, PathBuf};
+
+/// File name of the release history, in the XDG state directory.
+const HISTORY_FILE_NAME: &str = "releases.jsonl";
+
+#[derive(Debug)]
+pub enum Error {
+    IoError(std::io::Error),
+    JsonError(serde_json::Error),
+}
+
+impl From<std::io::Error> for Error {
+    fn from(e: std::io::Error) -> Self {
+        Error::IoError(e)
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(e: serde_json::Error) -> Self {
+        Error::JsonError(e)
+    }
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match &self {
+            Error::IoError(e) => write!(f, "IoError: {}", e),
+            Error::JsonError(e) => write!(f, "Invalid release history: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// A release that was performed.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct ReleaseEvent {
+    pub project: String,
+    pub version: String,
+    pub tag: String,
+    pub released: DateTime<Utc>,
+
+    /// Release notes, as taken from the news file.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub notes: Option<String>,
+
+    /// Locations the release was published to.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub urls: Vec<String>,
+
+    /// Manual steps on the release checklist, and whether they were confirmed.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub checklist: Vec<crate::checklist::ChecklistResult>,
+
+    /// Version of disperse that performed the release.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub disperse_version: Option<String>,
+}
+
+/// Storage for the releases that have been performed.
+///
+/// This allows other tools (e.g. the Debian Janitor) to find out what was
+/// released, without having to scrape the prometheus metrics.
+pub trait ReleaseHistory {
+    /// Record a release.
+    fn record(&self, event: &ReleaseEvent) -> Result<(), Error>;
+
+    /// Return the releases performed at or after `since`, oldest first.
+    fn since(&self, since: DateTime<Utc>) -> Result<Vec<ReleaseEvent>, Error>;
+}
+
+/// Release history stored as one JSON object per line.
+pub struct JsonLinesHistory {
+    path: PathBuf,
+}
+
+impl JsonLinesHistory {
+    pub fn new(path: &Path) -> Self {
+        Self {
+            path: path.to_path_buf(),
+        }
+    }
+
+    /// Open the history in the default location.
+    pub fn open_default() -> Result<Self, Box<dyn std::error::Error>> {
+        let xdg = xdg::BaseDirectories::with_prefix("disperse")?;
+        Ok(Self::new(&xdg.place_state_file(HISTORY_FILE_NAME)?))
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl ReleaseHistory for JsonLinesHistory {
+    fn record(&self, event: &ReleaseEvent) -> Result<(), Error> {
+        let mut f = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        writeln!(f, "{}", serde_json::to_string(event)?)?;
+        Ok(())
+    }
+
+    fn since(&self, since: DateTime<Utc>) -> Result<Vec<ReleaseEvent>, Error> {
+        let f = match std::fs::File::open(&self.path) {
+            Ok(f) => f,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(vec![]),
+            Err(e) => return Err(e.into()),
+        };
+        let mut ret = vec![];
+        for line in std::io::BufReader::new(f).lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let event: ReleaseEvent = serde_json::from_str(&line)?;
+            if event.released >= since {
+                ret.push(event);
+            }
+        }
+        ret.sort_by_key(|e| e.released);
+        Ok(ret)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(project: &str, released: &str) -> ReleaseEvent {
+        ReleaseEvent {
+            project: project.to_string(),
+            version: "1.0.0".to_string(),
+            tag: "v1.0.0".to_string(),
+            released: released.parse().unwrap(),
+            notes: None,
+            urls: vec![],
+            checklist: vec![],
+            disperse_version: None,
+        }
+    }
+
+    #[test]
+    fn test_json_lines_history() {
+        let td = tempfile::tempdir().unwrap();
+        let history = JsonLinesHistory::new(&td.path().join("releases.jsonl"));
+        assert_eq!(history.since(DateTime::UNIX_EPOCH).unwrap(), vec![]);
+
+        history
+            .record(&event("foo", "2024-01-02T00:00:00Z"))
+            .unwrap();
+        history
+            .record(&event("bar", "2024-01-01T00:00:00Z"))
+            .unwrap();
+
+        assert_eq!(
+            history
+                .since(DateTime::UNIX_EPOCH)
+                .unwrap()
+                .iter()
+                .map(|e| e.project.as_str())
+                .collect::<Vec<_>>(),
+            vec!["bar", "foo"]
+        );
+        assert_eq!(
+            history
+                .since("2024-01-02T00:00:00Z".parse().unwrap())
+                .unwrap(),
+            vec![event("foo", "2024-01-02T00:00:00Z")]
+        );
+    }
+}
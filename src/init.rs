@@ -0,0 +1,175 @@
+use crate::project_config::{ProjectConfig, UpdateManpage, UpdateVersion};
+use breezyshim::tree::{Tree, WorkingTree};
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Names of news files, in order of preference.
+const NEWS_FILES: &[&str] = &[
+    "NEWS",
+    "NEWS.md",
+    "NEWS.rst",
+    "CHANGELOG",
+    "CHANGELOG.md",
+    "CHANGELOG.rst",
+    "ChangeLog",
+    "CHANGES",
+    "CHANGES.md",
+    "CHANGES.rst",
+];
+
+/// Files that often mention the version, but that disperse doesn't update by itself.
+const VERSION_FILES: &[&str] = &[
+    "meson.build",
+    "CMakeLists.txt",
+    "configure.ac",
+    "VERSION",
+    "version.txt",
+];
+
+/// Places where manual pages usually live.
+const MANPAGE_GLOBS: &[&str] = &["*.[1-8]", "man/*.[1-8]", "doc/*.[1-8]", "docs/*.[1-8]"];
+
+/// Infer the tag name template from the names of existing tags.
+///
+/// Returns the template that matches the most tags, or None if no tag
+/// looks like a release.
+pub fn infer_tag_name(tags: &[String]) -> Option<String> {
+    let mut prefixes: HashMap<&str, usize> = HashMap::new();
+    for tag in tags {
+        if let Some((_, prefix)) = lazy_regex::regex_captures!(r"^(.*?)\d+(?:\.\d+)+$", tag) {
+            *prefixes.entry(prefix).or_default() += 1;
+        }
+    }
+    prefixes
+        .into_iter()
+        .max_by(|a, b| a.1.cmp(&b.1).then_with(|| b.0.cmp(a.0)))
+        .map(|(prefix, _)| format!("{}$VERSION", prefix))
+}
+
+/// Suggest a new-line for update_version, if a line mentions the version.
+pub fn suggest_new_line(line: &str, version: &str) -> Option<String> {
+    let is_boundary = |c: Option<char>| c.is_none_or(|c| !c.is_alphanumeric() && c != '.');
+    line.match_indices(version).find_map(|(i, _)| {
+        let before = line[..i].chars().next_back();
+        let after = line[i + version.len()..].chars().next();
+        if is_boundary(before) && is_boundary(after) {
+            Some(format!(
+                "{}$VERSION{}",
+                &line[..i],
+                &line[i + version.len()..]
+            ))
+        } else {
+            None
+        }
+    })
+}
+
+/// Generate a configuration for a project, based on what is in its tree.
+pub fn detect_config(tree: &WorkingTree) -> ProjectConfig {
+    let tags = tree
+        .branch()
+        .tags()
+        .ok()
+        .and_then(|tags| tags.get_tag_dict().ok())
+        .map(|tags| tags.into_keys().collect::<Vec<_>>())
+        .unwrap_or_default();
+    let tag_name = infer_tag_name(&tags).unwrap_or_else(|| "v$VERSION".to_string());
+
+    let mut cfg = ProjectConfig {
+        tag_name: Some(tag_name),
+        news_file: NEWS_FILES
+            .iter()
+            .map(Path::new)
+            .find(|p| tree.has_filename(p))
+            .map(|p| p.to_path_buf()),
+        ..Default::default()
+    };
+
+    if tree.has_filename(Path::new("pyproject.toml")) || tree.has_filename(Path::new("setup.py")) {
+        cfg.twine_upload = Some(true);
+    }
+
+    if tree.has_filename(Path::new("package.json"))
+        && !crate::npm::is_private(tree).unwrap_or(true)
+        && crate::npm::find_version(tree).ok().flatten().is_some()
+    {
+        cfg.npm_publish = Some(true);
+    }
+
+    let manpages = MANPAGE_GLOBS
+        .iter()
+        .filter(|pattern| crate::iter_glob(tree, pattern).next().is_some())
+        .map(|pattern| UpdateManpage::Path(pattern.into()))
+        .collect::<Vec<_>>();
+    if !manpages.is_empty() {
+        cfg.update_manpages = Some(manpages);
+    }
+
+    let version =
+        crate::find_last_version_in_tags(tree.branch().as_ref(), cfg.tag_name.as_ref().unwrap())
+            .ok()
+            .and_then(|(v, _)| v)
+            .or_else(|| {
+                crate::find_last_version_in_files(tree, &cfg)
+                    .ok()
+                    .flatten()
+                    .map(|(v, _)| v)
+            });
+    if let Some(version) = version {
+        let version = version.to_string();
+        let update_version = VERSION_FILES
+            .iter()
+            .map(Path::new)
+            .filter(|p| tree.has_filename(p))
+            .filter_map(|p| {
+                let text = tree.get_file_text(p).ok()?;
+                let new_line = String::from_utf8_lossy(&text)
+                    .lines()
+                    .find_map(|l| suggest_new_line(l, &version))?;
+                Some(UpdateVersion {
+                    path: p.to_path_buf(),
+                    r#match: None,
+                    new_line,
+                })
+            })
+            .collect::<Vec<_>>();
+        if !update_version.is_empty() {
+            cfg.update_version = Some(update_version);
+        }
+    }
+
+    cfg
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_infer_tag_name() {
+        let tags = |tags: &[&str]| tags.iter().map(|t| t.to_string()).collect::<Vec<_>>();
+        assert_eq!(
+            infer_tag_name(&tags(&["v1.0", "v1.1", "1.2", "latest"])),
+            Some("v$VERSION".to_string())
+        );
+        assert_eq!(
+            infer_tag_name(&tags(&["foo-0.1.0", "foo-0.2.0"])),
+            Some("foo-$VERSION".to_string())
+        );
+        assert_eq!(infer_tag_name(&tags(&["latest"])), None);
+    }
+
+    #[test]
+    fn test_suggest_new_line() {
+        assert_eq!(
+            suggest_new_line("  version: '1.2.3',", "1.2.3"),
+            Some("  version: '$VERSION',".to_string())
+        );
+        assert_eq!(
+            suggest_new_line("AC_INIT([foo], [1.2.3])", "1.2.3"),
+            Some("AC_INIT([foo], [$VERSION])".to_string())
+        );
+        assert_eq!(suggest_new_line("version 11.2.3", "1.2.3"), None);
+        assert_eq!(suggest_new_line("version 1.2.34", "1.2.3"), None);
+    }
+}
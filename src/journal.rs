@@ -0,0 +1,378 @@
+use std::collections::BTreeMap;
+use std::io::{BufRead, Write};
+use std::path::{Path, PathBuf};
+
+/// Directory with the journals of release workspaces, in the XDG state directory.
+const JOURNALS_DIR_NAME: &str = "journals";
+
+/// File name of the index of kept release workspaces, in the XDG state directory.
+const WORKSPACES_FILE_NAME: &str = "workspaces.json";
+
+/// File name of the list of workspaces created by disperse, in the XDG state directory.
+const CREATED_WORKSPACES_FILE_NAME: &str = "created-workspaces.json";
+
+#[derive(Debug)]
+pub enum Error {
+    IoError(std::io::Error),
+    JsonError(serde_json::Error),
+}
+
+impl From<std::io::Error> for Error {
+    fn from(e: std::io::Error) -> Self {
+        Error::IoError(e)
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(e: serde_json::Error) -> Self {
+        Error::JsonError(e)
+    }
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match &self {
+            Error::IoError(e) => write!(f, "IoError: {}", e),
+            Error::JsonError(e) => write!(f, "Invalid release journal: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// A step of a release that has completed.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "step", rename_all = "kebab-case")]
+pub enum Step {
+    VersionPicked {
+        version: String,
+    },
+    /// The news file and version strings were updated.
+    FilesUpdated {
+        files: Vec<PathBuf>,
+    },
+    /// The release commit was created and verified.
+    Committed {
+        revision: String,
+        /// Release notes, as taken from the news file.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        notes: Option<String>,
+    },
+    Tagged {
+        tag: String,
+    },
+    ArtifactsBuilt {
+        artifacts: Vec<PathBuf>,
+    },
+    ArtifactsPublished,
+    Pushed,
+}
+
+/// Record of the steps of a release, so that it can be resumed after a failure.
+pub struct Journal {
+    path: PathBuf,
+}
+
+impl Journal {
+    pub fn new(path: &Path) -> Self {
+        Self {
+            path: path.to_path_buf(),
+        }
+    }
+
+    /// The journal of the release performed in a workspace, kept in `directory`.
+    ///
+    /// Journals are not kept in the workspace itself, where they would be an
+    /// untracked file that e.g. makes cargo publish refuse to run.
+    pub fn in_directory(directory: &Path, workspace: &Path) -> Self {
+        use sha2::{Digest, Sha256};
+        let digest = Sha256::digest(workspace.as_os_str().as_encoded_bytes());
+        let name = digest
+            .iter()
+            .take(16)
+            .map(|b| format!("{:02x}", b))
+            .collect::<String>();
+        Self::new(&directory.join(format!("{}.jsonl", name)))
+    }
+
+    /// The journal of the release performed in a workspace, in the XDG state directory.
+    pub fn for_workspace(workspace: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        let xdg = xdg::BaseDirectories::with_prefix("disperse")?;
+        Ok(Self::in_directory(
+            &xdg.create_state_directory(JOURNALS_DIR_NAME)?,
+            workspace,
+        ))
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Record that a step has completed.
+    pub fn record(&self, step: &Step) -> Result<(), Error> {
+        let mut f = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        writeln!(f, "{}", serde_json::to_string(step)?)?;
+        Ok(())
+    }
+
+    /// Remove the journal, once the release no longer needs resuming.
+    pub fn remove(&self) -> Result<(), Error> {
+        match std::fs::remove_file(&self.path) {
+            Ok(()) => Ok(()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Return the steps that have completed, in order.
+    pub fn steps(&self) -> Result<Vec<Step>, Error> {
+        let f = match std::fs::File::open(&self.path) {
+            Ok(f) => f,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(vec![]),
+            Err(e) => return Err(e.into()),
+        };
+        let mut ret = vec![];
+        for line in std::io::BufReader::new(f).lines() {
+            let line = line?;
+            if !line.trim().is_empty() {
+                ret.push(serde_json::from_str(&line)?);
+            }
+        }
+        Ok(ret)
+    }
+}
+
+/// Workspaces of releases that failed part way through, by project.
+pub struct WorkspaceIndex {
+    path: PathBuf,
+}
+
+impl WorkspaceIndex {
+    pub fn new(path: &Path) -> Self {
+        Self {
+            path: path.to_path_buf(),
+        }
+    }
+
+    /// Open the index in the default location.
+    pub fn open_default() -> Result<Self, Box<dyn std::error::Error>> {
+        let xdg = xdg::BaseDirectories::with_prefix("disperse")?;
+        Ok(Self::new(&xdg.place_state_file(WORKSPACES_FILE_NAME)?))
+    }
+
+    fn load(&self) -> Result<BTreeMap<String, PathBuf>, Error> {
+        match std::fs::read(&self.path) {
+            Ok(contents) => Ok(serde_json::from_slice(&contents)?),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(BTreeMap::new()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn save(&self, workspaces: &BTreeMap<String, PathBuf>) -> Result<(), Error> {
+        let tmp = self.path.with_extension("tmp");
+        std::fs::write(&tmp, serde_json::to_vec_pretty(workspaces)?)?;
+        std::fs::rename(&tmp, &self.path)?;
+        Ok(())
+    }
+
+    /// Return the kept workspaces, by project.
+    pub fn list(&self) -> Result<BTreeMap<String, PathBuf>, Error> {
+        self.load()
+    }
+
+    /// Look up the kept workspace of a project.
+    pub fn get(&self, project: &str) -> Result<Option<PathBuf>, Error> {
+        Ok(self.load()?.remove(project))
+    }
+
+    /// Remember the workspace of a release of a project.
+    pub fn insert(&self, project: &str, workspace: &Path) -> Result<(), Error> {
+        let mut workspaces = self.load()?;
+        workspaces.insert(project.to_string(), workspace.to_path_buf());
+        self.save(&workspaces)
+    }
+
+    /// Forget the workspace of a project, returning it if there was one.
+    pub fn remove(&self, project: &str) -> Result<Option<PathBuf>, Error> {
+        let mut workspaces = self.load()?;
+        let workspace = workspaces.remove(project);
+        if workspace.is_some() {
+            self.save(&workspaces)?;
+        }
+        Ok(workspace)
+    }
+}
+
+/// A workspace that disperse created for a release.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct CreatedWorkspace {
+    pub project: String,
+    pub path: PathBuf,
+    pub created: chrono::DateTime<chrono::Utc>,
+}
+
+/// Workspaces created by disperse, so that those left behind by
+/// --preserve-temp or a crash can be cleaned up.
+pub struct WorkspaceTracker {
+    path: PathBuf,
+}
+
+impl WorkspaceTracker {
+    pub fn new(path: &Path) -> Self {
+        Self {
+            path: path.to_path_buf(),
+        }
+    }
+
+    /// Open the list in the default location.
+    pub fn open_default() -> Result<Self, Box<dyn std::error::Error>> {
+        let xdg = xdg::BaseDirectories::with_prefix("disperse")?;
+        Ok(Self::new(
+            &xdg.place_state_file(CREATED_WORKSPACES_FILE_NAME)?,
+        ))
+    }
+
+    fn load(&self) -> Result<Vec<CreatedWorkspace>, Error> {
+        match std::fs::read(&self.path) {
+            Ok(contents) => Ok(serde_json::from_slice(&contents)?),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(vec![]),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn save(&self, workspaces: &[CreatedWorkspace]) -> Result<(), Error> {
+        let tmp = self.path.with_extension("tmp");
+        std::fs::write(&tmp, serde_json::to_vec_pretty(workspaces)?)?;
+        std::fs::rename(&tmp, &self.path)?;
+        Ok(())
+    }
+
+    /// Remember that a workspace was created.
+    pub fn record(&self, workspace: CreatedWorkspace) -> Result<(), Error> {
+        let mut workspaces = self.load()?;
+        // Most workspaces are removed at the end of the release.
+        workspaces.retain(|w| w.path.exists());
+        workspaces.push(workspace);
+        self.save(&workspaces)
+    }
+
+    /// Return the workspaces that are still on disk, forgetting the others.
+    pub fn existing(&self) -> Result<Vec<CreatedWorkspace>, Error> {
+        let workspaces = self.load()?;
+        let count = workspaces.len();
+        let existing = workspaces
+            .into_iter()
+            .filter(|w| w.path.exists())
+            .collect::<Vec<_>>();
+        if existing.len() != count {
+            self.save(&existing)?;
+        }
+        Ok(existing)
+    }
+
+    /// Remove a workspace from disk and forget about it.
+    pub fn remove(&self, workspace: &CreatedWorkspace) -> Result<(), Error> {
+        match std::fs::remove_dir_all(&workspace.path) {
+            Ok(()) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e.into()),
+        }
+        let mut workspaces = self.load()?;
+        workspaces.retain(|w| w.path != workspace.path);
+        self.save(&workspaces)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_journal() {
+        let td = tempfile::tempdir().unwrap();
+        let workspace = td.path().join("workspace");
+        let journal = Journal::in_directory(td.path(), &workspace);
+        assert!(!journal.path().starts_with(&workspace));
+        assert_eq!(
+            Journal::in_directory(td.path(), &workspace).path(),
+            journal.path()
+        );
+        assert_ne!(
+            Journal::in_directory(td.path(), &td.path().join("other")).path(),
+            journal.path()
+        );
+        assert_eq!(journal.steps().unwrap(), vec![]);
+        journal
+            .record(&Step::VersionPicked {
+                version: "1.2.3".to_string(),
+            })
+            .unwrap();
+        journal
+            .record(&Step::Committed {
+                revision: "git-v1:abc".to_string(),
+                notes: None,
+            })
+            .unwrap();
+        assert_eq!(
+            std::fs::read_to_string(journal.path())
+                .unwrap()
+                .lines()
+                .last(),
+            Some(r#"{"step":"committed","revision":"git-v1:abc"}"#)
+        );
+        assert_eq!(
+            journal.steps().unwrap(),
+            vec![
+                Step::VersionPicked {
+                    version: "1.2.3".to_string()
+                },
+                Step::Committed {
+                    revision: "git-v1:abc".to_string(),
+                    notes: None
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_workspace_index() {
+        let td = tempfile::tempdir().unwrap();
+        let index = WorkspaceIndex::new(&td.path().join("workspaces.json"));
+        assert_eq!(index.get("foo").unwrap(), None);
+        index.insert("foo", Path::new("/tmp/ws")).unwrap();
+        assert_eq!(index.get("foo").unwrap(), Some(PathBuf::from("/tmp/ws")));
+        assert_eq!(index.remove("foo").unwrap(), Some(PathBuf::from("/tmp/ws")));
+        assert_eq!(index.remove("foo").unwrap(), None);
+    }
+
+    #[test]
+    fn test_workspace_tracker() {
+        let td = tempfile::tempdir().unwrap();
+        let tracker = WorkspaceTracker::new(&td.path().join("created-workspaces.json"));
+        assert_eq!(tracker.existing().unwrap(), vec![]);
+        let created = chrono::DateTime::parse_from_rfc3339("2024-03-01T00:00:00Z")
+            .unwrap()
+            .with_timezone(&chrono::Utc);
+        let kept = CreatedWorkspace {
+            project: "foo".to_string(),
+            path: td.path().join("kept"),
+            created,
+        };
+        std::fs::create_dir(&kept.path).unwrap();
+        tracker.record(kept.clone()).unwrap();
+        tracker
+            .record(CreatedWorkspace {
+                project: "bar".to_string(),
+                path: td.path().join("gone"),
+                created,
+            })
+            .unwrap();
+        assert_eq!(tracker.existing().unwrap(), vec![kept.clone()]);
+        tracker.remove(&kept).unwrap();
+        assert!(!kept.path.exists());
+        assert_eq!(tracker.existing().unwrap(), vec![]);
+    }
+}
@@ -1,16 +1,19 @@
 use futures::TryStreamExt;
 use launchpadlib::r#async::v1_0::{
-    Milestone, Project, ProjectFull, ProjectRelease, ProjectReleaseDiff, ProjectReleaseFull,
-    ProjectSeriesFull,
+    BugTaskDiff, BugTaskStatus, Milestone, Project, ProjectFull, ProjectRelease,
+    ProjectReleaseDiff, ProjectReleaseFull, ProjectSeriesFull,
 };
 use launchpadlib::r#async::Client;
 
+use crate::limits::Service;
+
 pub async fn find_project_series(
     client: &Client,
     project: &Project,
     series_name: Option<&str>,
     target_version: Option<&str>,
 ) -> Result<ProjectSeriesFull, String> {
+    let _permit = crate::limits::acquire(Service::Launchpad).await;
     let project = project
         .get(client)
         .await
@@ -65,6 +68,7 @@ pub async fn create_milestone(
     series_name: Option<&str>,
 ) -> Result<Milestone, String> {
     let series = find_project_series(client, project, series_name, None).await?;
+    let _permit = crate::limits::acquire(Service::Launchpad).await;
     let release_date = chrono::Utc::now().date_naive();
     Ok(series
         .self_()
@@ -76,6 +80,7 @@ pub async fn create_milestone(
 }
 
 pub async fn get_project(client: &Client, project: &str) -> Result<ProjectFull, String> {
+    let _permit = crate::limits::acquire(Service::Launchpad).await;
     let root = launchpadlib::r#async::v1_0::service_root(client)
         .await
         .map_err(|e| format!("Failed to get service root: {}", e))?;
@@ -102,6 +107,7 @@ pub async fn find_release(
     project: &Project,
     release: &str,
 ) -> Option<ProjectReleaseFull> {
+    let _permit = crate::limits::acquire(Service::Launchpad).await;
     let project = project.get(client).await.unwrap();
     let releases = project
         .releases(client)
@@ -121,6 +127,7 @@ pub async fn create_release_from_milestone(
     project: &Project,
     version: &str,
 ) -> Option<ProjectRelease> {
+    let _permit = crate::limits::acquire(Service::Launchpad).await;
     let project = project.get(client).await.unwrap();
 
     let mut milestones = project.all_milestones(client).await.unwrap();
@@ -151,6 +158,7 @@ pub async fn ensure_release(
 ) -> Result<ProjectRelease, String> {
     if let Some(release) = find_release(client, proj, version).await {
         let release = release.self_().unwrap();
+        let _permit = crate::limits::acquire(Service::Launchpad).await;
         let diff = ProjectReleaseDiff {
             release_notes: release_notes.map(|s| s.to_string()),
             ..Default::default()
@@ -162,6 +170,7 @@ pub async fn ensure_release(
             .map_err(|e| format!("Failed to update release: {}", e))?;
         Ok(release)
     } else if let Some(release) = create_release_from_milestone(client, proj, version).await {
+        let _permit = crate::limits::acquire(Service::Launchpad).await;
         let diff = ProjectReleaseDiff {
             release_notes: release_notes.map(|s| s.to_string()),
             ..Default::default()
@@ -173,6 +182,7 @@ pub async fn ensure_release(
         Ok(release)
     } else {
         let milestone = create_milestone(client, proj, version, series_name).await?;
+        let _permit = crate::limits::acquire(Service::Launchpad).await;
         let today = chrono::Utc::now();
         Ok(milestone
             .create_product_release(client, &today, None, release_notes)
@@ -189,6 +199,7 @@ pub async fn add_release_files(
 ) -> Result<(), String> {
     for artifact in artifacts {
         if artifact.ends_with(".tar.gz") {
+            let _permit = crate::limits::acquire(Service::Launchpad).await;
             release
                 .add_file(
                     client,
@@ -207,3 +218,112 @@ pub async fn add_release_files(
     }
     Ok(())
 }
+
+/// Find Launchpad bug references (e.g. "LP: #12345" or "LP: #1, #2") in text.
+///
+/// References that are not valid bug numbers are logged and skipped.
+pub fn find_bug_references(text: &str) -> Vec<u32> {
+    let mut ret = vec![];
+    for m in lazy_regex::regex!(r"(?i)\bLP:\s*#\d+(?:\s*,\s*#\d+)*").find_iter(text) {
+        for bug in lazy_regex::regex!(r"#(\d+)").captures_iter(m.as_str()) {
+            let bug = match bug[1].parse() {
+                Ok(bug) => bug,
+                Err(e) => {
+                    log::warn!(
+                        "Ignoring invalid Launchpad bug reference #{}: {}",
+                        &bug[1],
+                        e
+                    );
+                    continue;
+                }
+            };
+            if !ret.contains(&bug) {
+                ret.push(bug);
+            }
+        }
+    }
+    ret
+}
+
+/// Mark the bug tasks for the specified bugs on a project as Fix Released.
+///
+/// Bugs that can not be retrieved are logged and skipped.
+pub async fn mark_bugs_fix_released(
+    client: &Client,
+    project: &ProjectFull,
+    bugs: &[u32],
+) -> Result<(), String> {
+    if bugs.is_empty() {
+        return Ok(());
+    }
+    let root = {
+        let _permit = crate::limits::acquire(Service::Launchpad).await;
+        launchpadlib::r#async::v1_0::service_root(client)
+            .await
+            .map_err(|e| format!("Failed to get service root: {}", e))?
+    };
+    let bugs_collection = root
+        .bugs()
+        .ok_or_else(|| "Launchpad service root has no bugs collection".to_string())?;
+    let project_link = project.self_link.as_ref();
+
+    for bug_id in bugs {
+        let _permit = crate::limits::acquire(Service::Launchpad).await;
+        let bug = match bugs_collection.get_by_id(client, *bug_id).await {
+            Ok(bug) => bug,
+            Err(e) => {
+                log::warn!("Unable to retrieve Launchpad bug {}: {}", bug_id, e);
+                continue;
+            }
+        };
+        let tasks = bug
+            .bug_tasks(client)
+            .await
+            .map_err(|e| format!("Failed to get tasks for bug {}: {}", bug_id, e))?
+            .try_collect::<Vec<_>>()
+            .await
+            .map_err(|e| format!("Failed to retrieve tasks for bug {}: {}", bug_id, e))?;
+        for task in tasks {
+            if Some(&task.target_link) != project_link || task.status == BugTaskStatus::FixReleased
+            {
+                continue;
+            }
+            log::info!(
+                "Marking bug {} as Fix Released on {}",
+                bug_id,
+                task.bug_target_name
+            );
+            let diff = BugTaskDiff {
+                status: Some(BugTaskStatus::FixReleased),
+                ..Default::default()
+            };
+            task.self_()
+                .unwrap()
+                .patch(client, &diff)
+                .await
+                .map_err(|e| format!("Failed to update bug task: {}", e))?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_find_bug_references() {
+        assert_eq!(
+            super::find_bug_references(
+                "  * Fix crash. (LP: #12345)\n  * Fix other thing (lp: #1, #2)\n  * Again (LP: #12345)\n"
+            ),
+            vec![12345, 1, 2]
+        );
+        assert_eq!(
+            super::find_bug_references("  * Nothing to see (#12)\n"),
+            Vec::<u32>::new()
+        );
+        assert_eq!(
+            super::find_bug_references("  * Fix (LP: #99999999999999999999999, #7)\n"),
+            vec![7]
+        );
+    }
+}
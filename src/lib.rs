@@ -1,12 +1,44 @@
+pub mod appstream;
 pub mod cargo;
+pub mod checklist;
 pub mod config;
+pub mod conventional_commits;
+pub mod credits;
 pub mod custom;
+pub mod debian;
+pub mod doc_versions;
+pub mod docs;
+pub mod doctor;
+pub mod environment;
+pub mod failures;
 pub mod github;
+pub mod history;
+pub mod init;
+pub mod journal;
 pub mod launchpad;
+pub mod limits;
+pub mod logging;
+pub mod manifest;
 pub mod manpage;
+pub mod markdown;
+pub mod metadata;
 pub mod news_file;
+pub mod nix;
+pub mod npm;
+pub mod pkgbuild;
+pub mod plugins;
+pub mod profile;
+pub mod progress;
 pub mod project_config;
 pub mod python;
+pub mod rollout;
+pub mod self_update;
+pub mod smoke_test;
+pub mod tag;
+pub mod token_cache;
+pub mod upload;
+pub mod validate;
+pub mod verify;
 pub mod version;
 use breezyshim::branch::Branch;
 use breezyshim::tree::Tree;
@@ -14,6 +46,9 @@ use breezyshim::workingtree::WorkingTree;
 use log::warn;
 use std::path::{Path, PathBuf};
 
+#[cfg(feature = "pyo3")]
+use pyo3::prelude::*;
+
 pub use version::Version;
 
 pub const USER_AGENT: &str = concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION"),);
@@ -25,26 +60,32 @@ pub enum Status {
 }
 
 #[cfg(feature = "pyo3")]
-impl pyo3::FromPyObject<'_> for Status {
-    fn extract_bound(ob: &pyo3::Bound<pyo3::PyAny>) -> pyo3::PyResult<Self> {
-        use pyo3::prelude::*;
-        let s = ob.extract::<String>()?;
-        s.parse()
-            .map_err(pyo3::PyErr::new::<pyo3::exceptions::PyValueError, _>)
+impl FromPyObject<'_> for Status {
+    fn extract_bound(ob: &Bound<PyAny>) -> PyResult<Self> {
+        // Accept members of a Python enum (e.g. Status.FINAL) as well as
+        // the plain strings that older callers pass.
+        let s = match ob.extract::<String>() {
+            Ok(s) => s,
+            Err(e) => ob
+                .getattr("value")
+                .and_then(|v| v.extract::<String>())
+                .map_err(|_| e)?,
+        };
+        s.parse().map_err(pyo3::exceptions::PyValueError::new_err)
     }
 }
 
 #[cfg(feature = "pyo3")]
-impl pyo3::ToPyObject for Status {
-    fn to_object(&self, py: pyo3::Python) -> pyo3::PyObject {
+impl ToPyObject for Status {
+    fn to_object(&self, py: Python) -> PyObject {
         self.to_string().to_object(py)
     }
 }
 
 #[cfg(feature = "pyo3")]
-impl pyo3::IntoPy<pyo3::PyObject> for Status {
-    fn into_py(self, py: pyo3::Python) -> pyo3::PyObject {
-        self.to_string().into_py(py)
+impl IntoPy<PyObject> for Status {
+    fn into_py(self, py: Python) -> PyObject {
+        self.to_object(py)
     }
 }
 
@@ -68,9 +109,31 @@ impl std::str::FromStr for Status {
     }
 }
 
+/// Check whether the tip of a branch is tagged.
+pub fn is_at_release_tag(branch: &dyn Branch) -> Result<bool, Box<dyn std::error::Error>> {
+    Ok(branch
+        .tags()?
+        .get_reverse_tag_dict()?
+        .contains_key(&branch.last_revision()))
+}
+
+/// Check whether a change touches anything in `subpath`.
+fn change_in(change: &breezyshim::tree::TreeChange, subpath: &std::path::Path) -> bool {
+    [&change.path.0, &change.path.1]
+        .into_iter()
+        .flatten()
+        .any(|p| p.starts_with(subpath))
+}
+
+/// Check whether there are changes since the last tagged revision.
+///
+/// For a project in a repository with several of them, `scope` is its tag
+/// template and directory: only tags that match the template count as
+/// releases, and only changes in the directory count.
 pub fn check_new_revisions(
     branch: &dyn Branch,
     news_file_path: Option<&std::path::Path>,
+    scope: Option<(&str, &std::path::Path)>,
 ) -> std::result::Result<bool, Box<dyn std::error::Error>> {
     let tags = branch.tags().unwrap().get_reverse_tag_dict()?;
     let lock = branch.lock_read();
@@ -80,7 +143,14 @@ pub fn check_new_revisions(
         .iter_lefthand_ancestry(&branch.last_revision(), None)
         .find_map(|revid| {
             let revid = revid.ok()?;
-            if tags.contains_key(&revid) {
+            let is_release = match (tags.get(&revid), scope) {
+                (Some(tags), Some((tag_name, _))) => tags
+                    .iter()
+                    .any(|tag| crate::version::unexpand_tag(tag_name, tag).is_ok()),
+                (Some(_), None) => true,
+                (None, _) => false,
+            };
+            if is_release {
                 Some(revid)
             } else {
                 None
@@ -116,51 +186,190 @@ pub fn check_new_revisions(
             }
         }
     }
+    if let Some((_, subpath)) = scope {
+        for changes in [
+            &mut delta.added,
+            &mut delta.removed,
+            &mut delta.renamed,
+            &mut delta.copied,
+            &mut delta.kind_changed,
+            &mut delta.modified,
+        ] {
+            changes.retain(|c| change_in(c, subpath));
+        }
+    }
     std::mem::drop(lock);
     Ok(delta.has_changed())
 }
 
+/// Find the revisions on a branch since the last tagged revision, newest first.
+pub fn revisions_since_last_tag(
+    branch: &dyn breezyshim::branch::Branch,
+) -> Result<Vec<breezyshim::repository::Revision>, Box<dyn std::error::Error>> {
+    let tags = branch.tags()?.get_reverse_tag_dict()?;
+    let _lock = branch.lock_read();
+    let repository = branch.repository();
+    let graph = repository.get_graph();
+    let mut revisions = vec![];
+    for revid in graph.iter_lefthand_ancestry(&branch.last_revision(), None) {
+        let revid = revid.map_err(|e| format!("Unable to walk ancestry: {:?}", e))?;
+        if revid.is_null() || tags.contains_key(&revid) {
+            break;
+        }
+        revisions.push(repository.get_revision(&revid)?);
+    }
+    Ok(revisions)
+}
+
+/// Check whether a path matches one of the patterns, or is inside a
+/// directory that does.
+pub fn is_ignored_path(path: &Path, ignore_paths: &[glob::Pattern]) -> bool {
+    path.ancestors()
+        .filter(|p| !p.as_os_str().is_empty())
+        .any(|p| ignore_paths.iter().any(|pattern| pattern.matches_path(p)))
+}
+
+/// Find the newest revision on a branch that changes paths other than those
+/// matching `ignore_paths`.
+pub fn last_relevant_revision(
+    branch: &dyn breezyshim::branch::Branch,
+    ignore_paths: &[glob::Pattern],
+) -> Result<Option<breezyshim::repository::Revision>, Box<dyn std::error::Error>> {
+    let _lock = branch.lock_read();
+    let repository = branch.repository();
+    let graph = repository.get_graph();
+    for revid in graph.iter_lefthand_ancestry(&branch.last_revision(), None) {
+        let revid = revid.map_err(|e| format!("Unable to walk ancestry: {:?}", e))?;
+        if revid.is_null() {
+            break;
+        }
+        let revision = repository.get_revision(&revid)?;
+        let parent = revision
+            .parent_ids
+            .first()
+            .cloned()
+            .unwrap_or_else(breezyshim::revisionid::RevisionId::null);
+        let old_tree = repository.revision_tree(&parent)?;
+        let new_tree = repository.revision_tree(&revid)?;
+        let delta = breezyshim::intertree::get(&old_tree, &new_tree).compare();
+        let relevant = [
+            &delta.added,
+            &delta.removed,
+            &delta.renamed,
+            &delta.copied,
+            &delta.kind_changed,
+            &delta.modified,
+        ]
+        .into_iter()
+        .flatten()
+        .flat_map(|c| [c.path.0.as_deref(), c.path.1.as_deref()])
+        .flatten()
+        .any(|p| !is_ignored_path(p, ignore_paths));
+        if relevant {
+            return Ok(Some(revision));
+        }
+        log::debug!("Ignoring revision {} for release age", revid);
+    }
+    Ok(None)
+}
+
 pub fn find_last_version_in_tags(
     branch: &dyn breezyshim::branch::Branch,
     tag_name: &str,
 ) -> Result<(Option<Version>, Option<Status>), Box<dyn std::error::Error>> {
     let rev_tag_dict = branch.tags()?.get_reverse_tag_dict()?;
+    if rev_tag_dict.is_empty() {
+        warn!("No tags found in the branch");
+        return Ok((None, None));
+    }
+    let _lock = branch.lock_read();
     let graph = branch.repository().get_graph();
+    let last_revision = branch.last_revision();
 
-    let (revid, tags) = graph
-        .iter_lefthand_ancestry(&branch.last_revision(), None)
-        .find_map(|r| {
-            let revid = r.ok()?;
-            rev_tag_dict.get(&revid).map(|tags| (revid, tags))
-        })
-        .unwrap();
-
-    for tag in tags {
-        let release = match crate::version::unexpand_tag(tag_name, tag) {
-            Ok(release) => release,
-            Err(_) => continue,
-        };
-        let status = if revid == branch.last_revision() {
-            Status::Final
-        } else {
-            Status::Dev
+    // Other tags (e.g. for Debian packaging) can be more recent than the
+    // last release, so keep looking until a tag matches the template.
+    for revid in graph.iter_lefthand_ancestry(&last_revision, None) {
+        let revid = revid.map_err(|e| format!("Unable to walk ancestry: {:?}", e))?;
+        if revid.is_null() {
+            break;
+        }
+        let tags = match rev_tag_dict.get(&revid) {
+            Some(tags) => tags,
+            None => continue,
         };
-        return Ok((Some(release), Some(status)));
+        let release = tags
+            .iter()
+            .filter_map(|tag| crate::version::unexpand_tag(tag_name, tag).ok())
+            .max();
+        match release {
+            Some(release) => {
+                let status = if revid == last_revision {
+                    Status::Final
+                } else {
+                    Status::Dev
+                };
+                return Ok((Some(release), Some(status)));
+            }
+            None => log::debug!("Ignoring tags {:?} that don't match {}", tags, tag_name),
+        }
     }
 
     warn!("Unable to find any tags matching {}", tag_name);
     Ok((None, None))
 }
 
+/// Find the tag for the release of a version.
+///
+/// Tag templates with date variables can't be expanded without knowing the
+/// release date, so for those the existing tags are searched instead.
+pub fn find_release_tag(
+    branch: &dyn Branch,
+    tag_template: &str,
+    version: &Version,
+    project: Option<&str>,
+) -> Result<Option<(String, breezyshim::RevisionId)>, Box<dyn std::error::Error>> {
+    let tags = branch.tags()?;
+    if !crate::version::tag_has_date(tag_template) {
+        let context = crate::version::TagContext {
+            project,
+            date: None,
+        };
+        let tag = crate::version::expand_tag_with(tag_template, version, &context);
+        if !tags.has_tag(&tag) {
+            return Ok(None);
+        }
+        let revid = tags.lookup_tag(&tag)?;
+        return Ok(Some((tag, revid)));
+    }
+    Ok(tags
+        .get_tag_dict()?
+        .into_iter()
+        .find(|(tag, _)| crate::version::unexpand_tag(tag_template, tag).as_ref() == Ok(version)))
+}
+
+/// A version read from the project files, with the status recorded next to it, if any.
+pub type FileVersion = (crate::version::Version, Option<Status>);
+
 pub fn find_last_version_in_files(
     tree: &WorkingTree,
     cfg: &project_config::ProjectConfig,
-) -> Result<Option<(crate::version::Version, Option<Status>)>, Box<dyn std::error::Error>> {
-    if tree.has_filename(Path::new("Cargo.toml")) {
+) -> Result<Option<FileVersion>, Box<dyn std::error::Error>> {
+    if cfg.path.is_some() {
+        // Only crates are supported in repositories with several projects.
+        let cargo_toml = cfg.project_dir().join("Cargo.toml");
+        if tree.has_filename(&cargo_toml) {
+            log::debug!("Reading version from {}", cargo_toml.display());
+            let contents = tree.get_file_text(&cargo_toml)?;
+            return Ok(Some((
+                cargo::find_version_in_toml(&String::from_utf8_lossy(&contents))?,
+                None,
+            )));
+        }
+    } else if tree.has_filename(Path::new("Cargo.toml")) {
         log::debug!("Reading version from Cargo.toml");
         return Ok(Some((cargo::find_version(tree)?, None)));
     }
-    if tree.has_filename(Path::new("pyproject.toml")) {
+    if cfg.path.is_none() && tree.has_filename(Path::new("pyproject.toml")) {
         log::debug!("Reading version from pyproject.toml");
         if let Some(version) = python::find_version_in_pyproject_toml(tree)? {
             return Ok(Some((version, None)));
@@ -174,6 +383,12 @@ pub fn find_last_version_in_files(
             return Ok(Some((version, None)));
         }
     }
+    if cfg.path.is_none() && tree.has_filename(Path::new("package.json")) {
+        log::debug!("Reading version from package.json");
+        if let Some(version) = npm::find_version(tree)? {
+            return Ok(Some((version, None)));
+        }
+    }
     for update_cfg in cfg.update_version.as_ref().unwrap_or(&Vec::new()) {
         let path = &update_cfg.path;
         let new_line = &update_cfg.new_line;
@@ -189,6 +404,9 @@ pub fn find_last_version_in_files(
                 .map(|l| l.as_str())
                 .collect::<Vec<_>>()
                 .as_slice(),
+            cfg.version_variables
+                .as_ref()
+                .unwrap_or(&Default::default()),
         );
         if let Some(v) = v {
             return Ok(Some((v, s)));
@@ -197,6 +415,105 @@ pub fn find_last_version_in_files(
     Ok(None)
 }
 
+/// Where the name of a project was found.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NameSource {
+    Config,
+    PyProject,
+    Cargo,
+    PackageJson,
+    Forge,
+    Url,
+}
+
+impl std::fmt::Display for NameSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            NameSource::Config => write!(f, "disperse configuration"),
+            NameSource::PyProject => write!(f, "pyproject.toml"),
+            NameSource::Cargo => write!(f, "Cargo.toml"),
+            NameSource::PackageJson => write!(f, "package.json"),
+            NameSource::Forge => write!(f, "forge"),
+            NameSource::Url => write!(f, "repository URL"),
+        }
+    }
+}
+
+/// Find the name of a project from its configuration or the metadata files in the tree.
+pub fn find_name_in_tree(
+    tree: &dyn Tree,
+    cfg: &project_config::ProjectConfig,
+) -> Option<(String, NameSource)> {
+    if let Some(name) = cfg.name.as_ref() {
+        return Some((name.clone(), NameSource::Config));
+    }
+    if tree.has_filename(Path::new("pyproject.toml")) {
+        if let Some(name) = python::find_name_in_pyproject_toml(tree) {
+            return Some((name, NameSource::PyProject));
+        }
+    }
+    if tree.has_filename(Path::new("Cargo.toml")) {
+        if let Some(name) = cargo::find_name(tree) {
+            return Some((name, NameSource::Cargo));
+        }
+    }
+    if tree.has_filename(Path::new("package.json")) {
+        if let Some(name) = npm::find_name(tree).ok().flatten() {
+            return Some((name, NameSource::PackageJson));
+        }
+    }
+    None
+}
+
+/// The version found in the files in a tree and the latest release tag disagree.
+///
+/// This usually means that somebody released the project without updating
+/// the files, or bumped the version in the files without releasing it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VersionDivergence {
+    pub in_files: Version,
+    pub in_tags: Version,
+}
+
+impl std::fmt::Display for VersionDivergence {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let (in_files, in_tags) = (self.in_files.to_string(), self.in_tags.to_string());
+        write!(
+            f,
+            "version in files ({}) does not match the latest release tag ({}); using {}",
+            in_files,
+            in_tags,
+            if self.in_tags > self.in_files {
+                in_tags.as_str()
+            } else {
+                in_files.as_str()
+            }
+        )
+    }
+}
+
+/// Reconcile the last version found in files with the one found in tags.
+///
+/// The highest of the two versions is used, so that a manual release that
+/// did not update the files doesn't lead to reusing an old version number.
+pub fn reconcile_last_version(
+    in_files: Option<Version>,
+    in_tags: Option<Version>,
+) -> Option<(Version, Option<VersionDivergence>)> {
+    match (in_files, in_tags) {
+        (Some(in_files), Some(in_tags)) if in_files != in_tags => {
+            let version = if in_tags > in_files {
+                in_tags.clone()
+            } else {
+                in_files.clone()
+            };
+            Some((version, Some(VersionDivergence { in_files, in_tags })))
+        }
+        (Some(v), _) | (None, Some(v)) => Some((v, None)),
+        (None, None) => None,
+    }
+}
+
 #[derive(Debug)]
 pub enum FindPendingVersionError {
     OddPendingVersion(String),
@@ -226,13 +543,38 @@ impl std::fmt::Display for FindPendingVersionError {
 
 impl std::error::Error for FindPendingVersionError {}
 
+/// Find the version that is pending release in the news file.
+///
+/// If `series` is given, a pending entry in the same series (major and minor
+/// version) is preferred when the news file has several.
 pub fn find_pending_version(
     tree: &dyn breezyshim::tree::Tree,
     cfg: &project_config::ProjectConfig,
+    series: Option<&Version>,
 ) -> Result<Version, FindPendingVersionError> {
     if let Some(news_file) = cfg.news_file.as_ref() {
-        match news_file::tree_news_find_pending(tree, news_file) {
-            Ok(Some(version)) => Ok(version.parse().unwrap()),
+        let layout = news_file::NewsLayout::from_config(cfg)
+            .map_err(|e| FindPendingVersionError::Other(Box::new(e)))?;
+        // When catching up sequentially, release the oldest of several
+        // unreleased sections first.
+        let oldest = match cfg.catch_up {
+            Some(project_config::CatchUp::Sequential) => {
+                news_file::tree_news_find_oldest_pending(tree, news_file, &layout)
+            }
+            _ => Ok(None),
+        };
+        let pending = oldest.and_then(|oldest| match oldest {
+            Some(version) => Ok(Some(version)),
+            None => news_file::tree_news_find_pending(tree, news_file, &layout, series),
+        });
+        match pending {
+            Ok(Some(version)) => {
+                Version::parse_with_scheme(&version, cfg.version_scheme.unwrap_or_default())
+                    .map_err(|e| {
+                        log::debug!("Unable to parse pending version {}: {}", version, e);
+                        FindPendingVersionError::OddPendingVersion(version)
+                    })
+            }
             Ok(None) => Err(FindPendingVersionError::NoUnreleasedChanges),
             Err(news_file::Error::OddVersion(e)) => {
                 Err(FindPendingVersionError::OddPendingVersion(e))
@@ -287,10 +629,129 @@ pub fn iter_glob<'a>(
         .filter(|p| !local_tree.is_control_filename(p))
 }
 
+/// Directory that build artifacts are written to by default, relative to the tree root.
+pub const DEFAULT_ARTIFACT_DIRECTORY: &str = "dist";
+
+/// Make sure that a directory in a tree is ignored, so that build output
+/// doesn't show up as unknown files or end up in commits.
+///
+/// For git trees this adds the directory to .git/info/exclude, so that no
+/// versioned files are modified.
+pub fn ensure_ignored(local_tree: &WorkingTree, path: &Path) -> std::io::Result<()> {
+    if let Some(pattern) = local_tree.is_ignored(path) {
+        log::debug!("{} is already ignored ({})", path.display(), pattern);
+        return Ok(());
+    }
+
+    let git_dir = local_tree.basedir().join(".git");
+    if !git_dir.is_dir() {
+        warn!(
+            "{} is not ignored; build artifacts may show up as unknown files",
+            path.display()
+        );
+        return Ok(());
+    }
+
+    let info_dir = git_dir.join("info");
+    std::fs::create_dir_all(&info_dir)?;
+    use std::io::Write;
+    let mut f = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(info_dir.join("exclude"))?;
+    writeln!(f, "/{}/", path.display())?;
+    log::debug!("Added {} to .git/info/exclude", path.display());
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_change_in() {
+        let change = |old: Option<&str>, new: Option<&str>| breezyshim::tree::TreeChange {
+            path: (old.map(Into::into), new.map(Into::into)),
+            changed_content: true,
+            versioned: (Some(true), Some(true)),
+            name: (None, None),
+            kind: (None, None),
+            executable: (Some(false), Some(false)),
+            copied: false,
+        };
+        let subpath = std::path::Path::new("crates/foo");
+        assert!(change_in(
+            &change(Some("crates/foo/src/lib.rs"), None),
+            subpath
+        ));
+        assert!(change_in(
+            &change(Some("README"), Some("crates/foo/README")),
+            subpath
+        ));
+        assert!(!change_in(
+            &change(Some("crates/foobar/Cargo.toml"), None),
+            subpath
+        ));
+        assert!(!change_in(&change(None, Some("Cargo.toml")), subpath));
+    }
+
+    #[cfg(feature = "pyo3")]
+    #[test]
+    fn test_status_from_python() {
+        Python::with_gil(|py| {
+            let final_ = Status::Final.into_py(py);
+            assert_eq!(final_.extract::<String>(py).unwrap(), "final");
+            assert_eq!(final_.extract::<Status>(py).unwrap(), Status::Final);
+
+            let enum_ = py
+                .import_bound("enum")
+                .unwrap()
+                .getattr("Enum")
+                .unwrap()
+                .call1(("Status", vec![("FINAL", "final"), ("DEV", "dev")]))
+                .unwrap();
+            let dev = enum_.getattr("DEV").unwrap();
+            assert_eq!(dev.extract::<Status>().unwrap(), Status::Dev);
+            assert!("bogus".to_object(py).extract::<Status>(py).is_err());
+        });
+    }
+
+    #[test]
+    fn test_reconcile_last_version() {
+        let v = |s: &str| s.parse::<Version>().unwrap();
+        assert_eq!(reconcile_last_version(None, None), None);
+        assert_eq!(
+            reconcile_last_version(Some(v("1.2.0")), None),
+            Some((v("1.2.0"), None))
+        );
+        assert_eq!(
+            reconcile_last_version(Some(v("1.2.0")), Some(v("1.2.0"))),
+            Some((v("1.2.0"), None))
+        );
+        let (version, divergence) =
+            reconcile_last_version(Some(v("1.2.0")), Some(v("1.3.0"))).unwrap();
+        assert_eq!(version, v("1.3.0"));
+        assert_eq!(
+            divergence.unwrap().to_string(),
+            "version in files (1.2.0) does not match the latest release tag (1.3.0); using 1.3.0"
+        );
+    }
+
+    #[test]
+    fn test_is_ignored_path() {
+        let patterns = [
+            glob::Pattern::new(".github").unwrap(),
+            glob::Pattern::new("*.md").unwrap(),
+        ];
+        assert!(is_ignored_path(
+            Path::new(".github/workflows/ci.yml"),
+            &patterns
+        ));
+        assert!(is_ignored_path(Path::new("README.md"), &patterns));
+        assert!(!is_ignored_path(Path::new("src/lib.rs"), &patterns));
+        assert!(!is_ignored_path(Path::new("src/lib.rs"), &[]));
+    }
+
     #[test]
     fn test_iter_glob() {
         let td = tempfile::tempdir().unwrap();
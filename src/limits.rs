@@ -0,0 +1,244 @@
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use tokio::runtime::RuntimeFlavor;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+/// External service that disperse makes requests to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, serde::Deserialize)]
+pub enum Service {
+    #[serde(rename = "github")]
+    GitHub,
+    #[serde(rename = "crates-io")]
+    CratesIo,
+    #[serde(rename = "pypi")]
+    PyPI,
+    #[serde(rename = "launchpad")]
+    Launchpad,
+    /// Hosts that release tarballs are uploaded to.
+    #[serde(rename = "tarball-upload")]
+    TarballUpload,
+}
+
+impl std::fmt::Display for Service {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str(match self {
+            Service::GitHub => "github",
+            Service::CratesIo => "crates-io",
+            Service::PyPI => "pypi",
+            Service::Launchpad => "launchpad",
+            Service::TarballUpload => "tarball-upload",
+        })
+    }
+}
+
+/// Limits on the requests made to a service.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Deserialize)]
+pub struct ServiceLimits {
+    /// Maximum number of requests in flight at once.
+    pub concurrency: Option<usize>,
+
+    /// Maximum number of requests to start per minute.
+    #[serde(rename = "per-minute")]
+    pub per_minute: Option<u32>,
+}
+
+struct Limiter {
+    concurrency: Option<usize>,
+    semaphore: Option<Arc<Semaphore>>,
+    interval: Option<Duration>,
+    next_slot: Mutex<Option<Instant>>,
+}
+
+impl Limiter {
+    fn new(limits: &ServiceLimits) -> Self {
+        let concurrency = limits.concurrency.map(|c| c.max(1));
+        Self {
+            concurrency,
+            semaphore: concurrency.map(|c| Arc::new(Semaphore::new(c))),
+            interval: limits
+                .per_minute
+                .filter(|p| *p > 0)
+                .map(|p| Duration::from_secs(60) / p),
+            next_slot: Mutex::new(None),
+        }
+    }
+
+    /// Reserve the next slot allowed by the rate limit; returns how long
+    /// to wait for it.
+    fn reserve(&self, now: Instant) -> Duration {
+        let Some(interval) = self.interval else {
+            return Duration::ZERO;
+        };
+        let mut next_slot = self.next_slot.lock().unwrap();
+        let slot = next_slot.map_or(now, |n| n.max(now));
+        *next_slot = Some(slot + interval);
+        slot - now
+    }
+
+    /// Wait for a concurrency slot, from synchronous code.
+    fn acquire_permit_blocking(&self) -> Option<OwnedSemaphorePermit> {
+        let semaphore = self.semaphore.clone()?;
+        match tokio::runtime::Handle::try_current() {
+            // Let the runtime move other tasks, which may be holding permits,
+            // off this worker while waiting.
+            Ok(handle) if handle.runtime_flavor() == RuntimeFlavor::MultiThread => Some(
+                tokio::task::block_in_place(|| handle.block_on(semaphore.acquire_owned())).unwrap(),
+            ),
+            // Waiting would block the only thread that can release permits.
+            Ok(_) => {
+                let permit = semaphore.try_acquire_owned().ok();
+                if permit.is_none() {
+                    log::debug!("Not waiting for a concurrency slot on a current-thread runtime");
+                }
+                permit
+            }
+            Err(_) => Some(futures::executor::block_on(semaphore.acquire_owned()).unwrap()),
+        }
+    }
+}
+
+/// Permission to make a request to a service; released when dropped.
+pub struct Permit {
+    _permit: Option<OwnedSemaphorePermit>,
+}
+
+static LIMITERS: OnceLock<BTreeMap<Service, Limiter>> = OnceLock::new();
+
+/// Set the limits for services. Services without limits are unrestricted.
+pub fn configure(limits: &BTreeMap<Service, ServiceLimits>) {
+    if LIMITERS
+        .set(
+            limits
+                .iter()
+                .map(|(service, limits)| (*service, Limiter::new(limits)))
+                .collect(),
+        )
+        .is_err()
+    {
+        log::warn!("Service limits were already configured");
+    }
+}
+
+fn limiter(service: Service) -> Option<&'static Limiter> {
+    LIMITERS.get()?.get(&service)
+}
+
+/// Maximum number of requests to a service in flight at once, if limited.
+pub fn concurrency(service: Service) -> Option<usize> {
+    limiter(service)?.concurrency
+}
+
+/// Wait until a request to a service is allowed.
+pub async fn acquire(service: Service) -> Permit {
+    let Some(limiter) = limiter(service) else {
+        return Permit { _permit: None };
+    };
+    let permit = match limiter.semaphore.as_ref() {
+        Some(semaphore) => Some(semaphore.clone().acquire_owned().await.unwrap()),
+        None => None,
+    };
+    let delay = limiter.reserve(Instant::now());
+    if !delay.is_zero() {
+        log::debug!("Waiting {:?} for the {} rate limit", delay, service);
+        tokio::time::sleep(delay).await;
+    }
+    Permit { _permit: permit }
+}
+
+/// Wait until a request to a service is allowed, from synchronous code.
+///
+/// This may be called from synchronous code that runs on the async runtime.
+pub fn acquire_blocking(service: Service) -> Permit {
+    let Some(limiter) = limiter(service) else {
+        return Permit { _permit: None };
+    };
+    let permit = limiter.acquire_permit_blocking();
+    let delay = limiter.reserve(Instant::now());
+    if !delay.is_zero() {
+        log::debug!("Waiting {:?} for the {} rate limit", delay, service);
+        std::thread::sleep(delay);
+    }
+    Permit { _permit: permit }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reserve() {
+        let limiter = Limiter::new(&ServiceLimits {
+            concurrency: None,
+            per_minute: Some(30),
+        });
+        let now = Instant::now();
+        assert_eq!(limiter.reserve(now), Duration::ZERO);
+        assert_eq!(limiter.reserve(now), Duration::from_secs(2));
+        assert_eq!(limiter.reserve(now), Duration::from_secs(4));
+        // Unused slots don't accumulate.
+        let later = now + Duration::from_secs(60);
+        assert_eq!(limiter.reserve(later), Duration::ZERO);
+
+        let unlimited = Limiter::new(&ServiceLimits::default());
+        assert_eq!(unlimited.reserve(now), Duration::ZERO);
+        assert_eq!(unlimited.reserve(now), Duration::ZERO);
+    }
+
+    #[test]
+    fn test_concurrency() {
+        let limiter = Limiter::new(&ServiceLimits {
+            concurrency: Some(1),
+            per_minute: None,
+        });
+        let semaphore = limiter.semaphore.as_ref().unwrap();
+        let permit = semaphore.clone().try_acquire_owned().unwrap();
+        assert!(semaphore.clone().try_acquire_owned().is_err());
+        drop(permit);
+        assert!(semaphore.clone().try_acquire_owned().is_ok());
+    }
+
+    #[test]
+    fn test_acquire_permit_blocking() {
+        let limiter = Arc::new(Limiter::new(&ServiceLimits {
+            concurrency: Some(1),
+            per_minute: None,
+        }));
+
+        // Outside of a runtime, the permit is simply waited for.
+        let permit = limiter.acquire_permit_blocking();
+        assert!(permit.is_some());
+        drop(permit);
+
+        // On a multi-threaded runtime, the holder of the permit can still
+        // make progress while waiting for it.
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(1)
+            .enable_all()
+            .build()
+            .unwrap();
+        runtime.block_on(async {
+            let held = limiter.semaphore.clone().unwrap().acquire_owned().await;
+            let release = tokio::spawn(async move {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+                drop(held);
+            });
+            let waiter = limiter.clone();
+            assert!(
+                tokio::spawn(async move { waiter.acquire_permit_blocking().is_some() })
+                    .await
+                    .unwrap()
+            );
+            release.await.unwrap();
+        });
+
+        // On a current-thread runtime, it doesn't deadlock.
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        runtime.block_on(async {
+            let _held = limiter.semaphore.clone().unwrap().acquire_owned().await;
+            assert!(limiter.acquire_permit_blocking().is_none());
+        });
+    }
+}
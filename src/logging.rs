@@ -0,0 +1,155 @@
+use std::fs::File;
+use std::io::{BufRead, Read, Write};
+use std::path::Path;
+use std::process::{Command, ExitStatus, Stdio};
+use std::sync::{Mutex, OnceLock};
+
+/// File that full debug output is written to, if any.
+static LOG_FILE: OnceLock<Mutex<File>> = OnceLock::new();
+
+/// Level of the output on the console.
+static CONSOLE_LEVEL: OnceLock<log::LevelFilter> = OnceLock::new();
+
+fn write_to_log_file(line: &str) {
+    if let Some(f) = LOG_FILE.get() {
+        let mut f = f.lock().unwrap();
+        let _ = writeln!(f, "{}", line);
+    }
+}
+
+/// Logger that writes to the console and, if there is one, the log file.
+struct Logger {
+    console: env_logger::Logger,
+}
+
+impl log::Log for Logger {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        self.console.enabled(metadata)
+            || (LOG_FILE.get().is_some() && metadata.level() <= log::Level::Debug)
+    }
+
+    fn log(&self, record: &log::Record) {
+        if self.console.matches(record) {
+            let _terminal = crate::progress::clear();
+            self.console.log(record);
+        }
+        if LOG_FILE.get().is_some() && record.level() <= log::Level::Debug {
+            write_to_log_file(&format!(
+                "{} {} {}: {}",
+                chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+                record.level(),
+                record.target(),
+                record.args()
+            ));
+        }
+    }
+
+    fn flush(&self) {
+        self.console.flush();
+        if let Some(f) = LOG_FILE.get() {
+            let _ = f.lock().unwrap().flush();
+        }
+    }
+}
+
+/// Level of console output for the given number of --verbose options.
+pub fn console_level(quiet: bool, verbose: u8) -> log::LevelFilter {
+    match (quiet, verbose) {
+        (true, _) => log::LevelFilter::Warn,
+        (false, 0) => log::LevelFilter::Info,
+        (false, 1) => log::LevelFilter::Debug,
+        (false, _) => log::LevelFilter::Trace,
+    }
+}
+
+/// Check whether messages at `level` are shown on the console.
+pub fn console_enabled(level: log::Level) -> bool {
+    CONSOLE_LEVEL.get().is_some_and(|l| level <= *l)
+}
+
+/// Set up logging to the console at `level`.
+///
+/// If `log_file` is set, full debug output is appended to it as well,
+/// whatever the console level is.
+pub fn init(level: log::LevelFilter, log_file: Option<&Path>) -> Result<(), std::io::Error> {
+    if let Some(path) = log_file {
+        let f = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)?;
+        let _ = LOG_FILE.set(Mutex::new(f));
+    }
+    let console = env_logger::builder()
+        .format(|buf, record| writeln!(buf, "{}", record.args()))
+        .filter(None, level)
+        .build();
+    let max_level = if LOG_FILE.get().is_some() {
+        console.filter().max(log::LevelFilter::Debug)
+    } else {
+        console.filter()
+    };
+    log::set_boxed_logger(Box::new(Logger { console })).map_err(std::io::Error::other)?;
+    log::set_max_level(max_level);
+    let _ = CONSOLE_LEVEL.set(level);
+    Ok(())
+}
+
+/// Copy output of a subprocess line by line, to `to` and to the log file.
+fn copy_lines(from: impl Read, mut to: impl Write) {
+    for line in std::io::BufReader::new(from)
+        .split(b'\n')
+        .map_while(Result::ok)
+    {
+        let _ = to.write_all(&line);
+        let _ = to.write_all(b"\n");
+        let _ = to.flush();
+        write_to_log_file(&String::from_utf8_lossy(&line));
+    }
+}
+
+/// Run a command like [`Command::status`], copying its output to the log file.
+///
+/// Without a log file, the command simply inherits stdout and stderr.
+pub fn status(cmd: &mut Command) -> Result<ExitStatus, std::io::Error> {
+    let _suspended = crate::progress::suspend();
+    if LOG_FILE.get().is_none() {
+        return cmd.status();
+    }
+    // Not the Debug output of the command, as that includes environment
+    // variables holding tokens.
+    write_to_log_file(&format!(
+        "Running {:?} {:?}",
+        cmd.get_program(),
+        cmd.get_args().collect::<Vec<_>>()
+    ));
+    let mut child = cmd.stdout(Stdio::piped()).stderr(Stdio::piped()).spawn()?;
+    let stdout = child.stdout.take().unwrap();
+    let stderr = child.stderr.take().unwrap();
+    let stdout = std::thread::spawn(move || copy_lines(stdout, std::io::stdout()));
+    let stderr = std::thread::spawn(move || copy_lines(stderr, std::io::stderr()));
+    let status = child.wait();
+    let _ = stdout.join();
+    let _ = stderr.join();
+    write_to_log_file(&format!("Exited with {:?}", status));
+    status
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_console_level() {
+        assert_eq!(console_level(false, 0), log::LevelFilter::Info);
+        assert_eq!(console_level(false, 1), log::LevelFilter::Debug);
+        assert_eq!(console_level(false, 3), log::LevelFilter::Trace);
+        assert_eq!(console_level(true, 0), log::LevelFilter::Warn);
+    }
+
+    #[test]
+    fn test_copy_lines() {
+        let mut out = vec![];
+        copy_lines(&b"first\nsecond"[..], &mut out);
+        assert_eq!(out, b"first\nsecond\n");
+    }
+}
@@ -2,11 +2,16 @@ use breezyshim::error::Error as BrzError;
 use breezyshim::tree::{MutableTree, Tree};
 use breezyshim::workingtree::{self, WorkingTree};
 use clap::Parser;
-use disperse::project_config::{read_project_with_fallback, ProjectConfig};
+use disperse::journal::Step;
+use disperse::project_config::{
+    Bump, CatchUp, LockfileUpdate, NewsFromCommits, ProjectConfig, VersionBump, VersionScheme,
+};
+use disperse::smoke_test::Registry;
+use disperse::validate::{Finding, Severity};
 use disperse::version::Version;
 use disperse::{find_last_version_in_files, find_last_version_in_tags};
 use maplit::hashmap;
-use std::io::Write;
+use std::io::IsTerminal;
 use std::path::Path;
 use url::Url;
 
@@ -69,30 +74,144 @@ lazy_static::lazy_static! {
         &["project"]).unwrap();
 }
 
-async fn push_to_gateway(prometheus_url: &str) -> Result<(), Box<dyn std::error::Error>> {
+/// Build the push gateway URL for a job and its grouping key.
+///
+/// Label values that can't be represented in a URL path segment are base64
+/// encoded, as supported by the push gateway.
+fn pushgateway_url(
+    base_url: &str,
+    job: &str,
+    grouping: &[(&str, &str)],
+) -> Result<Url, url::ParseError> {
+    use base64::Engine;
+    let mut url: Url = base_url.parse()?;
+    {
+        let mut segments = url
+            .path_segments_mut()
+            .map_err(|_| url::ParseError::RelativeUrlWithCannotBeABaseBase)?;
+        segments.pop_if_empty().push("metrics");
+        for (label, value) in [("job", job)].iter().chain(grouping.iter()) {
+            if value.is_empty() || value.contains('/') {
+                segments.push(&format!("{}@base64", label));
+                segments.push(&base64::engine::general_purpose::URL_SAFE.encode(value));
+            } else {
+                segments.push(label);
+                segments.push(value);
+            }
+        }
+    }
+    Ok(url)
+}
+
+/// Split metrics by the project they are about, so that each project can be
+/// pushed under its own grouping key.
+///
+/// Metrics that are not about a particular project are listed under None.
+fn metrics_by_project(
+    families: &[prometheus::proto::MetricFamily],
+) -> std::collections::BTreeMap<Option<String>, Vec<prometheus::proto::MetricFamily>> {
+    let mut ret = std::collections::BTreeMap::<_, Vec<_>>::new();
+    for family in families {
+        let projects = family
+            .get_metric()
+            .iter()
+            .map(metric_project)
+            .collect::<std::collections::BTreeSet<_>>();
+        for project in projects {
+            let mut family = family.clone();
+            family.mut_metric().retain(|m| metric_project(m) == project);
+            ret.entry(project).or_default().push(family);
+        }
+    }
+    ret
+}
+
+fn metric_project(metric: &prometheus::proto::Metric) -> Option<String> {
+    metric
+        .get_label()
+        .iter()
+        .find(|l| l.get_name() == "project")
+        .map(|l| l.get_value().to_string())
+}
+
+async fn push_to_gateway(
+    prometheus_url: &str,
+    config: &disperse::config::PrometheusConfig,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let encoder = TextEncoder::new();
+
+    let mut grouping = vec![];
+    if let Some(instance) = config.instance.as_deref() {
+        grouping.push(("instance", instance));
+    }
+    grouping.extend(
+        config
+            .grouping
+            .iter()
+            .map(|(k, v)| (k.as_str(), v.as_str())),
+    );
+
+    for (project, families) in metrics_by_project(&default_registry().gather()) {
+        let mut buffer = vec![];
+        encoder.encode(&families, &mut buffer)?;
+        let metrics = String::from_utf8(buffer)?;
+
+        let mut grouping = grouping.clone();
+        if let Some(project) = project.as_deref() {
+            grouping.push(("project", project));
+        }
+        let url = pushgateway_url(
+            prometheus_url,
+            config.job.as_deref().unwrap_or("disperse"),
+            grouping.as_slice(),
+        )?;
+        let mut request = reqwest::Client::new().post(url).body(metrics);
+        if let Some(token) = config.bearer_token.as_ref() {
+            request = request.bearer_auth(token);
+        } else if let Some(username) = config.username.as_ref() {
+            request = request.basic_auth(username, config.password.as_ref());
+        }
+        request.send().await?.error_for_status()?;
+    }
+
+    Ok(())
+}
+
+/// Write metrics in the format used by the node_exporter textfile collector.
+///
+/// The file is written atomically, so that the collector never sees a partial file.
+fn write_textfile(directory: &Path, job: &str) -> Result<(), Box<dyn std::error::Error>> {
     let mut buffer = vec![];
     let encoder = TextEncoder::new();
     encoder.encode(&default_registry().gather(), &mut buffer)?;
 
-    let metrics = String::from_utf8(buffer)?;
-
-    let url = format!("{}/metrics/job/disperse", prometheus_url);
-    reqwest::Client::new()
-        .post(url)
-        .body(metrics)
-        .send()
-        .await?
-        .error_for_status()?;
+    let path = directory.join(format!("{}.prom", job));
+    let tmp_path = directory.join(format!(".{}.prom.{}", job, std::process::id()));
+    std::fs::write(&tmp_path, buffer)?;
+    std::fs::rename(&tmp_path, &path)?;
+    log::debug!("Wrote metrics to {}", path.display());
 
     Ok(())
 }
 
 #[derive(Parser)]
 struct Args {
-    /// Print debug output
+    /// Print debug output; the same as -v
     #[clap(long)]
     debug: bool,
 
+    /// Print more output; repeat for even more
+    #[clap(short, long, action = clap::ArgAction::Count)]
+    verbose: u8,
+
+    /// Only print warnings and errors
+    #[clap(short, long, conflicts_with_all = ["debug", "verbose"])]
+    quiet: bool,
+
+    /// Append full debug output, including that of the commands run, to this file
+    #[clap(long)]
+    log_file: Option<std::path::PathBuf>,
+
     /// Do not actually do anything
     #[clap(long)]
     dry_run: bool,
@@ -101,10 +220,57 @@ struct Args {
     #[clap(long)]
     prometheus: Option<String>,
 
+    /// Directory to write metrics to for the node_exporter textfile collector
+    #[clap(long)]
+    prometheus_textfile_dir: Option<std::path::PathBuf>,
+
+    /// Path to the global configuration file
+    #[clap(long, env = "DISPERSE_CONFIG")]
+    config: Option<std::path::PathBuf>,
+
+    /// Path to the project configuration file, relative to the root of the tree
+    #[clap(long, env = "DISPERSE_PROJECT_CONFIG")]
+    project_config: Option<std::path::PathBuf>,
+
+    /// Override a value in the project configuration, e.g. tag-name=v$VERSION
+    #[clap(long = "set", value_name = "KEY=VALUE")]
+    overrides: Vec<disperse::project_config::Override>,
+
+    /// Maximum time to wait for CI to pass, in seconds; overrides ci-timeout
+    #[clap(long)]
+    ci_timeout: Option<u64>,
+
+    /// Time between checks of the CI status, in seconds; overrides ci-poll-interval
+    #[clap(long)]
+    ci_poll_interval: Option<u64>,
+
+    /// Profile from the global configuration to release as
+    #[clap(long, env = "DISPERSE_PROFILE")]
+    profile: Option<String>,
+
+    /// Output format, for commands that support it
+    #[clap(long, global = true, value_enum, default_value_t = OutputFormat::Text)]
+    format: OutputFormat,
+
+    /// Shorthand for --format=json
+    #[clap(long, global = true, conflicts_with = "format")]
+    json: bool,
+
     #[command(subcommand)]
     command: Commands,
 }
 
+impl Args {
+    /// The output format to use, taking --json into account.
+    fn output_format(&self) -> OutputFormat {
+        if self.json {
+            OutputFormat::Json
+        } else {
+            self.format
+        }
+    }
+}
+
 #[derive(clap::Subcommand)]
 enum Commands {
     /// Release a new version of a project
@@ -122,11 +288,127 @@ enum Commands {
     /// Run the verify command
     Verify(VerifyArgs),
 
+    /// Build the release artifacts in the artifact directory, without releasing
+    Build(BuildArgs),
+
+    /// Publish the artifacts for an existing tag, without tagging or pushing
+    Publish(PublishArgs),
+
     /// Migrate configuration to a new version
     Migrate(MigrateArgs),
 
     /// Initialize a new project
     Init(InitArgs),
+
+    /// Promote a release published to a staged rollout channel
+    Promote(PromoteArgs),
+
+    /// Store credentials for a service in the keyring
+    Login(LoginArgs),
+
+    /// Manage tokens in the encrypted token cache
+    Auth(AuthArgs),
+
+    /// List releases performed by disperse
+    History(HistoryArgs),
+
+    /// Check whether a project is ready to be released, without releasing it
+    Ready(ReadyArgs),
+
+    /// Report the CI status of a project, without releasing anything
+    CheckCi(CheckCiArgs),
+
+    /// Remove workspaces left behind by earlier runs
+    Clean(CleanArgs),
+
+    /// Verify a signed release manifest against artifacts and the repository
+    VerifyManifest(VerifyManifestArgs),
+
+    /// Update disperse to the latest release
+    SelfUpdate,
+
+    /// Print the pending section of the news file
+    Changelog(ChangelogArgs),
+
+    /// List the commits since the last release
+    Unreleased(UnreleasedArgs),
+
+    /// Show the release state of several projects
+    Status(StatusArgs),
+
+    /// Check that tools, credentials and configuration are set up for releasing
+    Doctor(DoctorArgs),
+}
+
+#[derive(clap::Args)]
+struct DoctorArgs {
+    /// Path of the project whose configuration to check
+    #[clap(default_value = ".")]
+    path: std::path::PathBuf,
+}
+
+#[derive(clap::Args)]
+struct ChangelogArgs {
+    /// Path or URL for project
+    #[clap(default_value = ".")]
+    path: std::path::PathBuf,
+
+    /// Render the changes as Markdown
+    #[clap(long)]
+    markdown: bool,
+}
+
+#[derive(clap::Args)]
+struct UnreleasedArgs {
+    /// Path or URL for project
+    #[clap(default_value = ".")]
+    path: std::path::PathBuf,
+
+    /// Group the commits
+    #[clap(long, value_enum)]
+    group_by: Option<GroupBy>,
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum GroupBy {
+    /// By the name of the committer
+    Author,
+    /// By Conventional Commits type
+    Type,
+}
+
+#[derive(clap::Args)]
+struct VerifyManifestArgs {
+    /// Path to the release manifest
+    manifest: std::path::PathBuf,
+
+    /// Artifacts to check against the digests in the manifest
+    artifacts: Vec<std::path::PathBuf>,
+
+    /// Path or URL of the repository, to check that the tag points at the
+    /// revision in the manifest
+    #[clap(long)]
+    repository: Option<String>,
+}
+
+#[derive(clap::Args)]
+struct BuildArgs {
+    /// Path or URL for project
+    #[clap(default_value = ".")]
+    path: std::path::PathBuf,
+}
+
+#[derive(clap::Args)]
+struct PublishArgs {
+    /// Tag of the release to publish
+    tag: String,
+
+    /// Artifacts to publish; built from the tagged revision if none are given
+    artifacts: Vec<std::path::PathBuf>,
+
+    /// Path or URL for project
+    #[clap(long, default_value = ".")]
+    url: String,
 }
 
 #[derive(clap::Args)]
@@ -145,6 +427,10 @@ struct ReleaseArgs {
     #[clap(long)]
     new_version: Option<String>,
 
+    /// Version component to increase, if the last version has been released
+    #[clap(long, conflicts_with = "new_version")]
+    bump: Option<Bump>,
+
     /// Release even if the CI is not passing
     #[clap(long)]
     ignore_ci: bool,
@@ -159,6 +445,18 @@ struct ReleaseArgs {
     #[clap(long)]
     /// Preserve the temporary directory used for building
     preserve_temp: bool,
+
+    /// Continue a release that failed part way through from the last step that succeeded
+    #[clap(long)]
+    resume: bool,
+
+    /// Do not ask for confirmation before committing, tagging and publishing
+    #[clap(long)]
+    non_interactive: bool,
+
+    /// Project to release, in a repository with several of them; by default all are released
+    #[clap(long)]
+    project: Vec<String>,
 }
 
 #[derive(clap::Args)]
@@ -186,6 +484,18 @@ struct DiscoverArgs {
     /// Do not exit with non-zero if projects failed to be released
     #[clap(long)]
     r#try: bool,
+
+    /// Stop after releasing this many projects
+    #[clap(long)]
+    limit: Option<usize>,
+
+    /// Only consider projects whose name or URL matches this glob
+    #[clap(long)]
+    only: Vec<glob::Pattern>,
+
+    /// Skip projects whose name or URL matches this glob
+    #[clap(long)]
+    exclude: Vec<glob::Pattern>,
 }
 
 #[derive(clap::Args)]
@@ -193,6 +503,17 @@ struct ValidateArgs {
     /// Path or URL for project
     #[clap(default_value = ".")]
     path: std::path::PathBuf,
+
+    /// Print the JSON Schema for disperse.toml rather than validating
+    #[clap(long)]
+    schema: bool,
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+enum OutputFormat {
+    #[default]
+    Text,
+    Json,
 }
 
 #[derive(clap::Args)]
@@ -202,6 +523,39 @@ struct InfoArgs {
     path: std::path::PathBuf,
 }
 
+#[derive(clap::Args)]
+struct CleanArgs {
+    /// Only list the workspaces that would be removed
+    #[clap(long)]
+    list: bool,
+
+    /// Only remove workspaces created at least this many days ago
+    #[clap(long, default_value_t = 1)]
+    older_than: u64,
+
+    /// Also remove the workspaces of failed releases, so they can no longer be resumed
+    #[clap(long)]
+    include_resumable: bool,
+}
+
+#[derive(clap::Args)]
+struct CheckCiArgs {
+    /// Path or URL for project
+    #[clap(default_value = ".")]
+    path: std::path::PathBuf,
+
+    /// Wait for pending CI runs to finish, up to the CI timeout
+    #[clap(long)]
+    wait: bool,
+}
+
+#[derive(clap::Args)]
+struct ReadyArgs {
+    /// Path or URL for project
+    #[clap(default_value = ".")]
+    path: std::path::PathBuf,
+}
+
 #[derive(clap::Args)]
 struct MigrateArgs {
     /// Path or URL for project
@@ -216,6 +570,121 @@ struct InitArgs {
     path: std::path::PathBuf,
 }
 
+#[derive(clap::Args)]
+struct LoginArgs {
+    /// Service to store credentials for
+    #[clap(value_enum)]
+    service: LoginService,
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum LoginService {
+    CratesIo,
+}
+
+#[derive(clap::Args)]
+struct AuthArgs {
+    #[command(subcommand)]
+    command: AuthCommand,
+}
+
+#[derive(clap::Subcommand)]
+enum AuthCommand {
+    /// Store a token for a service in the token cache
+    Login {
+        /// Service to store the token for
+        #[clap(value_enum)]
+        service: AuthService,
+
+        /// Number of days after which the token expires
+        #[clap(long, default_value_t = 90)]
+        expires_in: u32,
+
+        /// Keep the token until it is removed
+        #[clap(long, conflicts_with = "expires_in")]
+        no_expiry: bool,
+    },
+
+    /// Remove the token for a service from the token cache
+    Logout {
+        /// Service to remove the token for
+        #[clap(value_enum)]
+        service: AuthService,
+    },
+
+    /// List the tokens in the token cache
+    Status,
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum AuthService {
+    Github,
+    CratesIo,
+    Pypi,
+    Npm,
+}
+
+impl From<AuthService> for disperse::token_cache::Service {
+    fn from(s: AuthService) -> Self {
+        match s {
+            AuthService::Github => Self::GitHub,
+            AuthService::CratesIo => Self::CratesIo,
+            AuthService::Pypi => Self::PyPI,
+            AuthService::Npm => Self::Npm,
+        }
+    }
+}
+
+#[derive(clap::Args)]
+struct StatusArgs {
+    /// URLs or paths of projects; defaults to the repositories in the configuration
+    url: Vec<String>,
+
+    /// Column to sort by
+    #[clap(long, value_enum, default_value_t = StatusSort::Project)]
+    sort: StatusSort,
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum StatusSort {
+    /// Alphabetically by project name
+    Project,
+    /// Longest since the last release first
+    Age,
+    /// Most unreleased revisions first
+    Unreleased,
+}
+
+#[derive(clap::Args)]
+struct HistoryArgs {
+    /// Only list releases performed since this date (YYYY-MM-DD or RFC 3339)
+    #[clap(long, value_parser = parse_since)]
+    since: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+fn parse_since(s: &str) -> Result<chrono::DateTime<chrono::Utc>, String> {
+    if let Ok(date) = chrono::NaiveDate::parse_from_str(s, "%Y-%m-%d") {
+        return Ok(date.and_time(chrono::NaiveTime::MIN).and_utc());
+    }
+    chrono::DateTime::parse_from_rfc3339(s)
+        .map(|dt| dt.with_timezone(&chrono::Utc))
+        .map_err(|e| e.to_string())
+}
+
+#[derive(clap::Args)]
+struct PromoteArgs {
+    /// Version to promote
+    version: String,
+
+    /// Path or URL for project
+    #[clap(long, default_value = ".")]
+    path: std::path::PathBuf,
+
+    /// Promote even if the soak period has not passed yet
+    #[clap(long)]
+    force: bool,
+}
+
 pub fn find_last_version(
     workingtree: &WorkingTree,
     cfg: &ProjectConfig,
@@ -249,53 +718,131 @@ pub fn find_last_version(
     Ok((None, None))
 }
 
-pub fn info(tree: &WorkingTree, branch: &dyn breezyshim::branch::Branch) -> i32 {
-    let cfg = match disperse::project_config::read_project_with_fallback(tree) {
-        Ok(cfg) => cfg,
+/// The version currently in the tree, used to identify the release series.
+fn current_series(tree: &WorkingTree, cfg: &ProjectConfig) -> Option<Version> {
+    cfg.news_file.as_ref()?;
+    match find_last_version_in_files(tree, cfg) {
+        Ok(v) => v.map(|(v, _)| v),
         Err(e) => {
-            log::info!("Error loading configuration: {}", e);
-            return 1;
+            log::debug!("Unable to determine release series: {}", e);
+            None
         }
-    };
+    }
+}
 
-    let name = if let Some(name) = cfg.name.as_ref() {
-        Some(name.clone())
-    } else if tree.has_filename(Path::new("pyproject.toml")) {
-        disperse::python::find_name_in_pyproject_toml(tree)
-    } else {
-        None
-    };
+/// Information about a project, as shown by `disperse info`.
+#[derive(Debug, Default, serde::Serialize)]
+struct ProjectInfo {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    url: Option<String>,
+    name: Option<String>,
+    last_version: Option<String>,
+    last_version_status: Option<String>,
+    tag: Option<String>,
+    released: Option<chrono::DateTime<chrono::Utc>>,
+    /// Number of revisions since the last release, if its tag was found.
+    revisions_since_release: Option<usize>,
+    /// Age in days of the oldest unreleased revision.
+    unreleased_age_days: Option<i64>,
+    pending_version: Option<String>,
+    /// Version that would be released if there is no pending version.
+    next_version: Option<String>,
+    ci_status: Option<String>,
+    metadata_drift: Vec<String>,
+    errors: Vec<String>,
+}
 
-    if let Some(name) = name {
-        log::info!("Project: {}", name);
+impl ProjectInfo {
+    fn print(&self) {
+        if let Some(name) = self.name.as_deref() {
+            log::info!("Project: {}", name);
+        }
+        if let Some(last_version) = self.last_version.as_deref() {
+            log::info!("Last release: {}", last_version);
+        }
+        if let Some(status) = self.last_version_status.as_deref() {
+            log::info!("  status: {}", status);
+        }
+        if let Some(tag) = self.tag.as_deref() {
+            log::info!("  tag name: {}", tag);
+        }
+        if let Some(released) = self.released {
+            log::info!("  date: {}", released.format("%Y-%m-%d %H:%M:%S"));
+        }
+        match (self.revisions_since_release, self.unreleased_age_days) {
+            (Some(0), _) => log::info!("  no revisions since last release"),
+            (Some(n), Some(age)) => log::info!(
+                "  {} revisions since last release. First is {} days old.",
+                n,
+                age
+            ),
+            _ => {}
+        }
+        for drift in &self.metadata_drift {
+            log::info!("{}", drift);
+        }
+        if let Some(pending_version) = self.pending_version.as_deref() {
+            log::info!("Pending version: {}", pending_version);
+        }
+        if let Some(next_version) = self.next_version.as_deref() {
+            log::info!("No pending version found; would use {}", next_version);
+        }
+        if let Some(ci_status) = self.ci_status.as_deref() {
+            log::info!("CI: {}", ci_status);
+        }
+        for error in &self.errors {
+            log::info!("{}", error);
+        }
     }
+}
+
+/// Gather information about a project; returns it along with the exit code.
+async fn project_info(
+    tree: &WorkingTree,
+    branch: &dyn breezyshim::branch::Branch,
+    options: &Options,
+) -> (ProjectInfo, i32) {
+    let mut info = ProjectInfo::default();
+    let cfg = match read_project_config(tree, options) {
+        Ok(cfg) => cfg,
+        Err(e) => {
+            info.errors
+                .push(format!("Error loading configuration: {}", e));
+            return (info, 1);
+        }
+    };
+
+    info.name = disperse::find_name_in_tree(tree, &cfg).map(|(name, source)| {
+        log::debug!("Project name {} from {}", name, source);
+        name
+    });
 
     let (mut last_version, last_version_status) = match find_last_version(tree, &cfg) {
         Ok((Some(v), s)) => (v, s),
         Ok((Option::None, _)) => {
-            log::info!("No version found");
-            return 1;
+            info.errors.push("No version found".to_string());
+            return (info, 1);
         }
         Err(e) => {
-            log::info!("Error loading last version: {}", e);
-            return 1;
+            info.errors
+                .push(format!("Error loading last version: {}", e));
+            return (info, 1);
         }
     };
-
-    log::info!("Last release: {}", last_version.to_string());
-    if let Some(status) = last_version_status {
-        log::info!("  status: {}", status.to_string());
-    }
-
-    let tags = branch.tags().unwrap();
-
-    let tag_name = disperse::version::expand_tag(cfg.tag_name.as_deref().unwrap(), &last_version);
-    match tags.lookup_tag(tag_name.as_str()) {
-        Ok(release_revid) => {
-            log::info!("  tag name: {} ({})", tag_name, release_revid);
+    info.last_version = Some(last_version.to_string());
+    info.last_version_status = last_version_status.map(|s| s.to_string());
+
+    match disperse::find_release_tag(
+        branch,
+        cfg.tag_name.as_deref().unwrap(),
+        &last_version,
+        info.name.as_deref(),
+    ) {
+        Ok(Some((tag_name, release_revid))) => {
+            info.tag = Some(tag_name);
 
             let rev = branch.repository().get_revision(&release_revid).unwrap();
-            log::info!("  date: {}", rev.datetime().format("%Y-%m-%d %H:%M:%S"));
+            info.released = Some(rev.datetime().with_timezone(&chrono::Utc));
 
             if rev.revision_id != branch.last_revision() {
                 let graph = branch.repository().get_graph();
@@ -304,52 +851,77 @@ pub fn info(tree: &WorkingTree, branch: &dyn breezyshim::branch::Branch) -> i32
                     .collect::<Result<Vec<_>, _>>()
                     .unwrap();
                 if missing.last().map(|r| r.is_null()).unwrap() {
-                    log::info!("  last release not found in ancestry");
+                    info.errors
+                        .push("Last release not found in ancestry".to_string());
                 } else {
                     let first = branch
                         .repository()
                         .get_revision(missing.last().unwrap())
                         .unwrap();
-                    let first_timestamp = first.datetime();
-                    let first_age = chrono::Utc::now()
-                        .signed_duration_since(first_timestamp)
-                        .num_days();
-                    log::info!(
-                        "  {} revisions since last release. First is {} days old.",
-                        missing.len(),
-                        first_age,
+                    info.revisions_since_release = Some(missing.len());
+                    info.unreleased_age_days = Some(
+                        chrono::Utc::now()
+                            .signed_duration_since(first.datetime())
+                            .num_days(),
                     );
                 }
             } else {
-                log::info!("  no revisions since last release");
+                info.revisions_since_release = Some(0);
             }
         }
-        Err(BrzError::NoSuchTag(name)) => {
-            log::info!("  tag {} for previous release not found", name);
-        }
-        Err(BrzError::TagAlreadyExists(_name)) => {
-            unreachable!();
+        Ok(None) => {
+            info.errors
+                .push("Tag for previous release not found".to_string());
         }
         Err(e) => {
-            log::info!("  error loading tag: {}", e);
+            info.errors.push(format!("Error loading tag: {}", e));
         }
     };
 
-    match disperse::find_pending_version(tree, &cfg) {
+    if let Some(name) = info.name.as_deref() {
+        match disperse::metadata::check_drift(tree, name) {
+            Ok(drift) => {
+                for (registry, fields) in drift {
+                    for d in fields {
+                        info.metadata_drift.push(format!(
+                            "Metadata drift on {}: {} is {:?}, but {:?} in the repository",
+                            registry,
+                            d.field,
+                            d.registry.as_deref().unwrap_or(""),
+                            d.repository
+                        ));
+                    }
+                }
+            }
+            Err(e) => info
+                .errors
+                .push(format!("Unable to check registry metadata: {}", e)),
+        }
+    }
+
+    info.ci_status = ci_status(&cfg, false).await.map(|status| match status {
+        Ok(status) => status.state().to_string(),
+        Err(e) => format!("unknown ({})", e),
+    });
+
+    let ret = match disperse::find_pending_version(tree, &cfg, current_series(tree, &cfg).as_ref())
+    {
         Ok(new_version) => {
-            log::info!("Pending version: {}", new_version.to_string());
+            info.pending_version = Some(new_version.to_string());
             0
         }
         Err(disperse::FindPendingVersionError::OddPendingVersion(e)) => {
-            log::info!("Pending version: {} (odd)", e);
+            info.errors.push(format!("Pending version: {} (odd)", e));
             1
         }
         Err(disperse::FindPendingVersionError::NotFound) => {
-            disperse::version::increase_version(&mut last_version, -1);
-            log::info!(
-                "No pending version found; would use {}",
-                last_version.to_string()
-            );
+            let (bump, reason) = choose_bump(branch, &cfg, &last_version, options.bump);
+            match bump {
+                Some(bump) => log::info!("Version bump: {} ({})", bump, reason),
+                None => log::info!("Version bump: last component ({})", reason),
+            }
+            apply_bump(&mut last_version, bump, &cfg);
+            info.next_version = Some(last_version.to_string());
             0
         }
         Err(disperse::FindPendingVersionError::NoUnreleasedChanges) => {
@@ -357,52 +929,254 @@ pub fn info(tree: &WorkingTree, branch: &dyn breezyshim::branch::Branch) -> i32
             0
         }
         Err(disperse::FindPendingVersionError::Other(e)) => {
-            log::info!("Error finding pending version: {}", e);
+            info.errors
+                .push(format!("Error finding pending version: {}", e));
             1
         }
+    };
+    (info, ret)
+}
+
+/// Print information about a project.
+async fn info(
+    tree: &WorkingTree,
+    branch: &dyn breezyshim::branch::Branch,
+    format: OutputFormat,
+    options: &Options,
+) -> i32 {
+    let (info, ret) = project_info(tree, branch, options).await;
+    match format {
+        OutputFormat::Text => info.print(),
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&info).unwrap()),
     }
+    ret
 }
 
-/// Print information about the current project.
-fn info_many(urls: &[Url]) -> i32 {
-    let mut ret = 0;
+/// Guess the name of a project from its repository URL.
+fn project_name_from_url(url: &Url) -> Option<&str> {
+    url.path_segments()?
+        .rfind(|s| !s.is_empty())
+        .map(|s| s.trim_end_matches(".git"))
+}
 
-    for url in urls {
-        if url.to_string() != "." {
-            log::info!("Processing {}", url);
-        }
+/// Check whether a discovered project passes the --only and --exclude filters.
+fn discovered_url_selected(url: &Url, only: &[glob::Pattern], exclude: &[glob::Pattern]) -> bool {
+    let name = project_name_from_url(url).unwrap_or_default();
+    let matches = |p: &glob::Pattern| p.matches(name) || p.matches(url.as_str());
+    (only.is_empty() || only.iter().any(matches)) && !exclude.iter().any(matches)
+}
 
-        let (local_wt, branch) = match breezyshim::controldir::open_tree_or_branch(url, None, None)
-        {
-            Ok(x) => x,
-            Err(e) => {
+/// Order projects so that the ones that have gone longest without a release come first.
+///
+/// Projects that disperse has never released are considered the most overdue.
+fn order_by_last_release(
+    mut urls: Vec<Url>,
+    last_released: &std::collections::HashMap<String, chrono::DateTime<chrono::Utc>>,
+) -> Vec<Url> {
+    urls.sort_by_key(|u| project_name_from_url(u).and_then(|n| last_released.get(n).copied()));
+    urls
+}
+
+fn prioritize_overdue(urls: Vec<Url>, options: &Options) -> Vec<Url> {
+    use disperse::history::ReleaseHistory;
+    let events =
+        match release_history(options).and_then(|h| Ok(h.since(chrono::DateTime::UNIX_EPOCH)?)) {
+            Ok(events) => events,
+            Err(e) => {
+                log::warn!("Unable to read release history: {}", e);
+                return urls;
+            }
+        };
+    // Events are sorted oldest first, so later releases win.
+    let last_released = events
+        .into_iter()
+        .map(|e| (e.project, e.released))
+        .collect::<std::collections::HashMap<_, _>>();
+    order_by_last_release(urls, &last_released)
+}
+
+/// Print information about the current project.
+/// Gather information about the project at a URL or local path.
+async fn project_info_at(url: &str, options: &Options) -> Result<(ProjectInfo, i32), String> {
+    let (local_wt, branch) =
+        breezyshim::controldir::open_tree_or_branch(url, None, None).map_err(|e| e.to_string())?;
+
+    let (mut info, ret) = if let Some(wt) = local_wt {
+        let _lock = wt.lock_read();
+        project_info(&wt, branch.as_ref(), options).await
+    } else {
+        let ws = silver_platter::workspace::Workspace::builder()
+            .main_branch(branch)
+            .build()
+            .map_err(|e| e.to_string())?;
+        let _lock = ws.local_tree().lock_read();
+        project_info(ws.local_tree(), ws.local_tree().branch().as_ref(), options).await
+    };
+    info.url = Some(url.to_string());
+    Ok((info, ret))
+}
+
+async fn info_many(urls: &[String], format: OutputFormat, options: &Options) -> i32 {
+    let mut ret = 0;
+    let mut infos = vec![];
+
+    for url in urls {
+        if url != "." {
+            log::info!("Processing {}", url);
+        }
+
+        match project_info_at(url, options).await {
+            Ok((info, r)) => {
+                ret += r;
+                match format {
+                    OutputFormat::Text => info.print(),
+                    OutputFormat::Json => infos.push(info),
+                }
+            }
+            Err(e) => {
                 ret = 1;
                 log::error!("Unable to open {}: {}", url, e);
-                continue;
             }
-        };
+        }
+    }
+    if format == OutputFormat::Json {
+        println!("{}", serde_json::to_string_pretty(&infos).unwrap());
+    }
+    ret
+}
 
-        if let Some(wt) = local_wt {
-            let lock = wt.lock_read();
-            ret += info(&wt, branch.as_ref());
-            std::mem::drop(lock);
-        } else {
-            let main_branch = breezyshim::branch::open(url).unwrap();
-            let ws = silver_platter::workspace::Workspace::builder()
-                .main_branch(main_branch)
-                .build()
-                .unwrap();
-            let lock = ws.local_tree().lock_read();
-            let r = info(ws.local_tree(), ws.local_tree().branch().as_ref());
-            std::mem::drop(lock);
-            ret += r;
+impl ProjectInfo {
+    fn days_since_release(&self, now: chrono::DateTime<chrono::Utc>) -> Option<i64> {
+        self.released
+            .map(|released| now.signed_duration_since(released).num_days())
+    }
+}
+
+/// Sort projects for `disperse status`; the ones most in need of a release come first.
+fn sort_status(infos: &mut [ProjectInfo], sort: StatusSort, now: chrono::DateTime<chrono::Utc>) {
+    match sort {
+        StatusSort::Project => infos.sort_by(|a, b| {
+            a.name
+                .as_ref()
+                .or(a.url.as_ref())
+                .cmp(&b.name.as_ref().or(b.url.as_ref()))
+        }),
+        StatusSort::Age => {
+            infos.sort_by_key(|i| std::cmp::Reverse(i.days_since_release(now)));
+        }
+        StatusSort::Unreleased => {
+            infos.sort_by_key(|i| std::cmp::Reverse(i.revisions_since_release));
+        }
+    }
+}
+
+/// Format a table with a line per project for `disperse status`.
+fn format_status_table(infos: &[ProjectInfo], now: chrono::DateTime<chrono::Utc>) -> String {
+    let optional = |v: Option<String>| v.unwrap_or_else(|| "-".to_string());
+    let header = [
+        "project",
+        "last release",
+        "days since",
+        "unreleased",
+        "pending",
+        "ci",
+    ]
+    .map(|h| h.to_string());
+    let rows = std::iter::once(header)
+        .chain(infos.iter().map(|i| {
+            [
+                optional(i.name.clone().or(i.url.clone())),
+                optional(i.last_version.clone()),
+                optional(i.days_since_release(now).map(|d| d.to_string())),
+                optional(i.revisions_since_release.map(|n| n.to_string())),
+                optional(i.pending_version.clone().or(i.next_version.clone())),
+                optional(i.ci_status.clone()),
+            ]
+        }))
+        .collect::<Vec<_>>();
+    let widths = (0..6)
+        .map(|c| rows.iter().map(|r| r[c].len()).max().unwrap_or(0))
+        .collect::<Vec<_>>();
+    rows.iter()
+        .map(|row| {
+            row.iter()
+                .zip(&widths)
+                .map(|(cell, width)| format!("{:width$}", cell, width = width))
+                .collect::<Vec<_>>()
+                .join("  ")
+                .trim_end()
+                .to_string()
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Print an overview of the release state of several projects.
+async fn status(urls: &[String], sort: StatusSort, format: OutputFormat, options: &Options) -> i32 {
+    let mut ret = 0;
+    let mut infos = vec![];
+    for url in urls {
+        log::debug!("Processing {}", url);
+        match project_info_at(url, options).await {
+            Ok((info, _)) => infos.push(info),
+            Err(e) => {
+                ret = 1;
+                log::error!("Unable to open {}: {}", url, e);
+            }
         }
     }
+    let now = chrono::Utc::now();
+    sort_status(&mut infos, sort, now);
+    match format {
+        OutputFormat::Text => println!("{}", format_status_table(&infos, now)),
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&infos).unwrap()),
+    }
     ret
 }
 
-pub fn pick_new_version(tree: &WorkingTree, cfg: &ProjectConfig) -> Result<Version, String> {
-    match disperse::find_pending_version(tree, cfg) {
+/// Decide which version component to increase for the next release, and why.
+///
+/// Returns None for the default of increasing the last component.
+fn choose_bump(
+    branch: &dyn breezyshim::branch::Branch,
+    cfg: &ProjectConfig,
+    last_version: &Version,
+    bump_override: Option<Bump>,
+) -> (Option<Bump>, &'static str) {
+    if let Some(bump) = bump_override {
+        return (Some(bump), "--bump");
+    }
+    if cfg.version_bump.unwrap_or_default() == VersionBump::ConventionalCommits {
+        match disperse::conventional_commits::bump_since_last_tag(branch, last_version.major == 0) {
+            Ok(Some(bump)) => return (Some(bump), "conventional commits"),
+            Ok(None) => {}
+            Err(e) => log::warn!("Unable to read commit messages: {}", e),
+        }
+    }
+    match cfg.default_bump {
+        Some(bump) => (Some(bump), "default-bump"),
+        None => (None, "default"),
+    }
+}
+
+fn apply_bump(version: &mut Version, bump: Option<Bump>, cfg: &ProjectConfig) {
+    match bump {
+        Some(bump) if cfg.reset_lower_components.unwrap_or(true) => {
+            disperse::version::bump_version(version, bump)
+        }
+        Some(bump) => {
+            disperse::version::increase_version(version, disperse::version::bump_index(bump))
+        }
+        None => disperse::version::increase_version(version, -1),
+    }
+}
+
+pub fn pick_new_version(
+    tree: &WorkingTree,
+    cfg: &ProjectConfig,
+    bump_override: Option<Bump>,
+) -> Result<Version, String> {
+    match disperse::find_pending_version(tree, cfg, current_series(tree, cfg).as_ref()) {
         Ok(new_version) => {
             return Ok(new_version);
         }
@@ -411,34 +1185,418 @@ pub fn pick_new_version(tree: &WorkingTree, cfg: &ProjectConfig) -> Result<Versi
             return Err(format!("Pending version: {} (odd)", e));
         }
         Err(disperse::FindPendingVersionError::NoUnreleasedChanges) => {
-            return Err("No unreleased changes".to_string());
+            // A pending section is added to the news file once the version is known.
+            if cfg.create_pending != Some(true) {
+                return Err("No unreleased changes".to_string());
+            }
         }
         Err(disperse::FindPendingVersionError::Other(o)) => {
             return Err(format!("Error finding pending version: {}", o));
         }
     }
 
-    let mut last_version = match find_last_version(tree, cfg) {
-        Ok((Some(v), _)) => v,
-        Ok((Option::None, _)) => {
-            return Err("No version found".to_string());
+    let in_files = match find_last_version_in_files(tree, cfg) {
+        // Version files in development status already name the next release.
+        Ok(Some((v, Some(disperse::Status::Dev)))) => {
+            log::info!("Version files are at development version {}", v.to_string());
+            return Ok(v);
         }
+        Ok(v) => v.map(|(v, _)| v),
         Err(e) => {
-            return Err(format!("Error loading last version: {}", e));
+            log::info!("Error finding last version in files: {}", e);
+            None
+        }
+    };
+    let in_tags = match cfg.tag_name.as_deref() {
+        Some(tag_name) => match find_last_version_in_tags(tree.branch().as_ref(), tag_name) {
+            Ok((v, _)) => v,
+            Err(e) => {
+                log::info!("Error finding last version in tags: {}", e);
+                None
+            }
+        },
+        None => None,
+    };
+    let last_version = match disperse::reconcile_last_version(in_files, in_tags) {
+        Some((v, None)) => Some(v),
+        Some((v, Some(divergence))) => {
+            log::warn!("{}", divergence);
+            Some(v)
         }
+        None => None,
+    };
+    let name = disperse::find_name_in_tree(tree, cfg).map(|(name, _)| name);
+    let has_release_tag = |version: &Version| {
+        disperse::find_release_tag(
+            tree.branch().as_ref(),
+            cfg.tag_name.as_deref().unwrap(),
+            version,
+            name.as_deref(),
+        )
+        .map(|t| t.is_some())
+        .map_err(|e| format!("Unable to look up release tag: {}", e))
     };
-    let tags = tree.branch().tags().unwrap();
+    if cfg.version_scheme == Some(VersionScheme::Calver) {
+        let new_version = disperse::version::next_calver(
+            cfg.calver_format
+                .as_deref()
+                .unwrap_or(disperse::version::DEFAULT_CALVER_FORMAT),
+            chrono::Utc::now().date_naive(),
+            last_version.as_ref(),
+        )?;
+        if cfg.tag_name.is_some() && has_release_tag(&new_version)? {
+            return Err(format!(
+                "Release tag for {} already exists",
+                new_version.to_string()
+            ));
+        }
+        return Ok(new_version);
+    }
+    let mut last_version = last_version.ok_or_else(|| "No version found".to_string())?;
+    let (bump, reason) = choose_bump(tree.branch().as_ref(), cfg, &last_version, bump_override);
     loop {
-        let last_version_tag_name =
-            disperse::version::expand_tag(cfg.tag_name.as_ref().unwrap(), &last_version);
-        if !tags.has_tag(last_version_tag_name.as_str()) {
+        if !has_release_tag(&last_version)? {
             break;
         }
-        disperse::version::increase_version(&mut last_version, -1);
+        if let Some(bump) = bump {
+            log::info!("Increasing {} version ({})", bump, reason);
+        }
+        apply_bump(&mut last_version, bump, cfg);
     }
     Ok(last_version)
 }
 
+fn auth(command: &AuthCommand) -> Result<(), disperse::token_cache::Error> {
+    let cache = disperse::token_cache::TokenCache::open_default()?;
+    match command {
+        AuthCommand::Login {
+            service,
+            expires_in,
+            no_expiry,
+        } => {
+            let service = disperse::token_cache::Service::from(*service);
+            println!("Please enter your {} token", service);
+            let mut token = String::new();
+            std::io::stdin().read_line(&mut token)?;
+            let token = token.trim();
+            if token.is_empty() {
+                return Err(disperse::token_cache::Error::Other(
+                    "No token provided".to_string(),
+                ));
+            }
+            let expires = if *no_expiry {
+                None
+            } else {
+                Some(chrono::Utc::now() + chrono::Duration::days(i64::from(*expires_in)))
+            };
+            cache.set(service, token, expires)?;
+            log::info!("Stored {} token in {}", service, cache.path().display());
+        }
+        AuthCommand::Logout { service } => {
+            let service = disperse::token_cache::Service::from(*service);
+            if !cache.remove(service)? {
+                log::info!("No {} token in token cache", service);
+            }
+        }
+        AuthCommand::Status => {
+            let now = chrono::Utc::now();
+            for (service, token) in cache.entries()? {
+                match token.expires {
+                    Some(expires) if token.is_expired(now) => {
+                        println!("{}: expired on {}", service, expires.format("%Y-%m-%d"))
+                    }
+                    Some(expires) => {
+                        println!("{}: expires on {}", service, expires.format("%Y-%m-%d"))
+                    }
+                    None => println!("{}: does not expire", service),
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+const DEFAULT_FAILURE_ISSUE_THRESHOLD: u32 = 3;
+
+/// Options for this run of disperse, mostly set on the command line.
+#[derive(Debug, Clone, Default)]
+struct Options {
+    /// Location of the project configuration, if overridden on the command line.
+    project_config: Option<std::path::PathBuf>,
+
+    /// Project settings from the global configuration, for projects that don't set them.
+    project_defaults: Option<toml::Table>,
+
+    /// Project configuration values set on the command line.
+    overrides: Vec<disperse::project_config::Override>,
+
+    /// Version to release, rather than the next one.
+    new_version: Option<Version>,
+
+    /// Version component to increase, if overridden on the command line.
+    bump: Option<Bump>,
+
+    force: bool,
+
+    dry_run: bool,
+
+    ignore_ci: bool,
+
+    ignore_verify_command: bool,
+
+    preserve_temp: bool,
+
+    /// Whether to resume a release that failed part way through.
+    resume: bool,
+
+    /// Whether to ask for confirmation before committing, tagging and publishing.
+    interactive: bool,
+
+    /// Format to print the plan of a dry run in.
+    plan_format: OutputFormat,
+
+    /// Projects to release in repositories with several of them, if selected.
+    projects: Vec<String>,
+
+    /// Location of the release history, if configured.
+    history: Option<std::path::PathBuf>,
+
+    /// Settings for individual projects from the global configuration, by URL.
+    project_overrides: std::collections::BTreeMap<String, disperse::config::ProjectOverrides>,
+
+    /// Number of consecutive failures after which an issue is filed, if enabled.
+    failure_issue_threshold: Option<u32>,
+
+    /// OpenPGP key to sign with, if set by the profile.
+    signing_key: Option<String>,
+
+    /// CI timeout, if overridden on the command line.
+    ci_timeout: Option<u64>,
+
+    /// CI poll interval, if overridden on the command line.
+    ci_poll_interval: Option<u64>,
+}
+
+impl Options {
+    /// Location of the project configuration in a tree.
+    fn project_config_path(&self) -> &Path {
+        self.project_config.as_deref().unwrap_or(Path::new(
+            disperse::project_config::DEFAULT_PROJECT_CONFIG_PATH,
+        ))
+    }
+
+    /// Settings for the project at a URL from the global configuration.
+    fn project_overrides(&self, url: &str) -> Option<&disperse::config::ProjectOverrides> {
+        self.project_overrides.get(url.trim_end_matches('/'))
+    }
+
+    /// Key to sign tags and release manifests with; the profile's takes precedence.
+    fn signing_key<'a>(&'a self, cfg: &'a ProjectConfig) -> Option<&'a str> {
+        self.signing_key.as_deref().or(cfg.signing_key.as_deref())
+    }
+
+    /// Apply the CI settings from the command line to the project configuration.
+    fn apply_ci_overrides(&self, cfg: &mut ProjectConfig) {
+        cfg.ci_timeout = self.ci_timeout.or(cfg.ci_timeout);
+        cfg.ci_poll_interval = self.ci_poll_interval.or(cfg.ci_poll_interval);
+    }
+}
+
+/// Read the project configuration, with the values set on the command line applied.
+fn read_project_config(
+    tree: &dyn breezyshim::tree::Tree,
+    options: &Options,
+) -> Result<ProjectConfig, String> {
+    read_project_config_for(tree, None, options)
+}
+
+/// Read the configuration of one of the projects in a repository with several
+/// of them, or of the repository itself if `project` is None.
+fn read_project_config_for(
+    tree: &dyn breezyshim::tree::Tree,
+    project: Option<&str>,
+    options: &Options,
+) -> Result<ProjectConfig, String> {
+    let mut cfg = disperse::project_config::read_project(tree, options.project_config.as_deref())
+        .map_err(|e| e.to_string())?;
+    if let Some(defaults) = options.project_defaults.as_ref() {
+        cfg = disperse::project_config::apply_defaults(&cfg, defaults)?;
+    }
+    if let Some(project) = project {
+        cfg = cfg.select_project(project)?;
+        if !tree.has_filename(&cfg.project_dir().join("Cargo.toml")) {
+            return Err(format!(
+                "Project {} in {} is not a crate; only crates are supported in repositories with several projects",
+                project,
+                cfg.project_dir().display()
+            ));
+        }
+    }
+    match options.overrides.as_slice() {
+        [] => Ok(cfg),
+        // Defaults and the selected project can make overrides that were
+        // accepted on startup invalid.
+        overrides => disperse::project_config::apply_overrides(&cfg, overrides),
+    }
+}
+
+fn release_history(
+    options: &Options,
+) -> Result<disperse::history::JsonLinesHistory, Box<dyn std::error::Error>> {
+    match options.history.as_ref() {
+        Some(path) => Ok(disperse::history::JsonLinesHistory::new(path)),
+        None => disperse::history::JsonLinesHistory::open_default(),
+    }
+}
+
+/// Keep track of repeated failures to release a project, and file an issue
+/// on its forge once there have been too many in a row.
+async fn track_failures(
+    url: &str,
+    result: Result<&ReleaseOutcome, &ReleaseError>,
+    options: &Options,
+) {
+    let threshold = match options.failure_issue_threshold {
+        Some(threshold) => threshold,
+        None => return,
+    };
+    let tracker = match disperse::failures::FailureTracker::open_default() {
+        Ok(tracker) => tracker,
+        Err(e) => {
+            log::warn!("Unable to open failure state: {}", e);
+            return;
+        }
+    };
+    let project = url.trim_end_matches('/');
+    let record = match result {
+        Ok(_) => {
+            match tracker.record_success(project) {
+                Ok(Some(disperse::failures::FailureRecord {
+                    issue: Some(issue), ..
+                })) => {
+                    if let Err(e) = close_failure_issue(url, issue).await {
+                        log::warn!("Unable to close issue {} for {}: {}", issue, url, e);
+                    }
+                }
+                Ok(_) => {}
+                Err(e) => log::warn!("Unable to update failure state: {}", e),
+            }
+            return;
+        }
+        Err(e) => match e.failure_class() {
+            Some(class) => {
+                match tracker.record_failure(project, class, &e.to_string(), chrono::Utc::now()) {
+                    Ok(record) => record,
+                    Err(e) => {
+                        log::warn!("Unable to update failure state: {}", e);
+                        return;
+                    }
+                }
+            }
+            None => return,
+        },
+    };
+    if record.count < threshold {
+        return;
+    }
+    let title = disperse::failures::issue_title(&record);
+    let body = disperse::failures::issue_body(
+        project,
+        &record,
+        &disperse::environment::fingerprint().to_string(),
+    );
+    let filed = async {
+        let parsed_url: Url = url.parse().map_err(|e| format!("invalid URL: {}", e))?;
+        if parsed_url.host_str() != Some("github.com") {
+            return Err("only GitHub is supported".to_string());
+        }
+        let gh = disperse::github::login().map_err(|e| e.to_string())?;
+        let repo = disperse::github::get_github_repo(&gh, &parsed_url)
+            .await
+            .map_err(|e| e.to_string())?;
+        disperse::github::file_or_update_issue(&gh, &repo, record.issue, &title, &body)
+            .await
+            .map_err(|e| e.to_string())
+    };
+    match filed.await {
+        Ok((number, issue_url)) => {
+            log::info!("Reported repeated failures of {} in {}", url, issue_url);
+            if let Err(e) = tracker.set_issue(project, number) {
+                log::warn!("Unable to update failure state: {}", e);
+            }
+        }
+        Err(e) => log::warn!("Unable to file issue for {}: {}", url, e),
+    }
+}
+
+async fn close_failure_issue(url: &str, issue: u64) -> Result<(), String> {
+    let parsed_url: Url = url.parse().map_err(|e| format!("invalid URL: {}", e))?;
+    let gh = disperse::github::login().map_err(|e| e.to_string())?;
+    let repo = disperse::github::get_github_repo(&gh, &parsed_url)
+        .await
+        .map_err(|e| e.to_string())?;
+    disperse::github::close_issue(
+        &gh,
+        &repo,
+        issue,
+        "disperse has released the project again.",
+    )
+    .await
+    .map_err(|e| e.to_string())
+}
+
+/// Record a release in the release history.
+fn record_release(outcome: &ReleaseOutcome, options: &Options) {
+    use disperse::history::ReleaseHistory;
+    let event = disperse::history::ReleaseEvent {
+        project: outcome.name.clone(),
+        version: outcome.version.to_string(),
+        tag: outcome.tag.clone(),
+        released: chrono::Utc::now(),
+        notes: outcome.notes.clone(),
+        urls: outcome.links.iter().map(|l| l.url.clone()).collect(),
+        checklist: outcome.checklist.clone(),
+        disperse_version: Some(env!("CARGO_PKG_VERSION").to_string()),
+    };
+    match release_history(options) {
+        Ok(history) => {
+            if let Err(e) = history.record(&event) {
+                log::warn!(
+                    "Unable to record release in {}: {}",
+                    history.path().display(),
+                    e
+                );
+            }
+        }
+        Err(e) => log::warn!("Unable to open release history: {}", e),
+    }
+}
+
+/// A link to something that was created while releasing a project.
+#[derive(Debug, Clone)]
+pub struct ReleaseLink {
+    pub kind: &'static str,
+    pub url: String,
+}
+
+/// Summary of a successful release.
+#[derive(Debug, Clone)]
+pub struct ReleaseOutcome {
+    pub name: String,
+    pub version: Version,
+    pub tag: String,
+    pub notes: Option<String>,
+    pub links: Vec<ReleaseLink>,
+    /// Smoke tests of published packages that failed.
+    pub smoke_test_failures: Vec<String>,
+    /// Results of the release checklist.
+    pub checklist: Vec<disperse::checklist::ChecklistResult>,
+    /// Whether there are more pending news sections to release.
+    pub more_pending: bool,
+    /// What the release would have done, in dry-run mode.
+    pub plan: Option<ReleasePlan>,
+}
+
 #[derive(Debug)]
 pub enum ReleaseError {
     /// The repository is unavailable.
@@ -457,6 +1615,12 @@ pub enum ReleaseError {
         version: String,
     },
 
+    /// Bumping the released version doesn't produce a newer version.
+    NextVersionNotNewer {
+        version: Box<Version>,
+        next: Box<Version>,
+    },
+
     NoSuchTag,
     NoDisperseConfig,
     PreDistCommandFailed {
@@ -475,7 +1639,7 @@ pub enum ReleaseError {
     ReleaseTagExists {
         project: String,
         tag: String,
-        version: Version,
+        version: Box<Version>,
     },
     CommitFailed(String),
     RecentCommits {
@@ -491,10 +1655,36 @@ pub enum ReleaseError {
     CIPending(String),
     PublishArtifactsFailed(String),
     DistCreationFailed,
+    /// The package did not build from its packaged sources.
+    PackageVerificationFailed(String),
     NoPublicBranch,
+    /// Files were changed that disperse did not modify itself.
+    UnexpectedChanges(Vec<std::path::PathBuf>),
+    /// The project is disabled or snoozed.
+    OnHold(disperse::project_config::Hold),
+    /// A plugin stopped the release.
+    Vetoed {
+        plugin: String,
+        reason: String,
+    },
+    /// The release was not confirmed.
+    Declined,
+    /// The repository has several projects, and none was selected.
+    SeveralProjects(Vec<String>),
     Other(String),
 }
 
+impl From<disperse::plugins::Error> for ReleaseError {
+    fn from(e: disperse::plugins::Error) -> Self {
+        match e {
+            disperse::plugins::Error::Vetoed { plugin, reason } => {
+                ReleaseError::Vetoed { plugin, reason }
+            }
+            e => ReleaseError::Other(e.to_string()),
+        }
+    }
+}
+
 impl From<silver_platter::workspace::Error> for ReleaseError {
     fn from(_e: silver_platter::workspace::Error) -> Self {
         ReleaseError::Other("workspace error".to_string())
@@ -512,6 +1702,12 @@ impl std::fmt::Display for ReleaseError {
             ReleaseError::OddPendingVersion { version } => {
                 write!(f, "Odd pending version: {}", version)
             }
+            ReleaseError::NextVersionNotNewer { version, next } => write!(
+                f,
+                "Next version {} is not newer than {}",
+                next.to_string(),
+                version.to_string()
+            ),
             ReleaseError::NoSuchTag => write!(f, "No such tag"),
             ReleaseError::NoDisperseConfig => write!(f, "No disperse config"),
             ReleaseError::PreDistCommandFailed { command, status } => write!(
@@ -571,13 +1767,114 @@ impl std::fmt::Display for ReleaseError {
                 write!(f, "Publish artifacts failed: {}", msg)
             }
             ReleaseError::DistCreationFailed => write!(f, "Dist creation failed"),
+            ReleaseError::PackageVerificationFailed(msg) => {
+                write!(f, "Package verification failed: {}", msg)
+            }
             ReleaseError::NoPublicBranch => write!(f, "No public branch"),
+            ReleaseError::UnexpectedChanges(paths) => write!(
+                f,
+                "Unexpected changes to {} (left behind by the pre-dist command?)",
+                paths
+                    .iter()
+                    .map(|p| p.display().to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            ReleaseError::OnHold(hold) => write!(f, "Project is {}", hold),
+            ReleaseError::Vetoed { plugin, reason } => {
+                write!(f, "Release vetoed by plugin {}: {}", plugin, reason)
+            }
+            ReleaseError::Declined => write!(f, "Release not confirmed"),
+            ReleaseError::SeveralProjects(names) => {
+                write!(f, "Repository has several projects: {}", names.join(", "))
+            }
         }
     }
 }
 
 impl std::error::Error for ReleaseError {}
 
+impl ReleaseError {
+    /// Class of the error, for telling repeated failures apart; None if
+    /// the project was skipped rather than failing to release.
+    fn failure_class(&self) -> Option<&'static str> {
+        match self {
+            ReleaseError::NoUnreleasedChanges
+            | ReleaseError::NoDisperseConfig
+            | ReleaseError::RecentCommits { .. }
+            | ReleaseError::ReleaseTagExists { .. }
+            | ReleaseError::OnHold(..)
+            | ReleaseError::Vetoed { .. }
+            | ReleaseError::Declined
+            | ReleaseError::SeveralProjects(..) => None,
+            ReleaseError::RepositoryUnavailable { .. } => Some("repository-unavailable"),
+            ReleaseError::NoVersion => Some("no-version"),
+            ReleaseError::OddPendingVersion { .. } => Some("odd-pending-version"),
+            ReleaseError::NextVersionNotNewer { .. } => Some("next-version-not-newer"),
+            ReleaseError::NoSuchTag => Some("no-such-tag"),
+            ReleaseError::PreDistCommandFailed { .. } => Some("pre-dist-command-failed"),
+            ReleaseError::UploadCommandFailed { .. } => Some("upload-command-failed"),
+            ReleaseError::VerifyCommandFailed { .. } => Some("verify-command-failed"),
+            ReleaseError::CommitFailed(..) => Some("commit-failed"),
+            ReleaseError::CreateTagFailed { .. } => Some("create-tag-failed"),
+            ReleaseError::CIFailed(..) => Some("ci-failed"),
+            ReleaseError::CIPending(..) => Some("ci-pending"),
+            ReleaseError::PublishArtifactsFailed(..) => Some("publish-artifacts-failed"),
+            ReleaseError::DistCreationFailed => Some("dist-creation-failed"),
+            ReleaseError::PackageVerificationFailed(..) => Some("package-verification-failed"),
+            ReleaseError::NoPublicBranch => Some("no-public-branch"),
+            ReleaseError::UnexpectedChanges(..) => Some("unexpected-changes"),
+            ReleaseError::Other(..) => Some("other"),
+        }
+    }
+
+    /// Exit code of a release that failed with this error.
+    fn exit_code(&self) -> i32 {
+        match self {
+            ReleaseError::NoUnreleasedChanges => EXIT_NOTHING_TO_DO,
+            ReleaseError::RecentCommits { .. }
+            | ReleaseError::OnHold(..)
+            | ReleaseError::Vetoed { .. }
+            | ReleaseError::Declined => EXIT_SKIPPED,
+            ReleaseError::CIFailed(..) | ReleaseError::CIPending(..) => EXIT_CI_FAILED,
+            ReleaseError::UploadCommandFailed { .. } | ReleaseError::PublishArtifactsFailed(..) => {
+                EXIT_PUBLISH_FAILED
+            }
+            _ => 1,
+        }
+    }
+}
+
+/// Exit code when there was nothing to release.
+const EXIT_NOTHING_TO_DO: i32 = 3;
+
+/// Exit code when a release was skipped by policy, e.g. because of recent commits.
+const EXIT_SKIPPED: i32 = 4;
+
+/// Exit code when CI was failing or had not finished.
+const EXIT_CI_FAILED: i32 = 5;
+
+/// Exit code when the release was tagged, but publishing artifacts failed.
+const EXIT_PUBLISH_FAILED: i32 = 6;
+
+/// Pick the exit code for several results; general failures beat
+/// publish failures, which beat CI failures, skips and having nothing to do.
+fn worse_exit_code(a: i32, b: i32) -> i32 {
+    const ORDER: &[i32] = &[
+        0,
+        EXIT_NOTHING_TO_DO,
+        EXIT_SKIPPED,
+        EXIT_CI_FAILED,
+        EXIT_PUBLISH_FAILED,
+    ];
+    let rank = |code| ORDER.iter().position(|c| *c == code).unwrap_or(ORDER.len());
+    if rank(b) > rank(a) {
+        b
+    } else {
+        a
+    }
+}
+
 fn is_git_repo(repository: &breezyshim::repository::Repository) -> bool {
     use pyo3::prelude::*;
     pyo3::Python::with_gil(|py| repository.to_object(py).bind(py).hasattr("_git")).unwrap()
@@ -601,141 +1898,604 @@ impl std::fmt::Display for RecentCommits {
 
 impl std::error::Error for RecentCommits {}
 
-fn check_release_age(
-    branch: &dyn breezyshim::branch::Branch,
-    cfg: &ProjectConfig,
-    now: chrono::DateTime<chrono::Utc>,
-) -> Result<(), RecentCommits> {
-    let rev = branch
-        .repository()
-        .get_revision(&branch.last_revision())
-        .unwrap();
-    if let Some(timeout_days) = cfg.release_timeout {
-        let commit_time = rev.datetime();
-        let time_delta = now.signed_duration_since(commit_time);
-        if (time_delta.num_days() as u64) < timeout_days {
-            return Err(RecentCommits {
-                min_commit_age: timeout_days as i64,
-                commit_age: time_delta.num_days(),
-            });
+/// Find the versioned files that have been changed in a working tree.
+fn changed_files(tree: &WorkingTree) -> Result<Vec<std::path::PathBuf>, ReleaseError> {
+    let basis_tree = tree
+        .basis_tree()
+        .map_err(|e| ReleaseError::Other(e.to_string()))?;
+    let mut ret = vec![];
+    for change in tree
+        .iter_changes(&basis_tree, None, Some(false), None)
+        .map_err(|e| ReleaseError::Other(e.to_string()))?
+    {
+        let change = change.map_err(|e| ReleaseError::Other(e.to_string()))?;
+        if let Some(path) = change.path.1.or(change.path.0) {
+            ret.push(path);
         }
     }
-
-    Ok(())
+    Ok(ret)
 }
 
-async fn publish_artifacts(
-    ws: &silver_platter::workspace::Workspace,
-    tag_name: &str,
-    dry_run: bool,
-    gh: &octocrab::Octocrab,
+/// Directory to build artifacts in, making sure it is ignored if it is in the tree.
+fn artifact_directory(
+    tree: &WorkingTree,
     cfg: &ProjectConfig,
-    pypi_paths: &[&std::path::Path],
-    gh_repo: Option<&octocrab::models::Repository>,
-) -> Result<Vec<std::path::PathBuf>, ReleaseError> {
-    let mut artifacts = vec![];
-    // Wait for CI to go green
-    if let Some(gh_repo) = gh_repo {
-        if dry_run {
-            log::info!("In dry-run mode, so unable to wait for CI");
-        } else {
-            disperse::github::wait_for_gh_actions(gh, gh_repo, Some(tag_name), cfg.ci_timeout)
-                .await
-                .map_err(|e| ReleaseError::CIFailed(e.to_string()))?;
-        }
+) -> Result<std::path::PathBuf, ReleaseError> {
+    let artifact_dir = cfg
+        .artifact_directory
+        .clone()
+        .unwrap_or_else(|| disperse::DEFAULT_ARTIFACT_DIRECTORY.into());
+    if artifact_dir.is_relative() {
+        disperse::ensure_ignored(tree, &artifact_dir)
+            .map_err(|e| ReleaseError::Other(e.to_string()))?;
     }
+    Ok(tree.basedir().join(artifact_dir))
+}
 
-    if !pypi_paths.is_empty() {
-        artifacts.extend(pypi_paths.iter().map(|x| x.to_path_buf()));
-        if dry_run {
-            log::info!("skipping twine upload due to dry run mode")
-        } else if !cfg.twine_upload.unwrap_or(false) {
-            log::info!("skipping twine upload; disabled in config")
+/// Run the pre-dist command of a project in the tree, in the project's directory.
+fn run_pre_dist_command(tree: &WorkingTree, cfg: &ProjectConfig) -> Result<(), ReleaseError> {
+    let Some(pre_dist_command) = cfg.pre_dist_command.as_deref() else {
+        return Ok(());
+    };
+    match disperse::logging::status(
+        std::process::Command::new("sh")
+            .arg("-c")
+            .arg(pre_dist_command)
+            .current_dir(tree.abspath(cfg.project_dir()).unwrap()),
+    ) {
+        Ok(s) if s.success() => Ok(()),
+        Ok(s) => Err(ReleaseError::PreDistCommandFailed {
+            command: pre_dist_command.to_string(),
+            status: Some(s),
+        }),
+        Err(_e) => Err(ReleaseError::PreDistCommandFailed {
+            command: pre_dist_command.to_string(),
+            status: None,
+        }),
+    }
+}
+
+fn create_python_artifacts(
+    tree: &WorkingTree,
+    cfg: &ProjectConfig,
+) -> Result<Vec<std::path::PathBuf>, ReleaseError> {
+    let artifact_dir = artifact_directory(tree, cfg)?;
+
+    if cfg.path.is_some() {
+        // Only crates are supported in repositories with several projects.
+        return Ok(vec![]);
+    }
+    let _progress = disperse::progress::Progress::start("Creating Python artifacts");
+    if cfg.python_upload_tool == Some(disperse::project_config::PythonUploadTool::Uv)
+        && (tree.has_filename(Path::new("pyproject.toml"))
+            || tree.has_filename(Path::new("setup.py")))
+    {
+        return disperse::python::create_uv_artifacts(tree, &artifact_dir)
+            .map_err(|e| ReleaseError::Other(e.to_string()));
+    }
+    if disperse::python::pyproject_uses_maturin(tree)
+        .map_err(|e| ReleaseError::Other(e.to_string()))?
+    {
+        return disperse::python::create_maturin_artifacts(
+            tree,
+            &artifact_dir,
+            cfg.maturin.as_ref(),
+        )
+        .map_err(|e| ReleaseError::Other(e.to_string()));
+    }
+    if tree.has_filename(Path::new("setup.py")) {
+        disperse::python::create_setup_py_artifacts(tree, &artifact_dir)
+    } else if tree.has_filename(Path::new("pyproject.toml")) {
+        disperse::python::create_python_artifacts(tree, &artifact_dir)
+    } else {
+        Ok(vec![])
+    }
+    .map_err(|e| ReleaseError::Other(e.to_string()))
+}
+
+fn find_sdist(paths: &[std::path::PathBuf]) -> Option<&std::path::PathBuf> {
+    paths
+        .iter()
+        .find(|p| p.to_string_lossy().ends_with(".tar.gz"))
+}
+
+/// Build the documentation for a release and publish it.
+fn publish_docs(
+    ws: &silver_platter::workspace::Workspace,
+    docs: &disperse::project_config::Docs,
+    gh_repo: Option<&octocrab::models::Repository>,
+    version: &Version,
+    dry_run: bool,
+) -> Result<Option<ReleaseLink>, String> {
+    let basedir = ws.local_tree().basedir();
+    log::info!("Building documentation");
+    disperse::docs::build(&basedir, &docs.command).map_err(|e| e.to_string())?;
+    let remote = docs
+        .remote
+        .clone()
+        .or_else(|| gh_repo.and_then(|r| r.ssh_url.clone()))
+        .ok_or_else(|| "no remote configured for documentation".to_string())?;
+    let branch = docs
+        .branch
+        .as_deref()
+        .unwrap_or(disperse::docs::DEFAULT_BRANCH);
+    if dry_run {
+        log::info!(
+            "skipping publishing documentation to {} due to dry run mode",
+            branch
+        );
+        return Ok(None);
+    }
+    disperse::docs::publish(
+        &remote,
+        branch,
+        &basedir.join(&docs.output),
+        &version.to_string(),
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(docs.url.as_ref().map(|url| ReleaseLink {
+        kind: "Documentation",
+        url: format!("{}/{}/", url.trim_end_matches('/'), version.to_string()),
+    }))
+}
+
+/// Update the PKGBUILD in a separate AUR repository for a new release.
+fn update_aur(
+    url: &str,
+    path: &Path,
+    new_version: &Version,
+    sha256: Option<&str>,
+    dry_run: bool,
+) -> Result<(), ReleaseError> {
+    let url: url::Url = url
+        .parse()
+        .map_err(|e| ReleaseError::Other(format!("Invalid AUR URL {}: {}", url, e)))?;
+    let branch =
+        breezyshim::branch::open(&url).map_err(|e| ReleaseError::RepositoryUnavailable {
+            url: url.to_string(),
+            reason: e.to_string(),
+        })?;
+    let ws = silver_platter::workspace::Workspace::builder()
+        .main_branch(branch)
+        .build()?;
+
+    if sha256.is_none() {
+        log::warn!("No source tarball built; not updating PKGBUILD checksums");
+    }
+    let modified =
+        disperse::pkgbuild::update_pkgbuild_file(ws.local_tree(), path, new_version, sha256)
+            .map_err(|e| ReleaseError::Other(e.to_string()))?;
+    ws.local_tree()
+        .build_commit()
+        .message(format!("Update to {}", new_version.to_string()).as_str())
+        .specific_files(
+            modified
+                .iter()
+                .map(|p| p.as_path())
+                .collect::<Vec<_>>()
+                .as_slice(),
+        )
+        .commit()
+        .map_err(|e| ReleaseError::CommitFailed(e.to_string()))?;
+
+    if dry_run {
+        log::info!(
+            "skipping push to AUR repository {} due to dry run mode",
+            url
+        );
+    } else {
+        log::info!("Pushing PKGBUILD update to {}", url);
+        ws.push(None)?;
+    }
+    Ok(())
+}
+
+fn check_release_age(
+    branch: &dyn breezyshim::branch::Branch,
+    cfg: &ProjectConfig,
+    now: chrono::DateTime<chrono::Utc>,
+) -> Result<(), RecentCommits> {
+    if let Some(timeout_days) = cfg.release_timeout {
+        let ignore_paths = cfg
+            .ignore_paths
+            .iter()
+            .flatten()
+            .filter_map(|p| match glob::Pattern::new(p) {
+                Ok(p) => Some(p),
+                Err(e) => {
+                    log::warn!("Invalid ignore-paths pattern {}: {}", p, e);
+                    None
+                }
+            })
+            .collect::<Vec<_>>();
+        let rev = if ignore_paths.is_empty() {
+            branch
+                .repository()
+                .get_revision(&branch.last_revision())
+                .unwrap()
+        } else {
+            match disperse::last_relevant_revision(branch, &ignore_paths) {
+                Ok(Some(rev)) => rev,
+                // Nothing but ignored paths has ever changed.
+                Ok(None) => return Ok(()),
+                Err(e) => {
+                    log::warn!("Unable to find last relevant commit: {}", e);
+                    branch
+                        .repository()
+                        .get_revision(&branch.last_revision())
+                        .unwrap()
+                }
+            }
+        };
+        let commit_time = rev.datetime();
+        let time_delta = now.signed_duration_since(commit_time);
+        if (time_delta.num_days() as u64) < timeout_days {
+            return Err(RecentCommits {
+                min_commit_age: timeout_days as i64,
+                commit_age: time_delta.num_days(),
+            });
+        }
+    }
+
+    Ok(())
+}
+
+/// Sign the manifest of a release, store it and attach it to the GitHub release.
+///
+/// Returns the URL of the manifest on GitHub, if it was attached.
+async fn publish_manifest(
+    manifest: disperse::manifest::ReleaseManifest,
+    signing_key: Option<&str>,
+    gh: &octocrab::Octocrab,
+    gh_repo: Option<&octocrab::models::Repository>,
+) -> Result<Option<url::Url>, String> {
+    let signed = manifest.sign(signing_key).map_err(|e| e.to_string())?;
+    let path = signed.store().map_err(|e| e.to_string())?;
+    log::info!("Stored release manifest in {}", path.display());
+    let Some(gh_repo) = gh_repo else {
+        return Ok(None);
+    };
+    let contents = signed.to_json().map_err(|e| e.to_string())?;
+    disperse::github::upload_release_asset(
+        gh,
+        gh_repo,
+        &signed.manifest.tag,
+        &signed.file_name(),
+        contents.into_bytes(),
+    )
+    .await
+    .map(Some)
+    .map_err(|e| e.to_string())
+}
+
+/// Places a release will be published to, as far as can be told in advance.
+/// Tag template and directory of a project in a repository with several of
+/// them, to tell its releases and changes apart from those of the others.
+fn project_scope(cfg: &ProjectConfig) -> Option<(&str, &Path)> {
+    Some((cfg.tag_name.as_deref()?, cfg.path.as_deref()?))
+}
+
+/// Whether the package in package.json is to be published to npm.
+fn publishes_to_npm(tree: &WorkingTree, cfg: &ProjectConfig) -> bool {
+    cfg.npm_publish.unwrap_or(false)
+        && tree.has_filename(Path::new("package.json"))
+        && !disperse::npm::is_private(tree).unwrap_or(true)
+}
+
+fn publish_targets(tree: &WorkingTree, cfg: &ProjectConfig) -> Vec<String> {
+    let mut targets = vec![];
+    let root_project = cfg.path.is_none();
+    if root_project
+        && cfg.twine_upload.unwrap_or(false)
+        && (tree.has_filename(Path::new("pyproject.toml"))
+            || tree.has_filename(Path::new("setup.py")))
+    {
+        targets.push("PyPI".to_string());
+    }
+    if tree.has_filename(&cfg.project_dir().join("Cargo.toml")) {
+        targets.push("crates.io".to_string());
+    }
+    if root_project && publishes_to_npm(tree, cfg) {
+        targets.push("npm".to_string());
+    }
+    if let Some(target) = cfg
+        .debian
+        .as_ref()
+        .filter(|d| d.build_source.unwrap_or(false))
+        .and_then(|d| d.upload.as_ref())
+    {
+        targets.push(format!("dput {}", target));
+    }
+    targets.extend(cfg.tarball_location.clone());
+    if cfg.github.is_some() {
+        targets.push("GitHub release".to_string());
+    }
+    if let Some(launchpad) = cfg.launchpad.as_ref() {
+        targets.push(format!("Launchpad project {}", launchpad.project));
+    }
+    if cfg.pkgbuild.as_ref().is_some_and(|p| p.aur.is_some()) {
+        targets.push("AUR".to_string());
+    }
+    targets
+}
+
+/// What a release does, shown for confirmation or instead of releasing in dry-run mode.
+#[derive(Debug, Clone, Default, serde::Serialize)]
+pub struct ReleasePlan {
+    pub project: String,
+    pub version: String,
+    pub tag: String,
+    pub steps: Vec<String>,
+    /// Files modified by the release commit.
+    pub files: Vec<std::path::PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub diff: Option<String>,
+    pub artifacts: Vec<std::path::PathBuf>,
+    /// Places the release is published to.
+    pub targets: Vec<String>,
+}
+
+impl std::fmt::Display for ReleasePlan {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        writeln!(f, "Release {} {}", self.project, self.version)?;
+        writeln!(f, "  Tag: {}", self.tag)?;
+        writeln!(f, "  Steps:")?;
+        for step in &self.steps {
+            writeln!(f, "    {}", step)?;
+        }
+        writeln!(f, "  Files to modify:")?;
+        for file in &self.files {
+            writeln!(f, "    {}", file.display())?;
+        }
+        if !self.artifacts.is_empty() {
+            writeln!(f, "  Artifacts:")?;
+            for artifact in &self.artifacts {
+                writeln!(f, "    {}", artifact.display())?;
+            }
+        }
+        if self.targets.is_empty() {
+            writeln!(f, "  Publish to: nothing")?;
+        } else {
+            writeln!(f, "  Publish to:")?;
+            for target in &self.targets {
+                writeln!(f, "    {}", target)?;
+            }
+        }
+        if let Some(diff) = self.diff.as_deref() {
+            write!(f, "{}", diff)?;
+        }
+        Ok(())
+    }
+}
+
+/// The steps of a release, in order.
+fn release_steps(
+    cfg: &ProjectConfig,
+    version: &Version,
+    tag_name: &str,
+    verify_command: Option<&str>,
+    targets: &[String],
+) -> Vec<String> {
+    let mut steps = vec![];
+    if let Some(pre_dist_command) = cfg.pre_dist_command.as_deref() {
+        steps.push(format!("Run pre-dist command: {}", pre_dist_command));
+    }
+    steps.push("Update the version and the news file".to_string());
+    steps.push(format!("Commit \"Release {}.\"", version.to_string()));
+    if let Some(verify_command) = verify_command {
+        steps.push(format!("Run verify command: {}", verify_command));
+    }
+    steps.push(format!("Create tag {}", tag_name));
+    steps.push("Build artifacts".to_string());
+    steps.extend(targets.iter().map(|t| format!("Publish to {}", t)));
+    steps.push("Push to the main branch".to_string());
+    steps
+}
+
+/// Unified diff of the uncommitted changes in a tree.
+fn uncommitted_diff(tree: &WorkingTree) -> Result<String, ReleaseError> {
+    let basis_tree = tree
+        .basis_tree()
+        .map_err(|e| ReleaseError::Other(e.to_string()))?;
+    let mut diff = vec![];
+    breezyshim::diff::show_diff_trees(&basis_tree, tree, &mut diff, None, None)
+        .map_err(|e| ReleaseError::Other(e.to_string()))?;
+    Ok(String::from_utf8_lossy(&diff).into_owned())
+}
+
+async fn publish_artifacts(
+    ws: &silver_platter::workspace::Workspace,
+    tag_name: &str,
+    dry_run: bool,
+    gh: &octocrab::Octocrab,
+    cfg: &ProjectConfig,
+    pypi_paths: &[&std::path::Path],
+    gh_repo: Option<&octocrab::models::Repository>,
+) -> Result<(Vec<std::path::PathBuf>, Vec<ReleaseLink>), ReleaseError> {
+    let mut artifacts = vec![];
+    let mut links = vec![];
+    // Wait for CI to go green
+    if let Some(gh_repo) = gh_repo {
+        if dry_run {
+            log::info!("In dry-run mode, so unable to wait for CI");
+        } else {
+            disperse::github::wait_for_gh_actions(
+                gh,
+                gh_repo,
+                Some(tag_name),
+                cfg.ci_timeout,
+                cfg.ci_poll_interval,
+            )
+            .await
+            .map_err(|e| ReleaseError::CIFailed(e.to_string()))?;
+        }
+    }
+
+    if !pypi_paths.is_empty() {
+        artifacts.extend(pypi_paths.iter().map(|x| x.to_path_buf()));
+        let tool = cfg.python_upload_tool.unwrap_or_default();
+        if dry_run {
+            log::info!("skipping PyPI upload due to dry run mode")
+        } else if !cfg.twine_upload.unwrap_or(false) {
+            log::info!("skipping PyPI upload; disabled in config")
         } else {
-            disperse::python::upload_python_artifacts(ws.local_tree(), pypi_paths).map_err(
+            let _progress = disperse::progress::Progress::start("Uploading to PyPI");
+            disperse::python::upload_python_artifacts(ws.local_tree(), pypi_paths, tool).map_err(
                 |e| ReleaseError::UploadCommandFailed {
-                    command: "twine upload".to_string(),
+                    command: disperse::python::upload_command(tool).join(" "),
                     status: None,
                     reason: Some(e.to_string()),
                 },
             )?;
+            if let Some(name) = disperse::python::find_name_in_pyproject_toml(ws.local_tree()) {
+                links.push(ReleaseLink {
+                    kind: "PyPI",
+                    url: Registry::PyPI.package_url(&name),
+                });
+            }
         }
     }
     if ws
         .local_tree()
-        .has_filename(std::path::Path::new("Cargo.toml"))
+        .has_filename(&cfg.project_dir().join("Cargo.toml"))
     {
         if dry_run {
-            log::info!("skipping cargo upload due to dry run mode");
+            log::info!("running cargo publish --dry-run due to dry run mode");
+            let crates = disperse::cargo::workspace_crates(ws.local_tree())
+                .map_err(|e| ReleaseError::PackageVerificationFailed(e.to_string()))?;
+            for krate in crates
+                .iter()
+                .filter(|c| c.publish && cfg.path.as_ref().is_none_or(|p| c.path == *p))
+            {
+                let files = disperse::cargo::package_list(ws.local_tree(), &krate.path)
+                    .map_err(|e| ReleaseError::PackageVerificationFailed(e.to_string()))?;
+                log::info!("{} would include {} files", krate.name, files.len());
+                for file in files {
+                    log::debug!("  {}", file.display());
+                }
+                disperse::cargo::publish_dry_run(ws.local_tree(), &krate.path)
+                    .map_err(|e| ReleaseError::PackageVerificationFailed(e.to_string()))?;
+            }
         } else {
-            disperse::cargo::publish(ws.local_tree(), std::path::Path::new(".")).map_err(|e| {
-                ReleaseError::UploadCommandFailed {
-                    command: "cargo publish".to_string(),
-                    status: None,
-                    reason: Some(e.to_string()),
+            let trusted = if disperse::cargo::use_trusted_publishing() {
+                Some(
+                    disperse::cargo::TrustedPublishingToken::obtain()
+                        .await
+                        .map_err(|e| ReleaseError::UploadCommandFailed {
+                            command: "cargo publish".to_string(),
+                            status: None,
+                            reason: Some(e.to_string()),
+                        })?,
+                )
+            } else {
+                None
+            };
+            let token = match trusted.as_ref() {
+                Some(trusted) => Some(trusted.token().to_string()),
+                None => disperse::cargo::token(),
+            };
+            let progress = disperse::progress::Progress::start("Publishing crates");
+            let published = disperse::cargo::publish_workspace(
+                ws.local_tree(),
+                cfg.path.as_deref(),
+                token.as_deref(),
+                disperse::cargo::DEFAULT_INDEX_TIMEOUT,
+            )
+            .await;
+            drop(progress);
+            if let Some(trusted) = trusted {
+                if let Err(e) = trusted.revoke().await {
+                    log::warn!("{}", e);
                 }
+            }
+            let published = published.map_err(|e| ReleaseError::UploadCommandFailed {
+                command: "cargo publish".to_string(),
+                status: None,
+                reason: Some(e.to_string()),
             })?;
+            links.extend(published.into_iter().map(|name| ReleaseLink {
+                kind: "crates.io",
+                url: Registry::CratesIo.package_url(&name),
+            }));
         }
     }
-    for loc in cfg.tarball_location.iter() {
+    if cfg.path.is_none() && publishes_to_npm(ws.local_tree(), cfg) {
+        let dist_tag = cfg.rollout.as_ref().map(|r| {
+            r.channel
+                .as_deref()
+                .unwrap_or(disperse::rollout::DEFAULT_CHANNEL)
+        });
         if dry_run {
-            log::info!("skipping scp to {} due to dry run mode", loc);
+            log::info!("skipping npm publish due to dry run mode");
         } else {
-            let args = artifacts
-                .iter()
-                .map(|s| s.to_path_buf().into_os_string())
-                .chain([std::ffi::OsString::from(loc)])
-                .collect::<Vec<std::ffi::OsString>>();
-            match std::process::Command::new("scp")
-                .args(args.clone())
-                .status()
-            {
-                Ok(status) => {
-                    if !status.success() {
-                        return Err(ReleaseError::UploadCommandFailed {
-                            command: format!(
-                                "scp {}",
-                                args.into_iter()
-                                    .map(|s| s.into_string().unwrap())
-                                    .collect::<Vec<String>>()
-                                    .join(" ")
-                            ),
-                            status: Some(status),
-                            reason: None,
-                        });
-                    }
-                }
-                Err(e) => {
-                    return Err(ReleaseError::UploadCommandFailed {
-                        command: format!(
-                            "scp {}",
-                            args.into_iter()
-                                .map(|s| s.into_string().unwrap())
-                                .collect::<Vec<String>>()
-                                .join(" ")
-                        ),
-                        status: None,
-                        reason: Some(e.to_string()),
-                    });
+            let _progress = disperse::progress::Progress::start("Publishing to npm");
+            disperse::npm::publish(
+                ws.local_tree(),
+                cfg.npm_provenance.unwrap_or(false),
+                dist_tag,
+            )
+            .map_err(|e| ReleaseError::UploadCommandFailed {
+                command: "npm publish".to_string(),
+                status: None,
+                reason: Some(e.to_string()),
+            })?;
+            if let Ok(Some(name)) = disperse::npm::find_name(ws.local_tree()) {
+                links.push(ReleaseLink {
+                    kind: "npm",
+                    url: Registry::Npm.package_url(&name),
+                });
+            }
+        }
+    }
+    if let Some(debian) = cfg.debian.as_ref() {
+        if debian.build_source.unwrap_or(false) {
+            let changes = disperse::debian::build_source_package(ws.local_tree())
+                .map_err(|e| ReleaseError::Other(e.to_string()))?;
+            if let Some(target) = debian.upload.as_ref() {
+                if dry_run {
+                    log::info!("skipping dput to {} due to dry run mode", target);
+                } else {
+                    disperse::debian::upload(&changes, target).map_err(|e| {
+                        ReleaseError::UploadCommandFailed {
+                            command: format!("dput {}", target),
+                            status: None,
+                            reason: Some(e.to_string()),
+                        }
+                    })?;
                 }
             }
         }
     }
-    Ok(artifacts)
-}
-
-fn determine_verify_command(cfg: &ProjectConfig, wt: &WorkingTree) -> Option<String> {
-    if let Some(verify_command) = cfg.verify_command.as_ref() {
-        Some(verify_command.clone())
-    } else if wt.has_filename(Path::new("tox.ini")) {
-        Some("tox".to_string())
-    } else if wt.has_filename(Path::new("Cargo.toml")) {
-        Some("cargo test --all".to_string())
-    } else {
-        None
+    for loc in cfg.tarball_location.iter() {
+        if dry_run {
+            log::info!("skipping upload to {} due to dry run mode", loc);
+        } else {
+            let location: disperse::upload::Location = loc
+                .parse()
+                .map_err(|e: disperse::upload::Error| ReleaseError::Other(e.to_string()))?;
+            disperse::upload::upload(
+                &location,
+                &artifacts,
+                cfg.tarball_transport.unwrap_or_default(),
+            )
+            .map_err(|e| match e {
+                disperse::upload::Error::CommandFailed {
+                    command,
+                    status,
+                    reason,
+                } => ReleaseError::UploadCommandFailed {
+                    command,
+                    status,
+                    reason,
+                },
+                e => ReleaseError::Other(e.to_string()),
+            })?;
+        }
     }
+    Ok((artifacts, links))
 }
 
+/// Authenticated Launchpad client, only needed for operations that modify Launchpad.
 async fn launchpad_client() -> Result<&'static launchpadlib::r#async::client::Client, ReleaseError>
 {
     static LAUNCHPAD_CLIENT: tokio::sync::OnceCell<launchpadlib::r#async::client::Client> =
@@ -750,39 +2510,475 @@ async fn launchpad_client() -> Result<&'static launchpadlib::r#async::client::Cl
         .await
 }
 
-pub async fn release_project(
-    repo_url: &str,
-    force: Option<bool>,
-    new_version: Option<&Version>,
-    dry_run: Option<bool>,
-    ignore_ci: Option<bool>,
-    ignore_verify_command: Option<bool>,
-    preserve_temp: bool,
-) -> Result<(String, Version), ReleaseError> {
-    let force = force.unwrap_or(false);
-    let dry_run = dry_run.unwrap_or(false);
-    let ignore_ci = ignore_ci.unwrap_or(false);
-    let ignore_verify_command = ignore_verify_command.unwrap_or(false);
+/// Launchpad client for read-only access, which doesn't require authentication.
+fn anonymous_launchpad_client() -> &'static launchpadlib::r#async::client::Client {
+    static ANONYMOUS_LAUNCHPAD_CLIENT: std::sync::OnceLock<launchpadlib::r#async::client::Client> =
+        std::sync::OnceLock::new();
+
+    ANONYMOUS_LAUNCHPAD_CLIENT
+        .get_or_init(|| launchpadlib::r#async::client::Client::anonymous("disperse"))
+}
+
+/// Open the news file of a project, with the layout from its configuration.
+fn open_news_file<'a>(
+    tree: &'a WorkingTree,
+    cfg: &ProjectConfig,
+    path: &Path,
+) -> Result<disperse::news_file::NewsFile<'a>, ReleaseError> {
+    let layout = disperse::news_file::NewsLayout::from_config(cfg)
+        .map_err(|e| ReleaseError::Other(e.to_string()))?;
+    Ok(disperse::news_file::NewsFile::new(tree, path)
+        .map_err(|e| ReleaseError::Other(e.to_string()))?
+        .with_layout(layout))
+}
+
+/// Key under which the workspace of a failed release of a project is kept.
+fn workspace_key(repo_url: &str) -> String {
+    match std::fs::canonicalize(repo_url) {
+        Ok(path) => path.display().to_string(),
+        Err(_) => repo_url.trim_end_matches('/').to_string(),
+    }
+}
+
+fn release_journal(workspace: &Path) -> Result<disperse::journal::Journal, ReleaseError> {
+    disperse::journal::Journal::for_workspace(workspace)
+        .map_err(|e| ReleaseError::Other(format!("Unable to open release journal: {}", e)))
+}
+
+fn workspace_index() -> Result<disperse::journal::WorkspaceIndex, ReleaseError> {
+    disperse::journal::WorkspaceIndex::open_default()
+        .map_err(|e| ReleaseError::Other(format!("Unable to open workspace index: {}", e)))
+}
+
+/// Keep the workspace of a release, so that the release can be resumed if a
+/// later step fails.
+fn keep_workspace(
+    ws: &mut silver_platter::workspace::Workspace,
+    key: &str,
+    preserved: bool,
+) -> Result<(), ReleaseError> {
+    if !preserved {
+        ws.defer_destroy();
+    }
+    workspace_index()?
+        .insert(key, &ws.path())
+        .map_err(|e| ReleaseError::Other(e.to_string()))
+}
+
+/// Remember a workspace, so that `disperse clean` can remove it if it is left behind.
+fn track_workspace(key: &str, path: &Path) {
+    let tracked = disperse::journal::WorkspaceTracker::open_default().and_then(|tracker| {
+        Ok(tracker.record(disperse::journal::CreatedWorkspace {
+            project: key.to_string(),
+            path: path.to_path_buf(),
+            created: chrono::Utc::now(),
+        })?)
+    });
+    if let Err(e) = tracked {
+        log::warn!("Unable to record workspace {}: {}", path.display(), e);
+    }
+}
+
+/// Find workspaces left behind by earlier runs, removing them if `remove` is set.
+///
+/// Only workspaces at least `older_than` days old are considered. The
+/// workspaces of failed releases, which can be resumed, are kept unless
+/// `include_resumable` is set.
+fn clean_workspaces(
+    older_than: u64,
+    include_resumable: bool,
+    remove: bool,
+) -> Result<Vec<disperse::journal::CreatedWorkspace>, String> {
+    let tracker = disperse::journal::WorkspaceTracker::open_default().map_err(|e| e.to_string())?;
+    let index = disperse::journal::WorkspaceIndex::open_default().map_err(|e| e.to_string())?;
+    let resumable = index.list().map_err(|e| e.to_string())?;
     let now = chrono::Utc::now();
+    let stale = tracker
+        .existing()
+        .map_err(|e| e.to_string())?
+        .into_iter()
+        .filter(|w| now.signed_duration_since(w.created).num_days() >= older_than as i64)
+        .filter(|w| include_resumable || !resumable.values().any(|p| p == &w.path))
+        .collect::<Vec<_>>();
+    if remove {
+        for w in stale.iter() {
+            tracker.remove(w).map_err(|e| e.to_string())?;
+            disperse::journal::Journal::for_workspace(&w.path)
+                .map_err(|e| e.to_string())?
+                .remove()
+                .map_err(|e| e.to_string())?;
+            if resumable.get(&w.project) == Some(&w.path) {
+                index.remove(&w.project).map_err(|e| e.to_string())?;
+            }
+        }
+    }
+    Ok(stale)
+}
 
-    let (local_wt, branch) = match breezyshim::controldir::open_tree_or_branch(repo_url, None, None)
-    {
-        Ok(x) => x,
+fn clean(older_than: u64, include_resumable: bool, list: bool) -> i32 {
+    match clean_workspaces(older_than, include_resumable, !list) {
+        Ok(workspaces) => {
+            for w in workspaces {
+                println!(
+                    "{}{} ({}, created {})",
+                    if list { "" } else { "Removed " },
+                    w.path.display(),
+                    w.project,
+                    w.created.format("%Y-%m-%d")
+                );
+            }
+            0
+        }
         Err(e) => {
-            return Err(ReleaseError::RepositoryUnavailable {
-                url: repo_url.to_string(),
-                reason: e.to_string(),
-            });
+            log::error!("Unable to clean up workspaces: {}", e);
+            1
         }
-    };
+    }
+}
 
-    let mut public_repo_url = None;
-    let mut public_branch = None;
-    let mut local_branch = None;
+/// Record a completed release step in the journal, unless an earlier attempt
+/// already did.
+fn record_step(journal: &disperse::journal::Journal, step: Step) {
+    match journal.steps() {
+        Ok(steps) if steps.contains(&step) => {}
+        _ => {
+            if let Err(e) = journal.record(&step) {
+                log::warn!(
+                    "Unable to record release step in {}: {}",
+                    journal.path().display(),
+                    e
+                );
+            }
+        }
+    }
+}
 
-    if branch.user_transport().base().scheme() == "file" {
-        local_branch = Some(branch);
-        if let Some(public_branch_url) = local_branch.as_ref().unwrap().get_public_branch() {
+/// Result of updating the files in the tree for a release.
+struct ReleaseFiles {
+    /// Release notes, as taken from the news file.
+    release_changes: Option<String>,
+    /// Files modified for the release; only these end up in the release commit.
+    modified_files: Vec<std::path::PathBuf>,
+    /// Python artifacts, if they had to be built before committing.
+    pypi_paths: Option<Vec<std::path::PathBuf>>,
+}
+
+/// Update the news file and version strings for a release.
+fn update_release_files(
+    tree: &WorkingTree,
+    cfg: &ProjectConfig,
+    name: &str,
+    new_version: &Version,
+    now: chrono::DateTime<chrono::Utc>,
+) -> Result<ReleaseFiles, ReleaseError> {
+    let mut modified_files: Vec<std::path::PathBuf> = vec![];
+    let release_changes = if let Some(news_file_path) = cfg.news_file.as_ref() {
+        let news_file = open_news_file(tree, cfg, news_file_path)?;
+        if cfg.create_pending == Some(true)
+            && news_file
+                .pending_changes()
+                .map_err(|e| ReleaseError::Other(e.to_string()))?
+                .is_none()
+        {
+            log::info!(
+                "Adding pending section for {} to {}",
+                new_version.to_string(),
+                news_file_path.display()
+            );
+            news_file
+                .add_pending(new_version)
+                .map_err(|e| ReleaseError::Other(e.to_string()))?;
+        }
+        let news_from_commits = cfg.news_from_commits.unwrap_or_default();
+        if news_from_commits != NewsFromCommits::Off {
+            let revisions = disperse::revisions_since_last_tag(tree.branch().as_ref())
+                .map_err(|e| ReleaseError::Other(e.to_string()))?;
+            // Oldest first, leaving out the commit that started the release cycle.
+            let changes = disperse::conventional_commits::news_entries(
+                revisions
+                    .iter()
+                    .rev()
+                    .map(|r| r.message.as_str())
+                    .filter(|m| !m.starts_with("Start on ")),
+                news_from_commits == NewsFromCommits::ConventionalCommits,
+            );
+            if !changes.is_empty()
+                && news_file
+                    .fill_pending(new_version, &changes)
+                    .map_err(|e| ReleaseError::Other(e.to_string()))?
+            {
+                log::info!("Generated news entries from {} commits", revisions.len());
+            }
+        }
+        let release_changes = news_file
+            .mark_released(new_version, &now.date_naive())
+            .map_err(|e| ReleaseError::Other(e.to_string()))?;
+        if let Some(tag_template) = cfg.tag_name.as_ref() {
+            let tag_name = disperse::version::expand_tag_with(
+                tag_template,
+                new_version,
+                &disperse::version::TagContext {
+                    project: Some(name),
+                    date: Some(now.date_naive()),
+                },
+            );
+            if news_file
+                .update_compare_links(new_version, &tag_name)
+                .map_err(|e| ReleaseError::Other(e.to_string()))?
+            {
+                log::info!("Updated comparison links in {}", news_file_path.display());
+            }
+        }
+        modified_files.push(news_file_path.clone());
+        Some(release_changes)
+    } else {
+        None
+    };
+
+    for update_version in cfg.update_version.as_ref().unwrap_or(&vec![]) {
+        disperse::custom::update_version_in_file(
+            tree,
+            &update_version.path,
+            &update_version.new_line,
+            update_version.r#match.as_deref(),
+            new_version,
+            disperse::Status::Final,
+            &disperse::custom::VersionContext {
+                custom: cfg.version_variables.as_ref(),
+                date: Some(now.date_naive()),
+            },
+        )
+        .map_err(ReleaseError::Other)?;
+        modified_files.push(update_version.path.clone());
+    }
+
+    for update_manpage in cfg.update_manpages.as_ref().unwrap_or(&vec![]) {
+        for path in disperse::iter_glob(tree, update_manpage.path().to_str().unwrap()) {
+            let update = disperse::manpage::update_version_in_manpage(
+                tree,
+                &path,
+                new_version,
+                now.date_naive(),
+                update_manpage.date_format(),
+                update_manpage.locale(),
+            )
+            .map_err(|e| ReleaseError::Other(e.to_string()))?;
+            if !update.date {
+                log::warn!(
+                    "Unable to recognize date in {}; leaving it untouched",
+                    path.display()
+                );
+            }
+            if !update.version {
+                log::warn!(
+                    "Unable to recognize version in {}; leaving it untouched",
+                    path.display()
+                );
+            }
+            modified_files.push(path);
+        }
+    }
+
+    for update_metainfo in cfg.update_metainfo.as_ref().unwrap_or(&vec![]) {
+        for path in disperse::iter_glob(tree, update_metainfo.to_str().unwrap()) {
+            disperse::appstream::update_metainfo(
+                tree,
+                &path,
+                new_version,
+                now.date_naive(),
+                release_changes.as_deref(),
+            )
+            .map_err(|e| ReleaseError::Other(e.to_string()))?;
+            modified_files.push(path);
+        }
+    }
+
+    if let Some(update_docs) = cfg.update_docs.as_ref() {
+        let name = disperse::find_name_in_tree(tree, cfg)
+            .map(|(name, _)| name)
+            .ok_or_else(|| {
+                ReleaseError::Other("update-docs is set, but no project name is known".to_string())
+            })?;
+        for pattern in update_docs {
+            for path in disperse::iter_glob(tree, pattern.to_str().unwrap()) {
+                disperse::doc_versions::update_doc_file(tree, &path, &name, new_version)
+                    .map_err(|e| ReleaseError::Other(format!("{}: {}", path.display(), e)))?;
+                modified_files.push(path);
+            }
+        }
+    }
+
+    let lockfile_update = cfg.cargo_lockfile.unwrap_or_default();
+    let cargo_toml = cfg.project_dir().join("Cargo.toml");
+    if tree.has_filename(&cargo_toml) {
+        modified_files.extend(
+            if cfg.path.is_some() {
+                disperse::cargo::update_crate_version(
+                    tree,
+                    cfg.project_dir(),
+                    new_version.to_string().as_str(),
+                )
+            } else {
+                disperse::cargo::update_version(tree, new_version.to_string().as_str())
+            }
+            .map_err(|e| ReleaseError::Other(e.to_string()))?,
+        );
+        if lockfile_update == LockfileUpdate::Workspace
+            && disperse::cargo::update_lockfile(tree)
+                .map_err(|e| ReleaseError::Other(e.to_string()))?
+        {
+            modified_files.push("Cargo.lock".into());
+        }
+    }
+    // Only crates are supported in repositories with several projects.
+    let root_project = cfg.path.is_none();
+    if root_project && tree.has_filename(Path::new("pyproject.toml")) {
+        disperse::python::update_version_in_pyproject_toml(tree, new_version)
+            .map_err(|e| ReleaseError::Other(e.to_string()))?;
+        modified_files.push("pyproject.toml".into());
+    }
+    if root_project
+        && (tree.has_filename(Path::new("pyproject.toml"))
+            || tree.has_filename(Path::new("setup.py")))
+        && cfg.update_python_version.unwrap_or(true)
+    {
+        let explicit = cfg
+            .update_version
+            .iter()
+            .flatten()
+            .map(|u| u.path.as_path())
+            .collect::<Vec<_>>();
+        modified_files.extend(
+            disperse::python::update_python_version_files(
+                tree,
+                new_version,
+                disperse::Status::Final,
+                &explicit,
+            )
+            .map_err(|e| ReleaseError::Other(e.to_string()))?,
+        );
+    }
+    if root_project
+        && tree.has_filename(Path::new("package.json"))
+        && disperse::npm::find_version(tree)
+            .map_err(|e| ReleaseError::Other(e.to_string()))?
+            .is_some()
+    {
+        disperse::npm::update_version(tree, new_version.to_string().as_str())
+            .map_err(|e| ReleaseError::Other(e.to_string()))?;
+        modified_files.push("package.json".into());
+        if tree.has_filename(Path::new("package-lock.json")) {
+            modified_files.push("package-lock.json".into());
+        }
+    }
+    // A debian/changelog that is the news file has already been updated.
+    if root_project
+        && tree.has_filename(Path::new("debian/changelog"))
+        && !cfg
+            .news_file
+            .as_deref()
+            .is_some_and(disperse::debian::is_changelog_path)
+    {
+        let debian_version = disperse::debian::update_changelog(
+            tree,
+            new_version,
+            cfg.debian.as_ref().and_then(|d| d.distribution.as_deref()),
+            release_changes.as_deref(),
+        )
+        .map_err(|e| ReleaseError::Other(e.to_string()))?;
+        log::info!("Added debian/changelog entry for {}", debian_version);
+        modified_files.push("debian/changelog".into());
+    }
+
+    // Source checksums in nix expressions and in-tree PKGBUILDs have to
+    // match the published artifacts, so build those before committing.
+    let in_tree_pkgbuild = cfg.pkgbuild.as_ref().filter(|p| p.aur.is_none());
+    let pypi_paths = if cfg.update_nix.is_some() || in_tree_pkgbuild.is_some() {
+        Some(create_python_artifacts(tree, cfg)?)
+    } else {
+        None
+    };
+    let sdist = pypi_paths.as_ref().and_then(|p| find_sdist(p));
+
+    if let Some(update_nix) = cfg.update_nix.as_ref() {
+        let sdist =
+            sdist.ok_or_else(|| ReleaseError::Other("No source tarball to hash".to_string()))?;
+        let hash =
+            disperse::nix::sri_hash(sdist).map_err(|e| ReleaseError::Other(e.to_string()))?;
+        for path in update_nix {
+            disperse::nix::update_nix_file(tree, path, new_version, &hash)
+                .map_err(|e| ReleaseError::Other(format!("{}: {}", path.display(), e)))?;
+            modified_files.push(path.clone());
+        }
+    }
+
+    if let Some(pkgbuild) = in_tree_pkgbuild {
+        let sha256 = sdist
+            .map(|p| disperse::pkgbuild::sha256sum(p))
+            .transpose()
+            .map_err(|e| ReleaseError::Other(e.to_string()))?;
+        if sha256.is_none() {
+            log::warn!("No source tarball built; not updating PKGBUILD checksums");
+        }
+        modified_files.extend(
+            disperse::pkgbuild::update_pkgbuild_file(
+                tree,
+                pkgbuild.path(),
+                new_version,
+                sha256.as_deref(),
+            )
+            .map_err(|e| ReleaseError::Other(e.to_string()))?,
+        );
+    }
+
+    Ok(ReleaseFiles {
+        release_changes,
+        modified_files,
+        pypi_paths,
+    })
+}
+
+async fn release_project(
+    repo_url: &str,
+    project: Option<&str>,
+    options: &Options,
+) -> Result<ReleaseOutcome, ReleaseError> {
+    let Options {
+        force,
+        dry_run,
+        ignore_ci,
+        ignore_verify_command,
+        preserve_temp,
+        ..
+    } = *options;
+    let new_version = options.new_version.as_ref();
+    let now = chrono::Utc::now();
+
+    if let Some(hold) = options
+        .project_overrides(repo_url)
+        .and_then(|o| o.hold(now.date_naive()))
+    {
+        return Err(ReleaseError::OnHold(hold));
+    }
+
+    let (local_wt, branch) = match breezyshim::controldir::open_tree_or_branch(repo_url, None, None)
+    {
+        Ok(x) => x,
+        Err(e) => {
+            return Err(ReleaseError::RepositoryUnavailable {
+                url: repo_url.to_string(),
+                reason: e.to_string(),
+            });
+        }
+    };
+
+    let mut public_repo_url = None;
+    let mut public_branch = None;
+    let mut local_branch = None;
+
+    if branch.user_transport().base().scheme() == "file" {
+        local_branch = Some(branch);
+        if let Some(public_branch_url) = local_branch.as_ref().unwrap().get_public_branch() {
             log::info!("Using public branch {}", &public_branch_url);
             let url: url::Url = public_branch_url.as_str().parse().unwrap();
             let url = disperse::drop_segment_parameters(&url);
@@ -849,6 +3045,31 @@ pub async fn release_project(
         return Err(ReleaseError::NoPublicBranch);
     }
 
+    let workspace_key = match project {
+        Some(project) => format!("{}#{}", workspace_key(repo_url), project),
+        None => workspace_key(repo_url),
+    };
+    // The workspace of an earlier attempt at the release, if it is being resumed.
+    let resumed = if options.resume {
+        let path = workspace_index()?
+            .get(&workspace_key)
+            .map_err(|e| ReleaseError::Other(e.to_string()))?
+            .ok_or_else(|| {
+                ReleaseError::Other(format!("No failed release of {} to resume", repo_url))
+            })?;
+        let steps = release_journal(&path)?
+            .steps()
+            .map_err(|e| ReleaseError::Other(e.to_string()))?;
+        let tree = workingtree::open(&path).map_err(|e| ReleaseError::RepositoryUnavailable {
+            url: path.display().to_string(),
+            reason: e.to_string(),
+        })?;
+        log::info!("Resuming release from {}", path.display());
+        Some((path, tree, steps))
+    } else {
+        None
+    };
+
     let mut wsbuilder = silver_platter::workspace::Workspace::builder();
 
     if let Some(public_branch) = public_branch.take() {
@@ -859,13 +3080,42 @@ pub async fn release_project(
         wsbuilder = wsbuilder.cached_branch(local_branch);
     }
 
-    let mut ws = wsbuilder.build().unwrap();
+    if let Some((_, tree, _)) = resumed.as_ref() {
+        wsbuilder = wsbuilder.resume_branch(tree.branch());
+    }
+
+    let mut ws = {
+        let _progress = disperse::progress::Progress::start("Preparing workspace");
+        wsbuilder.build().unwrap()
+    };
 
     if preserve_temp {
         ws.defer_destroy();
     }
+    track_workspace(&workspace_key, &ws.path());
+
+    let journal = release_journal(&ws.path())?;
+    let journaled = match resumed {
+        Some((path, _, steps)) => {
+            for step in &steps {
+                record_step(&journal, step.clone());
+            }
+            if !dry_run {
+                keep_workspace(&mut ws, &workspace_key, preserve_temp)?;
+                if let Err(e) = std::fs::remove_dir_all(&path) {
+                    log::warn!("Unable to remove {}: {}", path.display(), e);
+                }
+                if let Err(e) = release_journal(&path)?.remove() {
+                    log::warn!("Unable to remove journal of {}: {}", path.display(), e);
+                }
+            }
+            steps
+        }
+        None => vec![],
+    };
+    let resuming = !journaled.is_empty();
 
-    let cfg = match disperse::project_config::read_project_with_fallback(ws.local_tree()) {
+    let mut cfg = match read_project_config_for(ws.local_tree(), project, options) {
         Ok(cfg) => cfg,
         Err(e) => {
             log::error!("Unable to read project configuration: {}", e);
@@ -874,31 +3124,66 @@ pub async fn release_project(
         }
     };
 
-    let name = if let Some(name) = cfg.name.as_ref() {
-        Some(name.clone())
-    } else if ws.local_tree().has_filename(Path::new("pyproject.toml")) {
-        disperse::python::find_name_in_pyproject_toml(ws.local_tree())
-    } else {
-        None
-    };
+    if !cfg.projects.is_empty() {
+        return Err(ReleaseError::SeveralProjects(
+            cfg.project_names().into_iter().map(String::from).collect(),
+        ));
+    }
 
-    let name = if let Some(name) = name {
-        name
-    } else {
-        public_repo_url
-            .as_ref()
-            .map(|u| {
-                u.as_str()
-                    .rsplit('/')
-                    .next()
-                    .map(|s| s.to_string())
-                    .unwrap_or_default()
-            })
-            .unwrap_or_else(|| "".to_string())
-    };
+    if let Some(hold) = cfg.hold(now.date_naive()) {
+        return Err(ReleaseError::OnHold(hold));
+    }
+
+    let mut plugins = disperse::plugins::load(
+        &ws.local_tree().abspath(Path::new(".")).unwrap(),
+        cfg.plugins.as_deref().unwrap_or_default(),
+    )?;
+
+    options.apply_ci_overrides(&mut cfg);
+
+    let gh = disperse::github::login().map_err(|e| ReleaseError::Other(e.to_string()))?;
+
+    let (name, name_source) =
+        if let Some((name, source)) = disperse::find_name_in_tree(ws.local_tree(), &cfg) {
+            (name, source)
+        } else {
+            let github_url = cfg
+                .github
+                .as_ref()
+                .and_then(|g| g.url.parse::<url::Url>().ok())
+                .or_else(|| public_repo_url.clone())
+                .filter(|u| u.host_str() == Some("github.com"));
+            let forge_name = if let Some(github_url) = github_url {
+                disperse::github::get_github_repo(&gh, &github_url)
+                    .await
+                    .map(|r| r.name)
+                    .ok()
+            } else {
+                None
+            };
+            if let Some(name) = forge_name {
+                (name, disperse::NameSource::Forge)
+            } else {
+                (
+                    public_repo_url
+                        .as_ref()
+                        .map(|u| {
+                            u.as_str()
+                                .rsplit('/')
+                                .next()
+                                .map(|s| s.to_string())
+                                .unwrap_or_default()
+                        })
+                        .unwrap_or_else(|| "".to_string()),
+                    disperse::NameSource::Url,
+                )
+            }
+        };
+    log::info!("Using project name {} (from {})", name, name_source);
+    check_release_ledger(&name, options);
 
     let mut launchpad_project = if let Some(launchpad) = cfg.launchpad.as_ref() {
-        disperse::launchpad::get_project(launchpad_client().await?, &launchpad.project)
+        disperse::launchpad::get_project(anonymous_launchpad_client(), &launchpad.project)
             .await
             .ok()
     } else {
@@ -907,7 +3192,7 @@ pub async fn release_project(
 
     let mut launchpad_series =
         if let Some(series) = cfg.launchpad.as_ref().and_then(|l| l.series.as_ref()) {
-            let lp = launchpad_client().await?;
+            let lp = anonymous_launchpad_client();
             let series = disperse::launchpad::find_project_series(
                 lp,
                 &launchpad_project.as_ref().unwrap().self_().unwrap(),
@@ -930,8 +3215,6 @@ pub async fn release_project(
 
     let mut gh_repo = None;
 
-    let gh = disperse::github::login().map_err(|e| ReleaseError::Other(e.to_string()))?;
-
     if let Some(github) = cfg.github.as_ref() {
         let url = &github.url;
         public_repo_url = Some(url.parse().unwrap());
@@ -1016,6 +3299,12 @@ pub async fn release_project(
             .map_err(|e| ReleaseError::Other(e.to_string()))?,
         );
     }
+    if ws.local_tree().has_filename(Path::new("package.json")) {
+        possible_urls.extend(
+            disperse::npm::read_project_urls_from_package_json(ws.local_tree())
+                .map_err(|e| ReleaseError::Other(e.to_string()))?,
+        );
+    }
     possible_urls.push((public_repo_url, ws.main_branch().map(|b| b.name().unwrap())));
 
     for (parsed_url, branch_name) in possible_urls.iter() {
@@ -1069,7 +3358,7 @@ pub async fn release_project(
                 break;
             }
             Some("launchpad.net") => {
-                let lp = launchpad_client().await?;
+                let lp = anonymous_launchpad_client();
                 let parts = parsed_url.path_segments().unwrap().collect::<Vec<_>>();
                 launchpad_project = Some(
                     disperse::launchpad::get_project(lp, parts[0])
@@ -1095,11 +3384,21 @@ pub async fn release_project(
         }
     }
 
-    if !disperse::check_new_revisions(
-        ws.local_tree().branch().as_ref(),
-        cfg.news_file.as_ref().map(Path::new),
-    )
-    .map_err(|e| ReleaseError::Other(e.to_string()))?
+    // When releasing pending news sections one at a time, the sections after
+    // the first are released from the same revisions.
+    let catching_up = cfg.catch_up == Some(CatchUp::Sequential)
+        && disperse::is_at_release_tag(ws.local_tree().branch().as_ref())
+            .map_err(|e| ReleaseError::Other(e.to_string()))?
+        && disperse::find_pending_version(ws.local_tree(), &cfg, None).is_ok();
+
+    if !catching_up
+        && !resuming
+        && !disperse::check_new_revisions(
+            ws.local_tree().branch().as_ref(),
+            cfg.news_file.as_ref().map(Path::new),
+            project_scope(&cfg),
+        )
+        .map_err(|e| ReleaseError::Other(e.to_string()))?
     {
         NO_UNRELEASED_CHANGES_COUNT
             .with_label_values(&[&name])
@@ -1114,7 +3413,7 @@ pub async fn release_project(
     }) = check_release_age(ws.local_tree().branch().as_ref(), &cfg, now)
     {
         RECENT_COMMITS_COUNT.with_label_values(&[&name]).inc();
-        if !force {
+        if !force && !catching_up && !resuming {
             return Err(ReleaseError::RecentCommits {
                 min_commit_age,
                 commit_age,
@@ -1122,184 +3421,388 @@ pub async fn release_project(
         }
     }
 
-    let new_version: Version = new_version.map_or_else(
-        || {
-            let new_version =
-                pick_new_version(ws.local_tree(), &cfg).map_err(ReleaseError::Other)?;
-            log::info!("Picked new version: {}", new_version.to_string());
-            Ok::<Version, ReleaseError>(new_version)
-        },
-        |v| Ok(v.clone()),
-    )?;
-
-    if let Some(pre_dist_command) = cfg.pre_dist_command.as_ref() {
-        match std::process::Command::new("sh")
-            .arg("-c")
-            .arg(pre_dist_command)
-            .current_dir(ws.local_tree().abspath(Path::new(".")).unwrap())
-            .status()
-        {
-            Ok(s) => {
-                if !s.success() {
-                    PRE_DIST_COMMAND_FAILED.with_label_values(&[&name]).inc();
-                    return Err(ReleaseError::PreDistCommandFailed {
-                        command: pre_dist_command.clone(),
-                        status: Some(s),
-                    });
-                }
-            }
-            Err(_e) => {
-                PRE_DIST_COMMAND_FAILED.with_label_values(&[&name]).inc();
-                return Err(ReleaseError::PreDistCommandFailed {
-                    command: pre_dist_command.clone(),
-                    status: None,
-                });
-            }
+    if let (Some(news_file_path), false) = (cfg.news_file.as_ref(), resuming) {
+        let merged = open_news_file(ws.local_tree(), &cfg, news_file_path)?
+            .catch_up(cfg.catch_up.unwrap_or_default())
+            .map_err(|e| ReleaseError::Other(e.to_string()))?;
+        if merged > 0 {
+            log::info!(
+                "Merged {} older unreleased sections in {} into the newest one",
+                merged,
+                news_file_path.display()
+            );
         }
     }
 
-    let verify_command = determine_verify_command(&cfg, ws.local_tree());
-
-    log::info!("releasing {}", new_version.to_string());
-    let (news_file, release_changes) = if let Some(news_file_path) = cfg.news_file.as_ref() {
-        let news_file =
-            disperse::news_file::NewsFile::new(ws.local_tree(), Path::new(news_file_path))
-                .map_err(|e| ReleaseError::Other(e.to_string()))?;
-        let release_changes = news_file
-            .mark_released(&new_version, &now.date_naive())
-            .map_err(|e| ReleaseError::Other(e.to_string()))?;
-        (Some(news_file), Some(release_changes))
+    let journaled_version = journaled.iter().find_map(|step| match step {
+        Step::VersionPicked { version } => Some(version),
+        _ => None,
+    });
+    let new_version: Version = match (new_version, journaled_version) {
+        (Some(v), _) => v.clone(),
+        (None, Some(v)) => {
+            log::info!("Using version {} from the previous attempt", v);
+            v.parse()
+                .map_err(|_| ReleaseError::OddPendingVersion { version: v.clone() })?
+        }
+        (None, None) => {
+            let new_version = pick_new_version(ws.local_tree(), &cfg, options.bump)
+                .map_err(ReleaseError::Other)?;
+            log::info!("Picked new version: {}", new_version.to_string());
+            new_version
+        }
+    };
+    // Work out the version to start on afterwards now, so that a bad bump is
+    // reported before anything is tagged or published.
+    // With calendar versioning, the next version depends on when it is released.
+    let next_version = if cfg.version_scheme == Some(VersionScheme::Calver) {
+        None
     } else {
-        (None, None)
+        let mut next_version = new_version.clone();
+        apply_bump(&mut next_version, cfg.default_bump, &cfg);
+        if next_version <= new_version {
+            return Err(ReleaseError::NextVersionNotNewer {
+                version: Box::new(new_version),
+                next: Box::new(next_version),
+            });
+        }
+        Some(next_version)
     };
+    record_step(
+        &journal,
+        Step::VersionPicked {
+            version: new_version.to_string(),
+        },
+    );
 
-    for update_version in cfg.update_version.as_ref().unwrap_or(&vec![]) {
-        disperse::custom::update_version_in_file(
-            ws.local_tree(),
-            &update_version.path,
-            &update_version.new_line,
-            update_version.r#match.as_deref(),
-            &new_version,
-            disperse::Status::Final,
-        )
-        .map_err(ReleaseError::Other)?;
+    let plugin_context = |hook, tag: Option<&str>| disperse::plugins::ReleaseContext {
+        hook,
+        project: name.clone(),
+        version: new_version.to_string(),
+        tag: tag.map(|t| t.to_string()),
+        dry_run,
+    };
+    for note in disperse::plugins::run_hook(
+        &mut plugins,
+        &plugin_context(disperse::plugins::Hook::Plan, None),
+    )? {
+        log::info!("{}", note);
     }
 
-    for update_manpage in cfg.update_manpages.as_ref().unwrap_or(&vec![]) {
-        for path in disperse::iter_glob(ws.local_tree(), update_manpage.to_str().unwrap()) {
-            disperse::manpage::update_version_in_manpage(
-                ws.local_tree(),
-                &path,
-                &new_version,
-                now.date_naive(),
-            )
-            .map_err(|e| ReleaseError::Other(e.to_string()))?;
-        }
-    }
+    let checklist = match cfg.checklist.as_deref() {
+        Some(items) => disperse::checklist::run_checklist(
+            items,
+            disperse::checklist::confirmer(options.interactive && !dry_run),
+        )
+        .map_err(|e| ReleaseError::Other(e.to_string()))?,
+        None => vec![],
+    };
 
-    if ws.local_tree().has_filename(Path::new("Cargo.toml")) {
-        disperse::cargo::update_version(ws.local_tree(), new_version.to_string().as_str())
-            .map_err(|e| ReleaseError::Other(e.to_string()))?;
+    if ws
+        .local_tree()
+        .has_filename(&cfg.project_dir().join("Cargo.toml"))
+        && !dry_run
+    {
+        disperse::cargo::preflight(ws.local_tree())
+            .await
+            .map_err(|e| ReleaseError::Other(format!("crates.io preflight failed: {}", e)))?;
     }
-    if ws.local_tree().has_filename(Path::new("pyproject.toml")) {
-        disperse::python::update_version_in_pyproject_toml(ws.local_tree(), &new_version)
-            .map_err(|e| ReleaseError::Other(e.to_string()))?;
+
+    if let Err(e) = run_pre_dist_command(ws.local_tree(), &cfg) {
+        PRE_DIST_COMMAND_FAILED.with_label_values(&[&name]).inc();
+        return Err(e);
     }
-    let revid = ws
-        .local_tree()
-        .build_commit()
-        .message(format!("Release {}.", new_version.to_string()).as_str())
-        .commit()
-        .map_err(|e| ReleaseError::CommitFailed(e.to_string()))?;
 
-    if let Some(verify_command) = verify_command {
-        match std::process::Command::new("sh")
-            .arg("-c")
-            .arg(&verify_command)
-            .current_dir(ws.local_tree().abspath(Path::new(".")).unwrap())
-            .status()
-        {
-            Ok(s) => {
-                if !s.success() {
-                    VERIFY_COMMAND_FAILED.with_label_values(&[&name]).inc();
-                    if !ignore_verify_command {
-                        return Err(ReleaseError::VerifyCommandFailed {
-                            command: verify_command.clone(),
-                            status: Some(s),
+    let verify_command =
+        disperse::verify::determine_verify_command(&cfg, ws.local_tree()).map(|v| {
+            log::debug!("Using verify command {:?}: {}", v.command, v.reason);
+            v.command
+        });
+
+    // The date in the tag name is that of the first attempt.
+    let journaled_tag = journaled.iter().find_map(|step| match step {
+        Step::Tagged { tag } => Some(tag.clone()),
+        _ => None,
+    });
+    let tag_name = journaled_tag.clone().unwrap_or_else(|| {
+        disperse::version::expand_tag_with(
+            cfg.tag_name.as_ref().unwrap(),
+            &new_version,
+            &disperse::version::TagContext {
+                project: Some(&name),
+                date: Some(now.date_naive()),
+            },
+        )
+    });
+
+    log::info!("releasing {}", new_version.to_string());
+    let mut plan = None;
+    let committed = journaled.iter().find_map(|step| match step {
+        Step::Committed { revision, notes } => Some((revision, notes)),
+        _ => None,
+    });
+    let (release_changes, revid, pypi_paths) = if let Some((revision, notes)) = committed {
+        log::info!(
+            "Using release commit {} from the previous attempt",
+            revision
+        );
+        (
+            notes.clone(),
+            breezyshim::RevisionId::from(revision.as_bytes()),
+            None,
+        )
+    } else {
+        let ReleaseFiles {
+            release_changes,
+            modified_files,
+            pypi_paths,
+        } = update_release_files(ws.local_tree(), &cfg, &name, &new_version, now)?;
+        record_step(
+            &journal,
+            Step::FilesUpdated {
+                files: modified_files.clone(),
+            },
+        );
+
+        let unexpected_changes = changed_files(ws.local_tree())?
+            .into_iter()
+            .filter(|p| !modified_files.contains(p))
+            .collect::<Vec<_>>();
+        if !unexpected_changes.is_empty() {
+            return Err(ReleaseError::UnexpectedChanges(unexpected_changes));
+        }
+
+        let interactive = options.interactive && !dry_run;
+        if interactive || dry_run {
+            let targets = publish_targets(ws.local_tree(), &cfg);
+            plan = Some(ReleasePlan {
+                project: name.clone(),
+                version: new_version.to_string(),
+                tag: tag_name.clone(),
+                steps: release_steps(
+                    &cfg,
+                    &new_version,
+                    &tag_name,
+                    verify_command.as_deref(),
+                    &targets,
+                ),
+                files: modified_files.clone(),
+                diff: Some(uncommitted_diff(ws.local_tree())?),
+                artifacts: vec![],
+                targets,
+            });
+        }
+        if interactive {
+            let confirmed = disperse::checklist::confirm_terminal(&format!(
+                "{}Proceed?",
+                plan.as_ref().unwrap()
+            ))
+            .map_err(|e| ReleaseError::Other(e.to_string()))?;
+            if confirmed == Some(false) {
+                return Err(ReleaseError::Declined);
+            }
+        }
+
+        let revid = ws
+            .local_tree()
+            .build_commit()
+            .message(format!("Release {}.", new_version.to_string()).as_str())
+            .specific_files(
+                modified_files
+                    .iter()
+                    .map(|p| p.as_path())
+                    .collect::<Vec<_>>()
+                    .as_slice(),
+            )
+            .commit()
+            .map_err(|e| ReleaseError::CommitFailed(e.to_string()))?;
+
+        if ws
+            .local_tree()
+            .has_filename(&cfg.project_dir().join("Cargo.toml"))
+            && !dry_run
+        {
+            log::info!("Verifying that the crate builds from the packaged sources");
+            if cfg.path.is_some() {
+                disperse::cargo::verify_crate_package(ws.local_tree(), cfg.project_dir())
+            } else {
+                disperse::cargo::verify_package(ws.local_tree())
+            }
+            .map_err(|e| ReleaseError::PackageVerificationFailed(e.to_string()))?;
+        }
+
+        if let Some(verify_command) = verify_command {
+            match disperse::logging::status(
+                std::process::Command::new("sh")
+                    .arg("-c")
+                    .arg(&verify_command)
+                    .current_dir(ws.local_tree().abspath(cfg.project_dir()).unwrap()),
+            ) {
+                Ok(s) => {
+                    if !s.success() {
+                        VERIFY_COMMAND_FAILED.with_label_values(&[&name]).inc();
+                        if !ignore_verify_command {
+                            return Err(ReleaseError::VerifyCommandFailed {
+                                command: verify_command.clone(),
+                                status: Some(s),
+                            });
+                        }
+                    }
+                }
+                Err(_e) => {
+                    VERIFY_COMMAND_FAILED.with_label_values(&[&name]).inc();
+                    if !ignore_verify_command {
+                        return Err(ReleaseError::VerifyCommandFailed {
+                            command: verify_command.clone(),
+                            status: None,
                         });
                     }
                 }
             }
-            Err(_e) => {
-                VERIFY_COMMAND_FAILED.with_label_values(&[&name]).inc();
-                if !ignore_verify_command {
-                    return Err(ReleaseError::VerifyCommandFailed {
-                        command: verify_command.clone(),
-                        status: None,
-                    });
-                }
+        }
+
+        record_step(
+            &journal,
+            Step::Committed {
+                revision: revid.to_string(),
+                notes: release_changes.clone(),
+            },
+        );
+        if !dry_run {
+            keep_workspace(&mut ws, &workspace_key, preserve_temp)?;
+        }
+        (release_changes, revid, pypi_paths)
+    };
+    let news_file = match cfg.news_file.as_ref() {
+        Some(news_file_path) => Some(open_news_file(ws.local_tree(), &cfg, news_file_path)?),
+        None => None,
+    };
+    let lockfile_update = cfg.cargo_lockfile.unwrap_or_default();
+
+    // Release notes for the forges; unlike the news file, these thank contributors.
+    let release_notes = if cfg.thanks == Some(true) {
+        match disperse::credits::contributors_since_last_tag(ws.local_tree().branch().as_ref()) {
+            Ok(names) => match disperse::credits::thanks_paragraph(&names) {
+                Some(thanks) => Some(match release_changes.as_deref() {
+                    Some(changes) => format!("{}\n\n{}", changes.trim_end(), thanks),
+                    None => thanks,
+                }),
+                None => release_changes.clone(),
+            },
+            Err(e) => {
+                log::warn!("Unable to determine contributors: {}", e);
+                release_changes.clone()
             }
         }
-    }
+    } else {
+        release_changes.clone()
+    };
 
-    let tag_name = disperse::version::expand_tag(cfg.tag_name.as_ref().unwrap(), &new_version);
     let tags = ws.local_tree().branch().tags().unwrap();
-    if tags.has_tag(tag_name.as_str()) {
-        RELEASE_TAG_EXISTS.with_label_values(&[&name]).inc();
-        // Maybe there's a pending pull request merging new_version?
-        // TODO(jelmer): Do some more verification. Expect: release tag
-        // has one additional revision that's not on our branch.
-        return Err(ReleaseError::ReleaseTagExists {
-            project: name,
-            version: new_version,
-            tag: tag_name,
-        });
-    }
-    log::info!("Creating tag {}", tag_name);
-    if is_git_repo(&ws.local_tree().branch().repository()) {
-        match std::process::Command::new("git")
-            .arg("tag")
-            .arg("-as")
-            .arg(&tag_name)
-            .arg("-m")
-            .arg(format!("Release {}", new_version.to_string()))
-            .current_dir(ws.local_tree().abspath(Path::new(".")).unwrap())
-            .status()
-        {
-            Ok(s) => {
-                if !s.success() {
+    if journaled_tag.is_some() && tags.has_tag(tag_name.as_str()) {
+        log::info!("Using tag {} from the previous attempt", tag_name);
+    } else {
+        if tags.has_tag(tag_name.as_str()) {
+            RELEASE_TAG_EXISTS.with_label_values(&[&name]).inc();
+            // Maybe there's a pending pull request merging new_version?
+            // TODO(jelmer): Do some more verification. Expect: release tag
+            // has one additional revision that's not on our branch.
+            return Err(ReleaseError::ReleaseTagExists {
+                project: name,
+                version: Box::new(new_version),
+                tag: tag_name,
+            });
+        }
+        for note in disperse::plugins::run_hook(
+            &mut plugins,
+            &plugin_context(disperse::plugins::Hook::PreTag, Some(&tag_name)),
+        )? {
+            log::info!("{}", note);
+        }
+        log::info!("Creating tag {}", tag_name);
+        if is_git_repo(&ws.local_tree().branch().repository()) {
+            let mut cmd = std::process::Command::new("git");
+            cmd.arg("tag").arg("-as");
+            if let Some(signing_key) = options.signing_key(&cfg) {
+                cmd.arg("-u").arg(signing_key);
+            }
+            match cmd
+                .arg(&tag_name)
+                .arg("-m")
+                .arg(disperse::tag::tag_message(
+                    &new_version.to_string(),
+                    &disperse::tag::TagMetadata::new(
+                        &cfg,
+                        ws.local_tree().last_revision().unwrap().as_str(),
+                    ),
+                ))
+                .current_dir(ws.local_tree().abspath(Path::new(".")).unwrap())
+                .status()
+            {
+                Ok(s) => {
+                    if !s.success() {
+                        return Err(ReleaseError::CreateTagFailed {
+                            tag_name: tag_name.clone(),
+                            status: Some(s),
+                            reason: Some("git tag failed".to_string()),
+                        });
+                    }
+                }
+                Err(e) => {
                     return Err(ReleaseError::CreateTagFailed {
                         tag_name: tag_name.clone(),
-                        status: Some(s),
-                        reason: Some("git tag failed".to_string()),
+                        status: None,
+                        reason: Some(e.to_string()),
                     });
                 }
             }
-            Err(e) => {
-                return Err(ReleaseError::CreateTagFailed {
+        } else {
+            tags.set_tag(tag_name.as_str(), &revid)
+                .map_err(|e| ReleaseError::CreateTagFailed {
                     tag_name: tag_name.clone(),
                     status: None,
                     reason: Some(e.to_string()),
-                });
+                })?;
+        }
+        record_step(
+            &journal,
+            Step::Tagged {
+                tag: tag_name.clone(),
+            },
+        );
+    }
+
+    if lockfile_update == LockfileUpdate::Separate
+        && ws.local_tree().has_filename(Path::new("Cargo.toml"))
+        && disperse::cargo::update_lockfile(ws.local_tree())
+            .map_err(|e| ReleaseError::Other(e.to_string()))?
+    {
+        match ws
+            .local_tree()
+            .build_commit()
+            .message("Update Cargo.lock.")
+            .specific_files(&[Path::new("Cargo.lock")])
+            .commit()
+        {
+            Ok(_) => {}
+            Err(BrzError::PointlessCommit) => {
+                log::debug!("Cargo.lock is already up to date");
             }
+            Err(e) => return Err(ReleaseError::CommitFailed(e.to_string())),
         }
-    } else {
-        tags.set_tag(tag_name.as_str(), &ws.local_tree().last_revision().unwrap())
-            .map_err(|e| ReleaseError::CreateTagFailed {
-                tag_name: tag_name.clone(),
-                status: None,
-                reason: Some(e.to_string()),
-            })?;
     }
 
-    log::info!("Creating Python artifacts");
-    let pypi_paths = if ws.local_tree().has_filename(Path::new("setup.py")) {
-        disperse::python::create_setup_py_artifacts(ws.local_tree()).unwrap()
-    } else if ws.local_tree().has_filename(Path::new("pyproject.toml")) {
-        disperse::python::create_python_artifacts(ws.local_tree()).unwrap()
+    let pypi_paths = if let Some(pypi_paths) = pypi_paths {
+        pypi_paths
     } else {
-        vec![]
+        create_python_artifacts(ws.local_tree(), &cfg)?
     };
+    record_step(
+        &journal,
+        Step::ArtifactsBuilt {
+            artifacts: pypi_paths.clone(),
+        },
+    );
 
     if !dry_run {
         log::info!(
@@ -1317,23 +3820,28 @@ pub async fn release_project(
         })?;
     }
 
-    let result = publish_artifacts(
-        &ws,
-        &tag_name,
-        dry_run,
-        &gh,
-        &cfg,
-        pypi_paths
-            .iter()
-            .map(|p| p.as_path())
-            .collect::<Vec<_>>()
-            .as_slice(),
-        gh_repo.as_ref(),
-    )
-    .await;
+    let result = if journaled.contains(&Step::ArtifactsPublished) {
+        log::info!("Artifacts were published by the previous attempt");
+        Ok((vec![], vec![]))
+    } else {
+        publish_artifacts(
+            &ws,
+            &tag_name,
+            dry_run,
+            &gh,
+            &cfg,
+            pypi_paths
+                .iter()
+                .map(|p| p.as_path())
+                .collect::<Vec<_>>()
+                .as_slice(),
+            gh_repo.as_ref(),
+        )
+        .await
+    };
 
-    let artifacts = match result {
-        Ok(artifacts) => artifacts,
+    let (artifacts, mut links) = match result {
+        Ok(result) => result,
         Err(e) => {
             log::error!("Failed to publish artifacts: {}", e);
             log::info!("Deleting remote tag {}", tag_name);
@@ -1344,9 +3852,41 @@ pub async fn release_project(
             return Err(ReleaseError::PublishArtifactsFailed(e.to_string()));
         }
     };
+    if !dry_run {
+        record_step(&journal, Step::ArtifactsPublished);
+    }
+    if let Some(plan) = plan.as_mut() {
+        plan.artifacts = artifacts.clone();
+    }
+
+    let artifact_digests = if cfg.release_manifest.unwrap_or(false) {
+        match artifacts
+            .iter()
+            .map(|p| disperse::manifest::ArtifactDigest::from_path(p))
+            .collect::<Result<Vec<_>, _>>()
+        {
+            Ok(digests) => Some(digests),
+            Err(e) => {
+                log::warn!("Unable to compute artifact digests: {}", e);
+                None
+            }
+        }
+    } else {
+        None
+    };
+    let release_revision = revid.to_string();
+
+    // The artifacts are out, so a veto can no longer stop the release.
+    match disperse::plugins::run_hook(
+        &mut plugins,
+        &plugin_context(disperse::plugins::Hook::PostPublish, Some(&tag_name)),
+    ) {
+        Ok(notes) => notes.iter().for_each(|note| log::info!("{}", note)),
+        Err(e) => log::warn!("{}", e),
+    }
 
     // At this point, it's official - so let's push.
-    if !dry_run {
+    if !dry_run && !journaled.contains(&Step::Pushed) {
         match ws.push(None) {
             Ok(_) => {}
             Err(silver_platter::workspace::Error::BrzError(
@@ -1398,21 +3938,41 @@ pub async fn release_project(
                 return Err(e.into());
             }
         }
+        record_step(&journal, Step::Pushed);
     }
 
     if let Some(gh_repo) = gh_repo.as_ref() {
         if dry_run {
             log::info!("skipping creation of github release due to dry run mode");
         } else {
-            disperse::github::create_github_release(
+            let release_url = disperse::github::create_github_release(
                 &gh,
                 gh_repo,
                 tag_name.as_str(),
                 &new_version.to_string(),
-                release_changes.as_deref(),
+                release_notes
+                    .as_deref()
+                    .map(disperse::news_file::release_notes_markdown)
+                    .as_deref(),
             )
             .await
             .map_err(|e| ReleaseError::Other(e.to_string()))?;
+            links.insert(
+                0,
+                ReleaseLink {
+                    kind: "GitHub release",
+                    url: release_url.to_string(),
+                },
+            );
+        }
+        if let Some(html_url) = gh_repo.html_url.as_ref() {
+            links.insert(
+                0,
+                ReleaseLink {
+                    kind: "GitHub tag",
+                    url: format!("{}/tree/{}", html_url, tag_name),
+                },
+            );
         }
     }
 
@@ -1426,30 +3986,171 @@ pub async fn release_project(
                 &launchpad_project.self_().unwrap(),
                 &new_version.to_string(),
                 launchpad_series.as_ref().map(|s| s.name.as_str()),
-                release_changes.as_deref(),
+                release_notes.as_deref(),
             )
             .await
             .map_err(ReleaseError::Other)?;
             disperse::launchpad::add_release_files(lp, &lp_release, artifacts)
                 .await
                 .map_err(ReleaseError::Other)?;
+            if let Some(launchpad) = cfg.launchpad.as_ref() {
+                links.push(ReleaseLink {
+                    kind: "Launchpad",
+                    url: format!(
+                        "https://launchpad.net/{}/+milestone/{}",
+                        launchpad.project,
+                        new_version.to_string()
+                    ),
+                });
+            }
+        }
+    }
+
+    if let (Some(launchpad_project), Some(release_changes)) =
+        (launchpad_project.as_ref(), release_changes.as_deref())
+    {
+        let bugs = disperse::launchpad::find_bug_references(release_changes);
+        if bugs.is_empty() {
+            log::debug!("No Launchpad bugs referenced in release notes");
+        } else if dry_run {
+            log::info!("skipping marking of Launchpad bugs {:?} as released", bugs);
+        } else {
+            disperse::launchpad::mark_bugs_fix_released(
+                launchpad_client().await?,
+                launchpad_project,
+                &bugs,
+            )
+            .await
+            .map_err(ReleaseError::Other)?;
+        }
+    }
+
+    if let Some(aur) = cfg.pkgbuild.as_ref().and_then(|p| p.aur.as_ref()) {
+        let sha256 = find_sdist(&pypi_paths)
+            .map(|p| disperse::pkgbuild::sha256sum(p))
+            .transpose()
+            .map_err(|e| ReleaseError::Other(e.to_string()))?;
+        update_aur(
+            aur,
+            cfg.pkgbuild.as_ref().unwrap().path(),
+            &new_version,
+            sha256.as_deref(),
+            dry_run,
+        )?;
+    }
+
+    if let Some(docs) = cfg.docs.as_ref() {
+        match publish_docs(&ws, docs, gh_repo.as_ref(), &new_version, dry_run) {
+            Ok(Some(link)) => links.push(link),
+            Ok(None) => {}
+            // The release has already happened, so don't fail it.
+            Err(e) => log::warn!("Unable to publish documentation: {}", e),
+        }
+    }
+
+    if let Some(artifact_digests) = artifact_digests {
+        let manifest = disperse::manifest::ReleaseManifest {
+            project: name.clone(),
+            version: new_version.to_string(),
+            tag: tag_name.clone(),
+            revision: release_revision,
+            artifacts: artifact_digests,
+            destinations: links.iter().map(|l| l.url.clone()).collect(),
+        };
+        if dry_run {
+            log::info!("skipping release manifest due to dry run mode");
+        } else {
+            match publish_manifest(manifest, options.signing_key(&cfg), &gh, gh_repo.as_ref()).await
+            {
+                Ok(Some(url)) => links.push(ReleaseLink {
+                    kind: "Release manifest",
+                    url: url.to_string(),
+                }),
+                Ok(None) => {}
+                Err(e) => log::warn!("Unable to publish release manifest: {}", e),
+            }
         }
     }
 
-    // TODO(jelmer): Mark any news bugs in NEWS as fixed [later]
+    // When catching up sequentially, the next pending section is released
+    // next rather than starting on a new one.
+    let more_pending = match (news_file.as_ref(), cfg.catch_up) {
+        (Some(news_file), Some(CatchUp::Sequential)) => !news_file
+            .pending_backlog()
+            .map_err(|e| ReleaseError::Other(e.to_string()))?
+            .is_empty(),
+        _ => false,
+    };
+    let news_file = news_file.filter(|_| !more_pending);
+
     // * Commit:
     //  * Update NEWS and version strings for next version
-    let mut new_pending_version: Version = new_version.clone();
-    disperse::version::increase_version(&mut new_pending_version, -1);
-    assert!(new_pending_version > new_version);
-    log::info!("Using new version {}", new_pending_version.to_string());
-    if let Some(news_file) = news_file {
-        news_file
-            .add_pending(&new_pending_version)
-            .map_err(|e| ReleaseError::Other(e.to_string()))?;
+    let new_pending_version = next_version.filter(|_| !more_pending);
+    if let Some(new_pending_version) = new_pending_version.as_ref() {
+        log::info!("Using new version {}", new_pending_version.to_string());
+    }
+    let mut dev_files = vec![];
+    if let Some(new_pending_version) = new_pending_version.as_ref() {
+        for update_version in cfg
+            .update_version
+            .iter()
+            .flatten()
+            .filter(|u| disperse::custom::has_status(&u.new_line))
+        {
+            disperse::custom::update_version_in_file(
+                ws.local_tree(),
+                &update_version.path,
+                &update_version.new_line,
+                update_version.r#match.as_deref(),
+                new_pending_version,
+                disperse::Status::Dev,
+                &disperse::custom::VersionContext {
+                    custom: cfg.version_variables.as_ref(),
+                    date: Some(now.date_naive()),
+                },
+            )
+            .map_err(ReleaseError::Other)?;
+            dev_files.push(update_version.path.clone());
+        }
+        if cfg.path.is_none()
+            && (ws.local_tree().has_filename(Path::new("pyproject.toml"))
+                || ws.local_tree().has_filename(Path::new("setup.py")))
+            && cfg.update_python_version.unwrap_or(true)
+        {
+            let explicit = cfg
+                .update_version
+                .iter()
+                .flatten()
+                .map(|u| u.path.as_path())
+                .collect::<Vec<_>>();
+            dev_files.extend(
+                disperse::python::update_python_version_files(
+                    ws.local_tree(),
+                    new_pending_version,
+                    disperse::Status::Dev,
+                    &explicit,
+                )
+                .map_err(|e| ReleaseError::Other(e.to_string()))?,
+            );
+        }
+    }
+    if let Some(news_file) = news_file.as_ref() {
+        match new_pending_version.as_ref() {
+            Some(v) => news_file.add_pending(v),
+            None => news_file.add_unreleased(),
+        }
+        .map_err(|e| ReleaseError::Other(e.to_string()))?;
+    }
+    if news_file.is_some() || !dev_files.is_empty() {
         ws.local_tree()
             .build_commit()
-            .message(format!("Start on {}", new_pending_version.to_string()).as_str())
+            .message(
+                match new_pending_version.as_ref() {
+                    Some(v) => format!("Start on {}", v.to_string()),
+                    None => "Start on next release".to_string(),
+                }
+                .as_str(),
+            )
             .commit()
             .map_err(|e| ReleaseError::Other(e.to_string()))?;
         if !dry_run {
@@ -1457,7 +4158,9 @@ pub async fn release_project(
                 .map_err(|e| ReleaseError::Other(e.to_string()))?;
         }
     }
-    if let Some(launchpad_project) = launchpad_project.as_ref() {
+    if let (Some(launchpad_project), Some(new_pending_version)) =
+        (launchpad_project.as_ref(), new_pending_version.as_ref())
+    {
         if dry_run {
             log::info!(
                 "Skipping creation of new mileston {} on Launchpad",
@@ -1486,42 +4189,141 @@ pub async fn release_project(
         }
     }
 
+    // The release has happened at this point, so failures are only reported.
+    let mut smoke_test_failures = vec![];
+    if let (Some(smoke_test), false) = (cfg.smoke_test.as_ref(), dry_run) {
+        for (registry, package) in links
+            .iter()
+            .filter_map(|l| Registry::parse_package_url(&l.url))
+        {
+            log::info!("Running smoke test for {} {}", registry, package);
+            if let Err(e) = disperse::smoke_test::smoke_test(
+                registry,
+                &package,
+                &new_version,
+                smoke_test.command.as_deref(),
+            ) {
+                log::warn!("Smoke test for {} {} failed: {}", registry, package, e);
+                smoke_test_failures.push(format!("{} {}: {}", registry, package, e));
+            }
+        }
+    }
+
+    // The release is done, so there is nothing left to resume.
+    if !dry_run {
+        if let Err(e) = journal.remove() {
+            log::warn!("Unable to remove {}: {}", journal.path().display(), e);
+        }
+        match workspace_index()?.remove(&workspace_key) {
+            Ok(Some(path)) if !preserve_temp => {
+                if let Err(e) = std::fs::remove_dir_all(&path) {
+                    log::warn!("Unable to remove {}: {}", path.display(), e);
+                }
+            }
+            Ok(_) => {}
+            Err(e) => log::warn!("Unable to update workspace index: {}", e),
+        }
+    }
+
     RELEASED_COUNT.with_label_values(&[&name]).inc();
-    Ok((name, new_version))
+    Ok(ReleaseOutcome {
+        name,
+        version: new_version,
+        tag: tag_name,
+        notes: release_notes,
+        links,
+        smoke_test_failures,
+        checklist,
+        more_pending,
+        plan: plan.filter(|_| dry_run),
+    })
+}
+
+/// Format a table of what was released, and where.
+fn format_release_summary(outcomes: &[ReleaseOutcome]) -> String {
+    let rows = outcomes
+        .iter()
+        .flat_map(|o| {
+            let project = format!("{} {}", o.name, o.version.to_string());
+            std::iter::once((project, "tag", o.tag.clone()))
+                .chain(
+                    o.links
+                        .iter()
+                        .map(|l| (String::new(), l.kind, l.url.clone())),
+                )
+                .chain(
+                    o.smoke_test_failures
+                        .iter()
+                        .map(|f| (String::new(), "smoke test", format!("FAILED: {}", f))),
+                )
+        })
+        .collect::<Vec<_>>();
+    let project_width = rows.iter().map(|r| r.0.len()).max().unwrap_or(0);
+    let kind_width = rows.iter().map(|r| r.1.len()).max().unwrap_or(0);
+    rows.iter()
+        .map(|(project, kind, location)| {
+            format!(
+                "{:project_width$}  {:kind_width$}  {}",
+                project, kind, location
+            )
+            .trim_end()
+            .to_string()
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
 }
 
 async fn release_many(
     urls: &[String],
-    new_version: Option<String>,
-    ignore_ci: Option<bool>,
-    ignore_verify_command: Option<bool>,
-    dry_run: Option<bool>,
+    options: &Options,
     discover: bool,
-    force: Option<bool>,
-    preserve_temp: bool,
+    limit: Option<usize>,
 ) -> i32 {
     let mut failed: Vec<(String, String)> = Vec::new();
     let mut skipped: Vec<(String, String)> = Vec::new();
-    let mut success: Vec<String> = Vec::new();
+    let mut success: Vec<ReleaseOutcome> = Vec::new();
     let mut ret = 0;
-    for url in urls {
+    if options.dry_run {
+        // Makes it easier to tell why a release behaves differently elsewhere.
+        log::info!("Environment:\n{}", disperse::environment::fingerprint());
+    }
+    let mut queue = urls
+        .iter()
+        .flat_map(|url| match options.projects.as_slice() {
+            [] => vec![(url.clone(), None)],
+            projects => projects
+                .iter()
+                .map(|p| (url.clone(), Some(p.clone())))
+                .collect(),
+        })
+        .collect::<std::collections::VecDeque<_>>();
+    while let Some((repo_url, project)) = queue.pop_front() {
+        if let Some(limit) = limit.filter(|l| success.len() >= *l) {
+            log::info!("Released {} projects; not processing any more", limit);
+            break;
+        }
+        let label = match project.as_ref() {
+            Some(project) => format!("{} ({})", repo_url, project),
+            None => repo_url.clone(),
+        };
+        let url = &label;
         if url != "." {
             log::info!("Processing {}", url);
         }
-        match release_project(
-            url,
-            force,
-            new_version
-                .as_ref()
-                .map(|v| v.as_str().parse().unwrap())
-                .as_ref(),
-            dry_run,
-            ignore_ci,
-            ignore_verify_command,
-            preserve_temp,
-        )
-        .await
-        {
+        let result = release_project(&repo_url, project.as_deref(), options).await;
+        if discover && !options.dry_run {
+            track_failures(&repo_url, result.as_ref(), options).await;
+        }
+        let code = match result.as_ref() {
+            Ok(_) => 0,
+            Err(e) => e.exit_code(),
+        };
+        match result {
+            Err(ReleaseError::SeveralProjects(names)) => {
+                for name in names.into_iter().rev() {
+                    queue.push_front((repo_url.clone(), Some(name)));
+                }
+            }
             Err(ReleaseError::RecentCommits {
                 min_commit_age,
                 commit_age,
@@ -1532,7 +4334,7 @@ async fn release_many(
                     format!("Recent commits exist ({} < {})", min_commit_age, commit_age),
                 ));
                 if !discover {
-                    ret = 1;
+                    ret = worse_exit_code(ret, code);
                 }
             }
             Err(ReleaseError::VerifyCommandFailed { command, .. }) => {
@@ -1541,7 +4343,7 @@ async fn release_many(
                     url.to_string(),
                     format!("Verify command ({}) failed to run.", command),
                 ));
-                ret = 1;
+                ret = worse_exit_code(ret, code);
             }
             Err(ReleaseError::PreDistCommandFailed { command, .. }) => {
                 log::error!("Pre-Dist command ({}) failed to run.", command);
@@ -1549,7 +4351,7 @@ async fn release_many(
                     url.to_string(),
                     format!("Pre-Dist command ({}) failed to run.", command),
                 ));
-                ret = 1;
+                ret = worse_exit_code(ret, code);
             }
             Err(ReleaseError::UploadCommandFailed { command, .. }) => {
                 log::error!("Upload command ({}) failed to run.", command);
@@ -1557,7 +4359,7 @@ async fn release_many(
                     url.to_string(),
                     format!("Upload command ({}) failed to run.", command),
                 ));
-                ret = 1;
+                ret = worse_exit_code(ret, code);
             }
             Err(ReleaseError::ReleaseTagExists {
                 project,
@@ -1579,26 +4381,26 @@ async fn release_many(
                     ),
                 ));
                 if !discover {
-                    ret = 1;
+                    ret = worse_exit_code(ret, code);
                 }
             }
             Err(ReleaseError::DistCreationFailed) => {
                 log::error!("Dist creation failed to run.");
                 failed.push((url.to_string(), "Dist creation failed to run.".to_string()));
-                ret = 1;
+                ret = worse_exit_code(ret, code);
             }
             Err(ReleaseError::NoUnreleasedChanges) => {
                 log::error!("No unreleased changes");
                 skipped.push((url.to_string(), "No unreleased changes".to_string()));
                 if !discover {
-                    ret = 1;
+                    ret = worse_exit_code(ret, code);
                 }
             }
             Err(ReleaseError::NoDisperseConfig) => {
                 log::error!("No configuration for disperse");
                 skipped.push((url.to_string(), "No configuration for disperse".to_string()));
                 if !discover {
-                    ret = 1;
+                    ret = worse_exit_code(ret, code);
                 }
             }
             Err(ReleaseError::CIPending(n)) => {
@@ -1607,12 +4409,12 @@ async fn release_many(
                     url.to_string(),
                     format!("CI checks not finished yet: {}", n),
                 ));
-                ret = 1;
+                ret = worse_exit_code(ret, code);
             }
             Err(ReleaseError::CIFailed(n)) => {
                 log::error!("GitHub check failed: {}", n);
                 failed.push((url.to_string(), format!("GitHub check failed: {}", n)));
-                ret = 1;
+                ret = worse_exit_code(ret, code);
             }
             Err(ReleaseError::RepositoryUnavailable { url, reason }) => {
                 log::error!("Repository is unavailable: {}: {}", url, reason);
@@ -1620,37 +4422,42 @@ async fn release_many(
                     url.to_string(),
                     format!("Repository is unavailable: {}: {}", url, reason),
                 ));
-                ret = 1;
+                ret = worse_exit_code(ret, code);
             }
             Err(ReleaseError::OddPendingVersion { version }) => {
                 log::error!("Odd pending version: {}", version);
                 failed.push((url.to_string(), format!("Odd pending version: {}", version)));
-                ret = 1;
+                ret = worse_exit_code(ret, code);
+            }
+            Err(e @ ReleaseError::NextVersionNotNewer { .. }) => {
+                log::error!("{}", e);
+                failed.push((url.to_string(), e.to_string()));
+                ret = worse_exit_code(ret, code);
             }
             Err(ReleaseError::NoVersion) => {
                 log::error!("No version");
                 failed.push((url.to_string(), "No version".to_string()));
-                ret = 1;
+                ret = worse_exit_code(ret, code);
             }
             Err(ReleaseError::NoSuchTag) => {
                 log::error!("No such tag");
                 failed.push((url.to_string(), "No such tag".to_string()));
-                ret = 1;
+                ret = worse_exit_code(ret, code);
             }
             Err(ReleaseError::CreateTagFailed { .. }) => {
                 log::error!("Failed to create tag");
                 failed.push((url.to_string(), "Failed to create tag".to_string()));
-                ret = 1;
+                ret = worse_exit_code(ret, code);
             }
             Err(ReleaseError::Other(o)) => {
                 log::error!("Other error: {:?}", o);
                 failed.push((url.to_string(), format!("Other error: {}", o)));
-                ret = 1;
+                ret = worse_exit_code(ret, code);
             }
             Err(ReleaseError::CommitFailed(..)) => {
                 log::error!("Failed to commit");
                 failed.push((url.to_string(), "Failed to commit".to_string()));
-                ret = 1;
+                ret = worse_exit_code(ret, code);
             }
             Err(ReleaseError::PublishArtifactsFailed(o)) => {
                 log::error!("Failed to publish artifacts: {}", o);
@@ -1658,25 +4465,75 @@ async fn release_many(
                     url.to_string(),
                     format!("Failed to publish artifacts: {}", o),
                 ));
-                ret = 1;
+                ret = worse_exit_code(ret, code);
             }
             Err(ReleaseError::NoPublicBranch) => {
                 log::error!("No public branch");
                 failed.push((url.to_string(), "No public branch".to_string()));
-                ret = 1;
+                ret = worse_exit_code(ret, code);
             }
-            Ok((name, version)) => {
-                log::info!("Released {} version {}", name, version.to_string());
-                success.push(url.to_string());
+            Err(e @ ReleaseError::PackageVerificationFailed(..)) => {
+                log::error!("{}", e);
+                failed.push((url.to_string(), e.to_string()));
+                ret = worse_exit_code(ret, code);
             }
-        }
-    }
-
-    if discover {
-        log::info!(
-            "{} successfully released, {} skipped, {} failed",
-            success.len(),
-            skipped.len(),
+            Err(ReleaseError::OnHold(hold)) => {
+                log::info!("Project is {}", hold);
+                skipped.push((url.to_string(), format!("Project is {}", hold)));
+                if !discover {
+                    ret = worse_exit_code(ret, code);
+                }
+            }
+            Err(e @ (ReleaseError::Vetoed { .. } | ReleaseError::Declined)) => {
+                log::info!("{}", e);
+                skipped.push((url.to_string(), e.to_string()));
+                if !discover {
+                    ret = worse_exit_code(ret, code);
+                }
+            }
+            Err(e @ ReleaseError::UnexpectedChanges(..)) => {
+                log::error!("{}", e);
+                failed.push((url.to_string(), e.to_string()));
+                ret = worse_exit_code(ret, code);
+            }
+            Ok(outcome) => {
+                log::info!(
+                    "Released {} version {}",
+                    outcome.name,
+                    outcome.version.to_string()
+                );
+                if !options.dry_run {
+                    record_release(&outcome, options);
+                    // Release the next pending news section.
+                    if outcome.more_pending && options.new_version.is_none() {
+                        queue.push_front((repo_url, project));
+                    }
+                }
+                success.push(outcome);
+            }
+        }
+    }
+
+    let plans = success
+        .iter()
+        .filter_map(|o| o.plan.as_ref())
+        .collect::<Vec<_>>();
+    if !plans.is_empty() {
+        match options.plan_format {
+            OutputFormat::Text => plans.iter().for_each(|plan| print!("{}", plan)),
+            OutputFormat::Json => {
+                println!("{}", serde_json::to_string_pretty(&plans).unwrap())
+            }
+        }
+    } else if !success.is_empty() {
+        println!("{}", format_release_summary(&success));
+    }
+
+    if discover {
+        log::info!(
+            "{} successfully released, {} skipped, {} failed",
+            success.len(),
+            skipped.len(),
             failed.len()
         );
     }
@@ -1684,129 +4541,1150 @@ async fn release_many(
     ret
 }
 
-fn validate_config(path: &std::path::Path) -> i32 {
-    let wt = match workingtree::open(path) {
-        Ok(x) => x,
+/// Print the changes listed in the pending section of the news file.
+fn changelog(wt: &WorkingTree, markdown: bool, options: &Options) -> i32 {
+    let cfg = match read_project_config(wt, options) {
+        Ok(cfg) => cfg,
+        Err(e) => {
+            log::error!("Unable to read config: {}", e);
+            return 1;
+        }
+    };
+    let Some(news_file_path) = cfg.news_file.as_ref() else {
+        log::error!("No news-file configured");
+        return 1;
+    };
+    let layout = match disperse::news_file::NewsLayout::from_config(&cfg) {
+        Ok(layout) => layout,
         Err(e) => {
-            log::error!("Unable to open working tree: {}", e);
+            log::error!("Invalid news layout: {}", e);
             return 1;
         }
     };
+    let changes = match disperse::news_file::NewsFile::new(wt, news_file_path) {
+        Ok(news_file) => news_file.with_layout(layout).pending_changes(),
+        Err(e) => Err(e),
+    };
+    match changes {
+        Ok(Some(changes)) => {
+            if markdown {
+                print!("{}", disperse::news_file::release_notes_markdown(&changes));
+            } else {
+                println!("{}", changes.trim_matches('\n'));
+            }
+            0
+        }
+        Ok(None) => {
+            log::error!("No pending section in {}", news_file_path.display());
+            1
+        }
+        Err(e) => {
+            log::error!("Unable to read {}: {}", news_file_path.display(), e);
+            1
+        }
+    }
+}
 
-    let cfg = match read_project_with_fallback(&wt) {
-        Ok(x) => x,
+/// A commit that has not been released yet.
+#[derive(Debug, serde::Serialize)]
+struct UnreleasedCommit {
+    revision: String,
+    date: chrono::DateTime<chrono::FixedOffset>,
+    author: String,
+    summary: String,
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+    kind: Option<String>,
+}
+
+impl From<&breezyshim::repository::Revision> for UnreleasedCommit {
+    fn from(rev: &breezyshim::repository::Revision) -> Self {
+        Self {
+            revision: rev.revision_id.to_string(),
+            date: rev.datetime(),
+            author: rev
+                .committer
+                .split(" <")
+                .next()
+                .unwrap_or_default()
+                .to_string(),
+            summary: rev.message.lines().next().unwrap_or_default().to_string(),
+            kind: disperse::conventional_commits::commit_type(&rev.message),
+        }
+    }
+}
+
+impl UnreleasedCommit {
+    /// Abbreviated revision id; git commits are shown by their short hash.
+    fn short_revision(&self) -> &str {
+        match self.revision.strip_prefix("git-v1:") {
+            Some(sha) => &sha[..sha.len().min(12)],
+            None => &self.revision,
+        }
+    }
+
+    fn group(&self, by: GroupBy) -> &str {
+        match by {
+            GroupBy::Author => &self.author,
+            GroupBy::Type => self.kind.as_deref().unwrap_or("other"),
+        }
+    }
+}
+
+/// Group commits, keeping the groups in the order they first appear in.
+fn group_commits(commits: &[UnreleasedCommit], by: GroupBy) -> Vec<(&str, Vec<&UnreleasedCommit>)> {
+    let mut groups: Vec<(&str, Vec<&UnreleasedCommit>)> = vec![];
+    for commit in commits {
+        let group = commit.group(by);
+        match groups.iter_mut().find(|(g, _)| *g == group) {
+            Some((_, members)) => members.push(commit),
+            None => groups.push((group, vec![commit])),
+        }
+    }
+    groups
+}
+
+/// Format unreleased commits with a line per commit.
+fn format_unreleased(commits: &[UnreleasedCommit], group_by: Option<GroupBy>) -> String {
+    let line = |c: &UnreleasedCommit| {
+        format!(
+            "{} {} {}",
+            c.short_revision(),
+            c.date.format("%Y-%m-%d"),
+            c.summary
+        )
+    };
+    match group_by {
+        None => commits.iter().map(|c| format!("{}\n", line(c))).collect(),
+        Some(by) => group_commits(commits, by)
+            .into_iter()
+            .map(|(group, members)| {
+                let mut ret = format!("{} ({}):\n", group, members.len());
+                for c in members {
+                    ret.push_str(&format!("  {}\n", line(c)));
+                }
+                ret
+            })
+            .collect::<Vec<_>>()
+            .join("\n"),
+    }
+}
+
+/// List the commits since the last release.
+fn unreleased(wt: &WorkingTree, group_by: Option<GroupBy>, format: OutputFormat) -> i32 {
+    let revisions = match disperse::revisions_since_last_tag(wt.branch().as_ref()) {
+        Ok(revisions) => revisions,
+        Err(e) => {
+            log::error!("Unable to find revisions since the last release: {}", e);
+            return 1;
+        }
+    };
+    let commits = revisions
+        .iter()
+        .map(UnreleasedCommit::from)
+        .collect::<Vec<_>>();
+    match (format, group_by) {
+        (OutputFormat::Text, _) => {
+            if commits.is_empty() {
+                log::info!("No commits since the last release");
+            } else {
+                print!("{}", format_unreleased(&commits, group_by));
+            }
+        }
+        (OutputFormat::Json, None) => {
+            println!("{}", serde_json::to_string_pretty(&commits).unwrap())
+        }
+        (OutputFormat::Json, Some(by)) => {
+            let groups = group_commits(&commits, by)
+                .into_iter()
+                .map(|(group, members)| serde_json::json!({"group": group, "commits": members}))
+                .collect::<Vec<_>>();
+            println!("{}", serde_json::to_string_pretty(&groups).unwrap())
+        }
+    }
+    0
+}
+
+fn validate_config(path: &std::path::Path, format: OutputFormat, options: &Options) -> i32 {
+    let findings = match workingtree::open(path) {
+        Ok(wt) => {
+            let mut findings = match wt.get_file_text(options.project_config_path()) {
+                Ok(text) => disperse::validate::check_toml(&String::from_utf8_lossy(&text)),
+                // Older configuration formats are not checked strictly.
+                Err(_) => vec![],
+            };
+            if !findings.iter().any(|f| f.severity == Severity::Error) {
+                match read_project_config(&wt, options) {
+                    Ok(cfg) => findings.extend(disperse::validate::validate(&wt, &cfg)),
+                    Err(e) => {
+                        findings.push(Finding::error(format!("Unable to read config: {}", e)))
+                    }
+                }
+            }
+            findings
+        }
+        Err(e) => vec![Finding::error(format!(
+            "Unable to open working tree: {}",
+            e
+        ))],
+    };
+    report_findings(&findings, format)
+}
+
+/// Print findings, returning the exit code: 1 if any of them are errors.
+fn report_findings(findings: &[Finding], format: OutputFormat) -> i32 {
+    match format {
+        OutputFormat::Text => {
+            for finding in findings.iter() {
+                match finding.severity {
+                    Severity::Error => log::error!("{}", finding.message),
+                    Severity::Warning => log::warn!("{}", finding.message),
+                    Severity::Info => log::info!("{}", finding.message),
+                }
+            }
+        }
+        OutputFormat::Json => {
+            println!("{}", serde_json::to_string_pretty(&findings).unwrap());
+        }
+    }
+
+    if findings.iter().any(|f| f.severity == Severity::Error) {
+        1
+    } else {
+        0
+    }
+}
+
+/// Check that the environment is set up for releasing.
+async fn doctor(
+    path: &std::path::Path,
+    config_path: Option<&std::path::Path>,
+    format: OutputFormat,
+    options: &Options,
+) -> i32 {
+    let mut findings = vec![];
+
+    let config = match config_path {
+        Some(config_path) => disperse::config::load_config_from(config_path).map(Some),
+        None => disperse::config::load_config(),
+    };
+    if let Err(e) = config {
+        findings.push(Finding::error(format!(
+            "Unable to load global configuration: {}",
+            e
+        )));
+    }
+
+    match workingtree::open(path) {
+        Ok(wt) => {
+            if let Err(e) = read_project_config(&wt, options) {
+                findings.push(Finding::error(format!(
+                    "Unable to read project configuration: {}",
+                    e
+                )));
+            }
+        }
+        Err(e) => findings.push(Finding::info(format!(
+            "Not checking project configuration; unable to open working tree: {}",
+            e
+        ))),
+    }
+
+    findings.extend(disperse::doctor::check_tools());
+    findings.extend(disperse::doctor::check_breezy_plugins());
+    findings.extend(disperse::doctor::check_credentials().await);
+
+    report_findings(&findings, format)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+enum CheckStatus {
+    Pass,
+    Fail,
+    Skip,
+}
+
+impl std::fmt::Display for CheckStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            CheckStatus::Pass => write!(f, "PASS"),
+            CheckStatus::Fail => write!(f, "FAIL"),
+            CheckStatus::Skip => write!(f, "SKIP"),
+        }
+    }
+}
+
+/// Outcome of one of the gates that `disperse release` would check.
+#[derive(Debug, Clone, serde::Serialize)]
+struct ReadinessCheck {
+    name: &'static str,
+    status: CheckStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    detail: Option<String>,
+}
+
+impl ReadinessCheck {
+    fn new(name: &'static str, status: CheckStatus, detail: Option<String>) -> Self {
+        Self {
+            name,
+            status,
+            detail,
+        }
+    }
+
+    fn from_result<E: std::fmt::Display>(
+        name: &'static str,
+        result: Result<Option<String>, E>,
+    ) -> Self {
+        match result {
+            Ok(detail) => Self::new(name, CheckStatus::Pass, detail),
+            Err(e) => Self::new(name, CheckStatus::Fail, Some(e.to_string())),
+        }
+    }
+}
+
+/// Check the status of the CI for a project on GitHub, if it is hosted there.
+///
+/// With `wait`, wait for pending CI runs to finish, up to the CI timeout.
+async fn ci_status(
+    cfg: &ProjectConfig,
+    wait: bool,
+) -> Option<Result<disperse::github::GitHubCIStatus, String>> {
+    let github = cfg.github.as_ref()?;
+    let status = async {
+        let gh = disperse::github::login().map_err(|e| e.to_string())?;
+        let url = github
+            .url
+            .parse::<url::Url>()
+            .map_err(|e| format!("invalid GitHub URL {}: {}", github.url, e))?;
+        let repo = disperse::github::get_github_repo(&gh, &url)
+            .await
+            .map_err(|e| e.to_string())?;
+        if wait {
+            disperse::github::wait_for_gh_actions(
+                &gh,
+                &repo,
+                github.branch.as_deref(),
+                cfg.ci_timeout,
+                cfg.ci_poll_interval,
+            )
+            .await
+        } else {
+            disperse::github::check_gh_repo_action_status(&gh, &repo, github.branch.as_deref())
+                .await
+        }
+        .map_err(|e| e.to_string())
+    };
+    Some(status.await)
+}
+
+/// CI status of a project, as reported by `disperse check-ci`.
+#[derive(Debug, serde::Serialize)]
+struct CiReport {
+    status: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    revision: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    url: Option<String>,
+}
+
+impl From<&disperse::github::GitHubCIStatus> for CiReport {
+    fn from(status: &disperse::github::GitHubCIStatus) -> Self {
+        let (revision, url) = match status {
+            disperse::github::GitHubCIStatus::Ok => (None, None),
+            disperse::github::GitHubCIStatus::Failed { sha, html_url }
+            | disperse::github::GitHubCIStatus::Pending { sha, html_url } => {
+                (Some(sha.clone()), html_url.clone())
+            }
+        };
+        Self {
+            status: status.state(),
+            revision,
+            url,
+        }
+    }
+}
+
+/// Report the CI status of a project, without releasing anything.
+///
+/// Exits with EXIT_CI_FAILED if CI is failing or pending.
+async fn check_ci(wt: &WorkingTree, wait: bool, format: OutputFormat, options: &Options) -> i32 {
+    let mut cfg = match read_project_config(wt, options) {
+        Ok(cfg) => cfg,
         Err(e) => {
             log::error!("Unable to read config: {}", e);
             return 1;
         }
     };
+    options.apply_ci_overrides(&mut cfg);
+    let status = match ci_status(&cfg, wait).await {
+        Some(Ok(status)) => status,
+        Some(Err(e)) => {
+            log::error!("Unable to check CI status: {}", e);
+            return 1;
+        }
+        None => {
+            log::error!("No GitHub repository configured");
+            return 1;
+        }
+    };
+    match format {
+        OutputFormat::Text => println!("{}", status),
+        OutputFormat::Json => println!(
+            "{}",
+            serde_json::to_string_pretty(&CiReport::from(&status)).unwrap()
+        ),
+    }
+    if status.is_ok() {
+        0
+    } else {
+        EXIT_CI_FAILED
+    }
+}
+
+/// Run the checks that a release would run, without changing anything.
+async fn readiness_checks(wt: &WorkingTree, options: &Options) -> Vec<ReadinessCheck> {
+    let mut checks = vec![];
+
+    let mut cfg = match read_project_config(wt, options) {
+        Ok(cfg) => {
+            checks.push(ReadinessCheck::new(
+                "configuration",
+                CheckStatus::Pass,
+                None,
+            ));
+            cfg
+        }
+        Err(e) => {
+            checks.push(ReadinessCheck::new(
+                "configuration",
+                CheckStatus::Fail,
+                Some(e.to_string()),
+            ));
+            return checks;
+        }
+    };
+    options.apply_ci_overrides(&mut cfg);
+
+    checks.push(match cfg.hold(chrono::Utc::now().date_naive()) {
+        Some(hold) => ReadinessCheck::new("enabled", CheckStatus::Fail, Some(hold.to_string())),
+        None => ReadinessCheck::new("enabled", CheckStatus::Pass, None),
+    });
+
+    let errors = disperse::validate::validate(wt, &cfg)
+        .into_iter()
+        .filter(|f| f.severity == Severity::Error)
+        .map(|f| f.message)
+        .collect::<Vec<_>>();
+    checks.push(if errors.is_empty() {
+        ReadinessCheck::new("validate", CheckStatus::Pass, None)
+    } else {
+        ReadinessCheck::new("validate", CheckStatus::Fail, Some(errors.join("; ")))
+    });
+
+    let branch = wt.branch();
+    checks.push(
+        match disperse::check_new_revisions(
+            branch.as_ref(),
+            cfg.news_file.as_ref().map(Path::new),
+            project_scope(&cfg),
+        ) {
+            Ok(true) => ReadinessCheck::new("unreleased changes", CheckStatus::Pass, None),
+            Ok(false) => ReadinessCheck::new(
+                "unreleased changes",
+                CheckStatus::Fail,
+                Some("no new revisions since the last release".to_string()),
+            ),
+            Err(e) => {
+                ReadinessCheck::new("unreleased changes", CheckStatus::Fail, Some(e.to_string()))
+            }
+        },
+    );
+
+    checks.push(if cfg.release_timeout.is_some() {
+        ReadinessCheck::from_result(
+            "release age",
+            check_release_age(branch.as_ref(), &cfg, chrono::Utc::now()).map(|_| None),
+        )
+    } else {
+        ReadinessCheck::new("release age", CheckStatus::Skip, None)
+    });
+
+    checks.push(ReadinessCheck::from_result(
+        "pending version",
+        pick_new_version(wt, &cfg, options.bump).map(|v| Some(v.to_string())),
+    ));
+
+    checks.push(match ci_status(&cfg, false).await {
+        Some(status) => match status {
+            Ok(disperse::github::GitHubCIStatus::Ok) => {
+                ReadinessCheck::new("CI", CheckStatus::Pass, None)
+            }
+            Ok(disperse::github::GitHubCIStatus::Failed { html_url, sha }) => ReadinessCheck::new(
+                "CI",
+                CheckStatus::Fail,
+                Some(format!(
+                    "failed for revision {}: {}",
+                    sha,
+                    html_url.unwrap_or_else(|| "unknown".to_string())
+                )),
+            ),
+            Ok(disperse::github::GitHubCIStatus::Pending { html_url, sha }) => ReadinessCheck::new(
+                "CI",
+                CheckStatus::Fail,
+                Some(format!(
+                    "pending for revision {}: {}",
+                    sha,
+                    html_url.unwrap_or_else(|| "unknown".to_string())
+                )),
+            ),
+            Err(e) => ReadinessCheck::new("CI", CheckStatus::Fail, Some(e)),
+        },
+        None => ReadinessCheck::new("CI", CheckStatus::Skip, None),
+    });
+
+    checks.push(if wt.has_filename(Path::new("Cargo.toml")) {
+        ReadinessCheck::from_result(
+            "crates.io credentials",
+            disperse::cargo::preflight(wt).await.map(|_| None),
+        )
+    } else {
+        ReadinessCheck::new("crates.io credentials", CheckStatus::Skip, None)
+    });
+
+    checks
+}
+
+fn ready(
+    checks: &[ReadinessCheck],
+    environment: &disperse::environment::Fingerprint,
+    format: OutputFormat,
+) -> i32 {
+    match format {
+        OutputFormat::Text => {
+            for check in checks {
+                match check.detail.as_ref() {
+                    Some(detail) => println!("[{}] {}: {}", check.status, check.name, detail),
+                    None => println!("[{}] {}", check.status, check.name),
+                }
+            }
+            println!();
+            println!("Environment:");
+            for line in environment.to_string().lines() {
+                println!("  {}", line);
+            }
+        }
+        OutputFormat::Json => {
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&serde_json::json!({
+                    "checks": checks,
+                    "environment": environment,
+                }))
+                .unwrap()
+            );
+        }
+    }
+
+    if checks.iter().any(|c| c.status == CheckStatus::Fail) {
+        1
+    } else {
+        0
+    }
+}
+
+/// Verify a release manifest; returns the exit code.
+fn verify_manifest(args: &VerifyManifestArgs) -> i32 {
+    let manifest = match std::fs::read_to_string(&args.manifest)
+        .map_err(disperse::manifest::Error::from)
+        .and_then(|text| disperse::manifest::SignedManifest::from_json(&text))
+    {
+        Ok(manifest) => manifest,
+        Err(e) => {
+            log::error!("Unable to read {}: {}", args.manifest.display(), e);
+            return 1;
+        }
+    };
+    let mut ret = 0;
+    match manifest.verify_signature() {
+        Ok(()) => log::info!("Signature is valid"),
+        Err(e) => {
+            log::error!("{}", e);
+            ret = 1;
+        }
+    }
+    for artifact in &args.artifacts {
+        match manifest.verify_artifact(artifact) {
+            Ok(()) => log::info!("{} matches the manifest", artifact.display()),
+            Err(e) => {
+                log::error!("{}", e);
+                ret = 1;
+            }
+        }
+    }
+    if let Some(repository) = args.repository.as_deref() {
+        let tag = &manifest.manifest.tag;
+        let revid = breezyshim::controldir::open_tree_or_branch(repository, None, None)
+            .map_err(|e| e.to_string())
+            .and_then(|(_, branch)| branch.tags().map_err(|e| e.to_string()))
+            .and_then(|tags| tags.lookup_tag(tag).map_err(|e| e.to_string()));
+        match revid {
+            Ok(revid) if revid.to_string() == manifest.manifest.revision => {
+                log::info!("Tag {} points at {}", tag, revid);
+            }
+            Ok(revid) => {
+                log::error!(
+                    "Tag {} points at {}, but the manifest has {}",
+                    tag,
+                    revid,
+                    manifest.manifest.revision
+                );
+                ret = 1;
+            }
+            Err(e) => {
+                log::error!("Unable to look up tag {} in {}: {}", tag, repository, e);
+                ret = 1;
+            }
+        }
+    }
+    ret
+}
+
+/// Switch credentials, identity, signing key and notification settings to
+/// those of a profile.
+///
+/// This sets environment variables, so has to be called before any other
+/// threads are started.
+fn apply_profile(
+    name: &str,
+    profile: disperse::config::Profile,
+    config: &mut disperse::config::Config,
+    options: &mut Options,
+) -> Result<(), String> {
+    log::info!("Using profile {}", name);
+    disperse::profile::activate(name);
+    if let Some(identity) = profile.identity.as_deref() {
+        for (key, value) in disperse::profile::identity_env(identity)? {
+            std::env::set_var(key, value);
+        }
+    }
+    for (key, value) in &profile.env {
+        std::env::set_var(key, value);
+    }
+    if profile.signing_key.is_some() {
+        options.signing_key = profile.signing_key;
+    }
+    if profile.prometheus.is_some() {
+        config.prometheus = profile.prometheus;
+    }
+    if profile.failure_issues.is_some() {
+        config.failure_issues = profile.failure_issues;
+    }
+    Ok(())
+}
+
+async fn self_update(dry_run: bool) -> i32 {
+    let update = match disperse::self_update::check(&octocrab::Octocrab::default()).await {
+        Ok(Some(update)) => update,
+        Ok(None) => {
+            log::info!(
+                "disperse {} is the latest release",
+                env!("CARGO_PKG_VERSION")
+            );
+            return 0;
+        }
+        Err(e) => {
+            log::error!("Unable to check for updates: {}", e);
+            return 1;
+        }
+    };
+    if dry_run {
+        log::info!(
+            "disperse {} is available; not updating due to dry run mode",
+            update.version.to_string()
+        );
+        return 0;
+    }
+    let target = match std::env::current_exe() {
+        Ok(target) => target,
+        Err(e) => {
+            log::error!("Unable to find the running executable: {}", e);
+            return 1;
+        }
+    };
+    match disperse::self_update::install(&update, &target).await {
+        Ok(()) => {
+            log::info!(
+                "Updated {} to disperse {}",
+                target.display(),
+                update.version.to_string()
+            );
+            0
+        }
+        Err(e) => {
+            log::error!("Unable to update disperse: {}", e);
+            1
+        }
+    }
+}
+
+/// Warn if a newer disperse has released a project before.
+///
+/// Releasing with an older version may undo changes to the release process.
+fn check_release_ledger(name: &str, options: &Options) {
+    let newer = release_history(options)
+        .map_err(|e| e.to_string())
+        .and_then(|history| {
+            disperse::self_update::newer_release_version(&history, name).map_err(|e| e.to_string())
+        });
+    match newer {
+        Ok(Some(version)) => log::warn!(
+            "{} was last released with disperse {}, but this is disperse {}",
+            name,
+            version.to_string(),
+            env!("CARGO_PKG_VERSION")
+        ),
+        Ok(None) => {}
+        Err(e) => log::debug!("Unable to check release history: {}", e),
+    }
+}
 
-    if let Some(news_file) = &cfg.news_file {
-        let news_file = wt.basedir().join(news_file);
-        if !news_file.exists() {
-            log::error!("News file {} does not exist", news_file.display());
+fn list_history(
+    since: Option<chrono::DateTime<chrono::Utc>>,
+    format: OutputFormat,
+    options: &Options,
+) -> i32 {
+    use disperse::history::ReleaseHistory;
+    let events = match release_history(options)
+        .and_then(|h| Ok(h.since(since.unwrap_or(chrono::DateTime::UNIX_EPOCH))?))
+    {
+        Ok(events) => events,
+        Err(e) => {
+            log::error!("Unable to read release history: {}", e);
             return 1;
         }
+    };
+
+    match format {
+        OutputFormat::Text => {
+            for event in events.iter() {
+                println!(
+                    "{}  {}  {}  {}",
+                    event.released.format("%Y-%m-%d %H:%M:%S"),
+                    event.project,
+                    event.version,
+                    event.tag
+                );
+            }
+        }
+        OutputFormat::Json => {
+            println!("{}", serde_json::to_string_pretty(&events).unwrap());
+        }
     }
+    0
+}
 
-    for update_version in cfg.update_version.unwrap_or_default().iter() {
-        match disperse::custom::validate_update_version(&wt, update_version) {
-            Ok(_) => {}
+fn verify(wt: &WorkingTree, options: &Options) -> Result<(), i32> {
+    let cfg = match read_project_config(wt, options) {
+        Ok(cfg) => cfg,
+        Err(e) => {
+            log::info!("Error loading configuration: {}", e);
+            return Err(1);
+        }
+    };
+
+    let verify_command = disperse::verify::determine_verify_command(&cfg, wt);
+
+    if verify_command.is_none() {
+        log::info!("No verify command configured or detected");
+        return Ok(());
+    }
+
+    let verify_command = verify_command.unwrap();
+
+    log::info!(
+        "Running verify command: {} ({})",
+        verify_command.command,
+        verify_command.reason
+    );
+
+    let mut cmd = std::process::Command::new("sh");
+    cmd.arg("-c").arg(verify_command.command);
+    cmd.current_dir(wt.abspath(std::path::Path::new(".")).unwrap());
+    cmd.stdin(std::process::Stdio::null());
+    cmd.stdout(std::process::Stdio::inherit());
+    cmd.stderr(std::process::Stdio::inherit());
+    let status = disperse::logging::status(&mut cmd).unwrap();
+
+    if !status.success() {
+        log::error!("Verify command failed");
+        return Err(1);
+    }
+
+    Ok(())
+}
+
+/// Build the release artifacts of a project, without tagging or publishing anything.
+fn build(wt: &WorkingTree, options: &Options) -> Result<(), i32> {
+    let cfg = match read_project_config(wt, options) {
+        Ok(cfg) => cfg,
+        Err(e) => {
+            log::info!("Error loading configuration: {}", e);
+            return Err(1);
+        }
+    };
+
+    if let Some(pre_dist_command) = cfg.pre_dist_command.as_ref() {
+        log::info!("Running pre-dist command: {}", pre_dist_command);
+        if let Err(e) = run_pre_dist_command(wt, &cfg) {
+            log::error!("{}", e);
+            return Err(1);
+        }
+    }
+
+    let mut artifacts = match create_python_artifacts(wt, &cfg) {
+        Ok(artifacts) => artifacts,
+        Err(e) => {
+            log::error!("Unable to build Python artifacts: {}", e);
+            return Err(1);
+        }
+    };
+
+    if wt.has_filename(Path::new("Cargo.toml")) {
+        log::info!("Packaging crates");
+        let packaged = match artifact_directory(wt, &cfg) {
+            Ok(artifact_dir) => {
+                disperse::cargo::package(wt, &artifact_dir).map_err(|e| e.to_string())
+            }
+            Err(e) => Err(e.to_string()),
+        };
+        match packaged {
+            Ok(crates) => artifacts.extend(crates),
             Err(e) => {
-                log::error!("Invalid update_version: {}", e);
-                return 1;
+                log::error!("Unable to package crates: {}", e);
+                return Err(1);
             }
         }
     }
 
-    for update_manpage in cfg.update_manpages.unwrap_or_default().iter() {
-        for path in disperse::iter_glob(&wt, update_manpage.to_str().unwrap()) {
-            match disperse::manpage::validate_update_manpage(&wt, path.as_path()) {
-                Ok(_) => {}
-                Err(e) => {
-                    log::error!("Invalid update_manpage: {}", e);
-                    return 1;
-                }
-            }
-        }
-    }
+    if artifacts.is_empty() {
+        log::info!("No artifacts to build");
+    }
+    for artifact in artifacts {
+        println!("{}", artifact.display());
+    }
+    Ok(())
+}
+
+/// Perform only the publish phase of a release, for a tag that already exists.
+async fn publish(
+    url: &str,
+    tag: &str,
+    artifacts: &[std::path::PathBuf],
+    dry_run: bool,
+    options: &Options,
+) -> Result<Vec<ReleaseLink>, ReleaseError> {
+    let (_, branch) =
+        breezyshim::controldir::open_tree_or_branch(url, None, None).map_err(|e| {
+            ReleaseError::RepositoryUnavailable {
+                url: url.to_string(),
+                reason: e.to_string(),
+            }
+        })?;
+    let ws = silver_platter::workspace::Workspace::builder()
+        .main_branch(branch)
+        .build()
+        .map_err(|e| ReleaseError::Other(e.to_string()))?;
+
+    let tags = ws
+        .local_tree()
+        .branch()
+        .tags()
+        .map_err(|e| ReleaseError::Other(e.to_string()))?;
+    if !tags.has_tag(tag) {
+        return Err(ReleaseError::NoSuchTag);
+    }
+    let revid = tags
+        .lookup_tag(tag)
+        .map_err(|e| ReleaseError::Other(e.to_string()))?;
+    ws.local_tree()
+        .update(Some(&revid))
+        .map_err(|e| ReleaseError::Other(e.to_string()))?;
+
+    let cfg = read_project_config(ws.local_tree(), options).map_err(|e| {
+        log::error!("Unable to read project configuration: {}", e);
+        ReleaseError::NoDisperseConfig
+    })?;
+    let version = match cfg.tag_name.as_deref() {
+        Some(tag_template) => disperse::version::unexpand_tag(tag_template, tag)
+            .map_err(|e| ReleaseError::Other(format!("Unable to parse tag {}: {}", tag, e)))?,
+        None => return Err(ReleaseError::Other("No tag-name configured".to_string())),
+    };
+    log::info!("Publishing {} from tag {}", version.to_string(), tag);
+
+    let artifacts = if artifacts.is_empty() {
+        run_pre_dist_command(ws.local_tree(), &cfg)?;
+        create_python_artifacts(ws.local_tree(), &cfg)?
+    } else {
+        artifacts.to_vec()
+    };
+
+    let gh = disperse::github::login().map_err(|e| ReleaseError::Other(e.to_string()))?;
+    let gh_repo = match cfg.github.as_ref() {
+        Some(github) => {
+            let url = github
+                .url
+                .parse()
+                .map_err(|e: url::ParseError| ReleaseError::Other(e.to_string()))?;
+            Some(
+                disperse::github::get_github_repo(&gh, &url)
+                    .await
+                    .map_err(|e| ReleaseError::Other(e.to_string()))?,
+            )
+        }
+        None => None,
+    };
+
+    let (artifacts, mut links) = publish_artifacts(
+        &ws,
+        tag,
+        dry_run,
+        &gh,
+        &cfg,
+        artifacts
+            .iter()
+            .map(|p| p.as_path())
+            .collect::<Vec<_>>()
+            .as_slice(),
+        gh_repo.as_ref(),
+    )
+    .await?;
+
+    let release_notes = match cfg.news_file.as_ref() {
+        Some(news_file) => open_news_file(ws.local_tree(), &cfg, news_file)?
+            .released_changes(&version)
+            .map_err(|e| ReleaseError::Other(e.to_string()))?,
+        None => None,
+    };
+
+    if let Some(gh_repo) = gh_repo.as_ref() {
+        if dry_run {
+            log::info!("skipping creation of github release due to dry run mode");
+        } else {
+            let release_url = disperse::github::create_github_release(
+                &gh,
+                gh_repo,
+                tag,
+                &version.to_string(),
+                release_notes
+                    .as_deref()
+                    .map(disperse::news_file::release_notes_markdown)
+                    .as_deref(),
+            )
+            .await
+            .map_err(|e| ReleaseError::Other(e.to_string()))?;
+            links.insert(
+                0,
+                ReleaseLink {
+                    kind: "GitHub release",
+                    url: release_url.to_string(),
+                },
+            );
+        }
+    }
+
+    if let Some(launchpad) = cfg.launchpad.as_ref() {
+        if dry_run {
+            log::info!("skipping upload of tarball to Launchpad");
+        } else {
+            let lp = launchpad_client().await?;
+            let project = disperse::launchpad::get_project(lp, &launchpad.project)
+                .await
+                .map_err(ReleaseError::Other)?;
+            let lp_release = disperse::launchpad::ensure_release(
+                lp,
+                &project.self_().unwrap(),
+                &version.to_string(),
+                launchpad.series.as_deref(),
+                release_notes.as_deref(),
+            )
+            .await
+            .map_err(ReleaseError::Other)?;
+            disperse::launchpad::add_release_files(lp, &lp_release, artifacts)
+                .await
+                .map_err(ReleaseError::Other)?;
+            links.push(ReleaseLink {
+                kind: "Launchpad",
+                url: format!(
+                    "https://launchpad.net/{}/+milestone/{}",
+                    launchpad.project,
+                    version.to_string()
+                ),
+            });
+        }
+    }
+
+    Ok(links)
+}
+
+fn init(wt: &WorkingTree, options: &Options) -> Result<(), i32> {
+    let path = options.project_config_path();
+    if wt.has_filename(path) {
+        log::info!("Already initialized");
+        return Ok(());
+    }
+
+    let cfg = disperse::init::detect_config(wt);
+    let contents = toml::to_string_pretty(&cfg).unwrap();
+
+    print!("{}", contents);
+    let confirmed = disperse::checklist::confirm_terminal(&format!("Write {}?", path.display()))
+        .map_err(|e| {
+            log::error!("{}", e);
+            1
+        })?;
+    if confirmed == Some(false) {
+        return Err(1);
+    }
+
+    wt.put_file_bytes_non_atomic(path, contents.as_bytes())
+        .map_err(|e| {
+            log::error!("Unable to write {}: {}", path.display(), e);
+            1
+        })?;
+
+    wt.add(&[path]).map_err(|e| {
+        log::error!("Unable to add {}: {}", path.display(), e);
+        1
+    })?;
+
+    wt.build_commit()
+        .message("Initialize disperse.toml")
+        .commit()
+        .map_err(|e| {
+            log::error!("Unable to commit initialization: {}", e);
+            1
+        })?;
 
-    0
+    Ok(())
 }
 
-fn verify(wt: &WorkingTree) -> Result<(), i32> {
-    let cfg = match disperse::project_config::read_project_with_fallback(wt) {
+fn promote(
+    wt: &WorkingTree,
+    version: &Version,
+    force: bool,
+    dry_run: bool,
+    options: &Options,
+) -> Result<(), i32> {
+    let cfg = match read_project_config(wt, options) {
         Ok(cfg) => cfg,
         Err(e) => {
-            log::info!("Error loading configuration: {}", e);
+            log::error!("Unable to read project configuration: {}", e);
             return Err(1);
         }
     };
 
-    let verify_command = determine_verify_command(&cfg, wt);
+    let rollout = if let Some(rollout) = cfg.rollout.as_ref() {
+        rollout
+    } else {
+        log::error!("Staged rollouts are not enabled for this project");
+        return Err(1);
+    };
 
-    if verify_command.is_none() {
-        log::info!("No verify command configured or detected");
-        return Ok(());
+    if !wt.has_filename(Path::new("package.json")) {
+        log::error!("{}", disperse::rollout::Error::Unsupported);
+        return Err(1);
     }
 
-    let verify_command = verify_command.unwrap();
-
-    log::info!("Running verify command: {}", verify_command);
-
-    let mut cmd = std::process::Command::new("sh");
-    cmd.arg("-c").arg(verify_command);
-    cmd.current_dir(wt.abspath(std::path::Path::new(".")).unwrap());
-    cmd.stdin(std::process::Stdio::null());
-    cmd.stdout(std::process::Stdio::inherit());
-    cmd.stderr(std::process::Stdio::inherit());
-    let status = cmd.status().unwrap();
+    // The soak period starts when the release became available, not when
+    // its last commit was made.
+    let published = match disperse::npm::publish_time(wt, version) {
+        Ok(Some(published)) => published,
+        Ok(None) => {
+            log::error!("{} has not been published", version.to_string());
+            return Err(1);
+        }
+        Err(e) => {
+            log::error!(
+                "Unable to find out when {} was published: {}",
+                version.to_string(),
+                e
+            );
+            return Err(1);
+        }
+    };
 
-    if !status.success() {
-        log::error!("Verify command failed");
-        return Err(1);
+    if let Err(e) = disperse::rollout::check_soak(
+        published,
+        chrono::Utc::now(),
+        rollout.soak_days.unwrap_or(0),
+    ) {
+        if force {
+            log::warn!("{}; promoting anyway", e);
+        } else {
+            log::error!("{}", e);
+            log::info!("Pass --force to promote anyway");
+            return Err(1);
+        }
     }
 
-    Ok(())
-}
-
-fn init(wt: &WorkingTree) -> Result<(), i32> {
-    if wt.has_filename(Path::new("disperse.toml")) {
-        log::info!("Already initialized");
+    if dry_run {
+        log::info!(
+            "Would promote {} to {}",
+            version.to_string(),
+            disperse::rollout::PROMOTED_CHANNEL
+        );
         return Ok(());
     }
 
-    let cfg = disperse::project_config::ProjectConfig::default();
+    disperse::npm::add_dist_tag(wt, version, disperse::rollout::PROMOTED_CHANNEL).map_err(|e| {
+        log::error!("Unable to promote {}: {}", version.to_string(), e);
+        1
+    })
+}
 
-    wt.put_file_bytes_non_atomic(
-        Path::new("disperse.toml"),
-        toml::to_string_pretty(&cfg).unwrap().as_bytes(),
-    )
-    .map_err(|e| {
-        log::error!("Unable to write disperse.toml: {}", e);
+/// Rename deprecated keys in an existing project configuration.
+fn migrate_deprecated_keys(wt: &WorkingTree, path: &Path) -> Result<(), i32> {
+    let text = wt.get_file_text(path).map_err(|e| {
+        log::error!("Unable to read {}: {}", path.display(), e);
         1
     })?;
-
-    wt.add(&[Path::new("disperse.toml")]).map_err(|e| {
-        log::error!("Unable to add disperse.toml: {}", e);
+    let mut doc: toml_edit::DocumentMut = String::from_utf8_lossy(&text).parse().map_err(|e| {
+        log::error!("Unable to parse {}: {}", path.display(), e);
         1
     })?;
 
+    let renamed = disperse::project_config::rename_deprecated_keys(&mut doc);
+    if renamed.is_empty() {
+        log::info!("Already migrated");
+        return Ok(());
+    }
+    for deprecated in renamed.iter() {
+        log::info!("Renaming {} to {}", deprecated.old, deprecated.new);
+    }
+
+    wt.put_file_bytes_non_atomic(path, doc.to_string().as_bytes())
+        .map_err(|e| {
+            log::error!("Unable to write {}: {}", path.display(), e);
+            1
+        })?;
+
     wt.build_commit()
-        .message("Initialize disperse.toml")
+        .message("Rename deprecated configuration keys")
+        .specific_files(&[path])
         .commit()
         .map_err(|e| {
-            log::error!("Unable to commit initialization: {}", e);
+            log::error!("Unable to commit migration: {}", e);
             1
         })?;
 
     Ok(())
 }
 
-fn migrate(wt: &WorkingTree) -> Result<(), i32> {
-    if wt.has_filename(Path::new("disperse.toml")) {
-        log::info!("Already migrated");
-        return Ok(());
+fn migrate(wt: &WorkingTree, options: &Options) -> Result<(), i32> {
+    let path = options.project_config_path();
+    if wt.has_filename(path) {
+        return migrate_deprecated_keys(wt, path);
     }
 
     // TODO: Check that the old configuration files have no changes
@@ -1819,21 +5697,18 @@ fn migrate(wt: &WorkingTree) -> Result<(), i32> {
         }
     };
 
-    wt.put_file_bytes_non_atomic(
-        Path::new("disperse.toml"),
-        toml::to_string_pretty(&cfg).unwrap().as_bytes(),
-    )
-    .map_err(|e| {
-        log::error!("Unable to write disperse.toml: {}", e);
-        1
-    })?;
+    wt.put_file_bytes_non_atomic(path, toml::to_string_pretty(&cfg).unwrap().as_bytes())
+        .map_err(|e| {
+            log::error!("Unable to write {}: {}", path.display(), e);
+            1
+        })?;
 
-    wt.add(&[Path::new("disperse.toml")]).map_err(|e| {
-        log::error!("Unable to add disperse.toml: {}", e);
+    wt.add(&[path]).map_err(|e| {
+        log::error!("Unable to add {}: {}", path.display(), e);
         1
     })?;
 
-    let mut paths = vec![Path::new("disperse.toml")];
+    let mut paths = vec![path];
 
     match wt.remove(&[Path::new("disperse.conf")]) {
         Ok(_) => {
@@ -1872,42 +5747,159 @@ fn migrate(wt: &WorkingTree) -> Result<(), i32> {
     Ok(())
 }
 
-#[tokio::main]
-async fn main() {
+fn main() {
     let args = Args::parse();
 
-    env_logger::builder()
-        .format(|buf, record| writeln!(buf, "{}", record.args()))
-        .filter(
-            None,
-            if args.debug {
-                log::LevelFilter::Debug
-            } else {
-                log::LevelFilter::Info
-            },
-        )
-        .init();
+    let level = disperse::logging::console_level(args.quiet, args.verbose.max(args.debug as u8));
+    if let Err(e) = disperse::logging::init(level, args.log_file.as_deref()) {
+        eprintln!(
+            "Unable to open log file {}: {}",
+            args.log_file.as_deref().unwrap_or(Path::new("")).display(),
+            e
+        );
+        std::process::exit(1);
+    }
+
+    let mut config = match args.config.as_ref() {
+        // The doctor command reports problems with the configuration itself.
+        Some(_) if matches!(args.command, Commands::Doctor(..)) => Default::default(),
+        Some(path) => match disperse::config::load_config_from(path) {
+            Ok(config) => config,
+            Err(e) => {
+                log::error!(
+                    "Unable to load configuration from {}: {}",
+                    path.display(),
+                    e
+                );
+                std::process::exit(1);
+            }
+        },
+        None if matches!(args.command, Commands::Doctor(..)) => Default::default(),
+        None => disperse::config::load_config().unwrap().unwrap_or_default(),
+    };
+
+    let mut options = Options {
+        project_config: args.project_config.clone(),
+        ci_timeout: args.ci_timeout,
+        ci_poll_interval: args.ci_poll_interval,
+        ..Default::default()
+    };
+
+    if let Some(name) = args.profile.as_deref() {
+        let Some(profile) = config.profiles.remove(name) else {
+            log::error!("Unknown profile {}", name);
+            std::process::exit(1);
+        };
+        if let Err(e) = apply_profile(name, profile, &mut config, &mut options) {
+            log::error!("Unable to apply profile {}: {}", name, e);
+            std::process::exit(1);
+        }
+    }
 
-    let config = disperse::config::load_config().unwrap().unwrap_or_default();
+    // The runtime is only started now, as applying the profile sets
+    // environment variables, which is not safe once there are other threads.
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()
+        .unwrap();
+    std::process::exit(runtime.block_on(run(args, config, options)));
+}
+
+async fn run(args: Args, mut config: disperse::config::Config, mut options: Options) -> i32 {
+    options.history = config.history.take().and_then(|h| h.path);
+
+    if let Some(defaults) = config.defaults.take() {
+        if let Err(e) =
+            disperse::project_config::apply_defaults(&ProjectConfig::default(), &defaults)
+        {
+            log::error!("Invalid [defaults] in configuration: {}", e);
+            std::process::exit(1);
+        }
+        options.project_defaults = Some(defaults);
+    }
+
+    if !args.overrides.is_empty() {
+        if let Err(e) = disperse::project_config::apply_overrides(
+            &ProjectConfig::default(),
+            args.overrides.as_slice(),
+        ) {
+            log::error!("{}", e);
+            std::process::exit(1);
+        }
+        options.overrides = args.overrides.clone();
+    }
+
+    disperse::limits::configure(&config.limits);
+
+    if let Some(max_age) = config.self_update.take().and_then(|s| s.max_age) {
+        if let Some(age) = disperse::self_update::installed_age(chrono::Utc::now()) {
+            if age.num_days() as u64 > max_age {
+                log::warn!(
+                    "disperse {} was installed {} days ago; run `disperse self-update` to update",
+                    env!("CARGO_PKG_VERSION"),
+                    age.num_days()
+                );
+            }
+        }
+    }
+
+    if let Some(max_age) = config.workspaces.take().and_then(|w| w.max_age) {
+        if matches!(args.command, Commands::Release(..) | Commands::Discover(..)) && !args.dry_run {
+            match clean_workspaces(max_age, false, true) {
+                Ok(removed) => {
+                    for w in removed {
+                        log::info!("Removed stale workspace {}", w.path.display());
+                    }
+                }
+                Err(e) => log::warn!("Unable to clean up workspaces: {}", e),
+            }
+        }
+    }
+
+    options.failure_issue_threshold = config.failure_issues.take().map(|failure_issues| {
+        failure_issues
+            .threshold
+            .unwrap_or(DEFAULT_FAILURE_ISSUE_THRESHOLD)
+    });
+
+    options.project_overrides = std::mem::take(&mut config.projects)
+        .into_iter()
+        .map(|(url, overrides)| (url.trim_end_matches('/').to_string(), overrides))
+        .collect();
 
     log::debug!("Config: {:?}", config);
 
+    let prometheus_config = config.prometheus.take().unwrap_or_default();
+
     pyo3::prepare_freethreaded_python();
 
     breezyshim::init();
     breezyshim::plugin::load_plugins();
 
-    std::process::exit(match &args.command {
+    let ret = match &args.command {
         Commands::Release(release_args) => {
+            let options = Options {
+                new_version: release_args
+                    .new_version
+                    .as_ref()
+                    .map(|v| v.as_str().parse().unwrap()),
+                bump: release_args.bump,
+                force: true,
+                dry_run: args.dry_run,
+                ignore_ci: release_args.ignore_ci,
+                ignore_verify_command: release_args.ignore_verify_command,
+                preserve_temp: release_args.preserve_temp,
+                resume: release_args.resume,
+                interactive: !release_args.non_interactive && std::io::stdin().is_terminal(),
+                plan_format: args.output_format(),
+                projects: release_args.project.clone(),
+                ..options.clone()
+            };
             release_many(
                 release_args.url.as_slice(),
-                release_args.new_version.clone(),
-                Some(release_args.ignore_ci),
-                Some(release_args.ignore_verify_command),
-                Some(args.dry_run),
+                &options,
                 release_args.discover,
-                Some(true),
-                release_args.preserve_temp,
+                None,
             )
             .await
         }
@@ -1948,14 +5940,24 @@ async fn main() {
             let urls: Vec<Url> = vec![pypi_urls, crates_io_urls, repositories_urls]
                 .into_iter()
                 .flatten()
+                .filter(|u| discovered_url_selected(u, &discover_args.only, &discover_args.exclude))
                 .collect::<Vec<_>>();
+            let urls = prioritize_overdue(urls, &options);
 
             if urls.is_empty() {
                 log::error!("No projects found. Specify pypi or crates.io username, or add repositories to config");
                 0
             } else {
                 let ret = if discover_args.info {
-                    info_many(urls.as_slice())
+                    info_many(
+                        urls.iter()
+                            .map(|x| x.to_string())
+                            .collect::<Vec<_>>()
+                            .as_slice(),
+                        args.output_format(),
+                        &options,
+                    )
+                    .await
                 } else if discover_args.urls {
                     println!(
                         "{}",
@@ -1971,18 +5973,18 @@ async fn main() {
                             .map(|x| x.to_string())
                             .collect::<Vec<_>>()
                             .as_slice(),
-                        None,
-                        Some(false),
-                        Some(false),
-                        Some(false),
+                        &options,
                         true,
-                        Some(false),
-                        false,
+                        discover_args.limit,
                     )
                     .await
                 };
-                if let Some(prometheus) = args.prometheus {
-                    push_to_gateway(prometheus.as_str()).await.unwrap();
+                if let Some(prometheus) =
+                    args.prometheus.as_ref().or(prometheus_config.url.as_ref())
+                {
+                    push_to_gateway(prometheus.as_str(), &prometheus_config)
+                        .await
+                        .unwrap();
                 }
                 if discover_args.r#try {
                     0
@@ -1991,31 +5993,511 @@ async fn main() {
                 }
             }
         }
-        Commands::Validate(args) => validate_config(&args.path),
-        Commands::Info(args) => {
+        Commands::Validate(validate_args) if validate_args.schema => {
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&disperse::project_config::json_schema()).unwrap()
+            );
+            0
+        }
+        Commands::Validate(validate_args) => {
+            validate_config(&validate_args.path, args.output_format(), &options)
+        }
+        Commands::Status(status_args) => {
+            let urls = if status_args.url.is_empty() {
+                config
+                    .repositories
+                    .as_ref()
+                    .and_then(|repositories| repositories.owned.as_ref())
+                    .map(|owned| owned.iter().map(|u| u.to_string()).collect())
+                    .unwrap_or_default()
+            } else {
+                status_args.url.clone()
+            };
+            if urls.is_empty() {
+                log::error!("No projects specified, and no repositories in the configuration");
+                1
+            } else {
+                status(
+                    urls.as_slice(),
+                    status_args.sort,
+                    args.output_format(),
+                    &options,
+                )
+                .await
+            }
+        }
+        Commands::Doctor(doctor_args) => {
+            doctor(
+                &doctor_args.path,
+                args.config.as_deref(),
+                args.output_format(),
+                &options,
+            )
+            .await
+        }
+        Commands::Changelog(args) => {
             let wt = workingtree::open(args.path.as_ref()).unwrap();
-            info(&wt, wt.branch().as_ref())
+            let _lock = wt.lock_read();
+            changelog(&wt, args.markdown, &options)
+        }
+        Commands::Unreleased(unreleased_args) => {
+            let wt = workingtree::open(unreleased_args.path.as_ref()).unwrap();
+            let _lock = wt.lock_read();
+            unreleased(&wt, unreleased_args.group_by, args.output_format())
+        }
+        Commands::Info(info_args) => {
+            let wt = workingtree::open(info_args.path.as_ref()).unwrap();
+            info(&wt, wt.branch().as_ref(), args.output_format(), &options).await
         }
         Commands::Verify(args) => {
             let wt = workingtree::open(args.path.as_ref()).unwrap();
-            match verify(&wt) {
+            match verify(&wt, &options) {
+                Ok(_) => 0,
+                Err(e) => e,
+            }
+        }
+        Commands::Build(args) => {
+            let wt = workingtree::open(args.path.as_ref()).unwrap();
+            match build(&wt, &options) {
                 Ok(_) => 0,
                 Err(e) => e,
             }
         }
+        Commands::Publish(publish_args) => match publish(
+            &publish_args.url,
+            &publish_args.tag,
+            &publish_args.artifacts,
+            args.dry_run,
+            &options,
+        )
+        .await
+        {
+            Ok(links) => {
+                for link in links {
+                    println!("{}: {}", link.kind, link.url);
+                }
+                0
+            }
+            Err(e) => {
+                log::error!("Unable to publish {}: {}", publish_args.tag, e);
+                1
+            }
+        },
         Commands::Migrate(args) => {
             let wt = workingtree::open(args.path.as_ref()).unwrap();
-            match migrate(&wt) {
+            match migrate(&wt, &options) {
                 Ok(_) => 0,
                 Err(e) => e,
             }
         }
         Commands::Init(args) => {
             let wt = workingtree::open(args.path.as_ref()).unwrap();
-            match init(&wt) {
+            match init(&wt, &options) {
                 Ok(_) => 0,
                 Err(e) => e,
             }
         }
-    });
+        Commands::History(history_args) => {
+            list_history(history_args.since, args.output_format(), &options)
+        }
+        Commands::VerifyManifest(verify_manifest_args) => verify_manifest(verify_manifest_args),
+        Commands::SelfUpdate => self_update(args.dry_run).await,
+        Commands::Clean(clean_args) => clean(
+            clean_args.older_than,
+            clean_args.include_resumable,
+            clean_args.list || args.dry_run,
+        ),
+        Commands::Ready(ready_args) => {
+            let wt = workingtree::open(ready_args.path.as_ref()).unwrap();
+            let checks = {
+                let _lock = wt.lock_read();
+                readiness_checks(&wt, &options).await
+            };
+            ready(
+                &checks,
+                &disperse::environment::fingerprint(),
+                args.output_format(),
+            )
+        }
+        Commands::CheckCi(check_ci_args) => {
+            let wt = workingtree::open(check_ci_args.path.as_ref()).unwrap();
+            let _lock = wt.lock_read();
+            check_ci(&wt, check_ci_args.wait, args.output_format(), &options).await
+        }
+        Commands::Login(login_args) => match login_args.service {
+            LoginService::CratesIo => {
+                println!("Please enter your crates.io API token");
+                let mut token = String::new();
+                std::io::stdin().read_line(&mut token).unwrap();
+                match disperse::cargo::store_token(token.trim()) {
+                    Ok(()) => 0,
+                    Err(e) => {
+                        log::error!("{}", e);
+                        1
+                    }
+                }
+            }
+        },
+        Commands::Auth(auth_args) => match auth(&auth_args.command) {
+            Ok(()) => 0,
+            Err(e) => {
+                log::error!("{}", e);
+                1
+            }
+        },
+        Commands::Promote(promote_args) => {
+            let wt = workingtree::open(promote_args.path.as_ref()).unwrap();
+            let version: Version = match promote_args.version.parse() {
+                Ok(v) => v,
+                Err(e) => {
+                    log::error!("Invalid version {}: {}", promote_args.version, e);
+                    std::process::exit(1);
+                }
+            };
+            match promote(&wt, &version, promote_args.force, args.dry_run, &options) {
+                Ok(_) => 0,
+                Err(e) => e,
+            }
+        }
+    };
+
+    if matches!(args.command, Commands::Release(..) | Commands::Discover(..)) {
+        if let Some(directory) = args
+            .prometheus_textfile_dir
+            .as_ref()
+            .or(prometheus_config.textfile_directory.as_ref())
+        {
+            if let Err(e) = write_textfile(
+                directory,
+                prometheus_config.job.as_deref().unwrap_or("disperse"),
+            ) {
+                log::error!("Unable to write metrics to {}: {}", directory.display(), e);
+            }
+        }
+    }
+
+    ret
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_output_format() {
+        use clap::Parser;
+        let args = Args::try_parse_from(["disperse", "status", "--format", "json"]).unwrap();
+        assert_eq!(args.output_format(), OutputFormat::Json);
+        let args = Args::try_parse_from(["disperse", "--json", "status"]).unwrap();
+        assert_eq!(args.output_format(), OutputFormat::Json);
+        let args = Args::try_parse_from(["disperse", "status"]).unwrap();
+        assert_eq!(args.output_format(), OutputFormat::Text);
+        assert!(
+            Args::try_parse_from(["disperse", "status", "--json", "--format", "text"]).is_err()
+        );
+    }
+
+    #[test]
+    fn test_discovered_url_selected() {
+        let url: Url = "https://github.com/jelmer/dulwich.git".parse().unwrap();
+        let patterns = |ps: &[&str]| {
+            ps.iter()
+                .map(|p| glob::Pattern::new(p).unwrap())
+                .collect::<Vec<_>>()
+        };
+        assert!(discovered_url_selected(&url, &[], &[]));
+        assert!(discovered_url_selected(&url, &patterns(&["dul*"]), &[]));
+        assert!(discovered_url_selected(
+            &url,
+            &patterns(&["https://github.com/jelmer/*"]),
+            &[]
+        ));
+        assert!(!discovered_url_selected(&url, &patterns(&["breezy"]), &[]));
+        assert!(!discovered_url_selected(&url, &[], &patterns(&["dulwich"])));
+    }
+
+    #[test]
+    fn test_order_by_last_release() {
+        let urls: Vec<Url> = [
+            "https://github.com/jelmer/recent",
+            "https://github.com/jelmer/never",
+            "https://github.com/jelmer/old",
+        ]
+        .iter()
+        .map(|u| u.parse().unwrap())
+        .collect();
+        let last_released = [
+            (
+                "recent".to_string(),
+                "2024-06-01T00:00:00Z".parse().unwrap(),
+            ),
+            ("old".to_string(), "2023-01-01T00:00:00Z".parse().unwrap()),
+        ]
+        .into_iter()
+        .collect();
+        assert_eq!(
+            order_by_last_release(urls, &last_released)
+                .iter()
+                .map(|u| project_name_from_url(u).unwrap())
+                .collect::<Vec<_>>(),
+            vec!["never", "old", "recent"]
+        );
+    }
+
+    #[test]
+    fn test_ready_exit_code() {
+        let environment = disperse::environment::Fingerprint {
+            disperse_version: "0.1.0".to_string(),
+            tools: vec![],
+            env: Default::default(),
+        };
+        let mut checks = vec![
+            ReadinessCheck::new("configuration", CheckStatus::Pass, None),
+            ReadinessCheck::new("CI", CheckStatus::Skip, None),
+        ];
+        assert_eq!(ready(&checks, &environment, OutputFormat::Json), 0);
+        checks.push(ReadinessCheck::from_result::<String>(
+            "release age",
+            Err("too recent".to_string()),
+        ));
+        assert_eq!(ready(&checks, &environment, OutputFormat::Json), 1);
+    }
+
+    #[test]
+    fn test_metrics_by_project() {
+        let registry = prometheus::Registry::new();
+        let released = IntCounterVec::new(
+            prometheus::Opts::new("released", "Released projects"),
+            &["project"],
+        )
+        .unwrap();
+        let ignored = IntCounter::new("ignored", "Ignored projects").unwrap();
+        registry.register(Box::new(released.clone())).unwrap();
+        registry.register(Box::new(ignored.clone())).unwrap();
+        released.with_label_values(&["foo"]).inc();
+        released.with_label_values(&["bar"]).inc();
+        ignored.inc();
+
+        let groups = metrics_by_project(&registry.gather());
+        assert_eq!(
+            groups.keys().cloned().collect::<Vec<_>>(),
+            vec![None, Some("bar".to_string()), Some("foo".to_string())]
+        );
+        let foo = &groups[&Some("foo".to_string())];
+        assert_eq!(foo.len(), 1);
+        assert_eq!(foo[0].get_name(), "released");
+        assert_eq!(foo[0].get_metric().len(), 1);
+        assert_eq!(groups[&None][0].get_name(), "ignored");
+    }
+
+    #[test]
+    fn test_pushgateway_url() {
+        assert_eq!(
+            pushgateway_url("https://example.com/", "disperse", &[])
+                .unwrap()
+                .as_str(),
+            "https://example.com/metrics/job/disperse"
+        );
+        assert_eq!(
+            pushgateway_url(
+                "https://example.com/prefix",
+                "disperse",
+                &[("instance", "host 1"), ("path", "/srv/x")]
+            )
+            .unwrap()
+            .as_str(),
+            "https://example.com/prefix/metrics/job/disperse/instance/host%201/path@base64/L3Nydi94"
+        );
+    }
+
+    #[test]
+    fn test_format_release_summary() {
+        let outcomes = vec![
+            ReleaseOutcome {
+                name: "foo".to_string(),
+                version: "1.2.3".parse().unwrap(),
+                tag: "v1.2.3".to_string(),
+                notes: None,
+                links: vec![ReleaseLink {
+                    kind: "crates.io",
+                    url: "https://crates.io/crates/foo".to_string(),
+                }],
+                smoke_test_failures: vec![],
+                checklist: vec![],
+                more_pending: false,
+                plan: None,
+            },
+            ReleaseOutcome {
+                name: "barbaz".to_string(),
+                version: "0.1".parse().unwrap(),
+                tag: "barbaz-0.1".to_string(),
+                notes: None,
+                links: vec![],
+                smoke_test_failures: vec!["npm barbaz: npm install failed".to_string()],
+                checklist: vec![],
+                more_pending: false,
+                plan: None,
+            },
+        ];
+        assert_eq!(
+            format_release_summary(&outcomes),
+            "foo 1.2.3   tag         v1.2.3\n            crates.io   https://crates.io/crates/foo\nbarbaz 0.1  tag         barbaz-0.1\n            smoke test  FAILED: npm barbaz: npm install failed"
+        );
+    }
+
+    #[test]
+    fn test_release_plan() {
+        let cfg = ProjectConfig {
+            pre_dist_command: Some("make".to_string()),
+            ..Default::default()
+        };
+        let version = "1.2.3".parse().unwrap();
+        let targets = vec!["crates.io".to_string()];
+        let plan = ReleasePlan {
+            project: "foo".to_string(),
+            version: "1.2.3".to_string(),
+            tag: "v1.2.3".to_string(),
+            steps: release_steps(&cfg, &version, "v1.2.3", None, &targets),
+            files: vec!["NEWS".into()],
+            diff: None,
+            artifacts: vec![],
+            targets,
+        };
+        assert_eq!(
+            plan.to_string(),
+            r#"Release foo 1.2.3
+  Tag: v1.2.3
+  Steps:
+    Run pre-dist command: make
+    Update the version and the news file
+    Commit "Release 1.2.3."
+    Create tag v1.2.3
+    Build artifacts
+    Publish to crates.io
+    Push to the main branch
+  Files to modify:
+    NEWS
+  Publish to:
+    crates.io
+"#
+        );
+        assert_eq!(
+            serde_json::to_value(&plan).unwrap()["files"],
+            serde_json::json!(["NEWS"])
+        );
+    }
+
+    #[test]
+    fn test_worse_exit_code() {
+        assert_eq!(worse_exit_code(0, EXIT_NOTHING_TO_DO), EXIT_NOTHING_TO_DO);
+        assert_eq!(
+            worse_exit_code(EXIT_CI_FAILED, EXIT_SKIPPED),
+            EXIT_CI_FAILED
+        );
+        assert_eq!(worse_exit_code(EXIT_PUBLISH_FAILED, 1), 1);
+        assert_eq!(worse_exit_code(1, EXIT_PUBLISH_FAILED), 1);
+        assert_eq!(
+            ReleaseError::RecentCommits {
+                min_commit_age: 7,
+                commit_age: 1
+            }
+            .exit_code(),
+            EXIT_SKIPPED
+        );
+    }
+
+    #[test]
+    fn test_format_unreleased() {
+        let commit = |revision: &str, author: &str, summary: &str| UnreleasedCommit {
+            revision: revision.to_string(),
+            date: chrono::DateTime::parse_from_rfc3339("2024-03-01T12:00:00Z").unwrap(),
+            author: author.to_string(),
+            summary: summary.to_string(),
+            kind: disperse::conventional_commits::commit_type(summary),
+        };
+        let commits = vec![
+            commit(
+                "git-v1:0123456789abcdef0123456789abcdef01234567",
+                "Alice",
+                "feat: add --frob",
+            ),
+            commit("bob@example.com-20240301-abc", "Bob", "Tidy up"),
+            commit("git-v1:fedcba9876543210", "Alice", "fix: don't crash"),
+        ];
+        assert_eq!(
+            format_unreleased(&commits, None),
+            "0123456789ab 2024-03-01 feat: add --frob
+bob@example.com-20240301-abc 2024-03-01 Tidy up
+fedcba987654 2024-03-01 fix: don't crash
+"
+        );
+        assert_eq!(
+            format_unreleased(&commits, Some(GroupBy::Author)),
+            "Alice (2):
+  0123456789ab 2024-03-01 feat: add --frob
+  fedcba987654 2024-03-01 fix: don't crash
+
+Bob (1):
+  bob@example.com-20240301-abc 2024-03-01 Tidy up
+"
+        );
+        assert_eq!(
+            group_commits(&commits, GroupBy::Type)
+                .into_iter()
+                .map(|(g, members)| (g, members.len()))
+                .collect::<Vec<_>>(),
+            vec![("feat", 1), ("other", 1), ("fix", 1)]
+        );
+    }
+
+    #[test]
+    fn test_ci_report() {
+        assert_eq!(
+            serde_json::to_value(CiReport::from(&disperse::github::GitHubCIStatus::Ok)).unwrap(),
+            serde_json::json!({"status": "passing"})
+        );
+        assert_eq!(
+            serde_json::to_value(CiReport::from(&disperse::github::GitHubCIStatus::Pending {
+                sha: "abc".to_string(),
+                html_url: None,
+            }))
+            .unwrap(),
+            serde_json::json!({"status": "pending", "revision": "abc"})
+        );
+    }
+
+    #[test]
+    fn test_status_table() {
+        let now = chrono::DateTime::parse_from_rfc3339("2024-03-01T00:00:00Z")
+            .unwrap()
+            .with_timezone(&chrono::Utc);
+        let mut infos = vec![
+            ProjectInfo {
+                name: Some("foo".to_string()),
+                last_version: Some("1.2.3".to_string()),
+                released: Some(now - chrono::Duration::days(10)),
+                revisions_since_release: Some(4),
+                next_version: Some("1.2.4".to_string()),
+                ci_status: Some("passing".to_string()),
+                ..Default::default()
+            },
+            ProjectInfo {
+                url: Some("/src/bar".to_string()),
+                last_version: Some("0.1".to_string()),
+                released: Some(now - chrono::Duration::days(100)),
+                revisions_since_release: Some(0),
+                ..Default::default()
+            },
+        ];
+        sort_status(&mut infos, StatusSort::Age, now);
+        assert_eq!(
+            format_status_table(&infos, now),
+            "project   last release  days since  unreleased  pending  ci\n/src/bar  0.1           100         0           -        -\nfoo       1.2.3         10          4           1.2.4    passing"
+        );
+        sort_status(&mut infos, StatusSort::Unreleased, now);
+        assert_eq!(infos[0].name.as_deref(), Some("foo"));
+        sort_status(&mut infos, StatusSort::Project, now);
+        assert_eq!(infos[0].url.as_deref(), Some("/src/bar"));
+    }
 }
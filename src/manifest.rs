@@ -0,0 +1,333 @@
+use sha2::{Digest, Sha256};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+/// Directory with stored manifests, in the XDG state directory.
+const MANIFESTS_DIR_NAME: &str = "manifests";
+
+#[derive(Debug)]
+pub enum Error {
+    IoError(std::io::Error),
+    JsonError(serde_json::Error),
+    /// gpg failed to sign the manifest.
+    SigningFailed(String),
+    /// The manifest signature did not verify.
+    BadSignature(String),
+    /// An artifact does not match the manifest.
+    ArtifactMismatch {
+        name: String,
+        reason: String,
+    },
+}
+
+impl From<std::io::Error> for Error {
+    fn from(e: std::io::Error) -> Self {
+        Error::IoError(e)
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(e: serde_json::Error) -> Self {
+        Error::JsonError(e)
+    }
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match &self {
+            Error::IoError(e) => write!(f, "IoError: {}", e),
+            Error::JsonError(e) => write!(f, "Invalid manifest: {}", e),
+            Error::SigningFailed(e) => write!(f, "Unable to sign manifest: {}", e),
+            Error::BadSignature(e) => write!(f, "Bad manifest signature: {}", e),
+            Error::ArtifactMismatch { name, reason } => {
+                write!(f, "Artifact {} does not match manifest: {}", name, reason)
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// Digest of a released artifact.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct ArtifactDigest {
+    pub name: String,
+    pub sha256: String,
+}
+
+impl ArtifactDigest {
+    pub fn from_path(path: &Path) -> Result<Self, Error> {
+        Ok(Self {
+            name: artifact_name(path),
+            sha256: sha256sum(path)?,
+        })
+    }
+}
+
+/// Description of a release, for verifying that what was published matches
+/// the tagged source.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct ReleaseManifest {
+    pub project: String,
+    pub version: String,
+    pub tag: String,
+    /// Revision the tag points at.
+    pub revision: String,
+    pub artifacts: Vec<ArtifactDigest>,
+    /// Locations the release was published to.
+    pub destinations: Vec<String>,
+}
+
+/// A manifest along with a detached OpenPGP signature over its canonical form.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct SignedManifest {
+    pub manifest: ReleaseManifest,
+    pub signature: String,
+}
+
+fn artifact_name(path: &Path) -> String {
+    path.file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_else(|| path.display().to_string())
+}
+
+fn sha256sum(path: &Path) -> Result<String, Error> {
+    let contents = std::fs::read(path)?;
+    Ok(Sha256::digest(&contents)
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect())
+}
+
+/// Fingerprints of the keys that made good signatures, from the output of
+/// gpg --status-fd.
+///
+/// Both the fingerprint of the signing key and that of its primary key are
+/// included.
+fn valid_signature_fingerprints(status: &str) -> Vec<String> {
+    status
+        .lines()
+        .filter_map(|l| l.strip_prefix("[GNUPG:] VALIDSIG "))
+        .flat_map(|rest| {
+            let fields = rest.split_whitespace().collect::<Vec<_>>();
+            [fields.first(), fields.get(9)]
+                .into_iter()
+                .flatten()
+                .map(|f| f.to_uppercase())
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+/// Verify an armored detached signature over `data` with gpg, against the
+/// keys in the keyring.
+///
+/// Returns the fingerprints of the key that made the signature and of its
+/// primary key.
+pub fn verify_detached_signature(data: &[u8], signature: &str) -> Result<Vec<String>, Error> {
+    let mut signature_file = tempfile::Builder::new()
+        .prefix("disperse-signature-")
+        .suffix(".asc")
+        .tempfile()?;
+    signature_file.write_all(signature.as_bytes())?;
+    signature_file.flush()?;
+    // The signed data is read from standard input.
+    let mut child = Command::new("gpg")
+        .arg("--batch")
+        .arg("--status-fd")
+        .arg("1")
+        .arg("--verify")
+        .arg(signature_file.path())
+        .arg("-")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    child.stdin.take().unwrap().write_all(data)?;
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        return Err(Error::BadSignature(
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        ));
+    }
+    let fingerprints = valid_signature_fingerprints(&String::from_utf8_lossy(&output.stdout));
+    if fingerprints.is_empty() {
+        return Err(Error::BadSignature(
+            "gpg did not report a valid signature".to_string(),
+        ));
+    }
+    Ok(fingerprints)
+}
+
+impl ReleaseManifest {
+    /// Serialize the manifest in the form that is signed.
+    ///
+    /// Artifacts and destinations are sorted, so that the same release
+    /// always gives the same bytes.
+    pub fn canonical_json(&self) -> Result<Vec<u8>, Error> {
+        let mut manifest = self.clone();
+        manifest.artifacts.sort_by(|a, b| a.name.cmp(&b.name));
+        manifest.destinations.sort();
+        Ok(serde_json::to_vec(&manifest)?)
+    }
+
+    /// Sign the manifest with gpg, using the default key unless `key` is set.
+    pub fn sign(self, key: Option<&str>) -> Result<SignedManifest, Error> {
+        let mut cmd = Command::new("gpg");
+        cmd.args(["--batch", "--armor", "--detach-sign"]);
+        if let Some(key) = key {
+            cmd.args(["--local-user", key]);
+        }
+        let mut child = cmd
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+        child
+            .stdin
+            .take()
+            .unwrap()
+            .write_all(&self.canonical_json()?)?;
+        let output = child.wait_with_output()?;
+        if !output.status.success() {
+            return Err(Error::SigningFailed(
+                String::from_utf8_lossy(&output.stderr).trim().to_string(),
+            ));
+        }
+        Ok(SignedManifest {
+            manifest: self,
+            signature: String::from_utf8_lossy(&output.stdout).into_owned(),
+        })
+    }
+}
+
+impl SignedManifest {
+    /// File name to use for the manifest of a release.
+    pub fn file_name(&self) -> String {
+        format!(
+            "{}-{}.manifest.json",
+            self.manifest.project, self.manifest.version
+        )
+    }
+
+    pub fn to_json(&self) -> Result<String, Error> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    pub fn from_json(text: &str) -> Result<Self, Error> {
+        Ok(serde_json::from_str(text)?)
+    }
+
+    /// Verify the signature with gpg, against the keys in the keyring.
+    pub fn verify_signature(&self) -> Result<(), Error> {
+        verify_detached_signature(&self.manifest.canonical_json()?, &self.signature)?;
+        Ok(())
+    }
+
+    /// Check that an artifact is listed in the manifest with the same digest.
+    pub fn verify_artifact(&self, path: &Path) -> Result<(), Error> {
+        let digest = ArtifactDigest::from_path(path)?;
+        match self
+            .manifest
+            .artifacts
+            .iter()
+            .find(|a| a.name == digest.name)
+        {
+            Some(a) if a.sha256 == digest.sha256 => Ok(()),
+            Some(a) => Err(Error::ArtifactMismatch {
+                name: digest.name,
+                reason: format!("sha256 is {}, expected {}", digest.sha256, a.sha256),
+            }),
+            None => Err(Error::ArtifactMismatch {
+                name: digest.name,
+                reason: "not listed".to_string(),
+            }),
+        }
+    }
+
+    /// Store the manifest in the state directory.
+    pub fn store(&self) -> Result<PathBuf, Box<dyn std::error::Error>> {
+        let xdg = xdg::BaseDirectories::with_prefix("disperse")?;
+        let path = xdg.place_state_file(Path::new(MANIFESTS_DIR_NAME).join(self.file_name()))?;
+        std::fs::write(&path, self.to_json()?)?;
+        Ok(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_valid_signature_fingerprints() {
+        let status = "[GNUPG:] NEWSIG\n\
+[GNUPG:] GOODSIG 00806F2BD729A457 Jelmer Vernooij <jelmer@jelmer.uk>\n\
+[GNUPG:] VALIDSIG 1234567890abcdef1234567890abcdef12345678 2024-01-01 1704067200 0 4 0 1 10 00 DC837EE14A7E37347E87061700806F2BD729A457\n\
+[GNUPG:] TRUST_UNDEFINED 0 pgp\n";
+        assert_eq!(
+            valid_signature_fingerprints(status),
+            vec![
+                "1234567890ABCDEF1234567890ABCDEF12345678".to_string(),
+                "DC837EE14A7E37347E87061700806F2BD729A457".to_string()
+            ]
+        );
+        assert_eq!(
+            valid_signature_fingerprints("[GNUPG:] BADSIG 00806F2BD729A457 Jelmer\n"),
+            Vec::<String>::new()
+        );
+    }
+
+    fn manifest() -> ReleaseManifest {
+        ReleaseManifest {
+            project: "foo".to_string(),
+            version: "1.2.3".to_string(),
+            tag: "v1.2.3".to_string(),
+            revision: "abcdef".to_string(),
+            artifacts: vec![
+                ArtifactDigest {
+                    name: "foo-1.2.3.tar.gz".to_string(),
+                    sha256: "aa".to_string(),
+                },
+                ArtifactDigest {
+                    name: "foo-1.2.3-py3-none-any.whl".to_string(),
+                    sha256: "bb".to_string(),
+                },
+            ],
+            destinations: vec!["https://pypi.org/project/foo/1.2.3/".to_string()],
+        }
+    }
+
+    #[test]
+    fn test_canonical_json() {
+        let m = manifest();
+        let mut reordered = m.clone();
+        reordered.artifacts.reverse();
+        assert_eq!(
+            m.canonical_json().unwrap(),
+            reordered.canonical_json().unwrap()
+        );
+        assert!(String::from_utf8(m.canonical_json().unwrap())
+            .unwrap()
+            .starts_with(r#"{"project":"foo","version":"1.2.3","tag":"v1.2.3","revision":"abcdef","artifacts":[{"name":"foo-1.2.3-py3-none-any.whl""#));
+    }
+
+    #[test]
+    fn test_verify_artifact() {
+        let td = tempfile::tempdir().unwrap();
+        let path = td.path().join("foo-1.2.3.tar.gz");
+        std::fs::write(&path, b"contents").unwrap();
+        let mut m = manifest();
+        m.artifacts[0] = ArtifactDigest::from_path(&path).unwrap();
+        let signed = SignedManifest {
+            manifest: m,
+            signature: String::new(),
+        };
+        signed.verify_artifact(&path).unwrap();
+        std::fs::write(&path, b"tampered").unwrap();
+        assert!(matches!(
+            signed.verify_artifact(&path),
+            Err(Error::ArtifactMismatch { .. })
+        ));
+    }
+}
@@ -1,6 +1,7 @@
 use crate::Version;
 use breezyshim::tree::MutableTree;
-use chrono::NaiveDate;
+use chrono::{Datelike, NaiveDate};
+use lazy_regex::regex;
 use regex::Regex;
 use std::str::FromStr;
 
@@ -12,6 +13,7 @@ pub enum Error {
     BrzError(breezyshim::error::Error),
     IoError(std::io::Error),
     InvalidRegex(regex::Error),
+    UnknownLocale(String),
     NoMatches,
 }
 
@@ -39,6 +41,7 @@ impl std::fmt::Display for Error {
             Error::BrzError(e) => write!(f, "TreeError: {}", e),
             Error::IoError(e) => write!(f, "IoError: {}", e),
             Error::InvalidRegex(e) => write!(f, "InvalidRegex: {}", e),
+            Error::UnknownLocale(l) => write!(f, "No month names known for locale {}", l),
             Error::NoMatches => write!(f, "NoMatches"),
         }
     }
@@ -46,72 +49,287 @@ impl std::fmt::Display for Error {
 
 impl std::error::Error for Error {}
 
-/// Update the version in a manpage.
-pub fn update_version_in_manpage(
-    tree: &dyn MutableTree,
-    path: &Path,
+/// Month names, by language.
+const MONTH_NAMES: &[(&str, [&str; 12])] = &[
+    (
+        "en",
+        [
+            "January",
+            "February",
+            "March",
+            "April",
+            "May",
+            "June",
+            "July",
+            "August",
+            "September",
+            "October",
+            "November",
+            "December",
+        ],
+    ),
+    (
+        "de",
+        [
+            "Januar",
+            "Februar",
+            "März",
+            "April",
+            "Mai",
+            "Juni",
+            "Juli",
+            "August",
+            "September",
+            "Oktober",
+            "November",
+            "Dezember",
+        ],
+    ),
+    (
+        "es",
+        [
+            "enero",
+            "febrero",
+            "marzo",
+            "abril",
+            "mayo",
+            "junio",
+            "julio",
+            "agosto",
+            "septiembre",
+            "octubre",
+            "noviembre",
+            "diciembre",
+        ],
+    ),
+    (
+        "fr",
+        [
+            "janvier",
+            "février",
+            "mars",
+            "avril",
+            "mai",
+            "juin",
+            "juillet",
+            "août",
+            "septembre",
+            "octobre",
+            "novembre",
+            "décembre",
+        ],
+    ),
+    (
+        "it",
+        [
+            "gennaio",
+            "febbraio",
+            "marzo",
+            "aprile",
+            "maggio",
+            "giugno",
+            "luglio",
+            "agosto",
+            "settembre",
+            "ottobre",
+            "novembre",
+            "dicembre",
+        ],
+    ),
+    (
+        "nl",
+        [
+            "januari",
+            "februari",
+            "maart",
+            "april",
+            "mei",
+            "juni",
+            "juli",
+            "augustus",
+            "september",
+            "oktober",
+            "november",
+            "december",
+        ],
+    ),
+    (
+        "pt",
+        [
+            "janeiro",
+            "fevereiro",
+            "março",
+            "abril",
+            "maio",
+            "junho",
+            "julho",
+            "agosto",
+            "setembro",
+            "outubro",
+            "novembro",
+            "dezembro",
+        ],
+    ),
+];
+
+/// Look up the month names for a locale such as "fr" or "fr_FR.UTF-8".
+pub fn month_names(locale: &str) -> Option<&'static [&'static str; 12]> {
+    let language = locale.split(['_', '.', '@', '-']).next().unwrap_or(locale);
+    MONTH_NAMES
+        .iter()
+        .find(|(l, _)| l.eq_ignore_ascii_case(language))
+        .map(|(_, names)| names)
+}
+
+/// Find the language of a month name.
+fn month_language(name: &str) -> Option<&'static str> {
+    let name = name.to_lowercase();
+    MONTH_NAMES
+        .iter()
+        .find(|(_, names)| names.iter().any(|n| n.to_lowercase() == name))
+        .map(|(l, _)| *l)
+}
+
+/// Format a date, using localized month names for %B.
+pub fn format_date(date: NaiveDate, format: &str, month_names: &[&str; 12]) -> String {
+    let format = format.replace("%B", month_names[date.month0() as usize]);
+    date.format(&format).to_string()
+}
+
+/// Guess the format and language of the date in a .TH line.
+pub fn detect_date_format(date: &str) -> Option<(&'static str, &'static str)> {
+    if regex!(r"^[0-9]{4}-[0-1][0-9]-[0-3][0-9]$").is_match(date) {
+        return Some(("%Y-%m-%d", "en"));
+    }
+    if let Some(c) = regex!(r"^(\p{L}+) [0-9]{4}$").captures(date) {
+        return month_language(&c[1]).map(|l| ("%B %Y", l));
+    }
+    if let Some(c) = regex!(r"^[0-9]{1,2}\.? (\p{L}+) [0-9]{4}$").captures(date) {
+        let format = if date.contains('.') {
+            "%-d. %B %Y"
+        } else {
+            "%-d %B %Y"
+        };
+        return month_language(&c[1]).map(|l| (format, l));
+    }
+    if let Some(c) = regex!(r"^(\p{L}+) [0-9]{1,2}, [0-9]{4}$").captures(date) {
+        return month_language(&c[1]).map(|l| ("%B %-d, %Y", l));
+    }
+    None
+}
+
+/// Quote a macro argument for roff, which (unlike a shell) only knows about double quotes.
+fn roff_quote(arg: &str) -> String {
+    if arg.is_empty() || arg.contains([' ', '"']) {
+        format!("\"{}\"", arg.replace('"', "\"\""))
+    } else {
+        arg.to_string()
+    }
+}
+
+/// Which fields in the .TH line of a manpage were updated.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ManpageUpdate {
+    pub date: bool,
+    pub version: bool,
+}
+
+/// Update the version and date in the .TH line of a manpage.
+///
+/// If no date format is specified, it is guessed from the existing date. If
+/// the locale is not specified, it is guessed from the existing month name.
+pub fn update_manpage_text(
+    text: &str,
     new_version: &Version,
     release_date: NaiveDate,
-) -> Result<(), Error> {
-    let file = tree.get_file(path)?;
-
-    let mut lines = BufReader::new(file)
-        .split(b'\n')
-        .collect::<Result<Vec<_>, _>>()?;
+    date_format: Option<&str>,
+    locale: Option<&str>,
+) -> Result<(String, ManpageUpdate), Error> {
+    let configured_month_names = locale
+        .map(|l| month_names(l).ok_or_else(|| Error::UnknownLocale(l.to_string())))
+        .transpose()?;
 
-    let date_options: Vec<(&str, &str)> = vec![
-        (r"20[0-9][0-9]-[0-1][0-9]-[0-3][0-9]", "%Y-%m-%d"),
-        (r"[A-Za-z]+ ([0-9]{4})", "%B %Y"),
-    ];
+    let version_options: Vec<(&str, &str)> = vec![(r"([^ ]+) ([0-9a-z.]+)", r"${1} $VERSION")];
 
-    let version_options: Vec<(&str, &str)> = vec![(r"([^ ]+) ([0-9a-z.]+)", r"\1 $VERSION")];
+    let mut lines = text.split('\n').map(|l| l.to_string()).collect::<Vec<_>>();
+    let mut update = None;
 
-    for (i, line) in lines.iter_mut().enumerate() {
-        if !line.starts_with(&b".TH "[..]) {
+    for line in lines.iter_mut() {
+        if !line.starts_with(".TH ") {
             continue;
         }
 
-        let mut args = match shlex::split(String::from_utf8_lossy(line).as_ref()) {
+        let mut args = match shlex::split(line) {
             Some(args) => args,
             None => continue,
         };
+        let mut result = ManpageUpdate::default();
 
-        // Iterate through date options
-        for (r, f) in &date_options {
-            let re = Regex::new(r)?;
-            if let Some(_captures) = re.captures(&args[3]) {
-                let formatted_date = release_date.format(f).to_string();
-                args[3] = formatted_date;
-                break;
+        if let Some(date) = args.get_mut(3) {
+            let detected = detect_date_format(date);
+            let format = date_format.or(detected.map(|(f, _)| f));
+            let names = configured_month_names
+                .or_else(|| detected.and_then(|(_, l)| month_names(l)))
+                .unwrap_or(&MONTH_NAMES[0].1);
+            if let Some(format) = format {
+                *date = format_date(release_date, format, names);
+                result.date = true;
             }
         }
 
         // Iterate through version options
-        for (r, f) in &version_options {
-            let re = Regex::new(r)?;
-            if let Some(captures) = re.captures(&args[4]) {
-                let version_str = captures.get(0).unwrap().as_str();
-                let formatted_version = re.replace(
-                    version_str,
-                    f.replace("$VERSION", new_version.to_string().as_str()),
-                );
-                args[4] = formatted_version.to_string();
-                break;
+        if let Some(version) = args.get_mut(4) {
+            for (r, f) in &version_options {
+                let re = Regex::new(r)?;
+                if let Some(captures) = re.captures(version) {
+                    let version_str = captures.get(0).unwrap().as_str();
+                    let formatted_version = re.replace(
+                        version_str,
+                        f.replace("$VERSION", new_version.to_string().as_str()),
+                    );
+                    *version = formatted_version.to_string();
+                    result.version = true;
+                    break;
+                }
             }
         }
 
-        let updated_line = shlex::try_join(args.iter().map(|s| s.as_ref())).unwrap();
-        lines[i] = updated_line.into_bytes();
+        *line = args
+            .iter()
+            .map(|arg| roff_quote(arg))
+            .collect::<Vec<_>>()
+            .join(" ");
+        update = Some(result);
         break;
     }
 
-    if lines.iter().all(|line| !line.starts_with(&b".TH "[..])) {
-        return Err(Error::NoMatches);
+    match update {
+        Some(update) => Ok((lines.join("\n"), update)),
+        None => Err(Error::NoMatches),
     }
+}
 
-    tree.put_file_bytes_non_atomic(path, &lines.concat())?;
+/// Update the version in a manpage.
+pub fn update_version_in_manpage(
+    tree: &dyn MutableTree,
+    path: &Path,
+    new_version: &Version,
+    release_date: NaiveDate,
+    date_format: Option<&str>,
+    locale: Option<&str>,
+) -> Result<ManpageUpdate, Error> {
+    let text = tree.get_file_text(path)?;
+    let (updated, update) = update_manpage_text(
+        &String::from_utf8_lossy(&text),
+        new_version,
+        release_date,
+        date_format,
+        locale,
+    )?;
 
-    Ok(())
+    tree.put_file_bytes_non_atomic(path, updated.as_bytes())?;
+
+    Ok(update)
 }
 
 /// Validate that a manpage is updateable.
@@ -164,4 +382,56 @@ mod tests {
         let b = b".TH BZR 1 \"2019-12-31\" \"Bazaar 2.7.0\" \"Bazaar Reference Manual\"\n";
         super::validate_manpage_updateable(&mut std::io::Cursor::new(b)).unwrap();
     }
+
+    fn date() -> chrono::NaiveDate {
+        chrono::NaiveDate::from_ymd_opt(2024, 2, 3).unwrap()
+    }
+
+    #[test]
+    fn test_update_manpage_text() {
+        let text = ".\\\" comment\n.TH BZR 1 \"2019-12-31\" \"Bazaar 2.7.0\" \"Bazaar Reference Manual\"\n.SH NAME\n";
+        let (updated, update) =
+            super::update_manpage_text(text, &"2.8.0".parse().unwrap(), date(), None, None)
+                .unwrap();
+        assert_eq!(
+            updated,
+            ".\\\" comment\n.TH BZR 1 2024-02-03 \"Bazaar 2.8.0\" \"Bazaar Reference Manual\"\n.SH NAME\n"
+        );
+        assert_eq!(
+            update,
+            super::ManpageUpdate {
+                date: true,
+                version: true
+            }
+        );
+    }
+
+    #[test]
+    fn test_update_manpage_text_localized() {
+        let text = ".TH FOO 1 \"décembre 2023\" \"foo 0.1\"\n";
+        let (updated, _) =
+            super::update_manpage_text(text, &"0.2".parse().unwrap(), date(), None, None).unwrap();
+        assert_eq!(updated, ".TH FOO 1 \"février 2024\" \"foo 0.2\"\n");
+
+        let text = ".TH FOO 1 \"3 Dezember 2023\" \"foo 0.1\"\n";
+        let (updated, _) = super::update_manpage_text(
+            text,
+            &"0.2".parse().unwrap(),
+            date(),
+            Some("%d/%B/%Y"),
+            Some("fr_FR.UTF-8"),
+        )
+        .unwrap();
+        assert_eq!(updated, ".TH FOO 1 03/février/2024 \"foo 0.2\"\n");
+    }
+
+    #[test]
+    fn test_update_manpage_text_unknown_date() {
+        let text = ".TH FOO 1 \"sometime\" \"foo 0.1\"\n";
+        let (updated, update) =
+            super::update_manpage_text(text, &"0.2".parse().unwrap(), date(), None, None).unwrap();
+        assert_eq!(updated, ".TH FOO 1 sometime \"foo 0.2\"\n");
+        assert!(!update.date);
+        assert!(update.version);
+    }
 }
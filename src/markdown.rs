@@ -0,0 +1,144 @@
+/// Whether a line is a reStructuredText section underline.
+fn is_underline(line: &str) -> bool {
+    let line = line.trim_end();
+    line.len() >= 3
+        && line
+            .chars()
+            .next()
+            .is_some_and(|c| "=-~^\"'`*+#".contains(c) && line.chars().all(|x| x == c))
+}
+
+fn indentation(line: &str) -> usize {
+    line.len() - line.trim_start().len()
+}
+
+/// Convert the bullet of a list item to "* ".
+fn normalize_bullet(line: &str) -> String {
+    let indent = &line[..indentation(line)];
+    let rest = line.trim_start();
+    for bullet in ["- ", "+ ", "o ", "• "] {
+        if let Some(item) = rest.strip_prefix(bullet) {
+            return format!("{}* {}", indent, item);
+        }
+    }
+    line.to_string()
+}
+
+/// Convert common reStructuredText inline markup to Markdown.
+fn convert_inline(line: &str) -> String {
+    // Roles, e.g. :func:`foo` or :ref:`title <target>`
+    let line = lazy_regex::regex_replace_all!(
+        r"(?::[a-z]+)+:`([^`<]+?)(?:\s*<[^>]*>)?`",
+        line,
+        |_, text: &str| format!("`{}`", text)
+    );
+    // Hyperlinks with an inline target
+    let line = lazy_regex::regex_replace_all!(
+        r"`([^`<]+?)\s*<([^>`]+)>`__?",
+        &line,
+        |_, text: &str, url: &str| format!("[{}]({})", text, url)
+    );
+    // References to targets defined elsewhere
+    let line =
+        lazy_regex::regex_replace_all!(r"`([^`]+)`__?", &line, |_, text: &str| text.to_string());
+    // Inline literals
+    lazy_regex::regex_replace_all!(r"``(.+?)``", &line, |_, text: &str| format!("`{}`", text))
+        .into_owned()
+}
+
+/// Convert release notes written as plain text or reStructuredText to Markdown.
+///
+/// This handles bullets, section titles, literal blocks and common inline
+/// markup; anything else is left alone.
+pub fn from_rst(text: &str) -> String {
+    let lines = text.lines().collect::<Vec<_>>();
+    let mut ret: Vec<String> = vec![];
+    let mut i = 0;
+    while i < lines.len() {
+        let line = lines[i];
+        i += 1;
+        if is_underline(line) && ret.last().is_some_and(|l| !l.trim().is_empty()) {
+            let title = ret.pop().unwrap();
+            ret.push(format!("### {}", title.trim()));
+            continue;
+        }
+        let Some(prefix) = line.trim_end().strip_suffix("::") else {
+            ret.push(convert_inline(&normalize_bullet(line)));
+            continue;
+        };
+        // A paragraph ending in "::" introduces a literal block.
+        if !prefix.trim().is_empty() {
+            let prefix = if prefix.ends_with(' ') {
+                prefix.trim_end().to_string()
+            } else {
+                format!("{}:", prefix)
+            };
+            ret.push(convert_inline(&normalize_bullet(&prefix)));
+        }
+        let base = indentation(line);
+        let mut block = vec![];
+        while let Some(next) = lines.get(i) {
+            if !next.trim().is_empty() && indentation(next) <= base {
+                break;
+            }
+            block.push(*next);
+            i += 1;
+        }
+        while block.last().is_some_and(|l| l.trim().is_empty()) {
+            block.pop();
+            i -= 1;
+        }
+        let block = block
+            .into_iter()
+            .skip_while(|l| l.trim().is_empty())
+            .collect::<Vec<_>>();
+        let Some(indent) = block
+            .iter()
+            .filter(|l| !l.trim().is_empty())
+            .map(|l| indentation(l))
+            .min()
+        else {
+            continue;
+        };
+        ret.push(String::new());
+        ret.push("```".to_string());
+        ret.extend(
+            block
+                .iter()
+                .map(|l| l.get(indent..).unwrap_or_default().to_string()),
+        );
+        ret.push("```".to_string());
+    }
+    let mut text = ret.join("\n");
+    text.push('\n');
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_convert_inline() {
+        assert_eq!(
+            convert_inline("Use ``foo()`` instead of :func:`bar`."),
+            "Use `foo()` instead of `bar`."
+        );
+        assert_eq!(
+            convert_inline("See `the docs <https://example.com/>`_ and :ref:`usage <usage>`."),
+            "See [the docs](https://example.com/) and `usage`."
+        );
+        assert_eq!(convert_inline("Fixes `#12`_."), "Fixes #12.");
+    }
+
+    #[test]
+    fn test_from_rst() {
+        assert_eq!(
+            from_rst(
+                "Highlights\n----------\n\n- Add ``frob``::\n\n    frob --all\n\n+ Fix crash.\n"
+            ),
+            "### Highlights\n\n* Add `frob`:\n\n```\nfrob --all\n```\n\n* Fix crash.\n"
+        );
+        assert_eq!(from_rst("* Fix crash.\n"), "* Fix crash.\n");
+    }
+}
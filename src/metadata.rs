@@ -0,0 +1,348 @@
+use breezyshim::tree::Tree;
+use serde_json::Value;
+use std::path::Path;
+
+#[derive(Debug)]
+pub enum Error {
+    BrzError(breezyshim::error::Error),
+    HttpError(reqwest::Error),
+    ParseError(String),
+}
+
+impl From<breezyshim::error::Error> for Error {
+    fn from(e: breezyshim::error::Error) -> Self {
+        Error::BrzError(e)
+    }
+}
+
+impl From<reqwest::Error> for Error {
+    fn from(e: reqwest::Error) -> Self {
+        Error::HttpError(e)
+    }
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match &self {
+            Error::BrzError(e) => write!(f, "TreeError: {}", e),
+            Error::HttpError(e) => write!(f, "HttpError: {}", e),
+            Error::ParseError(e) => write!(f, "ParseError: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// Registry that a project is published to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Registry {
+    CratesIo,
+    PyPI,
+    Npm,
+}
+
+impl std::fmt::Display for Registry {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str(match self {
+            Registry::CratesIo => "crates.io",
+            Registry::PyPI => "PyPI",
+            Registry::Npm => "npm",
+        })
+    }
+}
+
+/// Package metadata that is shown on registry pages.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Metadata {
+    pub description: Option<String>,
+    pub homepage: Option<String>,
+    pub license: Option<String>,
+}
+
+/// A metadata field that differs between the registry and the repository.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Drift {
+    pub field: &'static str,
+    pub registry: Option<String>,
+    pub repository: String,
+}
+
+fn normalize(field: &str, value: &str) -> String {
+    let value = value.split_whitespace().collect::<Vec<_>>().join(" ");
+    if field == "homepage" {
+        value.trim_end_matches('/').to_string()
+    } else {
+        value
+    }
+}
+
+/// Compare registry metadata with the repository's.
+///
+/// Fields the repository doesn't set (e.g. because they're inherited from
+/// a workspace) are not compared.
+pub fn compare(registry: &Metadata, repository: &Metadata) -> Vec<Drift> {
+    [
+        (
+            "description",
+            &registry.description,
+            &repository.description,
+        ),
+        ("homepage", &registry.homepage, &repository.homepage),
+        ("license", &registry.license, &repository.license),
+    ]
+    .into_iter()
+    .filter_map(|(field, registry, repository)| {
+        let repository = repository.as_deref()?;
+        if registry.as_deref().map(|r| normalize(field, r)) == Some(normalize(field, repository)) {
+            return None;
+        }
+        Some(Drift {
+            field,
+            registry: registry.clone(),
+            repository: repository.to_string(),
+        })
+    })
+    .collect()
+}
+
+fn json_str(value: &Value, key: &str) -> Option<String> {
+    value
+        .get(key)?
+        .as_str()
+        .filter(|s| !s.is_empty())
+        .map(|s| s.to_string())
+}
+
+/// Find the homepage in a table of project URLs.
+fn homepage_url<'a>(urls: impl IntoIterator<Item = (&'a str, Option<&'a str>)>) -> Option<String> {
+    urls.into_iter()
+        .find(|(k, _)| k.eq_ignore_ascii_case("homepage"))
+        .and_then(|(_, v)| v.map(|s| s.to_string()))
+}
+
+/// Read the metadata from the [package] table of a Cargo.toml file.
+pub fn cargo_toml_metadata(contents: &str) -> Result<Metadata, Error> {
+    let doc: toml::Table = contents
+        .parse()
+        .map_err(|e: toml::de::Error| Error::ParseError(e.to_string()))?;
+    let package = doc.get("package");
+    let field = |key| {
+        package
+            .and_then(|p| p.get(key))
+            .and_then(|v| v.as_str())
+            .map(|s| s.to_string())
+    };
+    Ok(Metadata {
+        description: field("description"),
+        homepage: field("homepage"),
+        license: field("license"),
+    })
+}
+
+/// Read the metadata from the [project] table of a pyproject.toml file.
+pub fn pyproject_toml_metadata(contents: &str) -> Result<Metadata, Error> {
+    let doc: toml::Table = contents
+        .parse()
+        .map_err(|e: toml::de::Error| Error::ParseError(e.to_string()))?;
+    let Some(project) = doc.get("project") else {
+        return Ok(Metadata::default());
+    };
+    Ok(Metadata {
+        description: project
+            .get("description")
+            .and_then(|v| v.as_str())
+            .map(|s| s.to_string()),
+        homepage: project
+            .get("urls")
+            .and_then(|u| u.as_table())
+            .and_then(|u| homepage_url(u.iter().map(|(k, v)| (k.as_str(), v.as_str())))),
+        license: match project.get("license") {
+            Some(toml::Value::String(s)) => Some(s.clone()),
+            Some(toml::Value::Table(t)) => t
+                .get("text")
+                .and_then(|v| v.as_str())
+                .map(|s| s.to_string()),
+            _ => None,
+        },
+    })
+}
+
+/// Read the metadata from a package.json file.
+pub fn package_json_metadata(package_json: &Value) -> Metadata {
+    Metadata {
+        description: json_str(package_json, "description"),
+        homepage: json_str(package_json, "homepage"),
+        license: json_str(package_json, "license"),
+    }
+}
+
+/// Read the metadata of the project in a tree, for each registry it's
+/// published to.
+pub fn repository_metadata(tree: &dyn Tree) -> Result<Vec<(Registry, Metadata)>, Error> {
+    let mut ret = vec![];
+    let read = |path: &str| -> Result<Option<String>, Error> {
+        if !tree.has_filename(Path::new(path)) {
+            return Ok(None);
+        }
+        let text = tree.get_file_text(Path::new(path))?;
+        Ok(Some(String::from_utf8_lossy(&text).into_owned()))
+    };
+    if let Some(contents) = read("Cargo.toml")? {
+        ret.push((Registry::CratesIo, cargo_toml_metadata(&contents)?));
+    }
+    if let Some(contents) = read("pyproject.toml")? {
+        ret.push((Registry::PyPI, pyproject_toml_metadata(&contents)?));
+    }
+    if let Some(contents) = read("package.json")? {
+        let package_json: Value = serde_json::from_str(&contents)
+            .map_err(|e| Error::ParseError(format!("package.json: {}", e)))?;
+        ret.push((Registry::Npm, package_json_metadata(&package_json)));
+    }
+    Ok(ret)
+}
+
+fn fetch_json(client: &reqwest::blocking::Client, url: &str) -> Result<Option<Value>, Error> {
+    let response = client.get(url).send()?;
+    if response.status() == reqwest::StatusCode::NOT_FOUND {
+        return Ok(None);
+    }
+    Ok(Some(response.error_for_status()?.json()?))
+}
+
+/// Look up the metadata of the latest release of a package on a registry.
+///
+/// Returns None if the package hasn't been published.
+pub fn registry_metadata(registry: Registry, name: &str) -> Result<Option<Metadata>, Error> {
+    let client = reqwest::blocking::ClientBuilder::new()
+        .user_agent(crate::USER_AGENT)
+        .build()?;
+    let _permit = match registry {
+        Registry::CratesIo => Some(crate::limits::acquire_blocking(
+            crate::limits::Service::CratesIo,
+        )),
+        Registry::PyPI => Some(crate::limits::acquire_blocking(
+            crate::limits::Service::PyPI,
+        )),
+        Registry::Npm => None,
+    };
+    Ok(match registry {
+        Registry::CratesIo => fetch_json(
+            &client,
+            &format!("https://crates.io/api/v1/crates/{}", name),
+        )?
+        .map(|data| {
+            let krate = &data["crate"];
+            let newest = krate["newest_version"].as_str();
+            Metadata {
+                description: json_str(krate, "description"),
+                homepage: json_str(krate, "homepage"),
+                license: data["versions"]
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .find(|v| v["num"].as_str() == newest)
+                    .and_then(|v| json_str(v, "license")),
+            }
+        }),
+        Registry::PyPI => fetch_json(&client, &format!("https://pypi.org/pypi/{}/json", name))?
+            .map(|data| {
+                let info = &data["info"];
+                Metadata {
+                    description: json_str(info, "summary"),
+                    homepage: info["project_urls"]
+                        .as_object()
+                        .and_then(|u| homepage_url(u.iter().map(|(k, v)| (k.as_str(), v.as_str()))))
+                        .or_else(|| json_str(info, "home_page")),
+                    license: json_str(info, "license_expression")
+                        .or_else(|| json_str(info, "license")),
+                }
+            }),
+        Registry::Npm => fetch_json(
+            &client,
+            &format!("https://registry.npmjs.org/{}/latest", name),
+        )?
+        .map(|data| package_json_metadata(&data)),
+    })
+}
+
+/// Compare the metadata of the last published release of a project with
+/// the metadata in its repository, for each registry it's published to.
+pub fn check_drift(tree: &dyn Tree, name: &str) -> Result<Vec<(Registry, Vec<Drift>)>, Error> {
+    let mut ret = vec![];
+    for (registry, repository) in repository_metadata(tree)? {
+        match registry_metadata(registry, name)? {
+            Some(published) => ret.push((registry, compare(&published, &repository))),
+            None => log::debug!("{} has not been published to {}", name, registry),
+        }
+    }
+    Ok(ret)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compare() {
+        let registry = Metadata {
+            description: Some("A  frobnicator".to_string()),
+            homepage: Some("https://example.com/".to_string()),
+            license: Some("GPL-2.0+".to_string()),
+        };
+        let repository = Metadata {
+            description: Some("A frobnicator".to_string()),
+            homepage: Some("https://example.com".to_string()),
+            license: Some("Apache-2.0".to_string()),
+        };
+        assert_eq!(
+            compare(&registry, &repository),
+            vec![Drift {
+                field: "license",
+                registry: Some("GPL-2.0+".to_string()),
+                repository: "Apache-2.0".to_string(),
+            }]
+        );
+        assert_eq!(compare(&registry, &Metadata::default()), vec![]);
+    }
+
+    #[test]
+    fn test_pyproject_toml_metadata() {
+        assert_eq!(
+            pyproject_toml_metadata(
+                r#"[project]
+name = "foo"
+description = "Frobnicates"
+license = { text = "MIT" }
+
+[project.urls]
+Homepage = "https://example.com"
+"#
+            )
+            .unwrap(),
+            Metadata {
+                description: Some("Frobnicates".to_string()),
+                homepage: Some("https://example.com".to_string()),
+                license: Some("MIT".to_string()),
+            }
+        );
+    }
+
+    #[test]
+    fn test_cargo_toml_metadata() {
+        assert_eq!(
+            cargo_toml_metadata(
+                r#"[package]
+name = "foo"
+description = "Frobnicates"
+license.workspace = true
+"#
+            )
+            .unwrap(),
+            Metadata {
+                description: Some("Frobnicates".to_string()),
+                homepage: None,
+                license: None,
+            }
+        );
+    }
+}
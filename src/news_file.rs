@@ -1,5 +1,5 @@
 use crate::Version;
-use breezyshim::tree::MutableTree;
+use breezyshim::tree::{MutableTree, Tree};
 use lazy_regex::regex_is_match;
 
 fn date_is_placeholder(d: &str) -> bool {
@@ -11,45 +11,359 @@ fn check_version(v: &str) -> Result<bool, Error> {
         return Ok(true);
     }
 
-    if !regex_is_match!(r"^[0-9\.]+$", v) {
+    if !regex_is_match!(
+        r"^([0-9]+!)?[0-9\.]+((a|b|rc)[0-9]*|-[0-9A-Za-z\.-]+)?(\.post[0-9]+)?(\.dev[0-9]+)?(\+[0-9A-Za-z\.-]+)?$",
+        v
+    ) {
         return Err(Error::OddVersion(v.to_string()));
     }
 
     Ok(false)
 }
 
+/// Date formats that are recognized in existing news files; the first is the default.
+const DATE_FORMATS: &[&str] = &[
+    "%Y-%m-%d",
+    "%d %b %Y",
+    "%-d %b %Y",
+    "%d %B %Y",
+    "%-d %B %Y",
+    "%b %-d, %Y",
+    "%B %-d, %Y",
+    "%Y/%m/%d",
+    "%d.%m.%Y",
+    "%b %Y",
+    "%B %Y",
+];
+
+/// Check whether a date is written in a strftime format.
+///
+/// Formats without a day of the month (e.g. "January 2024") are accepted as well.
+fn date_matches_format(date: &str, format: &str) -> bool {
+    let parsed = if format.contains('d') {
+        chrono::NaiveDate::parse_from_str(date, format)
+    } else {
+        chrono::NaiveDate::parse_from_str(&format!("1 {}", date), &format!("%d {}", format))
+    };
+    // Parsing is lenient about e.g. abbreviated month names, so check that
+    // the date would be written the same way.
+    parsed.is_ok_and(|d| d.format(format).to_string() == date)
+}
+
 pub fn expand_template(template: &str, version: &Version, date: &str) -> String {
     template
         .replace("%(version)s", version.to_string().as_str())
         .replace("%(date)s", date)
 }
 
-pub fn skip_header<'a, I: Iterator<Item = &'a [u8]>>(iter: &mut std::iter::Peekable<I>) -> usize {
-    let mut i = 0;
-    while let Some(line) = iter.peek() {
-        if line.starts_with(b"Changelog for ") {
-            iter.next();
-            i += 1;
+/// Explicitly configured layout of a news file.
+///
+/// Without a template, the format of version lines is inferred from the
+/// first entry in the file.
+#[derive(Debug, Clone, Default)]
+pub struct NewsLayout {
+    template: Option<(String, regex::Regex)>,
+    skip: Vec<regex::Regex>,
+    /// strftime format for release dates; inferred from earlier entries if unset.
+    date_format: Option<String>,
+    /// Distribution to release entries to, if the news file is a Debian changelog.
+    distribution: Option<String>,
+}
+
+impl NewsLayout {
+    /// Create a layout from a version line template and patterns for
+    /// additional header lines to skip.
+    pub fn new(template: Option<&str>, skip: &[String]) -> Result<Self, Error> {
+        let template = template
+            .map(|t| template_regex(t).map(|re| (t.to_string(), re)))
+            .transpose()?;
+        let skip = skip
+            .iter()
+            .map(|s| {
+                regex::Regex::new(s)
+                    .map_err(|e| Error::InvalidData(format!("Invalid skip pattern {}: {}", s, e)))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Self {
+            template,
+            skip,
+            date_format: None,
+            distribution: None,
+        })
+    }
+
+    /// Write release dates in a specific strftime format.
+    pub fn with_date_format(mut self, date_format: &str) -> Result<Self, Error> {
+        if chrono::format::StrftimeItems::new(date_format)
+            .any(|i| matches!(i, chrono::format::Item::Error))
+        {
+            return Err(Error::InvalidData(format!(
+                "Invalid date format {:?}",
+                date_format
+            )));
+        }
+        self.date_format = Some(date_format.to_string());
+        Ok(self)
+    }
+
+    /// Create a layout from the news settings in a project configuration.
+    pub fn from_config(cfg: &crate::project_config::ProjectConfig) -> Result<Self, Error> {
+        let mut layout = Self::new(
+            cfg.news_format.as_deref(),
+            cfg.news_skip.as_deref().unwrap_or_default(),
+        )?;
+        layout.distribution = cfg.debian.as_ref().and_then(|d| d.distribution.clone());
+        match cfg.news_date_format.as_deref() {
+            Some(date_format) => layout.with_date_format(date_format),
+            None => Ok(layout),
+        }
+    }
+
+    /// The format to write release dates in, based on the dates of earlier
+    /// releases unless it was set explicitly.
+    fn date_format(&self, dates: &[&str]) -> &str {
+        if let Some(date_format) = self.date_format.as_deref() {
+            return date_format;
+        }
+        dates
+            .iter()
+            .find_map(|d| {
+                DATE_FORMATS
+                    .iter()
+                    .find(|f| date_matches_format(d, f))
+                    .copied()
+            })
+            .unwrap_or(DATE_FORMATS[0])
+    }
+
+    fn parse_version_line<'b>(&self, line: &'b str) -> Result<VersionLine<'b>, Error> {
+        match self.template.as_ref() {
+            Some((template, re)) => parse_version_line_with_template(line, template, re),
+            None => parse_version_line(line),
+        }
+    }
+}
+
+/// Build a regular expression matching lines generated from a template.
+fn template_regex(template: &str) -> Result<regex::Regex, Error> {
+    if !template.contains("%(version)s") {
+        return Err(Error::InvalidData(format!(
+            "News format {:?} does not contain %(version)s",
+            template
+        )));
+    }
+    let mut pattern = "^".to_string();
+    let mut rest = template;
+    while let Some(idx) = rest.find("%(") {
+        pattern.push_str(&regex::escape(&rest[..idx]));
+        rest = &rest[idx..];
+        if let Some(r) = rest.strip_prefix("%(version)s") {
+            pattern.push_str(r"(?P<version>\S+?)");
+            rest = r;
+        } else if let Some(r) = rest.strip_prefix("%(date)s") {
+            pattern.push_str("(?P<date>.+?)");
+            rest = r;
+        } else {
+            pattern.push_str(&regex::escape("%("));
+            rest = &rest[2..];
+        }
+    }
+    pattern.push_str(&regex::escape(rest));
+    pattern.push('$');
+    regex::Regex::new(&pattern).map_err(|e| Error::InvalidData(e.to_string()))
+}
+
+/// Characters used to underline section titles in reStructuredText.
+const UNDERLINE_CHARS: &[u8] = b"=-~^\"'`*+#";
+
+/// Return the character a line consists of, if it is a section underline.
+fn underline_char(line: &[u8]) -> Option<u8> {
+    let line = line.trim_ascii_end();
+    let c = *line.first()?;
+    if line.len() >= 3 && UNDERLINE_CHARS.contains(&c) && line.iter().all(|&x| x == c) {
+        Some(c)
+    } else {
+        None
+    }
+}
+
+/// Generate an underline of the right length for a section title.
+fn underline_for(c: u8, title: &str) -> Vec<u8> {
+    let mut ret = String::from(c as char)
+        .repeat(title.trim().chars().count())
+        .into_bytes();
+    ret.push(b'\n');
+    ret
+}
+
+/// Names of the category subsections that changes in an entry can be
+/// grouped under.
+pub const NEWS_CATEGORIES: &[&str] = &[
+    "Breaking changes",
+    "Added",
+    "Features",
+    "Changed",
+    "Deprecated",
+    "Removed",
+    "Fixed",
+    "Bug fixes",
+    "Performance improvements",
+    "Security",
+    "Documentation",
+];
+
+/// Return the name of the category a line is the heading of, if any.
+///
+/// Headings may be indented, and can be written as e.g. "Added", "Added:"
+/// or "### Added".
+fn category_heading(line: &str) -> Option<&str> {
+    let name = line
+        .trim()
+        .trim_start_matches('#')
+        .trim_end_matches(':')
+        .trim();
+    if NEWS_CATEGORIES.iter().any(|c| c.eq_ignore_ascii_case(name)) {
+        Some(name)
+    } else {
+        None
+    }
+}
+
+/// A group of changes in a news entry.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NewsCategory {
+    /// Name of the category; `None` for changes before the first heading.
+    pub name: Option<String>,
+    /// Non-blank lines listing the changes.
+    pub lines: Vec<String>,
+}
+
+/// Split the changes of a news entry into its category subsections.
+pub fn news_categories(changes: &str) -> Vec<NewsCategory> {
+    let mut ret = vec![NewsCategory {
+        name: None,
+        lines: vec![],
+    }];
+    let mut after_heading = false;
+    for line in changes.lines() {
+        if line.trim().is_empty() {
             continue;
         }
-        if line.ends_with(b" release notes") {
-            iter.next();
-            i += 1;
+        if after_heading && underline_char(line.trim().as_bytes()).is_some() {
+            after_heading = false;
             continue;
         }
-        if line.iter().all(|&x| x == b'=' || x == b'-') {
-            iter.next();
-            i += 1;
+        after_heading = false;
+        if let Some(name) = category_heading(line) {
+            ret.push(NewsCategory {
+                name: Some(name.to_string()),
+                lines: vec![],
+            });
+            after_heading = true;
+        } else {
+            ret.last_mut().unwrap().lines.push(line.to_string());
+        }
+    }
+    if ret[0].lines.is_empty() && ret.len() > 1 {
+        ret.remove(0);
+    }
+    ret
+}
+
+/// Find problems with the category subsections of a news entry.
+pub fn check_categories(changes: &str) -> Vec<String> {
+    let categories = news_categories(changes);
+    if categories.iter().all(|c| c.name.is_none()) {
+        return vec![];
+    }
+    let mut problems = vec![];
+    let mut seen = std::collections::HashSet::new();
+    for category in &categories {
+        match category.name.as_deref() {
+            None => problems.push("changes listed before the first category".to_string()),
+            Some(name) => {
+                if !seen.insert(name.to_lowercase()) {
+                    problems.push(format!("category {} appears more than once", name));
+                }
+                if category.lines.is_empty() {
+                    problems.push(format!("category {} is empty", name));
+                }
+            }
+        }
+    }
+    problems
+}
+
+/// Remove the indentation that all non-blank lines have in common.
+fn dedent(text: &str) -> String {
+    let indent = text
+        .lines()
+        .filter(|l| !l.trim().is_empty())
+        .map(|l| l.len() - l.trim_start().len())
+        .min()
+        .unwrap_or(0);
+    text.lines()
+        .map(|l| format!("{}\n", l.get(indent..).unwrap_or_default()))
+        .collect()
+}
+
+/// Format the changes of a news entry for a forge release description,
+/// turning category headings into Markdown headings and converting
+/// reStructuredText markup.
+pub fn release_notes_markdown(changes: &str) -> String {
+    let categories = news_categories(changes);
+    if categories.iter().all(|c| c.name.is_none()) {
+        return crate::markdown::from_rst(&dedent(changes.trim_matches('\n')));
+    }
+    let mut ret = vec![];
+    for category in categories {
+        if let Some(name) = category.name {
+            ret.push(format!("### {}\n\n", name));
+        }
+        let indent = category
+            .lines
+            .iter()
+            .map(|l| l.len() - l.trim_start().len())
+            .min()
+            .unwrap_or(0);
+        for line in &category.lines {
+            ret.push(format!("{}\n", &line[indent..]));
+        }
+        ret.push("\n".to_string());
+    }
+    ret.pop();
+    crate::markdown::from_rst(&ret.concat())
+}
+
+/// Count the header lines at the start of a news file.
+///
+/// Underlined (reStructuredText) titles are part of the header, unless they
+/// are version lines.
+pub fn skip_header(lines: &[Vec<u8>], layout: &NewsLayout) -> usize {
+    let mut i = 0;
+    while let Some(line) = lines.get(i) {
+        let line = line.trim_ascii_end();
+        if !line.is_empty()
+            && underline_char(line).is_none()
+            && lines.get(i + 1).and_then(|l| underline_char(l)).is_some()
+        {
+            if std::str::from_utf8(line).is_ok_and(|l| layout.parse_version_line(l).is_ok()) {
+                break;
+            }
+            i += 2;
             continue;
         }
-        if line.is_empty() {
-            iter.next();
+        if line.starts_with(b"Changelog for ")
+            || line.ends_with(b" release notes")
+            || line.iter().all(|&x| x == b'=' || x == b'-')
+            || std::str::from_utf8(line).is_ok_and(|l| layout.skip.iter().any(|re| re.is_match(l)))
+        {
             i += 1;
             continue;
         }
         break;
     }
-    i as usize
+    i
 }
 
 /// Find pending version in news file.
@@ -57,29 +371,271 @@ pub fn skip_header<'a, I: Iterator<Item = &'a [u8]>>(iter: &mut std::iter::Peeka
 /// # Arguments
 /// * `tree`: Tree object
 /// * `path`: Path to news file in tree
+/// * `layout`: Layout of the news file
+/// * `series`: Version in the series being released, if known
 ///
 /// # Returns
 /// * version string
 pub fn tree_news_find_pending(
     tree: &dyn breezyshim::tree::Tree,
     path: &std::path::Path,
+    layout: &NewsLayout,
+    series: Option<&Version>,
 ) -> Result<Option<String>, Error> {
+    if crate::debian::is_changelog_path(path) {
+        return Ok(crate::debian::pending_version(&read_debian_changelog(
+            tree, path,
+        )?));
+    }
     let lines = tree.get_file_lines(path)?;
-    news_find_pending(&lines)
+    news_find_pending(&lines, layout, series)
+}
+
+pub fn tree_news_find_oldest_pending(
+    tree: &dyn breezyshim::tree::Tree,
+    path: &std::path::Path,
+    layout: &NewsLayout,
+) -> Result<Option<String>, Error> {
+    // Debian changelogs only have a single unreleased entry.
+    if crate::debian::is_changelog_path(path) {
+        return Ok(None);
+    }
+    let lines = tree.get_file_lines(path)?;
+    news_find_oldest_pending(&lines, layout)
+}
+
+/// Read a Debian changelog that is used as news file.
+fn read_debian_changelog(
+    tree: &dyn breezyshim::tree::Tree,
+    path: &std::path::Path,
+) -> Result<debian_changelog::ChangeLog, Error> {
+    let text = tree.get_file_text(path)?;
+    String::from_utf8_lossy(&text)
+        .parse()
+        .map_err(|e: debian_changelog::ParseError| {
+            Error::InvalidData(format!("Unable to parse {}: {}", path.display(), e))
+        })
+}
+
+/// A release entry in a news file.
+struct Entry {
+    /// Index of the version line
+    index: usize,
+    version: Option<String>,
+    date: Option<String>,
+    template: String,
+    pending: bool,
+    /// Character the version line is underlined with, for reStructuredText
+    /// section titles.
+    underline: Option<u8>,
+}
+
+impl Entry {
+    /// Whether this entry has the same major and minor version as `version`.
+    fn in_series(&self, version: &Version) -> bool {
+        self.version
+            .as_deref()
+            .and_then(|v| v.parse::<Version>().ok())
+            .is_some_and(|v| v.major == version.major && v.minor == version.minor)
+    }
 }
 
-pub fn news_find_pending(lines: &[Vec<u8>]) -> Result<Option<String>, Error> {
-    let mut iter = lines.iter().map(|x| x.as_slice()).peekable();
-    skip_header(&mut iter);
-    let line = String::from_utf8(iter.next().unwrap().to_vec())
-        .map_err(|_| Error::InvalidData("Invalid UTF-8 in news file".to_string()))?;
-    let (last_version, _last_date, _line_format, pending) = parse_version_line(line.as_str())?;
-    if !pending {
+/// Find the release entries in a news file.
+///
+/// The first line after the header has to be a version line; after that,
+/// any unindented line that parses as a version line starts a new entry.
+/// If the first version line is underlined, only underlined version lines
+/// start entries.
+fn news_entries(lines: &[Vec<u8>], layout: &NewsLayout) -> Result<Vec<Entry>, Error> {
+    let start = skip_header(lines, layout);
+    if start >= lines.len() {
+        return Err(Error::InvalidData("No entries in news file".to_string()));
+    }
+    let underline_at = |i: usize| lines.get(i + 1).and_then(|l| underline_char(l));
+    let titled = underline_at(start).is_some();
+    let mut entries = vec![];
+    for (i, line) in lines.iter().enumerate().skip(start) {
+        if i > start
+            && (line.trim_ascii().is_empty()
+                || line.starts_with(b" ")
+                || line.starts_with(b"\t")
+                || (titled && underline_at(i).is_none()))
+        {
+            continue;
+        }
+        let line = match String::from_utf8(line.to_vec()) {
+            Ok(line) => line,
+            Err(_) if i == start => {
+                return Err(Error::InvalidData("Invalid UTF-8 in news file".to_string()));
+            }
+            Err(_) => continue,
+        };
+        match layout.parse_version_line(line.as_str()) {
+            Ok((version, date, template, pending)) => entries.push(Entry {
+                index: i,
+                version: version.map(|v| v.to_string()),
+                date: date.map(|d| d.to_string()),
+                template,
+                pending,
+                underline: underline_at(i),
+            }),
+            Err(e) if i == start => return Err(e),
+            Err(_) => {}
+        }
+    }
+    Ok(entries)
+}
+
+/// Pick the pending entry to release.
+///
+/// Projects that maintain several series at once can have multiple pending
+/// entries; if a series is given, a pending entry with the same major and
+/// minor version is preferred. Otherwise, only the first entry is considered.
+fn find_pending_entry<'a>(entries: &'a [Entry], series: Option<&Version>) -> Option<&'a Entry> {
+    series
+        .and_then(|s| entries.iter().find(|e| e.pending && e.in_series(s)))
+        .or_else(|| entries.first().filter(|e| e.pending))
+}
+
+/// Find the pending entries that are waiting to be released, newest first.
+///
+/// Normally there is at most one, but projects that missed several release
+/// cycles can have multiple unreleased sections stacked on top of each other.
+/// Pending entries for other series (which aren't in descending order, or
+/// are older than the last release) are not considered part of the backlog.
+fn pending_backlog(entries: &[Entry]) -> Vec<&Entry> {
+    let parse = |e: &Entry| e.version.as_deref().and_then(|v| v.parse::<Version>().ok());
+    let older = |a: Option<Version>, b: Option<Version>| match (a, b) {
+        (Some(a), Some(b)) => a < b,
+        _ => true,
+    };
+    let last_released = entries.iter().find(|e| !e.pending).and_then(parse);
+    let mut pending = entries.iter().take_while(|e| e.pending);
+    let mut backlog: Vec<&Entry> = pending.next().into_iter().collect();
+    for entry in pending {
+        let version = parse(entry);
+        if !older(version.clone(), backlog.last().and_then(|e| parse(e)))
+            || !older(last_released.clone(), version)
+        {
+            break;
+        }
+        backlog.push(entry);
+    }
+    backlog
+}
+
+/// Versions of the pending entries that are waiting to be released, newest first.
+pub fn news_pending_backlog(
+    lines: &[Vec<u8>],
+    layout: &NewsLayout,
+) -> Result<Vec<Option<String>>, Error> {
+    let entries = news_entries(lines, layout)?;
+    Ok(pending_backlog(&entries)
+        .into_iter()
+        .map(|e| e.version.clone())
+        .collect())
+}
+
+/// Find the oldest of several unreleased sections, if there are several.
+pub fn news_find_oldest_pending(
+    lines: &[Vec<u8>],
+    layout: &NewsLayout,
+) -> Result<Option<String>, Error> {
+    let entries = news_entries(lines, layout)?;
+    let backlog = pending_backlog(&entries);
+    if backlog.len() < 2 {
         return Ok(None);
     }
-    Ok(last_version.map(|v| v.to_string()))
+    Ok(backlog.last().and_then(|e| e.version.clone()))
+}
+
+/// The changes listed in the pending entry, if there is one.
+pub fn news_pending_changes(
+    lines: &[Vec<u8>],
+    layout: &NewsLayout,
+) -> Result<Option<String>, Error> {
+    let entries = news_entries(lines, layout)?;
+    Ok(find_pending_entry(&entries, None).map(|entry| {
+        lines[entry_body(lines, &entries, entry)]
+            .iter()
+            .map(|l| String::from_utf8_lossy(l))
+            .collect()
+    }))
+}
+
+/// The changes listed in the released entry for a version, if there is one.
+pub fn news_released_changes(
+    lines: &[Vec<u8>],
+    layout: &NewsLayout,
+    version: &str,
+) -> Result<Option<String>, Error> {
+    let entries = news_entries(lines, layout)?;
+    Ok(entries
+        .iter()
+        .find(|e| !e.pending && e.version.as_deref() == Some(version))
+        .map(|entry| {
+            lines[entry_body(lines, &entries, entry)]
+                .iter()
+                .map(|l| String::from_utf8_lossy(l))
+                .collect()
+        }))
+}
+
+/// Merge multiple unreleased sections into the newest one.
+///
+/// Returns the number of sections that were merged away.
+pub fn news_collapse_pending(
+    lines: &mut Vec<Vec<u8>>,
+    layout: &NewsLayout,
+) -> Result<usize, Error> {
+    let entries = news_entries(lines, layout)?;
+    let backlog = pending_backlog(&entries);
+    let is_blank = |l: &[u8]| l.trim_ascii().is_empty();
+    let mut remove = vec![];
+    for entry in backlog.iter().skip(1) {
+        let mut start = entry.index;
+        while start > 0 && is_blank(&lines[start - 1]) {
+            start -= 1;
+        }
+        let mut end = entry.index + 1 + entry.underline.is_some() as usize;
+        while end < lines.len() && is_blank(&lines[end]) {
+            end += 1;
+        }
+        remove.push(start..end);
+    }
+    let merged = remove.len();
+    for range in remove.into_iter().rev() {
+        lines.drain(range);
+    }
+    Ok(merged)
+}
+
+/// Prepare several unreleased sections for a release, as configured.
+///
+/// Returns the number of sections that were merged away.
+pub fn news_catch_up(
+    lines: &mut Vec<Vec<u8>>,
+    layout: &NewsLayout,
+    catch_up: crate::project_config::CatchUp,
+) -> Result<usize, Error> {
+    match catch_up {
+        crate::project_config::CatchUp::Collapse => news_collapse_pending(lines, layout),
+        crate::project_config::CatchUp::Sequential | crate::project_config::CatchUp::Off => Ok(0),
+    }
+}
+
+pub fn news_find_pending(
+    lines: &[Vec<u8>],
+    layout: &NewsLayout,
+    series: Option<&Version>,
+) -> Result<Option<String>, Error> {
+    let entries = news_entries(lines, layout)?;
+    Ok(find_pending_entry(&entries, series).and_then(|e| e.version.clone()))
 }
 
+/// Version, date released, line template and whether the entry is pending.
+type VersionLine<'a> = (Option<&'a str>, Option<&'a str>, String, bool);
+
 /// Extract version info from news line.
 ///
 /// # Arguments
@@ -87,7 +643,7 @@ pub fn news_find_pending(lines: &[Vec<u8>]) -> Result<Option<String>, Error> {
 ///
 /// # Returns
 ///   tuple with version, date released, line template, is_pending
-fn parse_version_line(line: &str) -> Result<(Option<&str>, Option<&str>, String, bool), Error> {
+fn parse_version_line(line: &str) -> Result<VersionLine<'_>, Error> {
     // Strip leading and trailing whitespace
     let line = line.trim();
 
@@ -161,31 +717,86 @@ fn parse_version_line(line: &str) -> Result<(Option<&str>, Option<&str>, String,
     ))
 }
 
-fn news_add_pending(lines: &mut Vec<Vec<u8>>, new_version: &crate::Version) -> Result<(), Error> {
-    let mut line_iter = lines.iter().map(|x| x.as_slice()).peekable();
-    let i = skip_header(&mut line_iter);
+/// Extract version info from a news line using an explicit template.
+fn parse_version_line_with_template<'a>(
+    line: &'a str,
+    template: &str,
+    re: &regex::Regex,
+) -> Result<VersionLine<'a>, Error> {
+    let line = line.trim();
+    let c = re.captures(line).ok_or_else(|| {
+        Error::InvalidData(format!(
+            "Line {:?} does not match news format {:?}",
+            line, template
+        ))
+    })?;
+    let version = c.name("version").unwrap().as_str();
+    let date = c.name("date").map(|d| d.as_str());
+    let version_is_placeholder = check_version(version)?;
+    let date_is_placeholder = date.is_some_and(date_is_placeholder);
+    Ok((
+        if !version_is_placeholder {
+            Some(version)
+        } else {
+            None
+        },
+        date.filter(|_| !date_is_placeholder),
+        template.to_string(),
+        version_is_placeholder || date_is_placeholder,
+    ))
+}
 
-    let line = String::from_utf8(line_iter.next().unwrap().to_vec())
-        .map_err(|_| Error::InvalidData("Invalid UTF-8 in news file".to_string()))?;
+/// Add a pending entry to a news file.
+///
+/// Without a version, the entry is marked as UNRELEASED; this is used for
+/// projects whose next version isn't known until it is released.
+fn news_add_pending(
+    lines: &mut Vec<Vec<u8>>,
+    new_version: Option<&crate::Version>,
+    layout: &NewsLayout,
+) -> Result<(), Error> {
+    let entries = news_entries(lines, layout)?;
 
-    let (last_version, last_date, line_format, pending) = parse_version_line(line.as_str())?;
-    if pending {
-        let last_date = last_date
+    // Pending entries for other series can coexist with the new one.
+    if let Some(pending) = entries
+        .iter()
+        .find(|e| e.pending && (e.version.is_none() || new_version.is_none_or(|v| e.in_series(v))))
+    {
+        let last_version = pending
+            .version
+            .as_deref()
+            .ok_or_else(|| Error::InvalidData("Pending entry without version".to_string()))?;
+        let last_date = pending
+            .date
+            .as_deref()
             .map(|x| x.parse().map_err(|_| Error::InvalidData(x.to_string())))
             .transpose()?;
         return Err(Error::PendingExists {
             last_version: last_version
-                .unwrap()
                 .parse()
-                .map_err(|_| Error::InvalidData(last_version.unwrap().to_string()))?,
+                .map_err(|_| Error::InvalidData(last_version.to_string()))?,
             last_date,
         });
     }
+    let first = &entries[0];
+    let i = first.index;
     lines.insert(i, b"\n".to_vec());
 
-    let mut new_version_line = expand_template(line_format.as_str(), new_version, "UNRELEASED")
-        .as_bytes()
-        .to_vec();
+    let mut new_version_line = match new_version {
+        Some(new_version) => expand_template(first.template.as_str(), new_version, "UNRELEASED"),
+        None => first
+            .template
+            .replace("%(version)s", "UNRELEASED")
+            .replace("%(date)s", "UNRELEASED"),
+    }
+    .as_bytes()
+    .to_vec();
+    if let Some(c) = first.underline {
+        lines.insert(
+            i,
+            underline_for(c, std::str::from_utf8(&new_version_line).unwrap()),
+        );
+    }
     new_version_line.push(b'\n');
 
     lines.insert(i, new_version_line);
@@ -195,10 +806,11 @@ fn news_add_pending(lines: &mut Vec<Vec<u8>>, new_version: &crate::Version) -> R
 fn tree_news_add_pending(
     tree: &dyn breezyshim::tree::MutableTree,
     path: &std::path::Path,
-    new_version: &crate::Version,
+    new_version: Option<&crate::Version>,
+    layout: &NewsLayout,
 ) -> Result<(), Error> {
     let mut lines = tree.get_file_lines(path)?;
-    news_add_pending(&mut lines, new_version)?;
+    news_add_pending(&mut lines, new_version, layout)?;
     tree.put_file_bytes_non_atomic(path, lines.concat().as_slice())?;
     Ok(())
 }
@@ -259,6 +871,15 @@ impl From<breezyshim::error::Error> for Error {
     }
 }
 
+impl From<crate::debian::Error> for Error {
+    fn from(e: crate::debian::Error) -> Self {
+        match e {
+            crate::debian::Error::BrzError(e) => Self::BrzError(e),
+            e => Self::InvalidData(e.to_string()),
+        }
+    }
+}
+
 /// Mark version as released in news file.
 ///
 /// # Arguments
@@ -266,58 +887,254 @@ impl From<breezyshim::error::Error> for Error {
 /// * `path`: Path to news file in tree
 /// * `expected_version`: Version to mark as released
 /// * `release_date`: Date to mark as released
+/// * `layout`: Layout of the news file
 pub fn news_mark_released(
     tree: &dyn MutableTree,
     path: &std::path::Path,
     expected_version: &Version,
     release_date: &chrono::NaiveDate,
+    layout: &NewsLayout,
 ) -> Result<String, Error> {
     let mut lines = tree.get_file_lines(path)?;
-    let mut iter = lines.iter().map(|x| x.as_slice()).peekable();
-    let i = skip_header(&mut iter);
-    let line = String::from_utf8(iter.next().unwrap().to_vec())
-        .map_err(|_| Error::InvalidData("Invalid UTF-8 in news file".to_string()))?;
-    let (version, _date, line_format, pending) = parse_version_line(line.as_str())?;
-    if !pending {
-        return Err(Error::NoUnreleasedChanges);
+    let changes = news_mark_released_lines(&mut lines, expected_version, release_date, layout)?;
+    tree.put_file_bytes_non_atomic(path, lines.concat().as_slice())?;
+    Ok(changes)
+}
+
+/// Find the pending entry for the version that is about to be released.
+fn find_release_entry<'a>(
+    entries: &'a [Entry],
+    expected_version: &Version,
+) -> Result<&'a Entry, Error> {
+    let expected = expected_version.to_string();
+    let entry = entries
+        .iter()
+        .find(|e| e.pending && e.version.as_deref() == Some(expected.as_str()))
+        .or_else(|| find_pending_entry(entries, Some(expected_version)))
+        .ok_or(Error::NoUnreleasedChanges)?;
+    if let Some(version) = entry.version.as_deref() {
+        if version != expected {
+            return Err(Error::InvalidData(format!(
+                "unexpected version: {} != {}",
+                expected, version
+            )));
+        }
     }
-    if let Some(version) = version {
-        assert_eq!(
-            expected_version.to_string().as_str(),
-            version,
-            "unexpected version: {} != {}",
-            expected_version.to_string(),
-            version
-        );
+    Ok(entry)
+}
+
+/// Find the lines with the changes listed in an entry.
+///
+/// Underlined sections run until the next version line, and their contents
+/// don't have to be indented; otherwise the changes are the indented lines
+/// after the version line, along with any category headings.
+fn entry_body(lines: &[Vec<u8>], entries: &[Entry], entry: &Entry) -> std::ops::Range<usize> {
+    let i = entry.index;
+    if entry.underline.is_some() {
+        let end = entries
+            .iter()
+            .map(|e| e.index)
+            .find(|&j| j > i)
+            .unwrap_or(lines.len());
+        return (i + 2).min(end)..end;
     }
-    let mut change_lines = Vec::new();
-    for line in lines[i + 1..].iter() {
-        let line = match String::from_utf8(line.to_vec()) {
-            Ok(line) => line,
-            Err(_) => {
-                continue;
-            }
-        };
-        if line.trim().is_empty() || line.starts_with(' ') || line.starts_with('\t') {
-            change_lines.push(line);
-        } else {
+    let is_heading =
+        |line: &[u8]| std::str::from_utf8(line).is_ok_and(|l| category_heading(l).is_some());
+    let mut end = i + 1;
+    while let Some(line) = lines.get(end) {
+        let heading_underline = underline_char(line).is_some() && is_heading(&lines[end - 1]);
+        if !heading_underline
+            && !is_heading(line)
+            && std::str::from_utf8(line)
+                .is_ok_and(|l| !(l.trim().is_empty() || l.starts_with(' ') || l.starts_with('\t')))
+        {
             break;
         }
+        end += 1;
     }
+    i + 1..end
+}
+
+/// Fill the pending entry for a version with generated changes, if it
+/// doesn't list any changes yet.
+///
+/// Returns whether the entry was filled.
+fn news_fill_pending(
+    lines: &mut Vec<Vec<u8>>,
+    expected_version: &Version,
+    changes: &[String],
+    layout: &NewsLayout,
+) -> Result<bool, Error> {
+    let entries = news_entries(lines, layout)?;
+    let entry = find_release_entry(&entries, expected_version)?;
+    let body = entry_body(lines, &entries, entry);
+    if lines[body.clone()]
+        .iter()
+        .any(|l| !l.trim_ascii().is_empty())
+    {
+        return Ok(false);
+    }
+    let indent = if entry.underline.is_some() { "" } else { "  " };
+    let mut new_lines = vec![b"\n".to_vec()];
+    new_lines.extend(changes.iter().map(|c| {
+        if c.is_empty() {
+            b"\n".to_vec()
+        } else {
+            format!("{}{}\n", indent, c).into_bytes()
+        }
+    }));
+    lines.splice(body.start..body.start, new_lines);
+    Ok(true)
+}
+
+/// Mark version as released in the lines of a news file.
+///
+/// Returns the changes listed for the version.
+fn news_mark_released_lines(
+    lines: &mut [Vec<u8>],
+    expected_version: &Version,
+    release_date: &chrono::NaiveDate,
+    layout: &NewsLayout,
+) -> Result<String, Error> {
+    let entries = news_entries(lines, layout)?;
+    let entry = find_release_entry(&entries, expected_version)?;
+    let i = entry.index;
+    let change_lines = lines[entry_body(lines, &entries, entry)]
+        .iter()
+        .filter_map(|line| String::from_utf8(line.to_vec()).ok())
+        .collect::<Vec<_>>();
+    let dates = entries
+        .iter()
+        .filter_map(|e| e.date.as_deref())
+        .collect::<Vec<_>>();
     let new_line = expand_template(
-        line_format.as_str(),
+        entry.template.as_str(),
         expected_version,
-        release_date.format("%Y-%m-%d").to_string().as_str(),
-    ) + "\n";
-    lines[i] = new_line.into_bytes();
+        release_date
+            .format(layout.date_format(&dates))
+            .to_string()
+            .as_str(),
+    );
+    if let Some(c) = entry.underline {
+        lines[i + 1] = underline_for(c, &new_line);
+    }
+    lines[i] = (new_line + "\n").into_bytes();
 
-    tree.put_file_bytes_non_atomic(path, lines.concat().as_slice())?;
     Ok(change_lines.concat())
 }
 
+/// Check a news file for problems that don't stop it from being parsed.
+///
+/// Returns a description of each problem, prefixed with its line number.
+pub fn news_lint(lines: &[Vec<u8>], layout: &NewsLayout) -> Result<Vec<String>, Error> {
+    let entries = news_entries(lines, layout)?;
+    let mut problems = vec![];
+    let mut seen = std::collections::HashSet::new();
+    let mut previous: Option<Version> = None;
+    let dates = entries
+        .iter()
+        .filter_map(|e| e.date.as_deref())
+        .collect::<Vec<_>>();
+    let date_format = layout.date_format(&dates);
+    for (n, entry) in entries.iter().enumerate() {
+        let lineno = entry.index + 1;
+        if let Some(c) = entry.underline {
+            let title = String::from_utf8_lossy(&lines[entry.index]);
+            if lines[entry.index + 1].trim_ascii_end() != underline_for(c, &title).trim_ascii_end()
+            {
+                problems.push(format!(
+                    "line {}: underline does not match the length of the title",
+                    lineno + 1
+                ));
+            }
+        }
+        if entry.pending {
+            if n > 0 && entries[..n].iter().all(|e| !e.pending) {
+                problems.push(format!(
+                    "line {}: unreleased section below released sections",
+                    lineno
+                ));
+            }
+            if lines[entry_body(lines, &entries, entry)]
+                .iter()
+                .all(|l| l.trim_ascii().is_empty())
+            {
+                problems.push(format!("line {}: unreleased section is empty", lineno));
+            }
+        }
+        if let Some(date) = entry.date.as_deref() {
+            if !date_matches_format(date, date_format) {
+                problems.push(format!(
+                    "line {}: date {:?} does not match format {:?}",
+                    lineno, date, date_format
+                ));
+            }
+        }
+        let Some(version) = entry.version.as_deref() else {
+            continue;
+        };
+        if !seen.insert(version) {
+            problems.push(format!(
+                "line {}: version {} is listed twice",
+                lineno, version
+            ));
+        }
+        // Pending sections for other series don't have to be in order.
+        if entry.pending {
+            continue;
+        }
+        if let Ok(version) = version.parse::<Version>() {
+            if let Some(newer) = previous.as_ref() {
+                if version >= *newer {
+                    problems.push(format!(
+                        "line {}: version {} is listed below older version {}",
+                        lineno,
+                        version.to_string(),
+                        newer.to_string()
+                    ));
+                }
+            }
+            previous = Some(version);
+        }
+    }
+    Ok(problems)
+}
+
+/// Update the "[Unreleased]: <repository>/compare/<tag>...HEAD" link, as
+/// used by Keep a Changelog, for a release, and add a link for the released
+/// version.
+///
+/// Returns whether a link was found.
+fn news_update_compare_links(lines: &mut Vec<Vec<u8>>, version: &Version, tag: &str) -> bool {
+    for i in 0..lines.len() {
+        let Ok(line) = std::str::from_utf8(&lines[i]) else {
+            continue;
+        };
+        let Some((_, label, base, previous)) = lazy_regex::regex_captures!(
+            r"^\[(unreleased)\]:\s*(\S+)/compare/(\S+?)\.\.\.HEAD\s*$"i,
+            line
+        ) else {
+            continue;
+        };
+        let unreleased = format!("[{}]: {}/compare/{}...HEAD\n", label, base, tag);
+        let released = format!(
+            "[{}]: {}/compare/{}...{}\n",
+            version.to_string(),
+            base,
+            previous,
+            tag
+        );
+        lines[i] = unreleased.into_bytes();
+        lines.insert(i + 1, released.into_bytes());
+        return true;
+    }
+    false
+}
+
 pub struct NewsFile<'a> {
     tree: &'a breezyshim::tree::WorkingTree,
     path: std::path::PathBuf,
+    layout: NewsLayout,
 }
 
 impl<'a> NewsFile<'a> {
@@ -328,15 +1145,157 @@ impl<'a> NewsFile<'a> {
         Ok(Self {
             tree,
             path: path.to_path_buf(),
+            layout: NewsLayout::default(),
         })
     }
 
+    /// Use an explicit layout rather than inferring it from the file.
+    pub fn with_layout(mut self, layout: NewsLayout) -> Self {
+        self.layout = layout;
+        self
+    }
+
+    /// The Debian changelog, if that is what the news file is.
+    fn debian_changelog(&self) -> Result<Option<debian_changelog::ChangeLog>, Error> {
+        if crate::debian::is_changelog_path(&self.path) {
+            Ok(Some(read_debian_changelog(self.tree, &self.path)?))
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn put_debian_changelog(&self, changelog: &debian_changelog::ChangeLog) -> Result<(), Error> {
+        self.tree
+            .put_file_bytes_non_atomic(&self.path, changelog.to_string().as_bytes())?;
+        Ok(())
+    }
+
     /// Add a new pending version to the news file.
     ///
     /// # Arguments
     /// * `new_version`: Version to add
     pub fn add_pending(&self, new_version: &crate::Version) -> Result<(), Error> {
-        tree_news_add_pending(self.tree, self.path.as_path(), new_version)
+        if let Some(mut changelog) = self.debian_changelog()? {
+            crate::debian::add_pending_entry(
+                &mut changelog,
+                new_version,
+                None,
+                chrono::Utc::now().into(),
+            )?;
+            return self.put_debian_changelog(&changelog);
+        }
+        tree_news_add_pending(
+            self.tree,
+            self.path.as_path(),
+            Some(new_version),
+            &self.layout,
+        )
+    }
+
+    /// Versions of the unreleased sections in the news file, newest first.
+    pub fn pending_backlog(&self) -> Result<Vec<Option<String>>, Error> {
+        if let Some(changelog) = self.debian_changelog()? {
+            return Ok(crate::debian::pending_version(&changelog)
+                .map(Some)
+                .into_iter()
+                .collect());
+        }
+        let lines = self.tree.get_file_lines(&self.path)?;
+        news_pending_backlog(&lines, &self.layout)
+    }
+
+    /// The changes listed in the pending entry, if there is one.
+    pub fn pending_changes(&self) -> Result<Option<String>, Error> {
+        if let Some(changelog) = self.debian_changelog()? {
+            return Ok(crate::debian::pending_changes(&changelog));
+        }
+        let lines = self.tree.get_file_lines(&self.path)?;
+        news_pending_changes(&lines, &self.layout)
+    }
+
+    /// The changes listed in the released entry for a version, if there is one.
+    pub fn released_changes(&self, version: &Version) -> Result<Option<String>, Error> {
+        if let Some(changelog) = self.debian_changelog()? {
+            return Ok(crate::debian::released_changes(
+                &changelog,
+                &version.to_string(),
+            ));
+        }
+        let lines = self.tree.get_file_lines(&self.path)?;
+        news_released_changes(&lines, &self.layout, &version.to_string())
+    }
+
+    /// Check the news file for problems, returning a description of each.
+    pub fn lint(&self) -> Result<Vec<String>, Error> {
+        // Debian changelogs are checked by parsing them.
+        if self.debian_changelog()?.is_some() {
+            return Ok(vec![]);
+        }
+        let lines = self.tree.get_file_lines(&self.path)?;
+        news_lint(&lines, &self.layout)
+    }
+
+    /// Prepare several unreleased sections for a release, as configured.
+    ///
+    /// Returns the number of sections that were merged away.
+    pub fn catch_up(&self, catch_up: crate::project_config::CatchUp) -> Result<usize, Error> {
+        if crate::debian::is_changelog_path(&self.path) {
+            return Ok(0);
+        }
+        let mut lines = self.tree.get_file_lines(&self.path)?;
+        let merged = news_catch_up(&mut lines, &self.layout, catch_up)?;
+        if merged > 0 {
+            self.tree
+                .put_file_bytes_non_atomic(&self.path, lines.concat().as_slice())?;
+        }
+        Ok(merged)
+    }
+
+    /// Fill the pending entry for a version with generated changes, if it
+    /// doesn't list any changes yet.
+    ///
+    /// Returns whether the entry was filled.
+    pub fn fill_pending(&self, version: &Version, changes: &[String]) -> Result<bool, Error> {
+        if let Some(changelog) = self.debian_changelog()? {
+            let filled = crate::debian::fill_pending_entry(&changelog, changes)?;
+            if filled {
+                self.put_debian_changelog(&changelog)?;
+            }
+            return Ok(filled);
+        }
+        let mut lines = self.tree.get_file_lines(&self.path)?;
+        let filled = news_fill_pending(&mut lines, version, changes, &self.layout)?;
+        if filled {
+            self.tree
+                .put_file_bytes_non_atomic(&self.path, lines.concat().as_slice())?;
+        }
+        Ok(filled)
+    }
+
+    /// Update the comparison links at the end of the news file for a release.
+    ///
+    /// Returns whether there were links to update.
+    pub fn update_compare_links(&self, version: &Version, tag: &str) -> Result<bool, Error> {
+        if crate::debian::is_changelog_path(&self.path) {
+            return Ok(false);
+        }
+        let mut lines = self.tree.get_file_lines(&self.path)?;
+        let updated = news_update_compare_links(&mut lines, version, tag);
+        if updated {
+            self.tree
+                .put_file_bytes_non_atomic(&self.path, lines.concat().as_slice())?;
+        }
+        Ok(updated)
+    }
+
+    /// Add a new pending entry without a version to the news file.
+    pub fn add_unreleased(&self) -> Result<(), Error> {
+        if crate::debian::is_changelog_path(&self.path) {
+            return Err(Error::InvalidData(
+                "Entries in debian/changelog need a version".to_string(),
+            ));
+        }
+        tree_news_add_pending(self.tree, self.path.as_path(), None, &self.layout)
     }
 
     /// Mark version as released in news file.
@@ -349,11 +1308,34 @@ impl<'a> NewsFile<'a> {
         expected_version: &Version,
         release_date: &chrono::NaiveDate,
     ) -> Result<String, Error> {
+        if let Some(mut changelog) = self.debian_changelog()? {
+            let version =
+                crate::debian::pending_version(&changelog).ok_or(Error::NoUnreleasedChanges)?;
+            if version != expected_version.to_string() {
+                return Err(Error::InvalidData(format!(
+                    "unexpected version: {} != {}",
+                    expected_version.to_string(),
+                    version
+                )));
+            }
+            let changes = crate::debian::pending_changes(&changelog).unwrap_or_default();
+            crate::debian::add_changelog_entry(
+                &mut changelog,
+                expected_version,
+                self.layout.distribution.as_deref(),
+                None,
+                debian_changelog::get_maintainer(),
+                chrono::Utc::now().into(),
+            )?;
+            self.put_debian_changelog(&changelog)?;
+            return Ok(changes);
+        }
         news_mark_released(
             self.tree,
             self.path.as_path(),
             expected_version,
             release_date,
+            &self.layout,
         )
     }
 }
@@ -415,7 +1397,8 @@ mod tests {
             b"  * Change 2\n".to_vec(),
         ];
         let new_version: crate::Version = "1.2.4".parse().expect("parse failed");
-        super::news_add_pending(&mut lines, &new_version).expect("add pending failed");
+        super::news_add_pending(&mut lines, Some(&new_version), &Default::default())
+            .expect("add pending failed");
         assert_eq!(
             String::from_utf8(lines.concat()).unwrap(),
             [
@@ -431,6 +1414,32 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_news_add_pending_unversioned() {
+        let mut lines = vec![
+            b"2024.10.0 2024-10-16\n".to_vec(),
+            b"\n".to_vec(),
+            b"  * Change 1\n".to_vec(),
+        ];
+        super::news_add_pending(&mut lines, None, &Default::default()).expect("add pending failed");
+        assert_eq!(
+            String::from_utf8(lines.concat()).unwrap(),
+            [
+                "UNRELEASED UNRELEASED\n",
+                "\n",
+                "2024.10.0 2024-10-16\n",
+                "\n",
+                "  * Change 1\n",
+            ]
+            .concat()
+        );
+        assert_eq!(
+            super::news_find_pending(&lines, &Default::default(), None).unwrap(),
+            None
+        );
+        assert!(super::news_add_pending(&mut lines, None, &Default::default()).is_err());
+    }
+
     #[test]
     fn test_news_find_pending() {
         let lines = vec![
@@ -440,10 +1449,26 @@ mod tests {
             b"  * Change 1\n".to_vec(),
             b"  * Change 2\n".to_vec(),
         ];
-        let version = super::news_find_pending(&lines).expect("find pending failed");
+        let version = super::news_find_pending(&lines, &Default::default(), None)
+            .expect("find pending failed");
         assert_eq!(version, Some("1.2.3".to_string()));
     }
 
+    #[test]
+    fn test_news_find_pending_prerelease() {
+        for version in ["1.2.0rc1", "1.2.0-beta.2", "1.2.0.post1", "1!1.2.0.dev0"] {
+            let lines = vec![
+                format!("{} UNRELEASED\n", version).into_bytes(),
+                b"\n".to_vec(),
+                b"  * Change 1\n".to_vec(),
+            ];
+            assert_eq!(
+                super::news_find_pending(&lines, &Default::default(), None).unwrap(),
+                Some(version.to_string())
+            );
+        }
+    }
+
     #[test]
     fn test_news_pending_not_found() {
         let lines = vec![
@@ -453,7 +1478,501 @@ mod tests {
             b"  * Change 1\n".to_vec(),
             b"  * Change 2\n".to_vec(),
         ];
-        let version = super::news_find_pending(&lines).expect("find pending failed");
+        let version = super::news_find_pending(&lines, &Default::default(), None)
+            .expect("find pending failed");
         assert_eq!(version, None);
     }
+
+    #[test]
+    fn test_news_layout_template() {
+        let layout = super::NewsLayout::new(
+            Some("Version %(version)s, released %(date)s"),
+            &["^#".to_string()],
+        )
+        .unwrap();
+        let lines = vec![
+            b"# News for foo\n".to_vec(),
+            b"\n".to_vec(),
+            b"Version 1.2.3, released 2021-01-01\n".to_vec(),
+            b"  * Change 1\n".to_vec(),
+        ];
+        assert_eq!(
+            super::news_find_pending(&lines, &layout, None).unwrap(),
+            None
+        );
+
+        let mut lines = lines;
+        super::news_add_pending(&mut lines, Some(&"1.2.4".parse().unwrap()), &layout).unwrap();
+        assert_eq!(
+            String::from_utf8(lines.concat()).unwrap(),
+            [
+                "# News for foo\n",
+                "\n",
+                "Version 1.2.4, released UNRELEASED\n",
+                "\n",
+                "Version 1.2.3, released 2021-01-01\n",
+                "  * Change 1\n",
+            ]
+            .concat()
+        );
+        assert_eq!(
+            super::news_find_pending(&lines, &layout, None).unwrap(),
+            Some("1.2.4".to_string())
+        );
+    }
+
+    #[test]
+    fn test_news_layout_mismatch() {
+        let layout = super::NewsLayout::new(Some("v%(version)s"), &[]).unwrap();
+        let lines = vec![b"1.2.3 2021-01-01\n".to_vec()];
+        assert!(matches!(
+            super::news_find_pending(&lines, &layout, None),
+            Err(super::Error::InvalidData(_))
+        ));
+        assert!(super::NewsLayout::new(Some("%(date)s"), &[]).is_err());
+    }
+
+    #[test]
+    fn test_news_find_pending_series() {
+        let lines = vec![
+            b"2.1.0 UNRELEASED\n".to_vec(),
+            b"\n".to_vec(),
+            b"  * Change 1\n".to_vec(),
+            b"\n".to_vec(),
+            b"1.4.3 UNRELEASED\n".to_vec(),
+            b"\n".to_vec(),
+            b"  * Change 2\n".to_vec(),
+            b"\n".to_vec(),
+            b"2.0.0 2021-01-01\n".to_vec(),
+        ];
+        let layout = Default::default();
+        let find = |series: Option<&str>| {
+            super::news_find_pending(&lines, &layout, series.map(|s| s.parse().unwrap()).as_ref())
+                .unwrap()
+        };
+        assert_eq!(find(None), Some("2.1.0".to_string()));
+        assert_eq!(find(Some("1.4.2")), Some("1.4.3".to_string()));
+        assert_eq!(find(Some("2.1.0")), Some("2.1.0".to_string()));
+        assert_eq!(find(Some("3.0")), Some("2.1.0".to_string()));
+    }
+
+    #[test]
+    fn test_news_add_pending_other_series() {
+        let mut lines = vec![
+            b"2.1.0 UNRELEASED\n".to_vec(),
+            b"\n".to_vec(),
+            b"1.4.3 2021-01-01\n".to_vec(),
+        ];
+        super::news_add_pending(
+            &mut lines,
+            Some(&"1.4.4".parse().unwrap()),
+            &Default::default(),
+        )
+        .unwrap();
+        assert_eq!(
+            String::from_utf8(lines.concat()).unwrap(),
+            "1.4.4 UNRELEASED\n\n2.1.0 UNRELEASED\n\n1.4.3 2021-01-01\n"
+        );
+        assert!(matches!(
+            super::news_add_pending(
+                &mut lines,
+                Some(&"2.1.1".parse().unwrap()),
+                &Default::default()
+            ),
+            Err(super::Error::PendingExists { .. })
+        ));
+    }
+
+    #[test]
+    fn test_news_pending_backlog() {
+        let lines = vec![
+            b"1.3.0 UNRELEASED\n".to_vec(),
+            b"\n".to_vec(),
+            b" * Add frobnicator.\n".to_vec(),
+            b"\n".to_vec(),
+            b"1.2.1 UNRELEASED\n".to_vec(),
+            b"\n".to_vec(),
+            b" * Fix crash.\n".to_vec(),
+            b"\n".to_vec(),
+            b"1.2.0 2021-01-01\n".to_vec(),
+        ];
+        assert_eq!(
+            super::news_pending_backlog(&lines, &Default::default()).unwrap(),
+            vec![Some("1.3.0".to_string()), Some("1.2.1".to_string())]
+        );
+        assert_eq!(
+            super::news_find_oldest_pending(&lines, &Default::default()).unwrap(),
+            Some("1.2.1".to_string())
+        );
+        // Pending entries for other series are not a backlog.
+        let lines = vec![
+            b"1.4.4 UNRELEASED\n".to_vec(),
+            b"\n".to_vec(),
+            b"2.1.0 UNRELEASED\n".to_vec(),
+            b"\n".to_vec(),
+            b"1.4.3 2021-01-01\n".to_vec(),
+        ];
+        assert_eq!(
+            super::news_pending_backlog(&lines, &Default::default()).unwrap(),
+            vec![Some("1.4.4".to_string())]
+        );
+        assert_eq!(
+            super::news_find_oldest_pending(&lines, &Default::default()).unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_news_rst() {
+        let mut lines = [
+            "Release History\n",
+            "===============\n",
+            "\n",
+            "1.2.4 (UNRELEASED)\n",
+            "------------------\n",
+            "\n",
+            "* Fix crash.\n",
+            "\n",
+            "1.2.3 (2021-01-01)\n",
+            "------------------\n",
+            "\n",
+            "* Add frobnicator.\n",
+        ]
+        .iter()
+        .map(|l| l.as_bytes().to_vec())
+        .collect::<Vec<_>>();
+        assert_eq!(super::skip_header(&lines, &Default::default()), 3);
+        assert_eq!(
+            super::news_find_pending(&lines, &Default::default(), None).unwrap(),
+            Some("1.2.4".to_string())
+        );
+        let changes = super::news_mark_released_lines(
+            &mut lines,
+            &"1.2.4".parse().unwrap(),
+            &chrono::NaiveDate::from_ymd_opt(2024, 1, 10).unwrap(),
+            &Default::default(),
+        )
+        .unwrap();
+        assert_eq!(changes, "\n* Fix crash.\n\n");
+        assert_eq!(
+            String::from_utf8(lines[3..5].concat()).unwrap(),
+            "1.2.4 (2024-01-10)\n------------------\n"
+        );
+
+        super::news_add_pending(
+            &mut lines,
+            Some(&"1.2.10".parse().unwrap()),
+            &Default::default(),
+        )
+        .unwrap();
+        assert_eq!(
+            String::from_utf8(lines[3..7].concat()).unwrap(),
+            "1.2.10 (UNRELEASED)\n-------------------\n\n1.2.4 (2024-01-10)\n"
+        );
+    }
+
+    #[test]
+    fn test_news_fill_pending() {
+        let mut lines = vec![
+            b"1.2.4 UNRELEASED\n".to_vec(),
+            b"\n".to_vec(),
+            b"1.2.3 2021-01-01\n".to_vec(),
+            b"\n".to_vec(),
+            b"  * Change 1\n".to_vec(),
+        ];
+        let version: crate::Version = "1.2.4".parse().unwrap();
+        let changes = vec!["* Change 2".to_string(), "* Change 3".to_string()];
+        assert!(
+            super::news_fill_pending(&mut lines, &version, &changes, &Default::default()).unwrap()
+        );
+        assert_eq!(
+            String::from_utf8(lines.concat()).unwrap(),
+            "1.2.4 UNRELEASED\n\n  * Change 2\n  * Change 3\n\n1.2.3 2021-01-01\n\n  * Change 1\n"
+        );
+        // Entries that already list changes are left alone.
+        assert!(
+            !super::news_fill_pending(&mut lines, &version, &changes, &Default::default()).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_news_categories() {
+        let mut lines = [
+            "1.2.4 UNRELEASED\n",
+            "\n",
+            "  Added\n",
+            "  * Frobnicator.\n",
+            "\n",
+            "Fixed:\n",
+            "  * Crash on startup.\n",
+            "    Really.\n",
+            "\n",
+            "1.2.3 2021-01-01\n",
+        ]
+        .iter()
+        .map(|l| l.as_bytes().to_vec())
+        .collect::<Vec<_>>();
+        let changes = super::news_mark_released_lines(
+            &mut lines,
+            &"1.2.4".parse().unwrap(),
+            &chrono::NaiveDate::from_ymd_opt(2024, 1, 10).unwrap(),
+            &Default::default(),
+        )
+        .unwrap();
+        assert_eq!(
+            changes,
+            "\n  Added\n  * Frobnicator.\n\nFixed:\n  * Crash on startup.\n    Really.\n\n"
+        );
+        assert_eq!(lines[0], b"1.2.4 2024-01-10\n");
+        assert_eq!(
+            super::news_categories(&changes)
+                .into_iter()
+                .map(|c| c.name)
+                .collect::<Vec<_>>(),
+            vec![Some("Added".to_string()), Some("Fixed".to_string())]
+        );
+        assert_eq!(
+            super::release_notes_markdown(&changes),
+            "### Added\n\n* Frobnicator.\n\n### Fixed\n\n* Crash on startup.\n  Really.\n"
+        );
+        assert!(super::check_categories(&changes).is_empty());
+        assert_eq!(
+            super::release_notes_markdown("\n  * Fix crash.\n\n    Really.\n\n"),
+            "* Fix crash.\n\n  Really.\n"
+        );
+        assert_eq!(
+            super::check_categories("  * Fix crash.\n\n  Fixed\n\n  Added\n  * Foo.\n  Added\n"),
+            vec![
+                "changes listed before the first category",
+                "category Fixed is empty",
+                "category Added appears more than once",
+                "category Added is empty",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_news_rst_categories() {
+        let lines = [
+            "1.2.4 (UNRELEASED)\n",
+            "------------------\n",
+            "\n",
+            "Security\n",
+            "~~~~~~~~\n",
+            "\n",
+            "* Escape input.\n",
+            "\n",
+            "1.2.3 (2021-01-01)\n",
+            "------------------\n",
+        ]
+        .iter()
+        .map(|l| l.as_bytes().to_vec())
+        .collect::<Vec<_>>();
+        let changes = super::news_pending_changes(&lines, &Default::default())
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            super::release_notes_markdown(&changes),
+            "### Security\n\n* Escape input.\n"
+        );
+    }
+
+    #[test]
+    fn test_news_update_compare_links() {
+        let mut lines = vec![
+            b"## [1.2.3] - 2021-01-01\n".to_vec(),
+            b"\n".to_vec(),
+            b"[Unreleased]: https://github.com/jelmer/foo/compare/v1.2.3...HEAD\n".to_vec(),
+            b"[1.2.3]: https://github.com/jelmer/foo/compare/v1.2.2...v1.2.3\n".to_vec(),
+        ];
+        assert!(super::news_update_compare_links(
+            &mut lines,
+            &"1.2.4".parse().unwrap(),
+            "v1.2.4"
+        ));
+        assert_eq!(
+            String::from_utf8(lines[2..].concat()).unwrap(),
+            "[Unreleased]: https://github.com/jelmer/foo/compare/v1.2.4...HEAD\n\
+             [1.2.4]: https://github.com/jelmer/foo/compare/v1.2.3...v1.2.4\n\
+             [1.2.3]: https://github.com/jelmer/foo/compare/v1.2.2...v1.2.3\n"
+        );
+        let mut lines = vec![b"1.2.3 2021-01-01\n".to_vec()];
+        assert!(!super::news_update_compare_links(
+            &mut lines,
+            &"1.2.4".parse().unwrap(),
+            "v1.2.4"
+        ));
+    }
+
+    #[test]
+    fn test_news_lint() {
+        let lines = [
+            "1.2.4 UNRELEASED\n",
+            "\n",
+            "  * Fix crash.\n",
+            "\n",
+            "1.2.2 2021-13-01\n",
+            "\n",
+            "1.2.3 2021-01-01\n",
+            "\n",
+            "1.2.2 2020-01-01\n",
+        ]
+        .iter()
+        .map(|l| l.as_bytes().to_vec())
+        .collect::<Vec<_>>();
+        assert_eq!(
+            super::news_lint(&lines, &Default::default()).unwrap(),
+            vec![
+                "line 5: date \"2021-13-01\" does not match format \"%Y-%m-%d\"",
+                "line 7: version 1.2.3 is listed below older version 1.2.2",
+                "line 9: version 1.2.2 is listed twice",
+            ]
+        );
+
+        let lines = [
+            "1.2.3 (2021-01-01)\n",
+            "---------\n",
+            "\n",
+            "1.2.4 (UNRELEASED)\n",
+            "------------------\n",
+        ]
+        .iter()
+        .map(|l| l.as_bytes().to_vec())
+        .collect::<Vec<_>>();
+        assert_eq!(
+            super::news_lint(&lines, &Default::default()).unwrap(),
+            vec![
+                "line 2: underline does not match the length of the title",
+                "line 4: unreleased section below released sections",
+                "line 4: unreleased section is empty",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_news_date_format() {
+        let release = |lines: &[&str], layout: &super::NewsLayout| {
+            let mut lines = lines
+                .iter()
+                .map(|l| l.as_bytes().to_vec())
+                .collect::<Vec<_>>();
+            super::news_mark_released_lines(
+                &mut lines,
+                &"1.2.4".parse().unwrap(),
+                &chrono::NaiveDate::from_ymd_opt(2024, 1, 10).unwrap(),
+                layout,
+            )
+            .unwrap();
+            String::from_utf8(lines[0].clone()).unwrap()
+        };
+        let lines = ["1.2.4 UNRELEASED\n", "\n", "1.2.3 01 Jan 2021\n"];
+        assert_eq!(release(&lines, &Default::default()), "1.2.4 10 Jan 2024\n");
+        let lines = ["1.2.4 UNRELEASED\n", "\n", "1.2.3 March 3, 2021\n"];
+        assert_eq!(
+            release(&lines, &Default::default()),
+            "1.2.4 January 10, 2024\n"
+        );
+        let lines = ["1.2.4 (UNRELEASED)\n", "\n", "1.2.3 (January 2021)\n"];
+        assert_eq!(
+            release(&lines, &Default::default()),
+            "1.2.4 (January 2024)\n"
+        );
+        let lines = ["1.2.4 UNRELEASED\n", "\n", "1.2.3 2021-01-01\n"];
+        assert_eq!(
+            release(
+                &lines,
+                &super::NewsLayout::default()
+                    .with_date_format("%d/%m/%Y")
+                    .unwrap()
+            ),
+            "1.2.4 10/01/2024\n"
+        );
+        assert!(super::NewsLayout::default().with_date_format("%Q").is_err());
+    }
+
+    #[test]
+    fn test_news_collapse_pending() {
+        let mut lines = vec![
+            b"1.3.0 UNRELEASED\n".to_vec(),
+            b"\n".to_vec(),
+            b" * Add frobnicator.\n".to_vec(),
+            b"\n".to_vec(),
+            b"1.2.1 UNRELEASED\n".to_vec(),
+            b"\n".to_vec(),
+            b" * Fix crash.\n".to_vec(),
+            b"\n".to_vec(),
+            b"1.2.0 2021-01-01\n".to_vec(),
+        ];
+        assert_eq!(
+            super::news_collapse_pending(&mut lines, &Default::default()).unwrap(),
+            1
+        );
+        assert_eq!(
+            String::from_utf8(lines.concat()).unwrap(),
+            "1.3.0 UNRELEASED\n\n * Add frobnicator.\n * Fix crash.\n\n1.2.0 2021-01-01\n"
+        );
+        assert_eq!(
+            super::news_collapse_pending(&mut lines, &Default::default()).unwrap(),
+            0
+        );
+    }
+
+    #[test]
+    fn test_news_catch_up_default_keeps_other_series() {
+        let mut lines = vec![
+            b"2.0.0 UNRELEASED\n".to_vec(),
+            b"\n".to_vec(),
+            b" * Drop Python 2 support.\n".to_vec(),
+            b"\n".to_vec(),
+            b"1.5.1 UNRELEASED\n".to_vec(),
+            b"\n".to_vec(),
+            b" * Fix crash.\n".to_vec(),
+            b"\n".to_vec(),
+            b"1.5.0 2021-01-01\n".to_vec(),
+        ];
+        let catch_up = crate::project_config::ProjectConfig::default()
+            .catch_up
+            .unwrap_or_default();
+        assert_eq!(
+            super::news_catch_up(&mut lines, &Default::default(), catch_up).unwrap(),
+            0
+        );
+        super::news_mark_released_lines(
+            &mut lines,
+            &"2.0.0".parse().unwrap(),
+            &chrono::NaiveDate::from_ymd_opt(2021, 6, 1).unwrap(),
+            &Default::default(),
+        )
+        .unwrap();
+        assert_eq!(
+            String::from_utf8(lines.concat()).unwrap(),
+            "2.0.0 2021-06-01\n\n * Drop Python 2 support.\n\n1.5.1 UNRELEASED\n\n * Fix crash.\n\n1.5.0 2021-01-01\n"
+        );
+    }
+
+    #[test]
+    fn test_news_released_changes() {
+        let lines = [
+            "1.2.4 UNRELEASED\n",
+            "\n",
+            " * Frobnicator.\n",
+            "\n",
+            "1.2.3 2021-01-01\n",
+            "\n",
+            " * Fix crash.\n",
+            "\n",
+            "1.2.2 2020-01-01\n",
+        ]
+        .iter()
+        .map(|l| l.as_bytes().to_vec())
+        .collect::<Vec<_>>();
+        assert_eq!(
+            super::news_released_changes(&lines, &Default::default(), "1.2.3").unwrap(),
+            Some("\n * Fix crash.\n\n".to_string())
+        );
+        assert_eq!(
+            super::news_released_changes(&lines, &Default::default(), "1.2.4").unwrap(),
+            None
+        );
+    }
 }
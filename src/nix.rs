@@ -0,0 +1,168 @@
+use crate::Version;
+use base64::Engine;
+use breezyshim::tree::{MutableTree, Tree, WorkingTree};
+use lazy_regex::regex;
+use sha2::{Digest, Sha256};
+use std::path::Path;
+
+#[derive(Debug)]
+pub enum Error {
+    BrzError(breezyshim::error::Error),
+    IoError(std::io::Error),
+    NoVersion,
+    NoHash,
+    Other(String),
+}
+
+impl From<breezyshim::error::Error> for Error {
+    fn from(e: breezyshim::error::Error) -> Self {
+        Error::BrzError(e)
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(e: std::io::Error) -> Self {
+        Error::IoError(e)
+    }
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match &self {
+            Error::BrzError(e) => write!(f, "TreeError: {}", e),
+            Error::IoError(e) => write!(f, "IoError: {}", e),
+            Error::NoVersion => write!(f, "No version attribute found"),
+            Error::NoHash => write!(f, "No hash or sha256 attribute found"),
+            Error::Other(e) => write!(f, "Other: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// Compute the SRI hash of a file, as used by fetchurl and friends.
+pub fn sri_hash(path: &Path) -> Result<String, Error> {
+    let contents = std::fs::read(path)?;
+    let digest = Sha256::digest(&contents);
+    Ok(format!(
+        "sha256-{}",
+        base64::engine::general_purpose::STANDARD.encode(digest)
+    ))
+}
+
+/// Update the first `version` and `hash` (or `sha256`) attributes in a nix expression.
+pub fn update_nix_expression(
+    text: &str,
+    new_version: &Version,
+    hash: &str,
+) -> Result<String, Error> {
+    let version_re = regex!(r#"(\bversion\s*=\s*")[^"]*(")"#);
+    if !version_re.is_match(text) {
+        return Err(Error::NoVersion);
+    }
+    let text = version_re.replace(text, |c: &regex::Captures| {
+        format!("{}{}{}", &c[1], new_version.to_string(), &c[2])
+    });
+
+    let hash_re = regex!(r#"(\b(?:hash|sha256)\s*=\s*")[^"]*(")"#);
+    if !hash_re.is_match(&text) {
+        return Err(Error::NoHash);
+    }
+    let text = hash_re.replace(&text, |c: &regex::Captures| {
+        format!("{}{}{}", &c[1], hash, &c[2])
+    });
+
+    Ok(text.into_owned())
+}
+
+/// Update the version and source hash in a nix expression in a tree.
+pub fn update_nix_file(
+    tree: &WorkingTree,
+    path: &Path,
+    new_version: &Version,
+    hash: &str,
+) -> Result<(), Error> {
+    let text = tree.get_file_text(path)?;
+    let text = String::from_utf8(text)
+        .map_err(|e| Error::Other(format!("Invalid UTF-8 in {}: {}", path.display(), e)))?;
+    let updated = update_nix_expression(&text, new_version, hash)?;
+    tree.put_file_bytes_non_atomic(path, updated.as_bytes())?;
+    Ok(())
+}
+
+/// Validate that a nix expression can be updated.
+pub fn validate_update_nix(tree: &dyn Tree, path: &Path) -> Result<(), Error> {
+    let text = tree.get_file_text(path)?;
+    let text = String::from_utf8(text)
+        .map_err(|e| Error::Other(format!("Invalid UTF-8 in {}: {}", path.display(), e)))?;
+    update_nix_expression(&text, &"0".parse().unwrap(), "")?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_update_nix_expression() {
+        let text = r#"{ buildPythonPackage, fetchPypi }:
+
+buildPythonPackage rec {
+  pname = "foo";
+  version = "0.1.0";
+
+  src = fetchPypi {
+    inherit pname version;
+    hash = "sha256-AAAA";
+  };
+}
+"#;
+        assert_eq!(
+            update_nix_expression(text, &"0.2.0".parse().unwrap(), "sha256-BBBB").unwrap(),
+            r#"{ buildPythonPackage, fetchPypi }:
+
+buildPythonPackage rec {
+  pname = "foo";
+  version = "0.2.0";
+
+  src = fetchPypi {
+    inherit pname version;
+    hash = "sha256-BBBB";
+  };
+}
+"#
+        );
+    }
+
+    #[test]
+    fn test_update_nix_expression_sha256() {
+        let text = "{\n  version = \"1.0\";\n  sha256 = \"0000\";\n}\n";
+        assert_eq!(
+            update_nix_expression(text, &"1.1".parse().unwrap(), "sha256-BBBB").unwrap(),
+            "{\n  version = \"1.1\";\n  sha256 = \"sha256-BBBB\";\n}\n"
+        );
+    }
+
+    #[test]
+    fn test_update_nix_expression_missing() {
+        assert!(matches!(
+            update_nix_expression("{ hash = \"\"; }", &"1.0".parse().unwrap(), "x"),
+            Err(Error::NoVersion)
+        ));
+        assert!(matches!(
+            update_nix_expression("{ version = \"0.1\"; }", &"1.0".parse().unwrap(), "x"),
+            Err(Error::NoHash)
+        ));
+    }
+
+    #[test]
+    fn test_sri_hash() {
+        let td = tempfile::tempdir().unwrap();
+        let path = td.path().join("foo");
+        std::fs::write(&path, "").unwrap();
+        assert_eq!(
+            sri_hash(&path).unwrap(),
+            "sha256-47DEQpj8HBSa+/TImW+5JCeuQeRkm5NMpJWZG3hSuFU="
+        );
+    }
+}
@@ -0,0 +1,341 @@
+use crate::Version;
+use breezyshim::tree::{MutableTree, Tree, WorkingTree};
+use serde_json::Value;
+
+use std::path::Path;
+use std::process::Command;
+
+#[derive(Debug)]
+pub enum Error {
+    BrzError(breezyshim::error::Error),
+    JsonError(serde_json::Error),
+    VersionError(String),
+    Other(String),
+}
+
+impl From<breezyshim::error::Error> for Error {
+    fn from(e: breezyshim::error::Error) -> Self {
+        Error::BrzError(e)
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(e: serde_json::Error) -> Self {
+        Error::JsonError(e)
+    }
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match &self {
+            Error::BrzError(e) => write!(f, "TreeError: {}", e),
+            Error::JsonError(e) => write!(f, "JsonError: {}", e),
+            Error::VersionError(e) => write!(f, "VersionError: {}", e),
+            Error::Other(e) => write!(f, "Other: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// Serialize JSON the way npm itself does: two-space indentation and a trailing newline.
+fn to_npm_json(value: &Value) -> Result<String, Error> {
+    let mut ret = serde_json::to_string_pretty(value)?;
+    ret.push('\n');
+    Ok(ret)
+}
+
+fn read_json(tree: &dyn Tree, path: &Path) -> Result<Value, Error> {
+    let contents = tree.get_file_text(path)?;
+    Ok(serde_json::from_slice(contents.as_slice())?)
+}
+
+/// Find the version in package.json; None if it has none, as is common for
+/// packages that are not published.
+pub fn find_version_in_package_json(package_json: &Value) -> Result<Option<Version>, Error> {
+    match package_json.get("version").and_then(|v| v.as_str()) {
+        Some(version) => Ok(Some(version.parse().map_err(|e| {
+            Error::VersionError(format!("Unable to parse version: {}", e))
+        })?)),
+        None => Ok(None),
+    }
+}
+
+pub fn find_version(tree: &dyn Tree) -> Result<Option<Version>, Error> {
+    find_version_in_package_json(&read_json(tree, Path::new("package.json"))?)
+}
+
+/// Check whether package.json marks the package as private, i.e. not to be published.
+pub fn is_private(tree: &dyn Tree) -> Result<bool, Error> {
+    Ok(read_json(tree, Path::new("package.json"))?
+        .get("private")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false))
+}
+
+pub fn find_name(tree: &dyn Tree) -> Result<Option<String>, Error> {
+    Ok(read_json(tree, Path::new("package.json"))?
+        .get("name")
+        .and_then(|v| v.as_str())
+        .map(|s| s.to_string()))
+}
+
+pub fn update_version_in_package_json(
+    package_json: &mut Value,
+    new_version: &str,
+) -> Result<(), Error> {
+    match package_json.get_mut("version") {
+        Some(version) => {
+            *version = Value::String(new_version.to_string());
+            Ok(())
+        }
+        None => Err(Error::Other(
+            "Unable to find version in package.json".to_string(),
+        )),
+    }
+}
+
+/// Update the version of the root package in a package-lock.json file.
+pub fn update_version_in_package_lock(package_lock: &mut Value, new_version: &str) {
+    if let Some(version) = package_lock.get_mut("version") {
+        *version = Value::String(new_version.to_string());
+    }
+    // lockfileVersion 2 and later also list the root package under "packages"
+    if let Some(version) = package_lock
+        .get_mut("packages")
+        .and_then(|p| p.get_mut(""))
+        .and_then(|p| p.get_mut("version"))
+    {
+        *version = Value::String(new_version.to_string());
+    }
+}
+
+pub fn update_version(tree: &WorkingTree, new_version: &str) -> Result<(), Error> {
+    let mut package_json = read_json(tree, Path::new("package.json"))?;
+    update_version_in_package_json(&mut package_json, new_version)?;
+    tree.put_file_bytes_non_atomic(
+        Path::new("package.json"),
+        to_npm_json(&package_json)?.as_bytes(),
+    )?;
+
+    if tree.has_filename(Path::new("package-lock.json")) {
+        let mut package_lock = read_json(tree, Path::new("package-lock.json"))?;
+        update_version_in_package_lock(&mut package_lock, new_version);
+        tree.put_file_bytes_non_atomic(
+            Path::new("package-lock.json"),
+            to_npm_json(&package_lock)?.as_bytes(),
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Convert the "repository" field of a package.json file to a URL.
+///
+/// This handles both the string and object forms, as well as the
+/// "github:user/repo" and "user/repo" shorthands.
+pub fn repository_url_from_package_json(package_json: &Value) -> Option<url::Url> {
+    let repository = package_json.get("repository")?;
+    let url = match repository {
+        Value::String(s) => s.as_str(),
+        Value::Object(o) => o.get("url")?.as_str()?,
+        _ => return None,
+    };
+
+    let url = url.strip_prefix("git+").unwrap_or(url);
+
+    if let Some(path) = url.strip_prefix("github:") {
+        return url::Url::parse(&format!("https://github.com/{}", path)).ok();
+    }
+
+    match url::Url::parse(url) {
+        Ok(url) => Some(url),
+        Err(_) if url.split('/').count() == 2 && !url.contains(':') => {
+            url::Url::parse(&format!("https://github.com/{}", url)).ok()
+        }
+        Err(e) => {
+            log::warn!("Could not parse repository URL {}: {}", url, e);
+            None
+        }
+    }
+}
+
+pub fn read_project_urls_from_package_json(
+    tree: &dyn Tree,
+) -> Result<Vec<(url::Url, Option<String>)>, Error> {
+    let package_json = read_json(tree, Path::new("package.json"))?;
+    Ok(repository_url_from_package_json(&package_json)
+        .map(|url| vec![(url, None)])
+        .unwrap_or_default())
+}
+
+/// npm reads configuration from npm_config_* environment variables, which
+/// avoids having to write the token to .npmrc.
+const NPM_AUTH_TOKEN_ENV: &str = "npm_config_//registry.npmjs.org/:_authToken";
+
+fn run_npm(tree: &WorkingTree, args: &[&str]) -> Result<(), Error> {
+    let mut command = Command::new("npm");
+    command
+        .args(args)
+        .current_dir(tree.abspath(Path::new("."))?);
+    if let Some(token) = crate::token_cache::cached_token(crate::token_cache::Service::Npm) {
+        command.env(NPM_AUTH_TOKEN_ENV, token);
+    }
+    let status = crate::logging::status(&mut command)
+        .map_err(|e| Error::Other(format!("Unable to spawn npm {}: {}", args[0], e)))?;
+    if !status.success() {
+        return Err(Error::Other(format!(
+            "npm {} failed: {}",
+            args.join(" "),
+            status
+        )));
+    }
+    Ok(())
+}
+
+/// Publish a package to the npm registry.
+///
+/// If `dist_tag` is set, the release is published under that tag rather than
+/// "latest".
+pub fn publish(tree: &WorkingTree, provenance: bool, dist_tag: Option<&str>) -> Result<(), Error> {
+    let mut args = vec!["publish"];
+    if provenance {
+        args.push("--provenance");
+    }
+    if let Some(dist_tag) = dist_tag {
+        args.extend(["--tag", dist_tag]);
+    }
+    run_npm(tree, &args)
+}
+
+/// Point a dist-tag at an already published version.
+pub fn add_dist_tag(tree: &WorkingTree, version: &Version, dist_tag: &str) -> Result<(), Error> {
+    let name = find_name(tree)?
+        .ok_or_else(|| Error::Other("Unable to find name in package.json".to_string()))?;
+    run_npm(
+        tree,
+        &[
+            "dist-tag",
+            "add",
+            format!("{}@{}", name, version.to_string()).as_str(),
+            dist_tag,
+        ],
+    )
+}
+
+/// Find out when a version of the package was published to the npm registry.
+///
+/// Returns None if the version has not been published.
+pub fn publish_time(
+    tree: &WorkingTree,
+    version: &Version,
+) -> Result<Option<chrono::DateTime<chrono::Utc>>, Error> {
+    let name = find_name(tree)?
+        .ok_or_else(|| Error::Other("Unable to find name in package.json".to_string()))?;
+    let output = Command::new("npm")
+        .args(["view", name.as_str(), "time", "--json"])
+        .current_dir(tree.abspath(Path::new("."))?)
+        .output()
+        .map_err(|e| Error::Other(format!("Unable to spawn npm view: {}", e)))?;
+    if !output.status.success() {
+        return Err(Error::Other(format!(
+            "npm view {} time failed: {}",
+            name, output.status
+        )));
+    }
+    parse_publish_time(&output.stdout, &version.to_string())
+}
+
+/// Extract the publish time of `version` from the output of `npm view <name> time --json`.
+fn parse_publish_time(
+    output: &[u8],
+    version: &str,
+) -> Result<Option<chrono::DateTime<chrono::Utc>>, Error> {
+    let times: std::collections::HashMap<String, String> = serde_json::from_slice(output)?;
+    times
+        .get(version)
+        .map(|t| {
+            chrono::DateTime::parse_from_rfc3339(t)
+                .map(|t| t.with_timezone(&chrono::Utc))
+                .map_err(|e| Error::Other(format!("Invalid publish time {:?}: {}", t, e)))
+        })
+        .transpose()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_publish_time() {
+        let output = br#"{
+  "created": "2024-01-01T10:00:00.000Z",
+  "modified": "2024-02-01T10:00:00.000Z",
+  "1.0.0": "2024-01-01T10:00:00.000Z",
+  "1.1.0": "2024-02-01T10:00:00.000Z"
+}"#;
+        assert_eq!(
+            parse_publish_time(output, "1.1.0").unwrap(),
+            Some("2024-02-01T10:00:00Z".parse().unwrap())
+        );
+        assert_eq!(parse_publish_time(output, "1.2.0").unwrap(), None);
+    }
+
+    #[test]
+    fn test_update_version_in_package_json() {
+        let mut package_json: Value =
+            serde_json::from_str(r#"{"name": "foo", "version": "0.1.0", "main": "index.js"}"#)
+                .unwrap();
+        update_version_in_package_json(&mut package_json, "0.2.0").unwrap();
+        assert_eq!(
+            to_npm_json(&package_json).unwrap(),
+            "{\n  \"name\": \"foo\",\n  \"version\": \"0.2.0\",\n  \"main\": \"index.js\"\n}\n"
+        );
+        assert_eq!(
+            find_version_in_package_json(&package_json).unwrap(),
+            Some("0.2.0".parse().unwrap())
+        );
+
+        let mut package_json: Value = serde_json::from_str(r#"{"name": "foo"}"#).unwrap();
+        assert_eq!(find_version_in_package_json(&package_json).unwrap(), None);
+        assert!(update_version_in_package_json(&mut package_json, "0.2.0").is_err());
+    }
+
+    #[test]
+    fn test_update_version_in_package_lock() {
+        let mut package_lock: Value = serde_json::from_str(
+            r#"{"name": "foo", "version": "0.1.0", "lockfileVersion": 3, "packages": {"": {"name": "foo", "version": "0.1.0"}, "node_modules/bar": {"version": "0.1.0"}}}"#,
+        )
+        .unwrap();
+        update_version_in_package_lock(&mut package_lock, "0.2.0");
+        assert_eq!(package_lock["version"], "0.2.0");
+        assert_eq!(package_lock["packages"][""]["version"], "0.2.0");
+        assert_eq!(
+            package_lock["packages"]["node_modules/bar"]["version"],
+            "0.1.0"
+        );
+    }
+
+    #[test]
+    fn test_repository_url_from_package_json() {
+        let parse = |s: &str| {
+            repository_url_from_package_json(&serde_json::from_str(s).unwrap())
+                .map(|u| u.to_string())
+        };
+        assert_eq!(
+            parse(
+                r#"{"repository": {"type": "git", "url": "git+https://github.com/foo/bar.git"}}"#
+            ),
+            Some("https://github.com/foo/bar.git".to_string())
+        );
+        assert_eq!(
+            parse(r#"{"repository": "github:foo/bar"}"#),
+            Some("https://github.com/foo/bar".to_string())
+        );
+        assert_eq!(
+            parse(r#"{"repository": "foo/bar"}"#),
+            Some("https://github.com/foo/bar".to_string())
+        );
+        assert_eq!(parse(r#"{"name": "foo"}"#), None);
+    }
+}
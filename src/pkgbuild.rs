@@ -0,0 +1,186 @@
+use crate::Version;
+use breezyshim::tree::{MutableTree, Tree, WorkingTree};
+use lazy_regex::regex;
+use sha2::{Digest, Sha256};
+use std::path::Path;
+
+#[derive(Debug)]
+pub enum Error {
+    BrzError(breezyshim::error::Error),
+    IoError(std::io::Error),
+    MissingField(&'static str),
+    Other(String),
+}
+
+impl From<breezyshim::error::Error> for Error {
+    fn from(e: breezyshim::error::Error) -> Self {
+        Error::BrzError(e)
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(e: std::io::Error) -> Self {
+        Error::IoError(e)
+    }
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match &self {
+            Error::BrzError(e) => write!(f, "TreeError: {}", e),
+            Error::IoError(e) => write!(f, "IoError: {}", e),
+            Error::MissingField(field) => write!(f, "No {} found", field),
+            Error::Other(e) => write!(f, "Other: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// Compute the hex-encoded SHA-256 checksum of a file, as used in sha256sums.
+pub fn sha256sum(path: &Path) -> std::io::Result<String> {
+    let contents = std::fs::read(path)?;
+    Ok(Sha256::digest(&contents)
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect())
+}
+
+/// Update pkgver, reset pkgrel and replace the first checksum in a PKGBUILD.
+pub fn update_pkgbuild(
+    text: &str,
+    new_version: &Version,
+    sha256: Option<&str>,
+) -> Result<String, Error> {
+    let pkgver_re = regex!(r"(?m)^pkgver=.*$");
+    if !pkgver_re.is_match(text) {
+        return Err(Error::MissingField("pkgver"));
+    }
+    let text = pkgver_re.replace(text, format!("pkgver={}", new_version.to_string()));
+    let text = regex!(r"(?m)^pkgrel=.*$").replace(&text, "pkgrel=1");
+
+    let text = if let Some(sha256) = sha256 {
+        let sums_re = regex!(r#"(?m)^(sha256sums=\(\s*['"]?)[0-9a-fA-F]+"#);
+        if !sums_re.is_match(&text) {
+            return Err(Error::MissingField("sha256sums"));
+        }
+        sums_re
+            .replace(&text, |c: &regex::Captures| format!("{}{}", &c[1], sha256))
+            .into_owned()
+    } else {
+        text.into_owned()
+    };
+
+    Ok(text)
+}
+
+/// Make the same changes as [`update_pkgbuild`] to a .SRCINFO file.
+///
+/// Since .SRCINFO contains expanded values, occurrences of the old version
+/// in source URLs are replaced as well.
+pub fn update_srcinfo(
+    text: &str,
+    new_version: &Version,
+    sha256: Option<&str>,
+) -> Result<String, Error> {
+    let new_version = new_version.to_string();
+    let old_version = text
+        .lines()
+        .find_map(|l| l.trim().strip_prefix("pkgver = "))
+        .ok_or(Error::MissingField("pkgver"))?
+        .to_string();
+
+    let mut seen_sha256sums = false;
+    let mut lines = vec![];
+    for line in text.lines() {
+        let indent = &line[..line.len() - line.trim_start().len()];
+        let (key, value) = match line.trim().split_once(" = ") {
+            Some(kv) => kv,
+            None => {
+                lines.push(line.to_string());
+                continue;
+            }
+        };
+        let value = match key {
+            "pkgver" => new_version.clone(),
+            "pkgrel" => "1".to_string(),
+            "sha256sums" if !seen_sha256sums && sha256.is_some() => {
+                seen_sha256sums = true;
+                sha256.unwrap().to_string()
+            }
+            k if k == "source" || k.starts_with("source_") => {
+                value.replace(&old_version, &new_version)
+            }
+            _ => value.to_string(),
+        };
+        lines.push(format!("{}{} = {}", indent, key, value));
+    }
+
+    let mut ret = lines.join("\n");
+    if text.ends_with('\n') {
+        ret.push('\n');
+    }
+    Ok(ret)
+}
+
+/// Update a PKGBUILD, and the .SRCINFO next to it if there is one.
+///
+/// Returns the paths that were modified.
+pub fn update_pkgbuild_file(
+    tree: &WorkingTree,
+    path: &Path,
+    new_version: &Version,
+    sha256: Option<&str>,
+) -> Result<Vec<std::path::PathBuf>, Error> {
+    let text = String::from_utf8_lossy(&tree.get_file_text(path)?).into_owned();
+    let updated = update_pkgbuild(&text, new_version, sha256)?;
+    tree.put_file_bytes_non_atomic(path, updated.as_bytes())?;
+    let mut modified = vec![path.to_path_buf()];
+
+    let srcinfo_path = path.with_file_name(".SRCINFO");
+    if tree.has_filename(&srcinfo_path) {
+        let text = String::from_utf8_lossy(&tree.get_file_text(&srcinfo_path)?).into_owned();
+        let updated = update_srcinfo(&text, new_version, sha256)?;
+        tree.put_file_bytes_non_atomic(&srcinfo_path, updated.as_bytes())?;
+        modified.push(srcinfo_path);
+    }
+
+    Ok(modified)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_update_pkgbuild() {
+        let text = r#"pkgname=foo
+pkgver=0.1.0
+pkgrel=3
+source=("https://example.com/foo-$pkgver.tar.gz")
+sha256sums=('0123456789abcdef')
+"#;
+        assert_eq!(
+            update_pkgbuild(text, &"0.2.0".parse().unwrap(), Some("fedcba")).unwrap(),
+            r#"pkgname=foo
+pkgver=0.2.0
+pkgrel=1
+source=("https://example.com/foo-$pkgver.tar.gz")
+sha256sums=('fedcba')
+"#
+        );
+        assert!(matches!(
+            update_pkgbuild("pkgname=foo\n", &"0.2.0".parse().unwrap(), None),
+            Err(Error::MissingField("pkgver"))
+        ));
+    }
+
+    #[test]
+    fn test_update_srcinfo() {
+        let text = "pkgbase = foo\n\tpkgver = 0.1.0\n\tpkgrel = 3\n\tsource = https://example.com/foo-0.1.0.tar.gz\n\tsha256sums = 0123456789abcdef\n\npkgname = foo\n";
+        assert_eq!(
+            update_srcinfo(text, &"0.2.0".parse().unwrap(), Some("fedcba")).unwrap(),
+            "pkgbase = foo\n\tpkgver = 0.2.0\n\tpkgrel = 1\n\tsource = https://example.com/foo-0.2.0.tar.gz\n\tsha256sums = fedcba\n\npkgname = foo\n"
+        );
+    }
+}
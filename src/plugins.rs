@@ -0,0 +1,330 @@
+//! Release pipeline extensions, as WebAssembly modules.
+//!
+//! A plugin exports its linear memory as `memory`, an `alloc(len: i32) -> i32`
+//! function that returns a buffer of `len` bytes, and a function for each hook
+//! it handles (`on_plan`, `on_pre_tag` and `on_post_publish`). Hooks are
+//! called as `hook(ptr: i32, len: i32) -> i64` with the JSON encoded
+//! [`ReleaseContext`] in the buffer at `ptr`, and return the location of the
+//! JSON encoded [`Verdict`] as `ptr << 32 | len`, or 0 to let the release
+//! proceed. Plugins can't import anything, so they have no access to the
+//! filesystem or network.
+
+use std::path::{Path, PathBuf};
+
+#[derive(Debug)]
+pub enum Error {
+    /// The plugin can't be loaded by this build of disperse.
+    Unsupported(PathBuf),
+    /// The plugin failed while handling a hook.
+    Failed { plugin: String, reason: String },
+    /// The plugin vetoed the release.
+    Vetoed { plugin: String, reason: String },
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match &self {
+            Error::Unsupported(path) => write!(
+                f,
+                "Unable to load plugin {}: this build of disperse has no WASM runtime",
+                path.display()
+            ),
+            Error::Failed { plugin, reason } => write!(f, "Plugin {} failed: {}", plugin, reason),
+            Error::Vetoed { plugin, reason } => {
+                write!(f, "Release vetoed by plugin {}: {}", plugin, reason)
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// Point in the release at which plugins are called.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+pub enum Hook {
+    /// The new version has been picked, but nothing has been changed yet.
+    #[serde(rename = "on_plan")]
+    Plan,
+    /// The release has been committed and is about to be tagged.
+    #[serde(rename = "on_pre_tag")]
+    PreTag,
+    /// The release has been tagged and its artifacts published.
+    #[serde(rename = "on_post_publish")]
+    PostPublish,
+}
+
+impl std::fmt::Display for Hook {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str(match self {
+            Hook::Plan => "on_plan",
+            Hook::PreTag => "on_pre_tag",
+            Hook::PostPublish => "on_post_publish",
+        })
+    }
+}
+
+/// What plugins get to see of a release.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct ReleaseContext {
+    pub hook: Hook,
+    pub project: String,
+    pub version: String,
+    pub tag: Option<String>,
+    pub dry_run: bool,
+}
+
+/// How a plugin responds to a hook.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "kebab-case", tag = "action", content = "message")]
+pub enum Verdict {
+    Proceed,
+    /// Let the release proceed, with a note to report alongside it.
+    Annotate(String),
+    /// Stop the release.
+    Veto(String),
+}
+
+/// A release pipeline extension.
+pub trait Plugin {
+    fn name(&self) -> &str;
+
+    fn call(&mut self, context: &ReleaseContext) -> Result<Verdict, Error>;
+}
+
+/// Amount of fuel a plugin gets for each hook call, so that a plugin that
+/// doesn't return can't hang the release.
+#[cfg(feature = "wasm")]
+const FUEL: u64 = 10_000_000_000;
+
+/// A plugin that runs in the WASM runtime.
+#[cfg(feature = "wasm")]
+pub struct WasmPlugin {
+    name: String,
+    store: wasmtime::Store<()>,
+    instance: wasmtime::Instance,
+}
+
+#[cfg(feature = "wasm")]
+impl WasmPlugin {
+    pub fn load(engine: &wasmtime::Engine, path: &Path) -> Result<Self, Error> {
+        let name = path.file_name().map_or_else(
+            || path.display().to_string(),
+            |n| n.to_string_lossy().into_owned(),
+        );
+        let failed = |e: wasmtime::Error| Error::Failed {
+            plugin: name.clone(),
+            reason: format!("{:#}", e),
+        };
+        let module = wasmtime::Module::from_file(engine, path).map_err(failed)?;
+        let mut store = wasmtime::Store::new(engine, ());
+        let instance = wasmtime::Instance::new(&mut store, &module, &[]).map_err(failed)?;
+        Ok(WasmPlugin {
+            name,
+            store,
+            instance,
+        })
+    }
+
+    fn failed(&self, reason: impl std::fmt::Display) -> Error {
+        Error::Failed {
+            plugin: self.name.clone(),
+            reason: format!("{:#}", reason),
+        }
+    }
+
+    fn call_hook(
+        &mut self,
+        hook: wasmtime::Func,
+        context: &ReleaseContext,
+    ) -> wasmtime::Result<Option<Vec<u8>>> {
+        let hook = hook.typed::<(i32, i32), i64>(&self.store)?;
+        let alloc = self
+            .instance
+            .get_typed_func::<i32, i32>(&mut self.store, "alloc")?;
+        let memory = self
+            .instance
+            .get_memory(&mut self.store, "memory")
+            .ok_or_else(|| wasmtime::Error::msg("no memory exported"))?;
+        self.store.set_fuel(FUEL)?;
+        let input = serde_json::to_vec(context)?;
+        let ptr = alloc.call(&mut self.store, input.len() as i32)?;
+        memory.write(&mut self.store, ptr as u32 as usize, &input)?;
+        let ret = hook.call(&mut self.store, (ptr, input.len() as i32))? as u64;
+        if ret == 0 {
+            return Ok(None);
+        }
+        let mut output = vec![0; (ret & 0xffff_ffff) as usize];
+        memory.read(&self.store, (ret >> 32) as usize, &mut output)?;
+        Ok(Some(output))
+    }
+}
+
+#[cfg(feature = "wasm")]
+impl Plugin for WasmPlugin {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn call(&mut self, context: &ReleaseContext) -> Result<Verdict, Error> {
+        let Some(hook) = self
+            .instance
+            .get_func(&mut self.store, &context.hook.to_string())
+        else {
+            return Ok(Verdict::Proceed);
+        };
+        match self.call_hook(hook, context) {
+            Ok(Some(output)) => serde_json::from_slice(&output)
+                .map_err(|e| self.failed(format!("invalid verdict: {}", e))),
+            Ok(None) => Ok(Verdict::Proceed),
+            Err(e) => Err(self.failed(e)),
+        }
+    }
+}
+
+/// Load the plugins at the given paths, relative to `base`.
+///
+/// Without the WASM runtime, configuring any plugin is an error rather than
+/// silently skipping it.
+pub fn load(base: &Path, paths: &[PathBuf]) -> Result<Vec<Box<dyn Plugin>>, Error> {
+    #[cfg(feature = "wasm")]
+    {
+        if paths.is_empty() {
+            return Ok(vec![]);
+        }
+        let mut config = wasmtime::Config::new();
+        config.consume_fuel(true);
+        let engine = wasmtime::Engine::new(&config).map_err(|e| Error::Failed {
+            plugin: paths[0].display().to_string(),
+            reason: format!("{:#}", e),
+        })?;
+        paths
+            .iter()
+            .map(|path| {
+                WasmPlugin::load(&engine, &base.join(path)).map(|p| Box::new(p) as Box<dyn Plugin>)
+            })
+            .collect()
+    }
+    #[cfg(not(feature = "wasm"))]
+    match paths.first() {
+        Some(path) => Err(Error::Unsupported(base.join(path))),
+        None => Ok(vec![]),
+    }
+}
+
+/// Call a hook on all plugins, in order.
+///
+/// Returns the annotations the plugins made, or an error for the first
+/// plugin that vetoed the release.
+pub fn run_hook(
+    plugins: &mut [Box<dyn Plugin>],
+    context: &ReleaseContext,
+) -> Result<Vec<String>, Error> {
+    let mut annotations = vec![];
+    for plugin in plugins.iter_mut() {
+        log::debug!("Calling {} on plugin {}", context.hook, plugin.name());
+        match plugin.call(context)? {
+            Verdict::Proceed => {}
+            Verdict::Annotate(note) => annotations.push(note),
+            Verdict::Veto(reason) => {
+                return Err(Error::Vetoed {
+                    plugin: plugin.name().to_string(),
+                    reason,
+                })
+            }
+        }
+    }
+    Ok(annotations)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Fixed(&'static str, Verdict);
+
+    impl Plugin for Fixed {
+        fn name(&self) -> &str {
+            self.0
+        }
+
+        fn call(&mut self, _context: &ReleaseContext) -> Result<Verdict, Error> {
+            Ok(self.1.clone())
+        }
+    }
+
+    #[test]
+    fn test_run_hook() {
+        let context = ReleaseContext {
+            hook: Hook::PreTag,
+            project: "foo".to_string(),
+            version: "1.2.3".to_string(),
+            tag: Some("v1.2.3".to_string()),
+            dry_run: false,
+        };
+        let mut plugins: Vec<Box<dyn Plugin>> = vec![
+            Box::new(Fixed("a", Verdict::Proceed)),
+            Box::new(Fixed("b", Verdict::Annotate("signed off".to_string()))),
+        ];
+        assert_eq!(
+            run_hook(&mut plugins, &context).unwrap(),
+            vec!["signed off".to_string()]
+        );
+        plugins.push(Box::new(Fixed("c", Verdict::Veto("freeze".to_string()))));
+        match run_hook(&mut plugins, &context) {
+            Err(Error::Vetoed { plugin, reason }) => {
+                assert_eq!((plugin, reason), ("c".to_string(), "freeze".to_string()))
+            }
+            r => panic!("unexpected result: {:?}", r),
+        }
+    }
+
+    #[cfg(feature = "wasm")]
+    #[test]
+    fn test_wasm_plugin() {
+        let td = tempfile::tempdir().unwrap();
+        std::fs::write(
+            td.path().join("freeze.wat"),
+            r#"(module
+                 (memory (export "memory") 1)
+                 (data (i32.const 16) "{\"action\":\"veto\",\"message\":\"freeze\"}")
+                 (func (export "alloc") (param i32) (result i32) i32.const 1024)
+                 (func (export "on_pre_tag") (param i32 i32) (result i64)
+                   i64.const 68719476772))"#,
+        )
+        .unwrap();
+        let mut plugins = load(td.path(), &[PathBuf::from("freeze.wat")]).unwrap();
+        let mut context = ReleaseContext {
+            hook: Hook::Plan,
+            project: "foo".to_string(),
+            version: "1.2.3".to_string(),
+            tag: None,
+            dry_run: false,
+        };
+        assert_eq!(
+            run_hook(&mut plugins, &context).unwrap(),
+            Vec::<String>::new()
+        );
+        context.hook = Hook::PreTag;
+        match run_hook(&mut plugins, &context) {
+            Err(Error::Vetoed { plugin, reason }) => {
+                assert_eq!(
+                    (plugin, reason),
+                    ("freeze.wat".to_string(), "freeze".to_string())
+                )
+            }
+            r => panic!("unexpected result: {:?}", r),
+        }
+    }
+
+    #[test]
+    fn test_verdict_deserialize() {
+        assert_eq!(
+            serde_json::from_str::<Verdict>(r#"{"action": "proceed"}"#).unwrap(),
+            Verdict::Proceed
+        );
+        assert_eq!(
+            serde_json::from_str::<Verdict>(r#"{"action": "veto", "message": "freeze"}"#).unwrap(),
+            Verdict::Veto("freeze".to_string())
+        );
+    }
+}
@@ -0,0 +1,76 @@
+use std::sync::OnceLock;
+
+static ACTIVE: OnceLock<String> = OnceLock::new();
+
+/// Select the profile whose credentials are used.
+pub fn activate(name: &str) {
+    if ACTIVE.set(name.to_string()).is_err() {
+        log::warn!("A profile was already activated; not switching to {}", name);
+    }
+}
+
+/// Name of the active profile, if any.
+pub fn active() -> Option<&'static str> {
+    ACTIVE.get().map(|s| s.as_str())
+}
+
+fn qualify_with(name: &str, profile: Option<&str>) -> String {
+    match profile {
+        Some(profile) => format!("{}@{}", name, profile),
+        None => name.to_string(),
+    }
+}
+
+/// Qualify the name under which something (e.g. a credential) is stored
+/// with the active profile, so that profiles don't share it.
+pub fn qualify(name: &str) -> String {
+    qualify_with(name, active())
+}
+
+/// Split an identity like "Jane Doe <jane@example.com>" into name and e-mail address.
+pub fn parse_identity(identity: &str) -> Option<(&str, &str)> {
+    let (_, name, email) = lazy_regex::regex_captures!(r"^\s*(.*?)\s*<([^<>\s]+)>\s*$", identity)?;
+    if name.is_empty() {
+        None
+    } else {
+        Some((name, email))
+    }
+}
+
+/// Environment variables that make Breezy and git commit and tag as an identity.
+pub fn identity_env(identity: &str) -> Result<Vec<(&'static str, String)>, String> {
+    let (name, email) = parse_identity(identity)
+        .ok_or_else(|| format!("Invalid identity {:?}; expected \"Name <email>\"", identity))?;
+    Ok(vec![
+        ("BRZ_EMAIL", format!("{} <{}>", name, email)),
+        ("GIT_AUTHOR_NAME", name.to_string()),
+        ("GIT_AUTHOR_EMAIL", email.to_string()),
+        ("GIT_COMMITTER_NAME", name.to_string()),
+        ("GIT_COMMITTER_EMAIL", email.to_string()),
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_qualify_with() {
+        assert_eq!(qualify_with("tokens", None), "tokens");
+        assert_eq!(qualify_with("tokens", Some("work")), "tokens@work");
+    }
+
+    #[test]
+    fn test_identity_env() {
+        assert_eq!(
+            parse_identity("Jane Doe <jane@example.com>"),
+            Some(("Jane Doe", "jane@example.com"))
+        );
+        assert_eq!(parse_identity("jane@example.com"), None);
+        assert_eq!(parse_identity("<jane@example.com>"), None);
+        let env = identity_env("Jane Doe <jane@example.com>").unwrap();
+        assert!(env.contains(&("BRZ_EMAIL", "Jane Doe <jane@example.com>".to_string())));
+        assert!(env.contains(&("GIT_COMMITTER_EMAIL", "jane@example.com".to_string())));
+        assert!(identity_env("Jane Doe").is_err());
+    }
+}
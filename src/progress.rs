@@ -0,0 +1,192 @@
+use std::io::{IsTerminal, Write};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+const SPINNER: &[char] = &['|', '/', '-', '\\'];
+
+/// How often the spinner is redrawn.
+const TICK: Duration = Duration::from_millis(100);
+
+/// Held while writing to the terminal, so that the spinner and log messages
+/// don't end up on the same line.
+static TERMINAL: Mutex<()> = Mutex::new(());
+
+/// Whether the spinner is currently on the terminal.
+static DRAWN: AtomicBool = AtomicBool::new(false);
+
+/// Number of subprocesses writing to the terminal; the spinner is hidden
+/// while there are any.
+static SUSPENDED: AtomicUsize = AtomicUsize::new(0);
+
+/// Format a duration for humans, e.g. "1m05s".
+pub fn format_duration(d: Duration) -> String {
+    let secs = d.as_secs();
+    if secs >= 3600 {
+        format!("{}h{:02}m{:02}s", secs / 3600, secs / 60 % 60, secs % 60)
+    } else if secs >= 60 {
+        format!("{}m{:02}s", secs / 60, secs % 60)
+    } else {
+        format!("{}s", secs)
+    }
+}
+
+fn status_line(
+    message: &str,
+    frame: usize,
+    elapsed: Duration,
+    remaining: Option<Duration>,
+) -> String {
+    let spinner = SPINNER[frame % SPINNER.len()];
+    match remaining {
+        Some(remaining) => format!(
+            "{} {} [{}, {} left]",
+            spinner,
+            message,
+            format_duration(elapsed),
+            format_duration(remaining)
+        ),
+        None => format!("{} {} [{}]", spinner, message, format_duration(elapsed)),
+    }
+}
+
+/// Remove the spinner from the terminal, so that something else can be written.
+///
+/// The spinner stays off the terminal until the returned guard is dropped.
+pub(crate) fn clear() -> MutexGuard<'static, ()> {
+    let guard = TERMINAL.lock().unwrap_or_else(|e| e.into_inner());
+    if DRAWN.swap(false, Ordering::SeqCst) {
+        let mut stderr = std::io::stderr().lock();
+        let _ = write!(stderr, "\r\x1b[K");
+        let _ = stderr.flush();
+    }
+    guard
+}
+
+/// Keeps the spinner off the terminal while a subprocess writes to it.
+pub struct Suspended(());
+
+impl Drop for Suspended {
+    fn drop(&mut self) {
+        SUSPENDED.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Hide the spinner until the returned value is dropped.
+pub fn suspend() -> Suspended {
+    SUSPENDED.fetch_add(1, Ordering::SeqCst);
+    drop(clear());
+    Suspended(())
+}
+
+/// Feedback on a long running operation.
+///
+/// On a terminal this shows a spinner with the time elapsed (and, if there is
+/// a timeout, the time left) until it is dropped. Elsewhere, the start of the
+/// operation is simply logged. Either way, the time taken is logged at the end.
+pub struct Progress {
+    message: String,
+    start: Instant,
+    spinner: Option<(Arc<AtomicBool>, JoinHandle<()>)>,
+}
+
+impl Progress {
+    pub fn start(message: impl Into<String>) -> Self {
+        Self::new(message.into(), None)
+    }
+
+    /// Start showing progress, with a countdown to `timeout`.
+    pub fn with_timeout(message: impl Into<String>, timeout: Duration) -> Self {
+        Self::new(message.into(), Some(timeout))
+    }
+
+    fn new(message: String, timeout: Option<Duration>) -> Self {
+        let start = Instant::now();
+        let spinner = if crate::logging::console_enabled(log::Level::Info)
+            && std::io::stderr().is_terminal()
+        {
+            let stop = Arc::new(AtomicBool::new(false));
+            let handle = {
+                let stop = stop.clone();
+                let message = message.clone();
+                std::thread::spawn(move || {
+                    let mut frame = 0;
+                    while !stop.load(Ordering::SeqCst) {
+                        {
+                            let _guard = TERMINAL.lock().unwrap_or_else(|e| e.into_inner());
+                            if SUSPENDED.load(Ordering::SeqCst) == 0 {
+                                let elapsed = start.elapsed();
+                                let line = status_line(
+                                    &message,
+                                    frame,
+                                    elapsed,
+                                    timeout.map(|t| t.saturating_sub(elapsed)),
+                                );
+                                let mut stderr = std::io::stderr().lock();
+                                let _ = write!(stderr, "\r\x1b[K{}", line);
+                                let _ = stderr.flush();
+                                DRAWN.store(true, Ordering::SeqCst);
+                            }
+                        }
+                        frame += 1;
+                        std::thread::sleep(TICK);
+                    }
+                })
+            };
+            Some((stop, handle))
+        } else {
+            log::info!("{}...", message);
+            None
+        };
+        Self {
+            message,
+            start,
+            spinner,
+        }
+    }
+}
+
+impl Drop for Progress {
+    fn drop(&mut self) {
+        if let Some((stop, handle)) = self.spinner.take() {
+            stop.store(true, Ordering::SeqCst);
+            let _ = handle.join();
+            drop(clear());
+        }
+        log::info!(
+            "{} took {}",
+            self.message,
+            format_duration(self.start.elapsed())
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_duration() {
+        assert_eq!(format_duration(Duration::from_secs(5)), "5s");
+        assert_eq!(format_duration(Duration::from_secs(65)), "1m05s");
+        assert_eq!(format_duration(Duration::from_secs(3725)), "1h02m05s");
+    }
+
+    #[test]
+    fn test_status_line() {
+        assert_eq!(
+            status_line("Waiting for CI", 1, Duration::from_secs(65), None),
+            "/ Waiting for CI [1m05s]"
+        );
+        assert_eq!(
+            status_line(
+                "Waiting for CI",
+                4,
+                Duration::from_secs(65),
+                Some(Duration::from_secs(120))
+            ),
+            "| Waiting for CI [1m05s, 2m00s left]"
+        );
+    }
+}
@@ -3,7 +3,8 @@ use breezyshim::tree::Tree;
 use std::path::{Path, PathBuf};
 include!(concat!(env!("OUT_DIR"), "/generated/mod.rs"));
 
-#[derive(serde::Deserialize, serde::Serialize, Default)]
+#[derive(serde::Deserialize, serde::Serialize, schemars::JsonSchema, Default)]
+#[schemars(deny_unknown_fields)]
 pub struct ProjectConfig {
     #[serde(default)]
     pub name: Option<String>,
@@ -14,8 +15,24 @@ pub struct ProjectConfig {
     #[serde(default)]
     pub update_version: Option<Vec<UpdateVersion>>,
 
+    /// Additional variables for update_version, mapping names to format
+    /// expressions such as "{major}_{minor}_{micro}".
+    #[serde(default, rename = "version-variables")]
+    pub version_variables: Option<crate::custom::CustomVariables>,
+
     #[serde(default, rename = "update-manpage")]
-    pub update_manpages: Option<Vec<PathBuf>>,
+    pub update_manpages: Option<Vec<UpdateManpage>>,
+
+    #[serde(default, rename = "update-metainfo")]
+    pub update_metainfo: Option<Vec<PathBuf>>,
+
+    #[serde(default, rename = "update-nix")]
+    pub update_nix: Option<Vec<PathBuf>>,
+
+    /// Documents (or globs) with install instructions and badges that
+    /// mention the version of the project.
+    #[serde(default, rename = "update-docs")]
+    pub update_docs: Option<Vec<PathBuf>>,
 
     #[serde(default)]
     pub launchpad: Option<Launchpad>,
@@ -26,6 +43,32 @@ pub struct ProjectConfig {
     #[serde(default, rename = "news-file")]
     pub news_file: Option<PathBuf>,
 
+    /// Template for version lines in the news file, e.g. "%(version)s (%(date)s)".
+    ///
+    /// If not set, the format is inferred from the first entry.
+    #[serde(default, rename = "news-format")]
+    pub news_format: Option<String>,
+
+    /// Regular expressions matching header lines to skip in the news file.
+    #[serde(default, rename = "news-skip")]
+    pub news_skip: Option<Vec<String>>,
+
+    /// Fill an empty pending section in the news file from the commit
+    /// messages since the last release.
+    #[serde(default, rename = "news-from-commits")]
+    pub news_from_commits: Option<NewsFromCommits>,
+
+    /// strftime format for release dates in the news file; by default the
+    /// format of earlier entries is used.
+    #[serde(default, rename = "news-date-format")]
+    pub news_date_format: Option<String>,
+
+    /// Add a pending section to the news file if it doesn't have one but
+    /// there are new commits, rather than refusing to release; defaults to
+    /// false.
+    #[serde(default, rename = "create-pending")]
+    pub create_pending: Option<bool>,
+
     #[serde(default, rename = "pre-dist-command")]
     pub pre_dist_command: Option<String>,
 
@@ -35,6 +78,24 @@ pub struct ProjectConfig {
     #[serde(default, rename = "twine-upload")]
     pub twine_upload: Option<bool>,
 
+    #[serde(default, rename = "python-upload-tool")]
+    pub python_upload_tool: Option<PythonUploadTool>,
+
+    #[serde(default)]
+    pub maturin: Option<Maturin>,
+
+    /// Whether to update __version__ in Python packages; defaults to true.
+    #[serde(default, rename = "update-python-version")]
+    pub update_python_version: Option<bool>,
+
+    /// Publish the package in package.json to npm. Packages marked as
+    /// private are never published.
+    #[serde(default, rename = "npm-publish")]
+    pub npm_publish: Option<bool>,
+
+    #[serde(default, rename = "npm-provenance")]
+    pub npm_provenance: Option<bool>,
+
     #[serde(
         default,
         rename = "tarball-location",
@@ -42,26 +103,584 @@ pub struct ProjectConfig {
     )]
     pub tarball_location: Vec<String>,
 
+    #[serde(default, rename = "tarball-transport")]
+    pub tarball_transport: Option<TarballTransport>,
+
     #[serde(default, rename = "release-timeout")]
     pub release_timeout: Option<u64>,
 
+    /// Globs of paths (e.g. CI configuration) whose changes don't count as
+    /// recent commits for the release timeout.
+    #[serde(default, rename = "ignore-paths")]
+    pub ignore_paths: Option<Vec<String>>,
+
     #[serde(default, rename = "ci-timeout")]
     pub ci_timeout: Option<u64>,
+
+    #[serde(default, rename = "ci-poll-interval")]
+    pub ci_poll_interval: Option<u64>,
+
+    #[serde(default)]
+    pub rollout: Option<Rollout>,
+
+    #[serde(default, rename = "cargo-lockfile")]
+    pub cargo_lockfile: Option<LockfileUpdate>,
+
+    #[serde(default)]
+    pub debian: Option<Debian>,
+
+    #[serde(default, rename = "artifact-directory")]
+    pub artifact_directory: Option<PathBuf>,
+
+    #[serde(default)]
+    pub pkgbuild: Option<Pkgbuild>,
+
+    #[serde(default, rename = "smoke-test")]
+    pub smoke_test: Option<SmokeTest>,
+
+    #[serde(default)]
+    pub docs: Option<Docs>,
+
+    /// Whether to thank contributors in the release notes; defaults to false.
+    #[serde(default)]
+    pub thanks: Option<bool>,
+
+    #[serde(default, rename = "version-scheme")]
+    pub version_scheme: Option<VersionScheme>,
+
+    /// Manual steps to confirm before releasing.
+    #[serde(default)]
+    pub checklist: Option<Vec<ChecklistItem>>,
+
+    /// How to decide which version component to increase.
+    #[serde(default, rename = "version-bump")]
+    pub version_bump: Option<VersionBump>,
+
+    /// Version component to increase for new releases; defaults to the last one.
+    #[serde(default, rename = "default-bump")]
+    pub default_bump: Option<Bump>,
+
+    /// Whether increasing a version component resets the less significant
+    /// ones (the default). Disable for the behaviour of older versions of
+    /// disperse, where a major bump of 1.2.3 gave 2.2.3.
+    #[serde(default, rename = "reset-lower-components")]
+    pub reset_lower_components: Option<bool>,
+
+    /// Format of calendar versions, e.g. "YYYY.MM.MICRO" (the default) or "YY.MM".
+    #[serde(default, rename = "calver-format")]
+    pub calver_format: Option<String>,
+
+    /// What to do when the news file has several unreleased sections.
+    #[serde(default, rename = "catch-up")]
+    pub catch_up: Option<CatchUp>,
+
+    /// Never release the project.
+    #[serde(default)]
+    pub disabled: Option<bool>,
+
+    /// Don't release the project before this date.
+    #[serde(default, rename = "snooze-until")]
+    pub snooze_until: Option<chrono::NaiveDate>,
+
+    /// Publish a signed manifest of the release, with the digests of its
+    /// artifacts.
+    #[serde(default, rename = "release-manifest")]
+    pub release_manifest: Option<bool>,
+
+    /// OpenPGP key to sign tags and release manifests with. The signing key
+    /// of the profile in use takes precedence.
+    #[serde(default, rename = "signing-key")]
+    pub signing_key: Option<String>,
+
+    /// WebAssembly plugins to call during the release, relative to the
+    /// repository root.
+    #[serde(default)]
+    pub plugins: Option<Vec<PathBuf>>,
+
+    /// Directory of the project, relative to the repository root. Only set
+    /// for projects in repositories with several of them.
+    #[serde(default)]
+    pub path: Option<PathBuf>,
+
+    /// Projects in a repository with several of them.
+    #[serde(default, rename = "project", skip_serializing_if = "Vec::is_empty")]
+    pub projects: Vec<SubProject>,
+}
+
+impl ProjectConfig {
+    /// Why the project shouldn't be released on `today`, if anything.
+    pub fn hold(&self, today: chrono::NaiveDate) -> Option<Hold> {
+        Hold::check(self.disabled, self.snooze_until, today)
+    }
+
+    /// Names of the projects in a repository with several of them.
+    pub fn project_names(&self) -> Vec<&str> {
+        self.projects.iter().map(|p| p.name.as_str()).collect()
+    }
+
+    /// Return the configuration of one of the projects in a repository with
+    /// several of them.
+    ///
+    /// The settings of the project take precedence over the top-level ones.
+    /// Unless the project sets it, the tag name is "<name>-v$VERSION".
+    pub fn select_project(&self, name: &str) -> Result<ProjectConfig, String> {
+        let project = self
+            .projects
+            .iter()
+            .find(|p| p.name == name)
+            .ok_or_else(|| {
+                format!(
+                    "No project named {}; known projects: {}",
+                    name,
+                    self.project_names().join(", ")
+                )
+            })?;
+        let mut table = toml::Table::try_from(self).map_err(|e| e.to_string())?;
+        table.remove("project");
+        table.extend(project.settings.clone());
+        table.insert("name".to_string(), toml::Value::String(name.to_string()));
+        if !project.settings.contains_key("tag-name") {
+            table.insert(
+                "tag-name".to_string(),
+                toml::Value::String(format!("{}-v$VERSION", name)),
+            );
+        }
+        let ret: ProjectConfig = table.try_into().map_err(|e: toml::de::Error| {
+            format!("Invalid settings for project {}: {}", name, e.message())
+        })?;
+        if ret.path.is_none() {
+            return Err(format!("No path set for project {}", name));
+        }
+        Ok(ret)
+    }
+
+    /// Directory of the project, relative to the repository root.
+    pub fn project_dir(&self) -> &Path {
+        self.path.as_deref().unwrap_or(Path::new(""))
+    }
+}
+
+/// A project in a repository with several of them, from a [[project]] table.
+#[derive(serde::Deserialize, serde::Serialize, schemars::JsonSchema, Debug, Clone)]
+#[schemars(deny_unknown_fields)]
+pub struct SubProject {
+    pub name: String,
+
+    /// Settings that differ from the top-level ones, e.g. path and tag-name.
+    #[serde(flatten)]
+    #[schemars(with = "ProjectConfig")]
+    pub settings: toml::Table,
+}
+
+/// Reason a project is not being released.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Hold {
+    Disabled,
+    Snoozed(chrono::NaiveDate),
+}
+
+impl Hold {
+    pub fn check(
+        disabled: Option<bool>,
+        snooze_until: Option<chrono::NaiveDate>,
+        today: chrono::NaiveDate,
+    ) -> Option<Self> {
+        if disabled.unwrap_or(false) {
+            Some(Hold::Disabled)
+        } else {
+            snooze_until.filter(|d| *d > today).map(Hold::Snoozed)
+        }
+    }
+}
+
+impl std::fmt::Display for Hold {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Hold::Disabled => write!(f, "disabled"),
+            Hold::Snoozed(until) => write!(f, "snoozed until {}", until),
+        }
+    }
+}
+
+/// What to do when a project missed release cycles and the news file has
+/// several unreleased sections.
+#[derive(
+    serde::Deserialize,
+    serde::Serialize,
+    schemars::JsonSchema,
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    Default,
+)]
+#[serde(rename_all = "kebab-case")]
+pub enum CatchUp {
+    /// Merge the sections into the newest one, and release that.
+    Collapse,
+
+    /// Release each section in turn, oldest first.
+    Sequential,
+
+    /// Leave the sections alone, e.g. for projects that maintain several
+    /// release series in one news file.
+    #[default]
+    Off,
+}
+
+/// Rules that versions of a project follow.
+#[derive(
+    serde::Deserialize,
+    serde::Serialize,
+    schemars::JsonSchema,
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    Default,
+)]
+#[serde(rename_all = "kebab-case")]
+pub enum VersionScheme {
+    /// One to three components, optionally with a pre-release.
+    #[default]
+    Simple,
+    /// PEP 440, with post-releases, development releases, epochs and local
+    /// versions; alternative spellings are normalized.
+    Pep440,
+    /// Strict semantic versioning, with pre-release and build metadata.
+    Semver,
+    /// Calendar versioning; new versions are derived from the release date,
+    /// following calver-format.
+    Calver,
+}
+
+/// Version component to increase for a new release.
+#[derive(
+    serde::Deserialize, serde::Serialize, schemars::JsonSchema, Debug, Clone, Copy, PartialEq, Eq,
+)]
+#[serde(rename_all = "kebab-case")]
+pub enum Bump {
+    Major,
+    Minor,
+    Patch,
+}
+
+impl std::fmt::Display for Bump {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Bump::Major => write!(f, "major"),
+            Bump::Minor => write!(f, "minor"),
+            Bump::Patch => write!(f, "patch"),
+        }
+    }
+}
+
+/// How to generate news entries from commit messages.
+#[derive(
+    serde::Deserialize,
+    serde::Serialize,
+    schemars::JsonSchema,
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    Default,
+)]
+#[serde(rename_all = "kebab-case")]
+pub enum NewsFromCommits {
+    /// Don't generate news entries.
+    #[default]
+    Off,
+
+    /// One entry per commit, with the first line of its message.
+    All,
+
+    /// Only commits whose Conventional Commits type is worth mentioning
+    /// (features, fixes, performance improvements and breaking changes),
+    /// grouped by type.
+    ConventionalCommits,
+}
+
+/// How to decide which version component to increase for a new release.
+#[derive(
+    serde::Deserialize,
+    serde::Serialize,
+    schemars::JsonSchema,
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    Default,
+)]
+#[serde(rename_all = "kebab-case")]
+pub enum VersionBump {
+    /// Always increase the same component, i.e. default-bump.
+    #[default]
+    Fixed,
+
+    /// Derive the component from Conventional Commits messages since the
+    /// last release, falling back to default-bump.
+    ConventionalCommits,
 }
 
-#[derive(serde::Deserialize, serde::Serialize)]
+impl std::str::FromStr for Bump {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "major" => Ok(Bump::Major),
+            "minor" => Ok(Bump::Minor),
+            "patch" => Ok(Bump::Patch),
+            _ => Err(format!(
+                "invalid bump {:?}; expected major, minor or patch",
+                s
+            )),
+        }
+    }
+}
+
+#[derive(serde::Deserialize, serde::Serialize, schemars::JsonSchema)]
+#[schemars(deny_unknown_fields)]
 pub struct GitHub {
     pub url: String,
     pub branch: Option<String>,
 }
 
-#[derive(serde::Deserialize, serde::Serialize)]
+#[derive(serde::Deserialize, serde::Serialize, schemars::JsonSchema)]
+#[schemars(deny_unknown_fields)]
 pub struct Launchpad {
     pub project: String,
     pub series: Option<String>,
 }
 
-#[derive(serde::Deserialize, serde::Serialize)]
+/// A manpage (or glob of manpages) to update the version and date in.
+///
+/// This is either just a path, or a table with a path and options.
+#[derive(
+    serde::Deserialize, serde::Serialize, schemars::JsonSchema, Clone, Debug, PartialEq, Eq,
+)]
+#[schemars(deny_unknown_fields)]
+#[serde(untagged)]
+pub enum UpdateManpage {
+    Path(PathBuf),
+    Options {
+        path: PathBuf,
+
+        /// strftime-style format of the date in the .TH line, e.g. "%d %B %Y".
+        #[serde(default, rename = "date-format")]
+        date_format: Option<String>,
+
+        /// Language to use for month names, e.g. "fr" or "de_DE".
+        #[serde(default)]
+        locale: Option<String>,
+    },
+}
+
+impl UpdateManpage {
+    pub fn path(&self) -> &Path {
+        match self {
+            UpdateManpage::Path(path) => path,
+            UpdateManpage::Options { path, .. } => path,
+        }
+    }
+
+    pub fn date_format(&self) -> Option<&str> {
+        match self {
+            UpdateManpage::Path(_) => None,
+            UpdateManpage::Options { date_format, .. } => date_format.as_deref(),
+        }
+    }
+
+    pub fn locale(&self) -> Option<&str> {
+        match self {
+            UpdateManpage::Path(_) => None,
+            UpdateManpage::Options { locale, .. } => locale.as_deref(),
+        }
+    }
+}
+
+/// A manual step that has to be confirmed before a release.
+#[derive(
+    serde::Deserialize, serde::Serialize, schemars::JsonSchema, Clone, Debug, PartialEq, Eq,
+)]
+#[schemars(deny_unknown_fields)]
+pub struct ChecklistItem {
+    pub description: String,
+
+    /// Whether the release fails if this isn't confirmed; defaults to false.
+    pub required: Option<bool>,
+}
+
+impl ChecklistItem {
+    pub fn is_required(&self) -> bool {
+        self.required.unwrap_or(false)
+    }
+}
+
+/// Publish releases to a pre-release channel first, and promote them later.
+#[derive(serde::Deserialize, serde::Serialize, schemars::JsonSchema)]
+#[schemars(deny_unknown_fields)]
+pub struct Rollout {
+    /// Channel (e.g. npm dist-tag) to initially publish to; defaults to "next".
+    pub channel: Option<String>,
+
+    /// Number of days a release has to soak after it was published before it can be promoted.
+    #[serde(rename = "soak-days")]
+    pub soak_days: Option<u64>,
+}
+
+/// Settings for projects that ship their own Debian packaging.
+#[derive(serde::Deserialize, serde::Serialize, schemars::JsonSchema)]
+#[schemars(deny_unknown_fields)]
+pub struct Debian {
+    /// Distribution for new debian/changelog entries; defaults to that of the previous entry.
+    pub distribution: Option<String>,
+
+    /// Whether to build a source package.
+    #[serde(default, rename = "build-source")]
+    pub build_source: Option<bool>,
+
+    /// dput target to upload the source package to.
+    pub upload: Option<String>,
+}
+
+/// Publish versioned documentation, e.g. to GitHub Pages.
+#[derive(serde::Deserialize, serde::Serialize, schemars::JsonSchema)]
+#[schemars(deny_unknown_fields)]
+pub struct Docs {
+    /// Command that builds the documentation.
+    pub command: String,
+
+    /// Directory the documentation is built into.
+    pub output: PathBuf,
+
+    /// Branch to publish to; defaults to "gh-pages".
+    pub branch: Option<String>,
+
+    /// Repository to publish to; defaults to the GitHub repository.
+    pub remote: Option<String>,
+
+    /// URL that the published branch is served from.
+    pub url: Option<String>,
+}
+
+/// Install the published packages from their registries after a release.
+#[derive(serde::Deserialize, serde::Serialize, schemars::JsonSchema)]
+#[schemars(deny_unknown_fields)]
+pub struct SmokeTest {
+    /// Command to run once the packages have been installed.
+    pub command: Option<String>,
+}
+
+/// How to upload tarballs to tarball-location.
+#[derive(
+    serde::Deserialize,
+    serde::Serialize,
+    schemars::JsonSchema,
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    Default,
+)]
+#[serde(rename_all = "kebab-case")]
+pub enum TarballTransport {
+    /// SFTP, spoken by disperse itself. Settings in ~/.ssh/config are not
+    /// used.
+    Sftp,
+
+    /// The external scp program, which uses the settings in ~/.ssh/config.
+    #[default]
+    Scp,
+}
+
+/// Settings for building Python artifacts with maturin.
+#[derive(serde::Deserialize, serde::Serialize, schemars::JsonSchema)]
+#[schemars(deny_unknown_fields)]
+pub struct Maturin {
+    /// Build abi3 wheels for this minimum Python version (e.g. "3.8").
+    pub abi3: Option<String>,
+
+    /// Additional cargo features to enable.
+    pub features: Option<Vec<String>>,
+
+    /// Rust target triples to build wheels for (e.g. "x86_64-unknown-linux-gnu").
+    ///
+    /// Without targets, only an sdist is uploaded.
+    pub targets: Option<Vec<String>>,
+}
+
+/// Settings for updating an Arch Linux PKGBUILD.
+#[derive(serde::Deserialize, serde::Serialize, schemars::JsonSchema)]
+#[schemars(deny_unknown_fields)]
+pub struct Pkgbuild {
+    /// Path to the PKGBUILD, relative to the root of the repository or AUR checkout.
+    pub path: Option<PathBuf>,
+
+    /// URL of a separate AUR repository to update once the release has succeeded.
+    pub aur: Option<String>,
+}
+
+impl Pkgbuild {
+    pub fn path(&self) -> &Path {
+        self.path.as_deref().unwrap_or(Path::new("PKGBUILD"))
+    }
+}
+
+/// How to handle Cargo.lock when the version is bumped.
+#[derive(
+    serde::Deserialize,
+    serde::Serialize,
+    schemars::JsonSchema,
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    Default,
+)]
+#[serde(rename_all = "kebab-case")]
+pub enum LockfileUpdate {
+    /// Leave the lockfile alone.
+    Skip,
+
+    /// Update the workspace crates in the lockfile as part of the release commit.
+    #[default]
+    Workspace,
+
+    /// Update the lockfile in a separate commit, after the release has been tagged.
+    Separate,
+}
+
+/// Tool used to build and upload Python artifacts.
+#[derive(
+    serde::Deserialize,
+    serde::Serialize,
+    schemars::JsonSchema,
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    Default,
+)]
+#[serde(rename_all = "kebab-case")]
+pub enum PythonUploadTool {
+    /// Build with python-build and upload with twine.
+    #[default]
+    Twine,
+
+    /// Build with `uv build` and upload with `uv publish`.
+    Uv,
+}
+
+#[derive(serde::Deserialize, serde::Serialize, schemars::JsonSchema)]
+#[schemars(deny_unknown_fields)]
 pub struct UpdateVersion {
     pub path: std::path::PathBuf,
     pub r#match: Option<String>,
@@ -100,21 +719,64 @@ impl From<config::Project> for ProjectConfig {
                 branch: p.github_branch.clone(),
             }),
             news_file: p.news_file.clone().map(|n| n.into()),
+            news_format: None,
+            news_skip: None,
+            news_from_commits: None,
+            news_date_format: None,
+            create_pending: None,
             update_manpages: {
-                let mps: Vec<_> = p.update_manpages.into_iter().map(|u| u.into()).collect();
+                let mps: Vec<_> = p
+                    .update_manpages
+                    .into_iter()
+                    .map(|u| UpdateManpage::Path(u.into()))
+                    .collect();
                 if mps.is_empty() {
                     None
                 } else {
                     Some(mps)
                 }
             },
+            update_metainfo: None,
+            update_nix: None,
+            update_docs: None,
             tag_name: p.tag_name.clone(),
             pre_dist_command: p.pre_dist_command.clone(),
             verify_command: p.verify_command.clone(),
             twine_upload: p.skip_twine_upload.map(|t| !t),
+            python_upload_tool: None,
+            maturin: None,
+            update_python_version: None,
+            npm_publish: None,
+            npm_provenance: None,
+            cargo_lockfile: None,
+            debian: None,
+            artifact_directory: None,
+            pkgbuild: None,
+            smoke_test: None,
+            docs: None,
+            thanks: None,
+            version_scheme: None,
+            checklist: None,
+            version_bump: None,
+            catch_up: None,
+            reset_lower_components: None,
+            disabled: None,
+            snooze_until: None,
+            plugins: None,
+            release_manifest: None,
+            signing_key: None,
+            version_variables: None,
+            default_bump: None,
+            calver_format: None,
             tarball_location: p.tarball_location.clone(),
+            tarball_transport: None,
             release_timeout: p.timeout_days.map(|t| t as u64),
+            ignore_paths: None,
             ci_timeout: p.ci_timeout.map(|t| t as u64),
+            ci_poll_interval: None,
+            rollout: None,
+            path: None,
+            projects: vec![],
         }
     }
 }
@@ -126,19 +788,116 @@ fn read_old_project(f: &mut dyn std::io::Read) -> config::Project {
     protobuf::text_format::parse_from_str(&s).unwrap()
 }
 
+/// Configuration keys that have been renamed, as (old name, new name).
+pub const RENAMED_KEYS: &[(&str, &str)] = &[
+    ("tag_name", "tag-name"),
+    ("news_file", "news-file"),
+    ("pre_dist_command", "pre-dist-command"),
+    ("verify_command", "verify-command"),
+    ("twine_upload", "twine-upload"),
+    ("tarball_location", "tarball-location"),
+    ("timeout_days", "release-timeout"),
+    ("release_timeout", "release-timeout"),
+    ("ci_timeout", "ci-timeout"),
+    ("update_manpages", "update-manpage"),
+    ("update-manpages", "update-manpage"),
+];
+
+/// JSON Schema for disperse.toml.
+///
+/// Unlike when reading the configuration, unknown keys are not allowed.
+/// Deprecated keys are, with the same schema as the key they were renamed to.
+pub fn json_schema() -> serde_json::Value {
+    let generator = schemars::gen::SchemaSettings::draft07()
+        .with(|s| {
+            // TOML has no null, so settings are left out rather than set to null.
+            s.option_add_null_type = false;
+        })
+        .into_generator();
+    let mut schema = serde_json::to_value(generator.into_root_schema_for::<ProjectConfig>())
+        .expect("schema is serializable");
+    let properties = schema["properties"]
+        .as_object_mut()
+        .expect("schema has properties");
+    for (old, new) in RENAMED_KEYS {
+        if let Some(renamed) = properties.get(*new).cloned() {
+            properties.insert(old.to_string(), renamed);
+        }
+    }
+    schema
+}
+
+/// A deprecated configuration key that was found.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DeprecatedKey {
+    pub old: &'static str,
+    pub new: &'static str,
+}
+
+impl std::fmt::Display for DeprecatedKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "{} is deprecated; use {} instead (run \"disperse migrate\" to update)",
+            self.old, self.new
+        )
+    }
+}
+
+/// Rename deprecated keys in a project configuration to their current names.
+///
+/// If both the old and the new key are present, the new one wins.
+pub fn rename_deprecated_keys(doc: &mut toml_edit::DocumentMut) -> Vec<DeprecatedKey> {
+    let mut ret = vec![];
+    for (old, new) in RENAMED_KEYS {
+        if let Some(item) = doc.remove(old) {
+            if !doc.contains_key(new) {
+                doc.insert(new, item);
+            }
+            ret.push(DeprecatedKey { old, new });
+        }
+    }
+    ret
+}
+
 fn read_toml_project(f: &mut dyn std::io::Read) -> ProjectConfig {
     let mut s = String::new();
     std::io::Read::read_to_string(f, &mut s).unwrap();
 
-    let ret: ProjectConfig = toml::from_str(&s).unwrap();
+    let mut doc: toml_edit::DocumentMut = s.parse().unwrap();
+    for deprecated in rename_deprecated_keys(&mut doc) {
+        log::warn!("{}", deprecated);
+    }
+
+    let ret: ProjectConfig = toml::from_str(&doc.to_string()).unwrap();
     ret
 }
 
+/// Default location of the project configuration, relative to the root of the tree.
+pub const DEFAULT_PROJECT_CONFIG_PATH: &str = "disperse.toml";
+
+/// Read the project configuration from a specific path in a tree.
+///
+/// Unlike [`read_project_with_fallback`], this does not fall back to the legacy
+/// configuration files.
+pub fn read_project_from_path(tree: &dyn Tree, path: &Path) -> Result<ProjectConfig, BrzError> {
+    let mut f = tree.get_file(path)?;
+    Ok(read_toml_project(&mut f))
+}
+
+/// Read the project configuration, either from an explicit path or the default locations.
+pub fn read_project(tree: &dyn Tree, path: Option<&Path>) -> Result<ProjectConfig, BrzError> {
+    match path {
+        Some(path) => read_project_from_path(tree, path),
+        None => read_project_with_fallback(tree),
+    }
+}
+
 pub fn read_project_with_fallback(tree: &dyn Tree) -> Result<ProjectConfig, BrzError> {
     #[allow(unused_assignments)]
     let mut first_error = None;
 
-    match tree.get_file(Path::new("disperse.toml")) {
+    match tree.get_file(Path::new(DEFAULT_PROJECT_CONFIG_PATH)) {
         Ok(mut f) => return Ok(read_toml_project(&mut f)),
         Err(e @ BrzError::NoSuchFile(_)) => {
             first_error = Some(e);
@@ -162,3 +921,273 @@ pub fn read_project_with_fallback(tree: &dyn Tree) -> Result<ProjectConfig, BrzE
 
     Ok(read_old_project(&mut old_config).into())
 }
+
+/// A configuration value set on the command line, e.g. "tag-name=v$VERSION".
+#[derive(Debug, Clone, PartialEq)]
+pub struct Override {
+    /// Key, with dots separating the names of nested tables.
+    pub key: String,
+    pub value: toml::Value,
+}
+
+impl std::str::FromStr for Override {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (key, value) = s
+            .split_once('=')
+            .ok_or_else(|| format!("Expected KEY=VALUE, got {:?}", s))?;
+        let key = key.trim();
+        if key.is_empty() || key.split('.').any(str::is_empty) {
+            return Err(format!("Invalid key {:?}", key));
+        }
+        // Values that aren't valid TOML, e.g. v$VERSION, are taken as strings.
+        let value = match toml::from_str::<toml::Table>(&format!("value = {}", value)) {
+            Ok(mut table) => table.remove("value").unwrap(),
+            Err(_) => toml::Value::String(value.to_string()),
+        };
+        Ok(Self {
+            key: key.to_string(),
+            value,
+        })
+    }
+}
+
+fn has_key(table: &toml::Table, key: &str) -> bool {
+    let mut parts = key.split('.').peekable();
+    let mut current = table;
+    while let Some(part) = parts.next() {
+        match (current.get(part), parts.peek()) {
+            (Some(_), None) => return true,
+            (Some(toml::Value::Table(t)), Some(_)) => current = t,
+            _ => return false,
+        }
+    }
+    false
+}
+
+/// Apply values set on the command line to a project configuration.
+pub fn apply_overrides(
+    cfg: &ProjectConfig,
+    overrides: &[Override],
+) -> Result<ProjectConfig, String> {
+    let mut table = toml::Table::try_from(cfg).map_err(|e| e.to_string())?;
+    for o in overrides {
+        let mut parts = o.key.split('.').collect::<Vec<_>>();
+        let last = parts.pop().unwrap();
+        let mut current = &mut table;
+        for part in parts {
+            current = current
+                .entry(part)
+                .or_insert_with(|| toml::Value::Table(toml::Table::new()))
+                .as_table_mut()
+                .ok_or_else(|| format!("{} is not a table", part))?;
+        }
+        current.insert(last.to_string(), o.value.clone());
+    }
+    let ret: ProjectConfig = table
+        .try_into()
+        .map_err(|e: toml::de::Error| format!("Invalid override: {}", e.message()))?;
+    // Unknown keys are silently dropped when deserializing.
+    let applied = toml::Table::try_from(&ret).map_err(|e| e.to_string())?;
+    if let Some(o) = overrides.iter().find(|o| !has_key(&applied, &o.key)) {
+        return Err(format!("Unknown configuration key {}", o.key));
+    }
+    Ok(ret)
+}
+
+fn merge_defaults(table: &mut toml::Table, defaults: &toml::Table) {
+    for (key, value) in defaults {
+        match (table.get_mut(key), value) {
+            (Some(toml::Value::Table(t)), toml::Value::Table(d)) => merge_defaults(t, d),
+            (Some(_), _) => {}
+            (None, _) => {
+                table.insert(key.clone(), value.clone());
+            }
+        }
+    }
+}
+
+fn leaf_keys(table: &toml::Table, prefix: &str) -> Vec<String> {
+    table
+        .iter()
+        .flat_map(|(key, value)| {
+            let key = format!("{}{}", prefix, key);
+            match value {
+                toml::Value::Table(t) => leaf_keys(t, &format!("{}.", key)),
+                _ => vec![key],
+            }
+        })
+        .collect()
+}
+
+/// Fill in the settings a project configuration doesn't have from `defaults`,
+/// e.g. the [defaults] table of the global configuration.
+pub fn apply_defaults(
+    cfg: &ProjectConfig,
+    defaults: &toml::Table,
+) -> Result<ProjectConfig, String> {
+    let mut table = toml::Table::try_from(cfg).map_err(|e| e.to_string())?;
+    merge_defaults(&mut table, defaults);
+    let ret: ProjectConfig = table
+        .try_into()
+        .map_err(|e: toml::de::Error| format!("Invalid default: {}", e.message()))?;
+    // Unknown keys are silently dropped when deserializing.
+    let applied = toml::Table::try_from(&ret).map_err(|e| e.to_string())?;
+    if let Some(key) = leaf_keys(defaults, "")
+        .into_iter()
+        .find(|k| !has_key(&applied, k))
+    {
+        return Err(format!("Unknown configuration key {}", key));
+    }
+    Ok(ret)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_apply_defaults() {
+        let cfg: ProjectConfig = toml::from_str(
+            r#"
+tag-name = "release-$VERSION"
+debian = { upload = "ppa" }
+"#,
+        )
+        .unwrap();
+        let defaults: toml::Table = toml::from_str(
+            r#"
+tag-name = "v$VERSION"
+twine-upload = true
+ci-timeout = 3600
+debian = { upload = "mentors", build-source = true }
+"#,
+        )
+        .unwrap();
+        let cfg = apply_defaults(&cfg, &defaults).unwrap();
+        assert_eq!(cfg.tag_name.as_deref(), Some("release-$VERSION"));
+        assert_eq!(cfg.twine_upload, Some(true));
+        assert_eq!(cfg.ci_timeout, Some(3600));
+        let debian = cfg.debian.as_ref().unwrap();
+        assert_eq!(debian.upload.as_deref(), Some("ppa"));
+        assert_eq!(debian.build_source, Some(true));
+
+        let unknown: toml::Table = toml::from_str("tag-nmae = \"v$VERSION\"").unwrap();
+        assert!(apply_defaults(&cfg, &unknown).is_err());
+    }
+
+    #[test]
+    fn test_apply_overrides() {
+        let cfg = ProjectConfig {
+            tag_name: Some("release-$VERSION".to_string()),
+            twine_upload: Some(true),
+            ..Default::default()
+        };
+        let overrides = [
+            "tag-name=v$VERSION",
+            "twine-upload=false",
+            "debian.upload=ppa",
+        ]
+        .iter()
+        .map(|o| o.parse::<Override>().unwrap())
+        .collect::<Vec<_>>();
+        let cfg = apply_overrides(&cfg, &overrides).unwrap();
+        assert_eq!(cfg.tag_name.as_deref(), Some("v$VERSION"));
+        assert_eq!(cfg.twine_upload, Some(false));
+        assert_eq!(
+            cfg.debian.as_ref().and_then(|d| d.upload.as_deref()),
+            Some("ppa")
+        );
+
+        let unknown = "tag-nmae=v$VERSION".parse::<Override>().unwrap();
+        assert!(apply_overrides(&cfg, &[unknown]).is_err());
+        let invalid = "twine-upload=maybe".parse::<Override>().unwrap();
+        assert!(apply_overrides(&cfg, &[invalid]).is_err());
+        assert!("tag-name".parse::<Override>().is_err());
+    }
+
+    #[test]
+    fn test_select_project() {
+        let cfg: ProjectConfig = toml::from_str(
+            r#"
+ci-timeout = 600
+tag-name = "v$VERSION"
+
+[[project]]
+name = "crate-a"
+path = "crates/a"
+news-file = "crates/a/NEWS"
+
+[[project]]
+name = "crate-b"
+path = "crates/b"
+tag-name = "b-$VERSION"
+ci-timeout = 60
+"#,
+        )
+        .unwrap();
+        assert_eq!(cfg.project_names(), vec!["crate-a", "crate-b"]);
+
+        let a = cfg.select_project("crate-a").unwrap();
+        assert_eq!(a.name.as_deref(), Some("crate-a"));
+        assert_eq!(a.project_dir(), Path::new("crates/a"));
+        assert_eq!(a.tag_name.as_deref(), Some("crate-a-v$VERSION"));
+        assert_eq!(a.news_file, Some(PathBuf::from("crates/a/NEWS")));
+        assert_eq!(a.ci_timeout, Some(600));
+        assert!(a.projects.is_empty());
+
+        let b = cfg.select_project("crate-b").unwrap();
+        assert_eq!(b.tag_name.as_deref(), Some("b-$VERSION"));
+        assert_eq!(b.ci_timeout, Some(60));
+        assert_eq!(b.news_file, None);
+
+        assert!(cfg.select_project("crate-c").is_err());
+        assert_eq!(ProjectConfig::default().project_dir(), Path::new(""));
+    }
+
+    #[test]
+    fn test_rename_deprecated_keys() {
+        let mut doc: toml_edit::DocumentMut =
+            "tag_name = \"v$VERSION\"\ntimeout_days = 5\nrelease-timeout = 3\nname = \"foo\"\n"
+                .parse()
+                .unwrap();
+        assert_eq!(
+            rename_deprecated_keys(&mut doc),
+            vec![
+                DeprecatedKey {
+                    old: "tag_name",
+                    new: "tag-name"
+                },
+                DeprecatedKey {
+                    old: "timeout_days",
+                    new: "release-timeout"
+                },
+            ]
+        );
+        assert_eq!(
+            doc.to_string(),
+            "release-timeout = 3\nname = \"foo\"\ntag-name = \"v$VERSION\"\n"
+        );
+        assert_eq!(rename_deprecated_keys(&mut doc), vec![]);
+    }
+
+    #[test]
+    fn test_hold() {
+        let today = chrono::NaiveDate::from_ymd_opt(2025, 8, 1).unwrap();
+        let cfg: ProjectConfig = toml::from_str("snooze-until = \"2025-09-01\"\n").unwrap();
+        assert_eq!(
+            cfg.hold(today),
+            Some(Hold::Snoozed(
+                chrono::NaiveDate::from_ymd_opt(2025, 9, 1).unwrap()
+            ))
+        );
+        assert_eq!(
+            cfg.hold(chrono::NaiveDate::from_ymd_opt(2025, 9, 1).unwrap()),
+            None
+        );
+        let cfg: ProjectConfig = toml::from_str("disabled = true\n").unwrap();
+        assert_eq!(cfg.hold(today), Some(Hold::Disabled));
+        assert_eq!(ProjectConfig::default().hold(today), None);
+    }
+}
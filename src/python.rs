@@ -1,7 +1,8 @@
-use crate::Version;
+use crate::project_config::{Maturin, PythonUploadTool};
+use crate::{Status, Version};
 use breezyshim::error::Error as BrzError;
-use breezyshim::tree::{Tree, WorkingTree};
-use pyo3::prelude::*;
+use breezyshim::tree::{MutableTree, Tree, WorkingTree};
+use lazy_regex::regex;
 
 use serde_json::Value;
 use std::path::Path;
@@ -15,6 +16,11 @@ pub enum Error {
     BrzError(BrzError),
     VersionError(String),
     IoError(std::io::Error),
+    BuildFailed {
+        command: String,
+        status: std::process::ExitStatus,
+        output: String,
+    },
     Other(String),
 }
 
@@ -37,6 +43,11 @@ impl std::fmt::Display for Error {
             Error::VersionError(e) => write!(f, "Version error: {}", e),
             Error::Other(e) => write!(f, "Other error: {}", e),
             Error::IoError(e) => write!(f, "IO error: {}", e),
+            Error::BuildFailed {
+                command,
+                status,
+                output,
+            } => write!(f, "{} failed ({}):\n{}", command, status, output),
         }
     }
 }
@@ -94,13 +105,68 @@ pub fn find_version_in_pyproject_toml(tree: &dyn Tree) -> Result<Option<Version>
         .transpose()
 }
 
-pub fn pypi_discover_urls(pypi_user: &str) -> Result<Vec<url::Url>, Error> {
+/// Upper bound on the number of profile pages to fetch for a single user.
+const MAX_PYPI_USER_PAGES: usize = 100;
+
+/// Extract the names of the projects listed on a PyPI user profile page.
+fn parse_pypi_user_page(html: &str) -> Vec<String> {
+    regex!(r#"<a[^>]*class="package-snippet"[^>]*href="/project/([^/"]+)/""#)
+        .captures_iter(html)
+        .map(|c| c[1].to_string())
+        .collect()
+}
+
+/// List the packages of a PyPI user by scraping their profile pages.
+fn pypi_user_packages_from_profile(
+    client: &reqwest::blocking::Client,
+    pypi_user: &str,
+) -> Result<Vec<String>, Error> {
+    let mut ret: Vec<String> = vec![];
+    for page in 1..=MAX_PYPI_USER_PAGES {
+        let url = format!("https://pypi.org/user/{}/?page={}", pypi_user, page);
+        let _permit = crate::limits::acquire_blocking(crate::limits::Service::PyPI);
+        let resp = client
+            .get(&url)
+            .send()
+            .map_err(|e| Error::Other(format!("Error fetching {}: {}", url, e)))?;
+        if page > 1 && resp.status() == reqwest::StatusCode::NOT_FOUND {
+            break;
+        }
+        let html = resp
+            .error_for_status()
+            .and_then(|r| r.text())
+            .map_err(|e| Error::Other(format!("Error fetching {}: {}", url, e)))?;
+        let names = parse_pypi_user_page(&html);
+        // Pages past the end either 404 or repeat the last page.
+        let new = names
+            .into_iter()
+            .filter(|n| !ret.contains(n))
+            .collect::<Vec<_>>();
+        if new.is_empty() {
+            break;
+        }
+        ret.extend(new);
+    }
+    Ok(ret)
+}
+
+/// List the packages of a PyPI user using the deprecated XML-RPC API.
+fn pypi_user_packages_from_xmlrpc(pypi_user: &str) -> Result<Vec<String>, Error> {
     let request = Request::new("user_packages").arg(pypi_user);
 
     let response = request
         .call_url("https://pypi.org/pypi")
         .map_err(|e| Error::Other(format!("Error calling PyPI: {}", e)))?;
 
+    Ok(response
+        .as_array()
+        .ok_or_else(|| Error::Other("Unexpected response from PyPI".to_string()))?
+        .iter()
+        .filter_map(|p| p.as_array()?.get(1)?.as_str().map(|s| s.to_string()))
+        .collect())
+}
+
+pub fn pypi_discover_urls(pypi_user: &str) -> Result<Vec<url::Url>, Error> {
     let mut ret = vec![];
 
     let client = reqwest::blocking::ClientBuilder::new()
@@ -108,58 +174,145 @@ pub fn pypi_discover_urls(pypi_user: &str) -> Result<Vec<url::Url>, Error> {
         .build()
         .map_err(|e| Error::Other(format!("Error building HTTP client: {}", e)))?;
 
-    for package in response.as_array().unwrap().iter() {
-        let package_str = package.as_array().unwrap()[1].as_str().unwrap();
-
-        let req_url = format!("https://pypi.org/pypi/{}/json", package_str);
-        let resp = client
-            .get(&req_url)
-            .send()
-            .map_err(|e| Error::Other(format!("Error fetching {}: {}", req_url, e)))?;
-
-        let data: Value = resp
-            .json()
-            .map_err(|e| Error::Other(format!("Error parsing JSON from {}: {}", req_url, e)))?;
-        if let Some(project_urls) = data["info"]["project_urls"].as_object() {
-            if project_urls.is_empty() {
-                log::debug!("Project {} does not have project URLs", package_str);
-                continue;
-            }
+    let packages = match pypi_user_packages_from_profile(&client, pypi_user) {
+        Ok(packages) if !packages.is_empty() => packages,
+        Ok(_) => {
+            log::debug!(
+                "No packages found on PyPI profile of {}; trying XML-RPC",
+                pypi_user
+            );
+            pypi_user_packages_from_xmlrpc(pypi_user)?
+        }
+        Err(e) => {
+            log::warn!(
+                "Unable to list packages from PyPI profile of {}: {}; trying XML-RPC",
+                pypi_user,
+                e
+            );
+            pypi_user_packages_from_xmlrpc(pypi_user)?
+        }
+    };
 
-            for (key, url) in project_urls.iter() {
-                if url == "UNKNOWN" {
-                    continue;
-                }
-                if key == "Repository" {
-                    ret.push(
-                        url.as_str().unwrap().parse().map_err(|e| {
-                            Error::Other(format!("Error parsing URL {}: {}", url, e))
-                        })?,
-                    );
-                    break;
-                }
-                let parsed_url = match Url::parse(url.as_str().unwrap()) {
-                    Ok(v) => v,
-                    Err(e) => {
-                        log::warn!("Could not parse URL {}: {}", url, e);
-                        continue;
+    let next = std::sync::atomic::AtomicUsize::new(0);
+    let mut results = std::thread::scope(|scope| {
+        let workers = (0..PYPI_FETCH_CONCURRENCY.min(packages.len()))
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut done = vec![];
+                    loop {
+                        let i = next.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                        let Some(package) = packages.get(i) else {
+                            break;
+                        };
+                        done.push((i, pypi_repository_url(&client, package)));
                     }
-                };
-                if parsed_url.host_str() == Some("github.com")
-                    && parsed_url.path().trim_matches('/').matches('/').count() == 1
+                    done
+                })
+            })
+            .collect::<Vec<_>>();
+        workers
+            .into_iter()
+            .flat_map(|w| w.join().unwrap())
+            .collect::<Vec<_>>()
+    });
+    results.sort_by_key(|(i, _)| *i);
+
+    for (_, result) in results {
+        if let Some(url) = result? {
+            ret.push(url);
+        }
+    }
+
+    Ok(ret)
+}
+
+/// Maximum number of PyPI metadata requests in flight during discovery.
+const PYPI_FETCH_CONCURRENCY: usize = 8;
+
+/// Number of times to try fetching metadata from PyPI.
+const PYPI_FETCH_ATTEMPTS: u32 = 4;
+
+fn is_transient_http_error(e: &reqwest::Error) -> bool {
+    e.is_timeout()
+        || e.is_connect()
+        || e.status()
+            .is_some_and(|s| s.is_server_error() || s == reqwest::StatusCode::TOO_MANY_REQUESTS)
+}
+
+/// Fetch a JSON document from PyPI, retrying on transient errors.
+fn fetch_pypi_json(client: &reqwest::blocking::Client, url: &str) -> Result<Value, Error> {
+    let mut attempt = 1;
+    loop {
+        let permit = crate::limits::acquire_blocking(crate::limits::Service::PyPI);
+        let result = client
+            .get(url)
+            .send()
+            .and_then(|r| {
+                if r.status().is_server_error()
+                    || r.status() == reqwest::StatusCode::TOO_MANY_REQUESTS
                 {
-                    ret.push(
-                        url.as_str().unwrap().parse().map_err(|e| {
-                            Error::Other(format!("Error parsing URL {}: {}", url, e))
-                        })?,
-                    );
-                    break;
+                    r.error_for_status()
+                } else {
+                    Ok(r)
                 }
+            })
+            .and_then(|r| r.json::<Value>());
+        drop(permit);
+        match result {
+            Ok(data) => return Ok(data),
+            Err(e) if attempt < PYPI_FETCH_ATTEMPTS && is_transient_http_error(&e) => {
+                let delay = std::time::Duration::from_secs(1 << attempt);
+                log::debug!(
+                    "Error fetching {}: {}; retrying in {} seconds",
+                    url,
+                    e,
+                    delay.as_secs()
+                );
+                std::thread::sleep(delay);
+                attempt += 1;
             }
+            Err(e) => return Err(Error::Other(format!("Error fetching {}: {}", url, e))),
         }
     }
+}
 
-    Ok(ret)
+/// Find the repository URL for a PyPI package from its project URLs.
+fn pypi_repository_url(
+    client: &reqwest::blocking::Client,
+    package: &str,
+) -> Result<Option<Url>, Error> {
+    let data = fetch_pypi_json(client, &format!("https://pypi.org/pypi/{}/json", package))?;
+    let Some(project_urls) = data["info"]["project_urls"].as_object() else {
+        return Ok(None);
+    };
+    if project_urls.is_empty() {
+        log::debug!("Project {} does not have project URLs", package);
+        return Ok(None);
+    }
+
+    for (key, url) in project_urls.iter() {
+        if url == "UNKNOWN" {
+            continue;
+        }
+        if key == "Repository" {
+            return Ok(Some(url.as_str().unwrap().parse().map_err(|e| {
+                Error::Other(format!("Error parsing URL {}: {}", url, e))
+            })?));
+        }
+        let parsed_url = match Url::parse(url.as_str().unwrap()) {
+            Ok(v) => v,
+            Err(e) => {
+                log::warn!("Could not parse URL {}: {}", url, e);
+                continue;
+            }
+        };
+        if parsed_url.host_str() == Some("github.com")
+            && parsed_url.path().trim_matches('/').matches('/').count() == 1
+        {
+            return Ok(Some(parsed_url));
+        }
+    }
+    Ok(None)
 }
 
 pub fn pyproject_uses_hatch_vcs(tree: &dyn Tree) -> Result<bool, Error> {
@@ -186,6 +339,27 @@ pub fn pyproject_uses_hatch_vcs(tree: &dyn Tree) -> Result<bool, Error> {
         == Some("vcs"))
 }
 
+/// Check whether pyproject.toml uses maturin as its build backend.
+pub fn pyproject_uses_maturin(tree: &dyn Tree) -> Result<bool, Error> {
+    let content = match tree.get_file_text(Path::new("pyproject.toml")) {
+        Ok(v) => v,
+        Err(_) => return Ok(false),
+    };
+
+    let parsed_toml: toml_edit::DocumentMut = String::from_utf8(content)
+        .map_err(|e| Error::Other(format!("Invalid UTF-8 in pyproject.toml: {}", e)))?
+        .parse()
+        .map_err(|e| Error::Other(format!("Invalid TOML in pyproject.toml: {}", e)))?;
+
+    Ok(parsed_toml
+        .as_table()
+        .get("build-system")
+        .and_then(|v| v.as_table())
+        .and_then(|v| v.get("build-backend"))
+        .and_then(|v| v.as_str())
+        == Some("maturin"))
+}
+
 pub fn find_name_in_pyproject_toml(tree: &dyn Tree) -> Option<String> {
     let content = tree.get_file_text(Path::new("pyproject.toml")).ok()?;
 
@@ -223,6 +397,11 @@ pub fn find_hatch_vcs_version(tree: &WorkingTree) -> Option<Version> {
         major: parts[0].parse().unwrap(),
         minor: parts.get(1).map(|v| v.parse().unwrap()),
         micro: parts.get(2).map(|v| v.parse().unwrap()),
+        pre: None,
+        build: None,
+        epoch: None,
+        post: None,
+        dev: None,
     })
 }
 
@@ -266,38 +445,46 @@ pub fn read_project_urls_from_pyproject_toml(
     Ok(result)
 }
 
-pub fn read_project_urls_from_setup_cfg(
-    path: &std::path::Path,
-) -> pyo3::PyResult<Vec<(url::Url, Option<String>)>> {
-    pyo3::Python::with_gil(|py| {
-        let setuptools = py.import_bound("setuptools.config.setupcfg")?;
-
-        let config = setuptools.call_method1("read_configuration", (path,))?;
-
-        let metadata = match config.get_item("metadata") {
-            Ok(v) => v,
-            Err(_) => return Ok(vec![]),
-        };
+fn parse_project_urls_from_setup_cfg(
+    content: &str,
+) -> Result<Vec<(url::Url, Option<String>)>, Error> {
+    let mut config = configparser::ini::Ini::new_cs();
+    config.set_multiline(true);
+    config
+        .read(content.to_string())
+        .map_err(|e| Error::Other(format!("Invalid setup.cfg: {}", e)))?;
 
-        let project_urls = match metadata.get_item("project_urls") {
-            Ok(v) => v,
-            Err(_) => return Ok(vec![]),
-        };
+    let project_urls = match config.get("metadata", "project_urls") {
+        Some(v) => v,
+        None => return Ok(vec![]),
+    };
 
-        let mut result = vec![];
+    // project_urls is a dangling list of "name = url" lines.
+    let project_urls = project_urls
+        .lines()
+        .filter_map(|l| l.split_once('='))
+        .map(|(k, v)| (k.trim(), v.trim()))
+        .collect::<Vec<_>>();
 
-        for key in ["GitHub", "Source Code", "Repository"].iter() {
-            match project_urls.get_item(key) {
-                Ok(url) => {
-                    let url_str = url.extract::<String>()?;
-                    result.push((url_str.parse::<url::Url>().unwrap(), None));
-                }
-                Err(_) => continue,
+    let mut result = vec![];
+    for key in ["GitHub", "Source Code", "Repository"].iter() {
+        if let Some((_, url)) = project_urls.iter().find(|(k, _)| k == key) {
+            if *url == "UNKNOWN" {
+                continue;
+            }
+            match url::Url::parse(url) {
+                Ok(v) => result.push((v, None)),
+                Err(e) => log::warn!("Could not parse URL {}: {}", url, e),
             }
         }
+    }
+    Ok(result)
+}
 
-        Ok(result)
-    })
+pub fn read_project_urls_from_setup_cfg(
+    path: &std::path::Path,
+) -> Result<Vec<(url::Url, Option<String>)>, Error> {
+    parse_project_urls_from_setup_cfg(&std::fs::read_to_string(path)?)
 }
 
 #[derive(Debug)]
@@ -314,19 +501,43 @@ impl std::fmt::Display for UploadCommandFailed {
 
 impl std::error::Error for UploadCommandFailed {}
 
+/// The command used to upload Python artifacts with a particular tool.
+pub fn upload_command(tool: PythonUploadTool) -> Vec<&'static str> {
+    match tool {
+        PythonUploadTool::Twine => vec!["twine", "upload", "--non-interactive"],
+        PythonUploadTool::Uv => vec!["uv", "publish"],
+    }
+}
+
 pub fn upload_python_artifacts(
     local_tree: &WorkingTree,
     pypi_paths: &[&std::path::Path],
+    tool: PythonUploadTool,
 ) -> Result<(), UploadCommandFailed> {
-    let mut command = vec!["twine", "upload", "--non-interactive"];
+    let mut command = upload_command(tool);
     command.extend(pypi_paths.iter().map(|v| v.to_str().unwrap()));
 
     let abs_path = local_tree.abspath(Path::new(".")).unwrap();
 
-    let output = Command::new("twine")
-        .args(&command[1..])
-        .current_dir(&abs_path)
-        .status();
+    let mut cmd = Command::new(command[0]);
+    cmd.args(&command[1..]).current_dir(&abs_path);
+    if let Some(token) = crate::token_cache::cached_token(crate::token_cache::Service::PyPI) {
+        match tool {
+            PythonUploadTool::Twine => {
+                if std::env::var_os("TWINE_PASSWORD").is_none() {
+                    cmd.env("TWINE_USERNAME", "__token__")
+                        .env("TWINE_PASSWORD", token);
+                }
+            }
+            PythonUploadTool::Uv => {
+                if std::env::var_os("UV_PUBLISH_TOKEN").is_none() {
+                    cmd.env("UV_PUBLISH_TOKEN", token);
+                }
+            }
+        }
+    }
+    let _permit = crate::limits::acquire_blocking(crate::limits::Service::PyPI);
+    let output = crate::logging::status(&mut cmd);
 
     match output {
         Ok(status) => {
@@ -346,95 +557,487 @@ pub fn upload_python_artifacts(
     }
 }
 
-pub fn create_setup_py_artifacts(
+/// Number of lines of build output to include in errors.
+const BUILD_OUTPUT_TAIL: usize = 20;
+
+fn output_tail(output: &std::process::Output) -> String {
+    let text = [
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr),
+    ]
+    .concat();
+    let lines = text.lines().collect::<Vec<_>>();
+    lines[lines.len().saturating_sub(BUILD_OUTPUT_TAIL)..].join("\n")
+}
+
+/// Build a single distribution (`"sdist"` or `"wheel"`) with `python -m build`.
+///
+/// Returns the artifacts that were written to `output_dir`.
+fn python_build(
     local_tree: &WorkingTree,
-) -> pyo3::PyResult<Vec<std::path::PathBuf>> {
-    pyo3::Python::with_gil(|py| {
-        // Initialize an empty vector to store pypi_paths
-        let mut pypi_paths: Vec<std::path::PathBuf> = Vec::new();
-
-        // Import required Python modules
-        let os = py.import_bound("os")?;
-        let run_setup = py.import_bound("distutils.core")?.getattr("run_setup")?;
-        let _setuptools = py.import_bound("setuptools")?;
-
-        // Save the original directory
-        let orig_dir = os.call_method0("getcwd")?;
-
-        // Change to the setup.py directory
-        let setup_dir = local_tree.abspath(Path::new(".")).unwrap();
-        os.call_method1("chdir", (setup_dir.clone(),))?;
-
-        let result = {
-            // Attempt to run setup.py
-            let run_setup_result =
-                run_setup.call1((local_tree.abspath(Path::new("setup.py")).unwrap(), "config"))?;
-            // Change back to the original directory
-            os.call_method1("chdir", (orig_dir,))?;
-
-            run_setup_result
-        };
+    output_dir: &Path,
+    distribution: &str,
+) -> Result<Vec<std::path::PathBuf>, Error> {
+    let build_dir = artifact_build_dir(output_dir)?;
+    let mut command = Command::new("python3");
+    command
+        .arg("-m")
+        .arg("build")
+        .arg(format!("--{}", distribution))
+        .arg("--outdir")
+        .arg(build_dir.path())
+        .current_dir(local_tree.abspath(Path::new("."))?);
+    run_build(command, format!("python3 -m build --{}", distribution))?;
+    collect_artifacts(build_dir.path(), output_dir)
+}
 
-        // Check for C libraries and extension modules
-        let is_pure = !result
-            .call_method0("has_c_libraries")?
-            .extract::<Option<bool>>()?
-            .unwrap_or(false)
-            && !result
-                .call_method0("has_ext_modules")?
-                .extract::<Option<bool>>()?
-                .unwrap_or(false);
-
-        let builder = py
-            .import_bound("build")?
-            .call_method1("ProjectBuilder", (setup_dir,))?;
-
-        if is_pure {
-            let wheels = builder.call_method1(
-                "build",
-                ("wheel", local_tree.abspath(Path::new("dist")).unwrap()),
-            )?;
-            pypi_paths.push(wheels.extract::<std::path::PathBuf>()?);
+/// Run a build command, reporting the tail of its output if it fails.
+fn run_build(mut command: Command, description: String) -> Result<(), Error> {
+    log::debug!("Running {:?}", command);
+    let output = command.output()?;
+    if !output.status.success() {
+        return Err(Error::BuildFailed {
+            command: description,
+            status: output.status,
+            output: output_tail(&output),
+        });
+    }
+    Ok(())
+}
+
+/// Create an empty directory for a build tool to write its artifacts to.
+///
+/// It lives inside `output_dir`, so that the artifacts can be moved there
+/// without copying.
+fn artifact_build_dir(output_dir: &Path) -> Result<tempfile::TempDir, Error> {
+    Ok(tempfile::Builder::new()
+        .prefix(".build-")
+        .tempdir_in(output_dir)?)
+}
+
+/// Move the Python artifacts a build wrote to `build_dir` into `output_dir`.
+///
+/// Returns the new paths of the artifacts.
+fn collect_artifacts(
+    build_dir: &Path,
+    output_dir: &Path,
+) -> Result<Vec<std::path::PathBuf>, Error> {
+    let mut pypi_paths = vec![];
+    for entry in std::fs::read_dir(build_dir)? {
+        let entry = entry?;
+        let name = entry.file_name();
+        if !(name.to_string_lossy().ends_with(".whl")
+            || name.to_string_lossy().ends_with(".tar.gz"))
+        {
+            continue;
+        }
+        let path = output_dir.join(&name);
+        std::fs::rename(entry.path(), &path)?;
+        pypi_paths.push(path);
+    }
+    pypi_paths.sort();
+    Ok(pypi_paths)
+}
+
+/// Check whether a wheel is platform independent, based on its filename.
+fn is_pure_wheel(path: &Path) -> bool {
+    path.file_name()
+        .map(|n| n.to_string_lossy().ends_with("-none-any.whl"))
+        .unwrap_or(false)
+}
+
+/// Build artifacts for a setup.py-based project.
+///
+/// Wheels are only kept for pure Python packages, since a binary wheel
+/// built here would only work on this platform.
+pub fn create_setup_py_artifacts(
+    local_tree: &WorkingTree,
+    output_dir: &Path,
+) -> Result<Vec<std::path::PathBuf>, Error> {
+    let mut pypi_paths = vec![];
+    for wheel in python_build(local_tree, output_dir, "wheel")? {
+        if is_pure_wheel(&wheel) {
+            pypi_paths.push(wheel);
         } else {
             log::warn!("python module is not pure; not uploading binary wheels");
+            std::fs::remove_file(&wheel)?;
         }
+    }
+    pypi_paths.extend(python_build(local_tree, output_dir, "sdist")?);
+    Ok(pypi_paths)
+}
+
+pub fn create_python_artifacts(
+    local_tree: &WorkingTree,
+    output_dir: &Path,
+) -> Result<Vec<std::path::PathBuf>, Error> {
+    let mut pypi_paths = python_build(local_tree, output_dir, "wheel")?;
+    pypi_paths.extend(python_build(local_tree, output_dir, "sdist")?);
+    Ok(pypi_paths)
+}
+
+/// Build an sdist and wheel with `uv build`.
+///
+/// Returns the artifacts that were written to `output_dir`.
+pub fn create_uv_artifacts(
+    local_tree: &WorkingTree,
+    output_dir: &Path,
+) -> Result<Vec<std::path::PathBuf>, Error> {
+    let build_dir = artifact_build_dir(output_dir)?;
+    let mut command = Command::new("uv");
+    command
+        .arg("build")
+        .arg("--out-dir")
+        .arg(build_dir.path())
+        .current_dir(local_tree.abspath(Path::new("."))?);
+    run_build(command, "uv build".to_string())?;
+    collect_artifacts(build_dir.path(), output_dir)
+}
 
-        let sdist_path = builder.call_method1(
-            "build",
-            ("sdist", local_tree.abspath(Path::new("dist")).unwrap()),
-        )?;
-        pypi_paths.push(sdist_path.extract::<std::path::PathBuf>()?);
+/// Python files that conventionally hold the version of a package.
+const VERSION_FILE_NAMES: &[&str] = &["__init__.py", "_version.py", "version.py", "__about__.py"];
 
-        Ok(pypi_paths)
-    })
+/// The release level and serial of a version, as used in `sys.version_info`.
+fn release_level(version: &Version, status: Status) -> (&'static str, u64) {
+    if status == Status::Dev {
+        return ("dev", version.dev.unwrap_or(0) as u64);
+    }
+    if let Some(pre) = version.pre.as_ref() {
+        let ids = pre.identifiers();
+        let level = match ids.first().map(|i| i.to_string()).as_deref() {
+            Some("a") | Some("alpha") => "alpha",
+            Some("b") | Some("beta") => "beta",
+            Some("c") | Some("rc") => "candidate",
+            _ => "final",
+        };
+        let serial = match ids.get(1) {
+            Some(crate::version::Identifier::Numeric(n)) => *n,
+            _ => 0,
+        };
+        return (level, serial);
+    }
+    match version.dev {
+        Some(dev) => ("dev", dev as u64),
+        None => ("final", 0),
+    }
 }
 
-pub fn create_python_artifacts(
+fn version_tuple(version: &Version, status: Status, with_status: bool) -> String {
+    let (major, minor, micro) = (
+        version.major,
+        version.minor.unwrap_or(0),
+        version.micro.unwrap_or(0),
+    );
+    if with_status {
+        let (level, serial) = release_level(version, status);
+        format!(
+            "({}, {}, {}, \"{}\", {})",
+            major, minor, micro, level, serial
+        )
+    } else {
+        format!("({}, {}, {})", major, minor, micro)
+    }
+}
+
+/// Update `__version__` and `version_info` assignments in Python source.
+///
+/// When starting on a new version (`Status::Dev`), only tuples that carry a
+/// release level are updated, like other version files with a status.
+///
+/// Returns None if the source doesn't contain any version assignments.
+pub fn update_version_in_python_source(
+    text: &str,
+    version: &Version,
+    status: Status,
+) -> Option<String> {
+    let string_re = regex!(r#"(?m)^(__version__\s*=\s*)(["'])[^"'\n]*["']"#);
+    let tuple_re = regex!(r"(?m)^((?:__version__|version_info)\s*=\s*)\(([^)]*)\)");
+    if !string_re.is_match(text) && !tuple_re.is_match(text) {
+        return None;
+    }
+    let text = if status == Status::Dev {
+        std::borrow::Cow::Borrowed(text)
+    } else {
+        string_re.replace_all(text, |c: &regex::Captures| {
+            format!("{}{}{}{}", &c[1], &c[2], version.to_string(), &c[2])
+        })
+    };
+    let text = tuple_re.replace_all(&text, |c: &regex::Captures| {
+        let with_status = c[2].split(',').filter(|p| !p.trim().is_empty()).count() >= 5;
+        if status == Status::Dev && !with_status {
+            return c[0].to_string();
+        }
+        format!("{}{}", &c[1], version_tuple(version, status, with_status))
+    });
+    Some(text.into_owned())
+}
+
+/// Find Python files in a tree that hold a version assignment.
+///
+/// This looks in the top-level packages, both in the root and under src/.
+pub fn find_python_version_files(tree: &WorkingTree) -> Result<Vec<std::path::PathBuf>, Error> {
+    let mut ret = vec![];
+    for root in [Path::new(""), Path::new("src")] {
+        let abs_root = tree.abspath(root)?;
+        let entries = match std::fs::read_dir(&abs_root) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(e.into()),
+        };
+        let mut packages = vec![];
+        for entry in entries {
+            let name = entry?.file_name();
+            let package = root.join(&name);
+            if !name.to_string_lossy().starts_with('.')
+                && tree.has_filename(&package.join("__init__.py"))
+            {
+                packages.push(package);
+            }
+        }
+        packages.sort();
+        for package in packages {
+            for name in VERSION_FILE_NAMES {
+                let path = package.join(name);
+                if !tree.has_filename(&path) {
+                    continue;
+                }
+                let text = tree.get_file_text(&path)?;
+                if update_version_in_python_source(
+                    &String::from_utf8_lossy(&text),
+                    &"0".parse().unwrap(),
+                    Status::Final,
+                )
+                .is_some()
+                {
+                    ret.push(path);
+                }
+            }
+        }
+    }
+    Ok(ret)
+}
+
+/// Update the version in the Python files that hold one, except those in `skip`.
+///
+/// Returns the paths that were modified.
+pub fn update_python_version_files(
+    tree: &WorkingTree,
+    version: &Version,
+    status: Status,
+    skip: &[&Path],
+) -> Result<Vec<std::path::PathBuf>, Error> {
+    let mut modified = vec![];
+    for path in find_python_version_files(tree)? {
+        if skip.contains(&path.as_path()) {
+            continue;
+        }
+        let text = tree.get_file_text(&path)?;
+        let text = String::from_utf8(text)
+            .map_err(|e| Error::Other(format!("Invalid UTF-8 in {}: {}", path.display(), e)))?;
+        if let Some(updated) = update_version_in_python_source(&text, version, status) {
+            if updated != text {
+                log::info!("Updating version in {}", path.display());
+                tree.put_file_bytes_non_atomic(&path, updated.as_bytes())?;
+                modified.push(path);
+            }
+        }
+    }
+    Ok(modified)
+}
+
+/// Cargo features to pass to maturin.
+fn maturin_features(settings: Option<&Maturin>) -> Vec<String> {
+    let mut features = settings
+        .and_then(|m| m.features.clone())
+        .unwrap_or_default();
+    if let Some(abi3) = settings.and_then(|m| m.abi3.as_deref()) {
+        features.push(format!("pyo3/abi3-py{}", abi3.replace('.', "")));
+    }
+    features
+}
+
+/// Build an sdist, and wheels for the configured targets, for a maturin-backed project.
+///
+/// A wheel built for the host would only work on this platform, so without
+/// configured targets only the sdist is built.
+pub fn create_maturin_artifacts(
     local_tree: &WorkingTree,
-) -> pyo3::PyResult<Vec<std::path::PathBuf>> {
-    pyo3::Python::with_gil(|py| {
-        let mut pypi_paths = Vec::new();
+    output_dir: &Path,
+    settings: Option<&Maturin>,
+) -> Result<Vec<std::path::PathBuf>, Error> {
+    let build_dir = artifact_build_dir(output_dir)?;
+    let cwd = local_tree.abspath(Path::new("."))?;
+
+    let mut command = Command::new("maturin");
+    command
+        .arg("sdist")
+        .arg("--out")
+        .arg(build_dir.path())
+        .current_dir(&cwd);
+    run_build(command, "maturin sdist".to_string())?;
+
+    let targets = settings
+        .and_then(|m| m.targets.as_deref())
+        .unwrap_or_default();
+    if targets.is_empty() {
+        log::warn!("no maturin targets configured; only uploading an sdist, not binary wheels");
+    }
+    let features = maturin_features(settings);
+    for target in targets {
+        let mut command = Command::new("maturin");
+        command
+            .arg("build")
+            .arg("--release")
+            .arg("--target")
+            .arg(target)
+            .arg("--out")
+            .arg(build_dir.path())
+            .current_dir(&cwd);
+        if !features.is_empty() {
+            command.arg("--features").arg(features.join(","));
+        }
+        run_build(command, format!("maturin build --target {}", target))?;
+    }
+    collect_artifacts(build_dir.path(), output_dir)
+}
 
-        let project_builder = py.import_bound("build")?.call_method1(
-            "ProjectBuilder",
-            (local_tree.abspath(Path::new(".")).unwrap(),),
-        )?;
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_pure_wheel() {
+        assert!(is_pure_wheel(Path::new(
+            "dist/dulwich-0.22.0-py3-none-any.whl"
+        )));
+        assert!(!is_pure_wheel(Path::new(
+            "dist/dulwich-0.22.0-cp312-cp312-manylinux_2_17_x86_64.whl"
+        )));
+    }
 
-        // Wrap Python exception handling using PyResult
-        let wheels = project_builder.call_method1(
-            "build",
-            ("wheel", local_tree.abspath(Path::new("dist")).unwrap()),
-        )?;
+    #[test]
+    fn test_collect_artifacts() {
+        let td = tempfile::tempdir().unwrap();
+        // Left over from an earlier build; must not be picked up.
+        std::fs::write(td.path().join("dulwich-0.21.0.tar.gz"), "").unwrap();
+        let build_dir = artifact_build_dir(td.path()).unwrap();
+        std::fs::write(build_dir.path().join("dulwich-0.22.0.tar.gz"), "").unwrap();
+        std::fs::write(build_dir.path().join("build.log"), "").unwrap();
+        assert_eq!(
+            collect_artifacts(build_dir.path(), td.path()).unwrap(),
+            vec![td.path().join("dulwich-0.22.0.tar.gz")]
+        );
+        assert!(td.path().join("dulwich-0.22.0.tar.gz").exists());
+    }
 
-        pypi_paths.push(std::path::PathBuf::from(wheels.extract::<String>()?));
+    #[test]
+    fn test_parse_project_urls_from_setup_cfg() {
+        let content = r#"[metadata]
+name = dulwich
+project_urls =
+    Repository = https://www.dulwich.io/code/
+    GitHub = https://github.com/dulwich/dulwich
+    Bug Tracker = https://github.com/dulwich/dulwich/issues
+
+[options]
+packages = dulwich
+"#;
+        assert_eq!(
+            parse_project_urls_from_setup_cfg(content).unwrap(),
+            vec![
+                ("https://github.com/dulwich/dulwich".parse().unwrap(), None),
+                ("https://www.dulwich.io/code/".parse().unwrap(), None),
+            ]
+        );
+        assert_eq!(
+            parse_project_urls_from_setup_cfg("[metadata]\nname = foo\n").unwrap(),
+            vec![]
+        );
+    }
 
-        let sdist_path = project_builder.call_method1(
-            "build",
-            ("source", local_tree.abspath(Path::new("dist")).unwrap()),
-        )?;
+    #[test]
+    fn test_parse_pypi_user_page() {
+        let html = r#"
+<div class="package-list">
+  <a class="package-snippet" href="/project/dulwich/">
+    <h3 class="package-snippet__title">dulwich</h3>
+  </a>
+  <a class="package-snippet" href="/project/breezy/">
+    <h3 class="package-snippet__title">breezy</h3>
+  </a>
+  <a href="/project/unrelated/">unrelated</a>
+</div>
+"#;
+        assert_eq!(parse_pypi_user_page(html), vec!["dulwich", "breezy"]);
+    }
 
-        pypi_paths.push(std::path::PathBuf::from(sdist_path.extract::<String>()?));
+    #[test]
+    fn test_update_version_in_python_source() {
+        let v: Version = "1.2.3".parse().unwrap();
+        assert_eq!(
+            update_version_in_python_source(
+                "import os\n__version__ = '1.2.2'\n",
+                &v,
+                Status::Final
+            )
+            .unwrap(),
+            "import os\n__version__ = '1.2.3'\n"
+        );
+        assert_eq!(
+            update_version_in_python_source(
+                "__version__ = (1, 2, 2)\nversion_info = (1, 2, 2, \"dev\", 0)\n",
+                &v,
+                Status::Final
+            )
+            .unwrap(),
+            "__version__ = (1, 2, 3)\nversion_info = (1, 2, 3, \"final\", 0)\n"
+        );
+        assert_eq!(
+            update_version_in_python_source("def f():\n    __version__ = '1'\n", &v, Status::Final),
+            None
+        );
+        let rc: Version = "1.3.0rc2".parse().unwrap();
+        assert_eq!(
+            update_version_in_python_source(
+                "version_info = (1, 2, 3, \"final\", 0)\n",
+                &rc,
+                Status::Final
+            )
+            .unwrap(),
+            "version_info = (1, 3, 0, \"candidate\", 2)\n"
+        );
+        let beta: Version = "1.3.0-beta.1".parse().unwrap();
+        assert_eq!(
+            update_version_in_python_source(
+                "version_info = (1, 2, 3, \"final\", 0)\n",
+                &beta,
+                Status::Final
+            )
+            .unwrap(),
+            "version_info = (1, 3, 0, \"beta\", 1)\n"
+        );
+        let next: Version = "1.2.4".parse().unwrap();
+        assert_eq!(
+            update_version_in_python_source(
+                "__version__ = '1.2.3'\nversion_info = (1, 2, 3, \"final\", 0)\n",
+                &next,
+                Status::Dev
+            )
+            .unwrap(),
+            "__version__ = '1.2.3'\nversion_info = (1, 2, 4, \"dev\", 0)\n"
+        );
+    }
 
-        Ok(pypi_paths)
-    })
+    #[test]
+    fn test_maturin_features() {
+        assert_eq!(maturin_features(None), Vec::<String>::new());
+        assert_eq!(
+            maturin_features(Some(&Maturin {
+                abi3: Some("3.8".to_string()),
+                features: Some(vec!["extension-module".to_string()]),
+                targets: None,
+            })),
+            vec!["extension-module", "pyo3/abi3-py38"]
+        );
+    }
 }
@@ -0,0 +1,65 @@
+/// Channel that releases are published under when staged rollouts are enabled.
+pub const DEFAULT_CHANNEL: &str = "next";
+
+/// Channel that releases are promoted to.
+pub const PROMOTED_CHANNEL: &str = "latest";
+
+#[derive(Debug)]
+pub enum Error {
+    /// The soak period has not passed yet.
+    Soaking { remaining: chrono::Duration },
+    /// None of the ecosystems used by the project support staged rollouts.
+    Unsupported,
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Error::Soaking { remaining } => write!(
+                f,
+                "Release is still soaking; {} hours remaining",
+                remaining.num_hours()
+            ),
+            Error::Unsupported => write!(
+                f,
+                "No ecosystem used by this project supports staged rollouts"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// Check whether a release made at `released` has soaked for at least `soak_days`.
+pub fn check_soak(
+    released: chrono::DateTime<chrono::Utc>,
+    now: chrono::DateTime<chrono::Utc>,
+    soak_days: u64,
+) -> Result<(), Error> {
+    let soak = chrono::Duration::days(soak_days as i64);
+    let age = now.signed_duration_since(released);
+    if age < soak {
+        return Err(Error::Soaking {
+            remaining: soak - age,
+        });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_soak() {
+        let now = chrono::Utc::now();
+        assert!(check_soak(now - chrono::Duration::days(3), now, 2).is_ok());
+        assert!(check_soak(now, now, 0).is_ok());
+        match check_soak(now - chrono::Duration::days(1), now, 2) {
+            Err(Error::Soaking { remaining }) => {
+                assert_eq!(remaining.num_days(), 1);
+            }
+            r => panic!("unexpected result: {:?}", r),
+        }
+    }
+}
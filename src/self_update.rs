@@ -0,0 +1,232 @@
+use crate::version::Version;
+use std::path::Path;
+
+/// Repository that disperse itself is released from.
+const REPOSITORY: (&str, &str) = ("jelmer", "disperse");
+
+/// Fingerprint of the OpenPGP key that disperse release binaries are signed with.
+const RELEASE_KEY_FINGERPRINT: &str = "DC837EE14A7E37347E87061700806F2BD729A457";
+
+#[derive(Debug)]
+pub enum Error {
+    GitHubError(octocrab::Error),
+    HttpError(reqwest::Error),
+    IoError(std::io::Error),
+    /// The release has no binary for this platform.
+    NoAsset(String),
+    /// The binary signature did not verify.
+    BadSignature(String),
+    Other(String),
+}
+
+impl From<octocrab::Error> for Error {
+    fn from(e: octocrab::Error) -> Self {
+        Error::GitHubError(e)
+    }
+}
+
+impl From<reqwest::Error> for Error {
+    fn from(e: reqwest::Error) -> Self {
+        Error::HttpError(e)
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(e: std::io::Error) -> Self {
+        Error::IoError(e)
+    }
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match &self {
+            Error::GitHubError(e) => write!(f, "GitHubError: {}", e),
+            Error::HttpError(e) => write!(f, "HttpError: {}", e),
+            Error::IoError(e) => write!(f, "IoError: {}", e),
+            Error::NoAsset(name) => write!(f, "Release has no asset named {}", name),
+            Error::BadSignature(e) => write!(f, "Bad signature: {}", e),
+            Error::Other(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// The version of the running disperse.
+pub fn current_version() -> Version {
+    env!("CARGO_PKG_VERSION").parse().unwrap()
+}
+
+/// Name of the release asset with the binary for this platform.
+pub fn asset_name() -> String {
+    format!(
+        "disperse-{}-{}",
+        std::env::consts::ARCH,
+        std::env::consts::OS
+    )
+}
+
+/// A newer release of disperse.
+#[derive(Debug, Clone)]
+pub struct Update {
+    pub version: Version,
+    pub binary_url: url::Url,
+    pub signature_url: url::Url,
+}
+
+/// Check GitHub for a release of disperse newer than the running one.
+pub async fn check(instance: &octocrab::Octocrab) -> Result<Option<Update>, Error> {
+    let _permit = crate::limits::acquire(crate::limits::Service::GitHub).await;
+    let release = instance
+        .repos(REPOSITORY.0, REPOSITORY.1)
+        .releases()
+        .get_latest()
+        .await?;
+    let version: Version = release
+        .tag_name
+        .trim_start_matches('v')
+        .parse()
+        .map_err(|e| Error::Other(format!("Invalid release tag {}: {}", release.tag_name, e)))?;
+    if version <= current_version() {
+        return Ok(None);
+    }
+    let asset_url = |name: &str| {
+        release
+            .assets
+            .iter()
+            .find(|a| a.name == name)
+            .map(|a| a.browser_download_url.clone())
+    };
+    let binary_name = asset_name();
+    let signature_name = format!("{}.asc", binary_name);
+    Ok(Some(Update {
+        version,
+        binary_url: asset_url(&binary_name).ok_or(Error::NoAsset(binary_name))?,
+        signature_url: asset_url(&signature_name).ok_or(Error::NoAsset(signature_name))?,
+    }))
+}
+
+/// Download an update, verify its signature and replace the binary at `target`.
+pub async fn install(update: &Update, target: &Path) -> Result<(), Error> {
+    let client = reqwest::Client::builder()
+        .user_agent(crate::USER_AGENT)
+        .build()?;
+    let binary = client
+        .get(update.binary_url.clone())
+        .send()
+        .await?
+        .error_for_status()?
+        .bytes()
+        .await?;
+    let signature = client
+        .get(update.signature_url.clone())
+        .send()
+        .await?
+        .error_for_status()?
+        .text()
+        .await?;
+    let fingerprints = crate::manifest::verify_detached_signature(&binary, &signature)
+        .map_err(|e| Error::BadSignature(e.to_string()))?;
+    check_release_key(&fingerprints).map_err(Error::BadSignature)?;
+    replace_executable(target, &binary)?;
+    Ok(())
+}
+
+/// Check that a signature was made by the disperse release key, rather than
+/// by any key that happens to be in the keyring.
+fn check_release_key(fingerprints: &[String]) -> Result<(), String> {
+    if fingerprints
+        .iter()
+        .any(|f| f.eq_ignore_ascii_case(RELEASE_KEY_FINGERPRINT))
+    {
+        Ok(())
+    } else {
+        Err(format!(
+            "signed by {}, not by the disperse release key {}",
+            fingerprints.join(", "),
+            RELEASE_KEY_FINGERPRINT
+        ))
+    }
+}
+
+/// Replace an executable, without leaving a partially written file behind.
+fn replace_executable(target: &Path, contents: &[u8]) -> std::io::Result<()> {
+    let new = target.with_extension("new");
+    std::fs::write(&new, contents)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(&new, std::fs::Permissions::from_mode(0o755))?;
+    }
+    std::fs::rename(&new, target)
+}
+
+/// How long ago the running disperse binary was installed.
+pub fn installed_age(now: chrono::DateTime<chrono::Utc>) -> Option<chrono::Duration> {
+    let modified = std::env::current_exe()
+        .ok()?
+        .metadata()
+        .ok()?
+        .modified()
+        .ok()?;
+    Some(now.signed_duration_since(chrono::DateTime::<chrono::Utc>::from(modified)))
+}
+
+/// Find the newest disperse version that released a project, if it is newer
+/// than the running one.
+pub fn newer_release_version(
+    history: &dyn crate::history::ReleaseHistory,
+    project: &str,
+) -> Result<Option<Version>, crate::history::Error> {
+    let current = current_version();
+    Ok(history
+        .since(chrono::DateTime::UNIX_EPOCH)?
+        .into_iter()
+        .filter(|e| e.project == project)
+        .filter_map(|e| e.disperse_version?.parse::<Version>().ok())
+        .filter(|v| *v > current)
+        .max())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::history::{JsonLinesHistory, ReleaseEvent, ReleaseHistory};
+
+    #[test]
+    fn test_check_release_key() {
+        assert!(check_release_key(&[RELEASE_KEY_FINGERPRINT.to_lowercase()]).is_ok());
+        assert!(
+            check_release_key(&["1234567890ABCDEF1234567890ABCDEF12345678".to_string()]).is_err()
+        );
+    }
+
+    #[test]
+    fn test_newer_release_version() {
+        let td = tempfile::tempdir().unwrap();
+        let history = JsonLinesHistory::new(&td.path().join("releases.jsonl"));
+        let record = |project: &str, disperse_version: Option<&str>| {
+            history
+                .record(&ReleaseEvent {
+                    project: project.to_string(),
+                    version: "1.0.0".to_string(),
+                    tag: "v1.0.0".to_string(),
+                    released: chrono::Utc::now(),
+                    notes: None,
+                    urls: vec![],
+                    checklist: vec![],
+                    disperse_version: disperse_version.map(|v| v.to_string()),
+                })
+                .unwrap()
+        };
+        record("foo", None);
+        record("foo", Some(env!("CARGO_PKG_VERSION")));
+        assert_eq!(newer_release_version(&history, "foo").unwrap(), None);
+        record("foo", Some("9999.0.0"));
+        record("bar", Some("10000.0.0"));
+        assert_eq!(
+            newer_release_version(&history, "foo").unwrap(),
+            Some("9999.0.0".parse().unwrap())
+        );
+    }
+}
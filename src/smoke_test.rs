@@ -0,0 +1,238 @@
+use crate::Version;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Number of times to try installing a package, since registries can take a
+/// while before new releases become visible.
+const INSTALL_ATTEMPTS: u32 = 5;
+
+/// Delay between attempts to install a package.
+const INSTALL_RETRY_DELAY: std::time::Duration = std::time::Duration::from_secs(30);
+
+#[derive(Debug)]
+pub enum Error {
+    IoError(std::io::Error),
+    CommandFailed {
+        command: String,
+        status: std::process::ExitStatus,
+    },
+}
+
+impl From<std::io::Error> for Error {
+    fn from(e: std::io::Error) -> Self {
+        Error::IoError(e)
+    }
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match &self {
+            Error::IoError(e) => write!(f, "IoError: {}", e),
+            Error::CommandFailed { command, status } => {
+                write!(f, "{} failed: {}", command, status)
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// A package registry that releases are published to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Registry {
+    PyPI,
+    CratesIo,
+    Npm,
+}
+
+impl std::fmt::Display for Registry {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Registry::PyPI => write!(f, "PyPI"),
+            Registry::CratesIo => write!(f, "crates.io"),
+            Registry::Npm => write!(f, "npm"),
+        }
+    }
+}
+
+impl Registry {
+    const ALL: &'static [Registry] = &[Registry::PyPI, Registry::CratesIo, Registry::Npm];
+
+    fn package_url_prefix(&self) -> &'static str {
+        match self {
+            Registry::PyPI => "https://pypi.org/project/",
+            Registry::CratesIo => "https://crates.io/crates/",
+            Registry::Npm => "https://www.npmjs.com/package/",
+        }
+    }
+
+    /// URL of the page for a package on this registry.
+    pub fn package_url(&self, name: &str) -> String {
+        match self {
+            Registry::PyPI => format!("{}{}/", self.package_url_prefix(), name),
+            _ => format!("{}{}", self.package_url_prefix(), name),
+        }
+    }
+
+    /// Find the registry and package name for a URL created by [`Registry::package_url`].
+    pub fn parse_package_url(url: &str) -> Option<(Registry, String)> {
+        Self::ALL.iter().find_map(|r| {
+            url.strip_prefix(r.package_url_prefix())
+                .map(|name| (*r, name.trim_end_matches('/').to_string()))
+        })
+    }
+}
+
+fn run(command: &mut Command) -> Result<(), Error> {
+    let status = crate::logging::status(command)?;
+    if !status.success() {
+        return Err(Error::CommandFailed {
+            command: format!("{:?}", command),
+            status,
+        });
+    }
+    Ok(())
+}
+
+/// Run an install command, retrying while the release propagates.
+fn install(mut command: impl FnMut() -> Command) -> Result<(), Error> {
+    let mut attempt = 1;
+    loop {
+        match run(&mut command()) {
+            Err(Error::CommandFailed { command, status }) if attempt < INSTALL_ATTEMPTS => {
+                log::info!(
+                    "{} failed ({}); retrying in {} seconds",
+                    command,
+                    status,
+                    INSTALL_RETRY_DELAY.as_secs()
+                );
+                std::thread::sleep(INSTALL_RETRY_DELAY);
+                attempt += 1;
+            }
+            r => return r,
+        }
+    }
+}
+
+/// Prepend a directory to PATH.
+fn path_with(dir: &Path) -> std::ffi::OsString {
+    let mut paths = vec![dir.to_path_buf()];
+    if let Some(path) = std::env::var_os("PATH") {
+        paths.extend(std::env::split_paths(&path));
+    }
+    std::env::join_paths(paths).unwrap()
+}
+
+/// Install a published package into a scratch environment in `dir`.
+///
+/// Returns the directory to run the smoke test command in, and the
+/// directory with installed executables.
+fn prepare(
+    dir: &Path,
+    registry: Registry,
+    name: &str,
+    version: &str,
+) -> Result<(PathBuf, PathBuf), Error> {
+    match registry {
+        Registry::PyPI => {
+            let venv = dir.join("venv");
+            run(Command::new("python3").arg("-m").arg("venv").arg(&venv))?;
+            install(|| {
+                let mut c = Command::new(venv.join("bin/pip"));
+                c.arg("install")
+                    .arg("--no-cache-dir")
+                    .arg(format!("{}=={}", name, version));
+                c
+            })?;
+            Ok((dir.to_path_buf(), venv.join("bin")))
+        }
+        Registry::CratesIo => {
+            let krate = dir.join("smoke");
+            run(Command::new("cargo")
+                .arg("new")
+                .arg("--lib")
+                .arg("--vcs")
+                .arg("none")
+                .arg(&krate))?;
+            run(Command::new("cargo")
+                .arg("add")
+                .arg(format!("{}@={}", name, version))
+                .current_dir(&krate))?;
+            install(|| {
+                let mut c = Command::new("cargo");
+                c.arg("check").current_dir(&krate);
+                c
+            })?;
+            Ok((krate.clone(), krate.join("target/debug")))
+        }
+        Registry::Npm => {
+            run(Command::new("npm").arg("init").arg("-y").current_dir(dir))?;
+            install(|| {
+                let mut c = Command::new("npm");
+                c.arg("install")
+                    .arg(format!("{}@{}", name, version))
+                    .current_dir(dir);
+                c
+            })?;
+            Ok((dir.to_path_buf(), dir.join("node_modules/.bin")))
+        }
+    }
+}
+
+/// Install a just-published package from its registry and run a command against it.
+///
+/// The command is run with the installed executables on the PATH, and with
+/// DISPERSE_PACKAGE and DISPERSE_VERSION set.
+pub fn smoke_test(
+    registry: Registry,
+    name: &str,
+    version: &Version,
+    command: Option<&str>,
+) -> Result<(), Error> {
+    let version = version.to_string();
+    let dir = std::env::temp_dir().join(format!(
+        "disperse-smoke-{}-{}",
+        name.replace('/', "-"),
+        std::process::id()
+    ));
+    std::fs::create_dir_all(&dir)?;
+    let result = prepare(&dir, registry, name, &version).and_then(|(cwd, bin)| {
+        if let Some(command) = command {
+            run(Command::new("sh")
+                .arg("-c")
+                .arg(command)
+                .current_dir(cwd)
+                .env("PATH", path_with(&bin))
+                .env("DISPERSE_PACKAGE", name)
+                .env("DISPERSE_VERSION", &version))?;
+        }
+        Ok(())
+    });
+    if let Err(e) = std::fs::remove_dir_all(&dir) {
+        log::warn!("Unable to remove {}: {}", dir.display(), e);
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_package_url() {
+        for (registry, name) in [
+            (Registry::PyPI, "dulwich"),
+            (Registry::CratesIo, "disperse"),
+            (Registry::Npm, "@scope/foo"),
+        ] {
+            assert_eq!(
+                Registry::parse_package_url(&registry.package_url(name)),
+                Some((registry, name.to_string()))
+            );
+        }
+        assert_eq!(
+            Registry::parse_package_url("https://launchpad.net/foo/+milestone/1.0"),
+            None
+        );
+    }
+}
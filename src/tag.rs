@@ -0,0 +1,121 @@
+use crate::project_config::ProjectConfig;
+use sha2::{Digest, Sha256};
+
+const VERSION_TRAILER: &str = "Disperse-Version";
+const CONFIG_HASH_TRAILER: &str = "Disperse-Config-Hash";
+const REVISION_TRAILER: &str = "Source-Revision";
+const BUILDER_TRAILER: &str = "Builder";
+
+/// Information about the tool state that produced a release, recorded in
+/// the tag annotation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TagMetadata {
+    pub disperse_version: String,
+    pub config_hash: String,
+    pub revision: String,
+    pub builder: Option<String>,
+}
+
+/// Hash of the effective project configuration.
+pub fn config_hash(cfg: &ProjectConfig) -> String {
+    let serialized = serde_json::to_vec(cfg).unwrap();
+    format!("sha256:{:x}", Sha256::digest(serialized))
+}
+
+/// Name of the host that is performing the release, if it can be determined.
+fn builder_host() -> Option<String> {
+    std::env::var("HOSTNAME")
+        .ok()
+        .or_else(|| std::fs::read_to_string("/proc/sys/kernel/hostname").ok())
+        .or_else(|| std::fs::read_to_string("/etc/hostname").ok())
+        .map(|h| h.trim().to_string())
+        .filter(|h| !h.is_empty())
+}
+
+impl TagMetadata {
+    pub fn new(cfg: &ProjectConfig, revision: &str) -> Self {
+        Self {
+            disperse_version: env!("CARGO_PKG_VERSION").to_string(),
+            config_hash: config_hash(cfg),
+            revision: revision.to_string(),
+            builder: builder_host(),
+        }
+    }
+
+    /// Format as a block of git-style trailers.
+    pub fn to_trailers(&self) -> String {
+        let mut ret = format!(
+            "{}: {}\n{}: {}\n{}: {}\n",
+            VERSION_TRAILER,
+            self.disperse_version,
+            CONFIG_HASH_TRAILER,
+            self.config_hash,
+            REVISION_TRAILER,
+            self.revision
+        );
+        if let Some(builder) = self.builder.as_ref() {
+            ret.push_str(&format!("{}: {}\n", BUILDER_TRAILER, builder));
+        }
+        ret
+    }
+
+    /// Parse the trailer block at the end of a tag annotation.
+    pub fn parse(message: &str) -> Option<Self> {
+        let block = message.trim_end().rsplit("\n\n").next()?;
+        let mut trailers = std::collections::HashMap::new();
+        for line in block.lines() {
+            let (key, value) = line.split_once(": ")?;
+            trailers.insert(key.trim(), value.trim().to_string());
+        }
+        Some(Self {
+            disperse_version: trailers.remove(VERSION_TRAILER)?,
+            config_hash: trailers.remove(CONFIG_HASH_TRAILER)?,
+            revision: trailers.remove(REVISION_TRAILER)?,
+            builder: trailers.remove(BUILDER_TRAILER),
+        })
+    }
+}
+
+/// Annotation for a release tag.
+pub fn tag_message(version: &str, metadata: &TagMetadata) -> String {
+    format!("Release {}\n\n{}", version, metadata.to_trailers())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tag_message_roundtrip() {
+        let metadata = TagMetadata {
+            disperse_version: "0.1.0".to_string(),
+            config_hash: "sha256:abcdef".to_string(),
+            revision: "git-v1:0123456789abcdef".to_string(),
+            builder: Some("build.example.com".to_string()),
+        };
+        let message = tag_message("1.2.3", &metadata);
+        assert!(message.starts_with("Release 1.2.3\n\nDisperse-Version: 0.1.0\n"));
+        assert_eq!(TagMetadata::parse(&message), Some(metadata.clone()));
+
+        let metadata = TagMetadata {
+            builder: None,
+            ..metadata
+        };
+        assert_eq!(
+            TagMetadata::parse(&tag_message("1.2.3", &metadata)),
+            Some(metadata)
+        );
+        assert_eq!(TagMetadata::parse("Release 1.2.3"), None);
+    }
+
+    #[test]
+    fn test_config_hash() {
+        let cfg = ProjectConfig::default();
+        assert_eq!(config_hash(&cfg), config_hash(&ProjectConfig::default()));
+        let other = ProjectConfig {
+            tag_name: Some("v$VERSION".to_string()),
+            ..Default::default()
+        };
+        assert_ne!(config_hash(&cfg), config_hash(&other));
+    }
+}
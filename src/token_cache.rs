@@ -0,0 +1,351 @@
+use base64::Engine;
+use chrono::{DateTime, Utc};
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, CHACHA20_POLY1305, NONCE_LEN};
+use ring::rand::{SecureRandom, SystemRandom};
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+const TOKEN_CACHE_FILE_NAME: &str = "tokens";
+const MACHINE_KEY_FILE_NAME: &str = "tokens.key";
+
+/// Environment variable with the passphrase to encrypt the token cache with.
+///
+/// Without it, a randomly generated key that is stored next to the cache is used.
+pub const PASSPHRASE_ENV: &str = "DISPERSE_TOKEN_PASSPHRASE";
+
+const KDF_ITERATIONS: u32 = 100_000;
+const SALT_LEN: usize = 16;
+const KEY_LEN: usize = 32;
+
+#[derive(Debug)]
+pub enum Error {
+    IoError(std::io::Error),
+    InvalidData(String),
+    DecryptionFailed,
+    Other(String),
+}
+
+impl From<std::io::Error> for Error {
+    fn from(e: std::io::Error) -> Self {
+        Error::IoError(e)
+    }
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match &self {
+            Error::IoError(e) => write!(f, "IoError: {}", e),
+            Error::InvalidData(e) => write!(f, "Invalid token cache: {}", e),
+            Error::DecryptionFailed => write!(
+                f,
+                "Unable to decrypt token cache; is {} set correctly?",
+                PASSPHRASE_ENV
+            ),
+            Error::Other(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// A service that disperse can store a token for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Service {
+    GitHub,
+    CratesIo,
+    PyPI,
+    Npm,
+}
+
+impl Service {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Service::GitHub => "github.com",
+            Service::CratesIo => "crates.io",
+            Service::PyPI => "pypi.org",
+            Service::Npm => "npmjs.com",
+        }
+    }
+}
+
+impl std::fmt::Display for Service {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// A token in the cache.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct CachedToken {
+    pub token: String,
+    pub expires: Option<DateTime<Utc>>,
+}
+
+impl CachedToken {
+    pub fn is_expired(&self, now: DateTime<Utc>) -> bool {
+        self.expires.is_some_and(|e| e <= now)
+    }
+}
+
+/// On-disk format of the token cache.
+#[derive(serde::Serialize, serde::Deserialize)]
+struct EncryptedFile {
+    version: u32,
+    salt: String,
+    nonce: String,
+    ciphertext: String,
+}
+
+fn derive_key(secret: &[u8], salt: &[u8]) -> LessSafeKey {
+    let mut key = [0u8; KEY_LEN];
+    ring::pbkdf2::derive(
+        ring::pbkdf2::PBKDF2_HMAC_SHA256,
+        std::num::NonZeroU32::new(KDF_ITERATIONS).unwrap(),
+        salt,
+        secret,
+        &mut key,
+    );
+    LessSafeKey::new(UnboundKey::new(&CHACHA20_POLY1305, &key).unwrap())
+}
+
+fn encrypt(secret: &[u8], plaintext: &[u8]) -> Result<EncryptedFile, Error> {
+    let rng = SystemRandom::new();
+    let mut salt = [0u8; SALT_LEN];
+    let mut nonce = [0u8; NONCE_LEN];
+    rng.fill(&mut salt)
+        .and_then(|_| rng.fill(&mut nonce))
+        .map_err(|_| Error::Other("Unable to generate random data".to_string()))?;
+    let mut data = plaintext.to_vec();
+    derive_key(secret, &salt)
+        .seal_in_place_append_tag(Nonce::assume_unique_for_key(nonce), Aad::empty(), &mut data)
+        .map_err(|_| Error::Other("Unable to encrypt token cache".to_string()))?;
+    let b64 = base64::engine::general_purpose::STANDARD;
+    Ok(EncryptedFile {
+        version: 1,
+        salt: b64.encode(salt),
+        nonce: b64.encode(nonce),
+        ciphertext: b64.encode(data),
+    })
+}
+
+fn decrypt(secret: &[u8], file: &EncryptedFile) -> Result<Vec<u8>, Error> {
+    if file.version != 1 {
+        return Err(Error::InvalidData(format!(
+            "unsupported version {}",
+            file.version
+        )));
+    }
+    let b64 = base64::engine::general_purpose::STANDARD;
+    let decode = |s: &str| {
+        b64.decode(s)
+            .map_err(|e| Error::InvalidData(format!("invalid base64: {}", e)))
+    };
+    let salt = decode(&file.salt)?;
+    let nonce = Nonce::try_assume_unique_for_key(&decode(&file.nonce)?)
+        .map_err(|_| Error::InvalidData("invalid nonce".to_string()))?;
+    let mut data = decode(&file.ciphertext)?;
+    let plaintext = derive_key(secret, &salt)
+        .open_in_place(nonce, Aad::empty(), &mut data)
+        .map_err(|_| Error::DecryptionFailed)?;
+    Ok(plaintext.to_vec())
+}
+
+/// Write a file that is only readable by the current user.
+fn write_private(path: &Path, contents: &[u8]) -> Result<(), Error> {
+    let tmp = path.with_extension("tmp");
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    let mut f = options.open(&tmp)?;
+    f.write_all(contents)?;
+    f.sync_all()?;
+    std::fs::rename(&tmp, path)?;
+    Ok(())
+}
+
+/// Encrypted on-disk cache of API tokens, for hosts without a usable keyring.
+pub struct TokenCache {
+    path: PathBuf,
+    secret: Vec<u8>,
+}
+
+impl TokenCache {
+    pub fn new(path: &Path, secret: &[u8]) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            secret: secret.to_vec(),
+        }
+    }
+
+    /// Open the token cache in the default location, which is separate for
+    /// each profile.
+    ///
+    /// The cache is encrypted with the passphrase in DISPERSE_TOKEN_PASSPHRASE
+    /// if set, and with a generated machine key otherwise.
+    pub fn open_default() -> Result<Self, Error> {
+        let xdg = xdg::BaseDirectories::with_prefix("disperse")
+            .map_err(|e| Error::Other(e.to_string()))?;
+        let path = xdg.place_data_file(crate::profile::qualify(TOKEN_CACHE_FILE_NAME))?;
+        let secret = match std::env::var(PASSPHRASE_ENV) {
+            Ok(passphrase) => passphrase.into_bytes(),
+            Err(_) => Self::machine_key(&xdg.place_data_file(MACHINE_KEY_FILE_NAME)?)?,
+        };
+        Ok(Self::new(&path, &secret))
+    }
+
+    /// Read the machine key, generating it if it doesn't exist yet.
+    fn machine_key(path: &Path) -> Result<Vec<u8>, Error> {
+        match std::fs::read(path) {
+            Ok(key) => Ok(key),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                let mut key = [0u8; KEY_LEN];
+                SystemRandom::new()
+                    .fill(&mut key)
+                    .map_err(|_| Error::Other("Unable to generate machine key".to_string()))?;
+                write_private(path, &key)?;
+                Ok(key.to_vec())
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn load(&self) -> Result<BTreeMap<String, CachedToken>, Error> {
+        let contents = match std::fs::read(&self.path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(BTreeMap::new()),
+            Err(e) => return Err(e.into()),
+        };
+        let file: EncryptedFile =
+            serde_json::from_slice(&contents).map_err(|e| Error::InvalidData(e.to_string()))?;
+        serde_json::from_slice(&decrypt(&self.secret, &file)?)
+            .map_err(|e| Error::InvalidData(e.to_string()))
+    }
+
+    fn save(&self, tokens: &BTreeMap<String, CachedToken>) -> Result<(), Error> {
+        let plaintext = serde_json::to_vec(tokens).unwrap();
+        let file = encrypt(&self.secret, &plaintext)?;
+        write_private(&self.path, &serde_json::to_vec(&file).unwrap())
+    }
+
+    /// List the tokens in the cache, including expired ones.
+    pub fn entries(&self) -> Result<BTreeMap<String, CachedToken>, Error> {
+        self.load()
+    }
+
+    /// Look up the token for a service, ignoring it if it has expired.
+    pub fn get(&self, service: Service) -> Result<Option<String>, Error> {
+        Ok(self
+            .load()?
+            .remove(service.name())
+            .filter(|t| !t.is_expired(Utc::now()))
+            .map(|t| t.token))
+    }
+
+    /// Store the token for a service, replacing any existing one.
+    pub fn set(
+        &self,
+        service: Service,
+        token: &str,
+        expires: Option<DateTime<Utc>>,
+    ) -> Result<(), Error> {
+        let mut tokens = self.load()?;
+        let now = Utc::now();
+        tokens.retain(|_, t| !t.is_expired(now));
+        tokens.insert(
+            service.name().to_string(),
+            CachedToken {
+                token: token.to_string(),
+                expires,
+            },
+        );
+        self.save(&tokens)
+    }
+
+    /// Remove the token for a service; returns whether there was one.
+    pub fn remove(&self, service: Service) -> Result<bool, Error> {
+        let mut tokens = self.load()?;
+        let removed = tokens.remove(service.name()).is_some();
+        if removed {
+            self.save(&tokens)?;
+        }
+        Ok(removed)
+    }
+}
+
+/// Look up a token for a service in the default token cache.
+///
+/// Problems with the cache are logged rather than returned, so that callers
+/// can fall back to other sources of credentials.
+pub fn cached_token(service: Service) -> Option<String> {
+    // Don't generate a machine key for a cache that doesn't exist.
+    xdg::BaseDirectories::with_prefix("disperse")
+        .ok()?
+        .find_data_file(crate::profile::qualify(TOKEN_CACHE_FILE_NAME))?;
+    match TokenCache::open_default().and_then(|c| c.get(service)) {
+        Ok(token) => token,
+        Err(e) => {
+            log::warn!("Unable to read {} token from token cache: {}", service, e);
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_roundtrip() {
+        let td = tempfile::tempdir().unwrap();
+        let path = td.path().join("tokens");
+        let cache = TokenCache::new(&path, b"passphrase");
+        assert_eq!(cache.get(Service::GitHub).unwrap(), None);
+        cache.set(Service::GitHub, "ghp_secret", None).unwrap();
+        cache
+            .set(
+                Service::CratesIo,
+                "cio_secret",
+                Some(Utc::now() - chrono::Duration::days(1)),
+            )
+            .unwrap();
+        assert_eq!(
+            cache.get(Service::GitHub).unwrap(),
+            Some("ghp_secret".to_string())
+        );
+        // Expired tokens are not returned.
+        assert_eq!(cache.get(Service::CratesIo).unwrap(), None);
+        assert!(!std::fs::read_to_string(&path)
+            .unwrap()
+            .contains("ghp_secret"));
+
+        assert!(matches!(
+            TokenCache::new(&path, b"wrong").get(Service::GitHub),
+            Err(Error::DecryptionFailed)
+        ));
+
+        assert!(cache.remove(Service::GitHub).unwrap());
+        assert!(!cache.remove(Service::GitHub).unwrap());
+        assert_eq!(cache.get(Service::GitHub).unwrap(), None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_private() {
+        use std::os::unix::fs::PermissionsExt;
+        let td = tempfile::tempdir().unwrap();
+        let path = td.path().join("tokens");
+        TokenCache::new(&path, b"passphrase")
+            .set(Service::Npm, "npm_secret", None)
+            .unwrap();
+        assert_eq!(
+            std::fs::metadata(&path).unwrap().permissions().mode() & 0o777,
+            0o600
+        );
+    }
+}
@@ -0,0 +1,497 @@
+use crate::project_config::TarballTransport;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+/// Port SSH servers listen on by default.
+const SSH_PORT: u16 = 22;
+
+/// Number of files uploaded at once, unless limited in the configuration.
+const DEFAULT_UPLOAD_CONCURRENCY: usize = 4;
+
+/// Keys to try when no identity file is set and the SSH agent can't be used,
+/// as in OpenSSH.
+const DEFAULT_IDENTITIES: &[&str] = &["id_ed25519", "id_ecdsa", "id_rsa"];
+
+#[derive(Debug)]
+pub enum Error {
+    InvalidLocation(String),
+    UploadFailed {
+        file: PathBuf,
+        destination: String,
+        reason: String,
+    },
+    CommandFailed {
+        command: String,
+        status: Option<std::process::ExitStatus>,
+        reason: Option<String>,
+    },
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match &self {
+            Error::InvalidLocation(l) => write!(f, "Invalid tarball location: {}", l),
+            Error::UploadFailed {
+                file,
+                destination,
+                reason,
+            } => write!(
+                f,
+                "Uploading {} to {} failed: {}",
+                file.display(),
+                destination,
+                reason
+            ),
+            Error::CommandFailed {
+                command,
+                status,
+                reason,
+            } => {
+                write!(f, "{} failed", command)?;
+                if let Some(status) = status {
+                    write!(f, " ({})", status)?;
+                }
+                if let Some(reason) = reason {
+                    write!(f, ": {}", reason)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// A location to upload release tarballs to.
+///
+/// This is either an scp-style location ("user@host:path") or a URL
+/// ("sftp://user@host:port/path?identity-file=~/.ssh/id_release").
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Location {
+    pub user: Option<String>,
+    pub host: String,
+    pub port: Option<u16>,
+    pub path: String,
+    pub identity_file: Option<PathBuf>,
+}
+
+impl std::str::FromStr for Location {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.starts_with("sftp://") || s.starts_with("scp://") {
+            let url: url::Url = s
+                .parse()
+                .map_err(|e| Error::InvalidLocation(format!("{}: {}", s, e)))?;
+            let host = match url.host() {
+                Some(url::Host::Ipv6(addr)) => addr.to_string(),
+                Some(host) => host.to_string(),
+                None => return Err(Error::InvalidLocation(format!("{}: no host", s))),
+            };
+            let identity_file = url
+                .query_pairs()
+                .find(|(k, _)| k == "identity-file")
+                .map(|(_, v)| PathBuf::from(v.as_ref()));
+            // Like in OpenSSH, "/~/" refers to the home directory.
+            let path = url.path();
+            let path = path.strip_prefix("/~/").unwrap_or(path);
+            return Ok(Location {
+                user: Some(url.username())
+                    .filter(|u| !u.is_empty())
+                    .map(|u| u.to_string()),
+                host,
+                port: url.port(),
+                path: path.to_string(),
+                identity_file,
+            });
+        }
+
+        let (remote, path) =
+            split_remote(s).ok_or_else(|| Error::InvalidLocation(format!("{}: missing ':'", s)))?;
+        let (user, host) = match remote.split_once('@') {
+            Some((user, host)) => (Some(user.to_string()), host),
+            None => (None, remote),
+        };
+        let host = host
+            .strip_prefix('[')
+            .and_then(|h| h.strip_suffix(']'))
+            .unwrap_or(host);
+        if host.is_empty() {
+            return Err(Error::InvalidLocation(format!("{}: no host", s)));
+        }
+        // Paths are relative to the home directory, so "~/" can be dropped.
+        let path = match path {
+            "~" => "",
+            path => path.strip_prefix("~/").unwrap_or(path),
+        };
+        Ok(Location {
+            user,
+            host: host.to_string(),
+            port: None,
+            path: path.to_string(),
+            identity_file: None,
+        })
+    }
+}
+
+/// Split an scp-style location into the remote and the path.
+///
+/// The host may be an IPv6 address in brackets, which contains colons itself.
+fn split_remote(s: &str) -> Option<(&str, &str)> {
+    let colon = s.find(':')?;
+    match s.find('[') {
+        Some(open) if open < colon => {
+            let close = open + s[open..].find(']')?;
+            let path = s[close + 1..].strip_prefix(':')?;
+            Some((&s[..=close], path))
+        }
+        _ => Some((&s[..colon], &s[colon + 1..])),
+    }
+}
+
+impl Location {
+    fn destination(&self) -> String {
+        let host = if self.host.contains(':') {
+            format!("[{}]", self.host)
+        } else {
+            self.host.clone()
+        };
+        match self.user.as_ref() {
+            Some(user) => format!("{}@{}", user, host),
+            None => host,
+        }
+    }
+
+    /// Options for scp.
+    fn options(&self) -> Vec<OsString> {
+        let mut args: Vec<OsString> = vec!["-o".into(), "BatchMode=yes".into()];
+        if let Some(port) = self.port {
+            args.push("-P".into());
+            args.push(port.to_string().into());
+        }
+        if let Some(identity_file) = self.identity_file.as_ref() {
+            args.push("-i".into());
+            args.push(identity_file.into());
+        }
+        args
+    }
+
+    fn remote_path(&self, file: &Path) -> String {
+        let name = file.file_name().unwrap().to_string_lossy();
+        if self.path.is_empty() {
+            name.into_owned()
+        } else {
+            format!("{}/{}", self.path.trim_end_matches('/'), name)
+        }
+    }
+
+    /// Open an authenticated SSH session to the location.
+    fn connect(&self) -> Result<ssh2::Session, String> {
+        let port = self.port.unwrap_or(SSH_PORT);
+        let tcp = std::net::TcpStream::connect((self.host.as_str(), port))
+            .map_err(|e| format!("unable to connect to {}: {}", self.host, e))?;
+        let mut session = ssh2::Session::new().map_err(|e| e.to_string())?;
+        session.set_tcp_stream(tcp);
+        session.handshake().map_err(|e| e.to_string())?;
+        self.check_host_key(&session, port)?;
+        let user = self
+            .user
+            .clone()
+            .or_else(|| std::env::var("USER").ok())
+            .ok_or_else(|| "unable to determine user name".to_string())?;
+        self.authenticate(&session, &user)?;
+        Ok(session)
+    }
+
+    /// Check the host key against ~/.ssh/known_hosts. Like ssh in batch
+    /// mode, hosts that aren't known yet are refused.
+    fn check_host_key(&self, session: &ssh2::Session, port: u16) -> Result<(), String> {
+        let (key, _) = session
+            .host_key()
+            .ok_or_else(|| format!("no host key for {}", self.host))?;
+        let mut known_hosts = session.known_hosts().map_err(|e| e.to_string())?;
+        let path = home_dir().map(|h| h.join(".ssh/known_hosts"));
+        if let Some(path) = path.filter(|p| p.exists()) {
+            known_hosts
+                .read_file(&path, ssh2::KnownHostFileKind::OpenSSH)
+                .map_err(|e| format!("unable to read {}: {}", path.display(), e))?;
+        }
+        match known_hosts.check_port(&self.host, port, key) {
+            ssh2::CheckResult::Match => Ok(()),
+            ssh2::CheckResult::Mismatch => Err(format!(
+                "host key for {} does not match the one in known_hosts",
+                self.host
+            )),
+            ssh2::CheckResult::NotFound => {
+                Err(format!("host key for {} is not in known_hosts", self.host))
+            }
+            ssh2::CheckResult::Failure => {
+                Err(format!("unable to check host key for {}", self.host))
+            }
+        }
+    }
+
+    fn authenticate(&self, session: &ssh2::Session, user: &str) -> Result<(), String> {
+        if let Some(identity_file) = self.identity_file.as_ref() {
+            return session
+                .userauth_pubkey_file(user, None, &expand_home(identity_file, home_dir()), None)
+                .map_err(|e| format!("unable to authenticate as {}: {}", user, e));
+        }
+        if session.userauth_agent(user).is_ok() {
+            return Ok(());
+        }
+        let identities = home_dir()
+            .into_iter()
+            .flat_map(|home| {
+                DEFAULT_IDENTITIES
+                    .iter()
+                    .map(move |n| home.join(".ssh").join(n))
+            })
+            .filter(|p| p.exists());
+        for identity in identities {
+            if session
+                .userauth_pubkey_file(user, None, &identity, None)
+                .is_ok()
+            {
+                return Ok(());
+            }
+        }
+        Err(format!("unable to authenticate as {}", user))
+    }
+
+    /// Upload a file over SFTP.
+    fn put(&self, file: &Path) -> Result<(), Error> {
+        let failed = |reason: String| Error::UploadFailed {
+            file: file.to_path_buf(),
+            destination: format!("{}:{}", self.destination(), self.path),
+            reason,
+        };
+        let session = self.connect().map_err(failed)?;
+        let sftp = session.sftp().map_err(|e| failed(e.to_string()))?;
+        let mut local = std::fs::File::open(file).map_err(|e| failed(e.to_string()))?;
+        let remote_path = self.remote_path(file);
+        log::debug!("Uploading {} to {}", file.display(), remote_path);
+        let mut remote = sftp
+            .create(Path::new(&remote_path))
+            .map_err(|e| failed(format!("unable to create {}: {}", remote_path, e)))?;
+        std::io::copy(&mut local, &mut remote).map_err(|e| failed(e.to_string()))?;
+        Ok(())
+    }
+}
+
+fn home_dir() -> Option<PathBuf> {
+    std::env::var_os("HOME").map(PathBuf::from)
+}
+
+/// Expand a leading "~" in a path to the home directory, as ssh does.
+fn expand_home(path: &Path, home: Option<PathBuf>) -> PathBuf {
+    match (path.strip_prefix("~"), home) {
+        (Ok(rest), Some(home)) => home.join(rest),
+        _ => path.to_path_buf(),
+    }
+}
+
+fn describe(program: &str, args: &[OsString]) -> String {
+    std::iter::once(program.to_string())
+        .chain(args.iter().map(|a| a.to_string_lossy().into_owned()))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Log all errors, and return the first.
+fn first_error(results: Vec<Result<(), Error>>) -> Result<(), Error> {
+    let mut first_error = None;
+    for e in results.into_iter().filter_map(Result::err) {
+        log::error!("{}", e);
+        first_error.get_or_insert(e);
+    }
+    match first_error {
+        Some(e) => Err(e),
+        None => Ok(()),
+    }
+}
+
+/// Upload files to a location, uploading several of them at once.
+pub fn upload(
+    location: &Location,
+    files: &[PathBuf],
+    transport: TarballTransport,
+) -> Result<(), Error> {
+    let concurrency = crate::limits::concurrency(crate::limits::Service::TarballUpload)
+        .unwrap_or(DEFAULT_UPLOAD_CONCURRENCY);
+    first_error(in_parallel(files, concurrency, |file| {
+        let _permit = crate::limits::acquire_blocking(crate::limits::Service::TarballUpload);
+        match transport {
+            TarballTransport::Sftp => location.put(file),
+            TarballTransport::Scp => scp(location, file),
+        }
+    }))
+}
+
+/// Run `f` on each of `items`, on at most `concurrency` threads at once.
+fn in_parallel<T: Sync, R: Send>(
+    items: &[T],
+    concurrency: usize,
+    f: impl Fn(&T) -> R + Sync,
+) -> Vec<R> {
+    let next = std::sync::atomic::AtomicUsize::new(0);
+    let results = std::sync::Mutex::new(Vec::with_capacity(items.len()));
+    std::thread::scope(|s| {
+        for _ in 0..concurrency.clamp(1, items.len().max(1)) {
+            s.spawn(|| loop {
+                let i = next.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+                let Some(item) = items.get(i) else {
+                    break;
+                };
+                let result = f(item);
+                results.lock().unwrap().push((i, result));
+            });
+        }
+    });
+    let mut results = results.into_inner().unwrap();
+    results.sort_by_key(|(i, _)| *i);
+    results.into_iter().map(|(_, r)| r).collect()
+}
+
+/// Upload a file with the external scp program.
+fn scp(location: &Location, file: &Path) -> Result<(), Error> {
+    let mut args = location.options();
+    args.push(file.into());
+    args.push(format!("{}:{}", location.destination(), location.path).into());
+    let command = describe("scp", &args);
+    log::debug!("Running {}", command);
+    match Command::new("scp")
+        .args(&args)
+        .stdin(Stdio::null())
+        .status()
+    {
+        Ok(status) if status.success() => Ok(()),
+        Ok(status) => Err(Error::CommandFailed {
+            command,
+            status: Some(status),
+            reason: None,
+        }),
+        Err(e) => Err(Error::CommandFailed {
+            command,
+            status: None,
+            reason: Some(e.to_string()),
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_scp_location() {
+        assert_eq!(
+            "jelmer@example.com:public_html/releases"
+                .parse::<Location>()
+                .unwrap(),
+            Location {
+                user: Some("jelmer".to_string()),
+                host: "example.com".to_string(),
+                port: None,
+                path: "public_html/releases".to_string(),
+                identity_file: None,
+            }
+        );
+        assert_eq!("example.com:".parse::<Location>().unwrap().user, None);
+        assert!("example.com".parse::<Location>().is_err());
+        assert_eq!(
+            "example.com:~/releases".parse::<Location>().unwrap().path,
+            "releases"
+        );
+        assert_eq!(
+            "example.com:~/releases/a@b".parse::<Location>().unwrap(),
+            Location {
+                user: None,
+                host: "example.com".to_string(),
+                port: None,
+                path: "releases/a@b".to_string(),
+                identity_file: None,
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_ipv6_location() {
+        let location = "jelmer@[2001:db8::1]:releases".parse::<Location>().unwrap();
+        assert_eq!(
+            location,
+            Location {
+                user: Some("jelmer".to_string()),
+                host: "2001:db8::1".to_string(),
+                port: None,
+                path: "releases".to_string(),
+                identity_file: None,
+            }
+        );
+        assert_eq!(location.destination(), "jelmer@[2001:db8::1]");
+        assert_eq!("[::1]:".parse::<Location>().unwrap().host, "::1");
+        assert!("[::1]".parse::<Location>().is_err());
+        assert_eq!(
+            "sftp://[::1]:2222/srv".parse::<Location>().unwrap().host,
+            "::1"
+        );
+    }
+
+    #[test]
+    fn test_in_parallel() {
+        let running = std::sync::atomic::AtomicUsize::new(0);
+        let most = std::sync::atomic::AtomicUsize::new(0);
+        let items = (0..10).collect::<Vec<_>>();
+        let results = in_parallel(&items, 3, |i| {
+            let now = running.fetch_add(1, std::sync::atomic::Ordering::SeqCst) + 1;
+            most.fetch_max(now, std::sync::atomic::Ordering::SeqCst);
+            std::thread::sleep(std::time::Duration::from_millis(5));
+            running.fetch_sub(1, std::sync::atomic::Ordering::SeqCst);
+            i * 2
+        });
+        assert_eq!(results, (0..10).map(|i| i * 2).collect::<Vec<_>>());
+        assert!(most.load(std::sync::atomic::Ordering::SeqCst) <= 3);
+    }
+
+    #[test]
+    fn test_expand_home() {
+        let home = Some(PathBuf::from("/home/jelmer"));
+        assert_eq!(
+            expand_home(Path::new("~/.ssh/id_release"), home.clone()),
+            PathBuf::from("/home/jelmer/.ssh/id_release")
+        );
+        assert_eq!(
+            expand_home(Path::new("/tmp/id"), home),
+            PathBuf::from("/tmp/id")
+        );
+    }
+
+    #[test]
+    fn test_parse_sftp_location() {
+        let location = "sftp://jelmer@example.com:2222/~/releases?identity-file=/tmp/id"
+            .parse::<Location>()
+            .unwrap();
+        assert_eq!(
+            location,
+            Location {
+                user: Some("jelmer".to_string()),
+                host: "example.com".to_string(),
+                port: Some(2222),
+                path: "releases".to_string(),
+                identity_file: Some(PathBuf::from("/tmp/id")),
+            }
+        );
+        assert_eq!(
+            location.remote_path(Path::new("dist/foo-1.0.tar.gz")),
+            "releases/foo-1.0.tar.gz"
+        );
+        assert_eq!(
+            "sftp://example.com/srv/releases"
+                .parse::<Location>()
+                .unwrap()
+                .path,
+            "/srv/releases"
+        );
+    }
+}
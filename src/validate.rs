@@ -0,0 +1,560 @@
+use crate::project_config::ProjectConfig;
+use breezyshim::tree::{Tree, WorkingTree};
+use std::path::Path;
+
+/// How serious a validation finding is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Info,
+    Warning,
+    Error,
+}
+
+impl std::fmt::Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Severity::Info => write!(f, "info"),
+            Severity::Warning => write!(f, "warning"),
+            Severity::Error => write!(f, "error"),
+        }
+    }
+}
+
+/// A single problem (or point of interest) found while validating a configuration.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct Finding {
+    pub severity: Severity,
+    pub message: String,
+}
+
+impl Finding {
+    pub fn error(message: impl Into<String>) -> Self {
+        Self {
+            severity: Severity::Error,
+            message: message.into(),
+        }
+    }
+
+    pub fn warning(message: impl Into<String>) -> Self {
+        Self {
+            severity: Severity::Warning,
+            message: message.into(),
+        }
+    }
+
+    pub fn info(message: impl Into<String>) -> Self {
+        Self {
+            severity: Severity::Info,
+            message: message.into(),
+        }
+    }
+}
+
+impl std::fmt::Display for Finding {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}: {}", self.severity, self.message)
+    }
+}
+
+/// Line and column (both starting at 1) of a byte offset in `text`.
+fn line_column(text: &str, offset: usize) -> (usize, usize) {
+    let before = &text[..offset.min(text.len())];
+    let line = before.matches('\n').count() + 1;
+    let column = before.chars().rev().take_while(|c| *c != '\n').count() + 1;
+    (line, column)
+}
+
+fn located(text: &str, span: Option<std::ops::Range<usize>>, message: &str) -> String {
+    match span {
+        Some(span) => {
+            let (line, column) = line_column(text, span.start);
+            format!("line {}, column {}: {}", line, column, message)
+        }
+        None => message.to_string(),
+    }
+}
+
+/// A value in a parsed TOML document.
+#[derive(Clone, Copy)]
+enum Node<'a> {
+    Item(&'a toml_edit::Item),
+    Table(&'a toml_edit::Table),
+    Value(&'a toml_edit::Value),
+}
+
+impl<'a> Node<'a> {
+    fn span(&self) -> Option<std::ops::Range<usize>> {
+        match self {
+            Node::Item(item) => item.span(),
+            Node::Table(table) => table.span(),
+            Node::Value(value) => value.span(),
+        }
+    }
+
+    /// Look up a child, returning it and the span of its key, if any.
+    fn get(
+        &self,
+        segment: &jsonschema::paths::LocationSegment,
+    ) -> Option<(Node<'a>, Option<std::ops::Range<usize>>)> {
+        use jsonschema::paths::LocationSegment;
+        match segment {
+            LocationSegment::Property(name) => {
+                let table: &dyn toml_edit::TableLike = match self {
+                    Node::Item(item) => item.as_table_like()?,
+                    Node::Table(table) => *table,
+                    Node::Value(value) => value.as_inline_table()?,
+                };
+                let (key, item) = table.get_key_value(name)?;
+                Some((Node::Item(item), key.span()))
+            }
+            LocationSegment::Index(i) => match self {
+                Node::Item(toml_edit::Item::ArrayOfTables(tables)) => {
+                    Some((Node::Table(tables.get(*i)?), None))
+                }
+                Node::Item(toml_edit::Item::Value(toml_edit::Value::Array(values)))
+                | Node::Value(toml_edit::Value::Array(values)) => {
+                    Some((Node::Value(values.get(*i)?), None))
+                }
+                _ => None,
+            },
+        }
+    }
+}
+
+/// Convert TOML to the JSON data model, which JSON Schema is defined on.
+fn toml_to_json(value: &toml::Value) -> serde_json::Value {
+    match value {
+        toml::Value::String(s) => serde_json::Value::String(s.clone()),
+        toml::Value::Integer(i) => serde_json::Value::from(*i),
+        toml::Value::Float(f) => serde_json::Value::from(*f),
+        toml::Value::Boolean(b) => serde_json::Value::Bool(*b),
+        toml::Value::Datetime(d) => serde_json::Value::String(d.to_string()),
+        toml::Value::Array(a) => serde_json::Value::Array(a.iter().map(toml_to_json).collect()),
+        toml::Value::Table(t) => serde_json::Value::Object(
+            t.iter()
+                .map(|(k, v)| (k.clone(), toml_to_json(v)))
+                .collect(),
+        ),
+    }
+}
+
+/// Check the text of a disperse.toml strictly against the [JSON
+/// Schema](crate::project_config::json_schema): unlike when reading it for a
+/// release, unknown keys (e.g. typos like "tagname") and values of the wrong
+/// type are reported, with their location.
+pub fn check_toml(text: &str) -> Vec<Finding> {
+    let doc = match toml_edit::ImDocument::parse(text) {
+        Ok(doc) => doc,
+        Err(e) => return vec![Finding::error(located(text, e.span(), e.message()))],
+    };
+    let instance = match text.parse::<toml::Table>() {
+        Ok(table) => toml_to_json(&toml::Value::Table(table)),
+        Err(e) => return vec![Finding::error(located(text, e.span(), e.message()))],
+    };
+    let validator =
+        jsonschema::validator_for(&crate::project_config::json_schema()).expect("schema is valid");
+    let mut findings = vec![];
+    for error in validator.iter_errors(&instance) {
+        let path = error.instance_path().iter().collect::<Vec<_>>();
+        let mut node = Some(Node::Table(doc.as_table()));
+        for segment in &path {
+            node = node.and_then(|n| n.get(segment)).map(|(n, _)| n);
+        }
+        let keys = path
+            .iter()
+            .filter_map(|s| match s {
+                jsonschema::paths::LocationSegment::Property(name) => Some(name.to_string()),
+                jsonschema::paths::LocationSegment::Index(_) => None,
+            })
+            .collect::<Vec<_>>();
+        match error.kind() {
+            jsonschema::error::ValidationErrorKind::AdditionalProperties { unexpected } => {
+                for name in unexpected {
+                    let segment = jsonschema::paths::LocationSegment::Property(name.into());
+                    let span = node.and_then(|n| n.get(&segment)).and_then(|(_, key)| key);
+                    let key = keys
+                        .iter()
+                        .chain(std::iter::once(name))
+                        .cloned()
+                        .collect::<Vec<_>>();
+                    findings.push(Finding::error(located(
+                        text,
+                        span,
+                        &format!("unknown key {}", key.join(".")),
+                    )));
+                }
+            }
+            _ => findings.push(Finding::error(located(
+                text,
+                node.and_then(|n| n.span()),
+                &format!("{}: {}", keys.join("."), error),
+            ))),
+        }
+    }
+    findings
+}
+
+/// Check settings that are legal but probably not what the user intended.
+///
+/// These checks only look at the configuration itself, not at the tree.
+pub fn check_settings(cfg: &ProjectConfig) -> Vec<Finding> {
+    let mut findings = vec![];
+
+    if let Some(tag_name) = cfg.tag_name.as_deref() {
+        if !tag_name.contains("$VERSION") && !tag_name.contains("$MAJOR") {
+            findings.push(Finding::warning(format!(
+                "tag-name {:?} does not contain $VERSION; every release would use the same tag",
+                tag_name
+            )));
+        }
+    }
+
+    if let Some(debian) = cfg.debian.as_ref() {
+        if debian.upload.is_some() && !debian.build_source.unwrap_or(false) {
+            findings.push(Finding::warning(
+                "debian.upload is set, but build-source is not enabled; nothing will be uploaded",
+            ));
+        }
+    }
+
+    for (name, expr) in cfg.version_variables.iter().flatten() {
+        if let Err(e) = crate::custom::validate_custom_variable(name, expr) {
+            findings.push(Finding::error(format!("Invalid version variable: {}", e)));
+        }
+    }
+
+    for pattern in cfg.ignore_paths.iter().flatten() {
+        if let Err(e) = glob::Pattern::new(pattern) {
+            findings.push(Finding::error(format!(
+                "Invalid ignore-paths pattern {:?}: {}",
+                pattern, e
+            )));
+        }
+    }
+    if cfg.ignore_paths.is_some() && cfg.release_timeout.is_none() {
+        findings.push(Finding::warning(
+            "ignore-paths is set, but release-timeout is not; it has no effect",
+        ));
+    }
+
+    if let Err(e) = crate::news_file::NewsLayout::from_config(cfg) {
+        findings.push(Finding::error(format!("Invalid news layout: {}", e)));
+    }
+
+    if cfg.version_scheme == Some(crate::project_config::VersionScheme::Calver) {
+        let format = cfg
+            .calver_format
+            .as_deref()
+            .unwrap_or(crate::version::DEFAULT_CALVER_FORMAT);
+        if let Err(e) = crate::version::next_calver(format, chrono::Utc::now().date_naive(), None) {
+            findings.push(Finding::error(format!("Invalid calver-format: {}", e)));
+        }
+    } else if cfg.calver_format.is_some() {
+        findings.push(Finding::warning(
+            "calver-format is set, but version-scheme is not calver",
+        ));
+    }
+
+    if cfg.news_file.is_none() {
+        findings.push(Finding::info(
+            "No news-file configured; release notes will not be updated",
+        ));
+        if cfg.create_pending == Some(true) {
+            findings.push(Finding::warning(
+                "create-pending is set, but news-file is not; it has no effect",
+            ));
+        }
+    }
+
+    findings
+}
+
+/// Lint a news file, reporting all problems found.
+fn check_news_file(news: &crate::news_file::NewsFile, path: &Path) -> Vec<Finding> {
+    let mut findings = vec![];
+    match news.lint() {
+        Ok(problems) => {
+            for problem in problems {
+                findings.push(Finding::warning(format!("{}: {}", path.display(), problem)));
+            }
+        }
+        Err(e) => {
+            findings.push(Finding::error(format!(
+                "Unable to parse news file {}: {}",
+                path.display(),
+                e
+            )));
+            return findings;
+        }
+    }
+    match news.pending_changes() {
+        Ok(Some(changes)) => {
+            for problem in crate::news_file::check_categories(&changes) {
+                findings.push(Finding::warning(format!(
+                    "Pending entry in {}: {}",
+                    path.display(),
+                    problem
+                )));
+            }
+        }
+        Ok(None) => {}
+        Err(e) => {
+            findings.push(Finding::error(format!(
+                "Unable to parse news file {}: {}",
+                path.display(),
+                e
+            )));
+        }
+    }
+    findings
+}
+
+/// Validate a project configuration against the tree it applies to.
+///
+/// Unlike a release, this does not stop at the first problem.
+pub fn validate(tree: &WorkingTree, cfg: &ProjectConfig) -> Vec<Finding> {
+    let mut findings = check_settings(cfg);
+
+    if let Some(news_file) = &cfg.news_file {
+        if !tree.has_filename(news_file) {
+            findings.push(Finding::error(format!(
+                "News file {} does not exist",
+                news_file.display()
+            )));
+        } else if let Ok(layout) = crate::news_file::NewsLayout::from_config(cfg) {
+            match crate::news_file::NewsFile::new(tree, news_file) {
+                Ok(f) => findings.extend(check_news_file(&f.with_layout(layout), news_file)),
+                Err(e) => findings.push(Finding::error(format!(
+                    "Unable to open news file {}: {}",
+                    news_file.display(),
+                    e
+                ))),
+            }
+        }
+    }
+
+    for update_version in cfg.update_version.iter().flatten() {
+        if let Err(e) = crate::custom::validate_update_version(
+            tree,
+            update_version,
+            cfg.version_variables
+                .as_ref()
+                .unwrap_or(&Default::default()),
+        ) {
+            findings.push(Finding::error(format!("Invalid update_version: {}", e)));
+        }
+    }
+
+    for update_manpage in cfg.update_manpages.iter().flatten() {
+        let paths =
+            crate::iter_glob(tree, update_manpage.path().to_str().unwrap()).collect::<Vec<_>>();
+        if paths.is_empty() {
+            findings.push(Finding::warning(format!(
+                "update-manpage {} does not match any files",
+                update_manpage.path().display()
+            )));
+        }
+        if let Some(locale) = update_manpage.locale() {
+            if crate::manpage::month_names(locale).is_none() {
+                findings.push(Finding::error(format!(
+                    "update-manpage {}: no month names known for locale {}",
+                    update_manpage.path().display(),
+                    locale
+                )));
+            }
+        }
+        for path in paths {
+            if let Err(e) = crate::manpage::validate_update_manpage(tree, path.as_path()) {
+                findings.push(Finding::error(format!("Invalid update_manpage: {}", e)));
+            }
+        }
+    }
+
+    for update_metainfo in cfg.update_metainfo.iter().flatten() {
+        let paths = crate::iter_glob(tree, update_metainfo.to_str().unwrap()).collect::<Vec<_>>();
+        if paths.is_empty() {
+            findings.push(Finding::warning(format!(
+                "update-metainfo {} does not match any files",
+                update_metainfo.display()
+            )));
+        }
+        for path in paths {
+            if let Err(e) = crate::appstream::validate_update_metainfo(tree, path.as_path()) {
+                findings.push(Finding::error(format!("Invalid update-metainfo: {}", e)));
+            }
+        }
+    }
+
+    for update_nix in cfg.update_nix.iter().flatten() {
+        if let Err(e) = crate::nix::validate_update_nix(tree, update_nix) {
+            findings.push(Finding::error(format!(
+                "Invalid update-nix: {}: {}",
+                update_nix.display(),
+                e
+            )));
+        }
+    }
+
+    if let Some(update_docs) = cfg.update_docs.as_ref() {
+        match crate::find_name_in_tree(tree, cfg) {
+            Some((name, _)) => {
+                // A version in development hasn't been released yet.
+                let current = crate::find_last_version_in_files(tree, cfg)
+                    .ok()
+                    .flatten()
+                    .filter(|(_, s)| *s != Some(crate::Status::Dev))
+                    .map(|(v, _)| v);
+                for pattern in update_docs {
+                    let paths =
+                        crate::iter_glob(tree, pattern.to_str().unwrap()).collect::<Vec<_>>();
+                    if paths.is_empty() {
+                        findings.push(Finding::warning(format!(
+                            "update-docs {} does not match any files",
+                            pattern.display()
+                        )));
+                    }
+                    for path in paths {
+                        match crate::doc_versions::validate_update_docs(
+                            tree,
+                            &path,
+                            &name,
+                            current.as_ref(),
+                        ) {
+                            Ok(stale) => {
+                                for documented in stale {
+                                    findings.push(Finding::warning(format!(
+                                        "{} mentions version {} of {}, but the current version is {}",
+                                        path.display(),
+                                        documented,
+                                        name,
+                                        current.as_ref().unwrap().to_string()
+                                    )));
+                                }
+                            }
+                            Err(e) => findings.push(Finding::error(format!(
+                                "Invalid update-docs: {}: {}",
+                                path.display(),
+                                e
+                            ))),
+                        }
+                    }
+                }
+            }
+            None => findings.push(Finding::error(
+                "update-docs is set, but no project name is known",
+            )),
+        }
+    }
+
+    if let Some(pkgbuild) = cfg.pkgbuild.as_ref() {
+        if pkgbuild.aur.is_none() && !tree.has_filename(pkgbuild.path()) {
+            findings.push(Finding::error(format!(
+                "PKGBUILD {} does not exist",
+                pkgbuild.path().display()
+            )));
+        }
+    }
+
+    if (tree.has_filename(Path::new("pyproject.toml")) || tree.has_filename(Path::new("setup.py")))
+        && cfg.twine_upload.is_none()
+    {
+        findings.push(Finding::warning(
+            "twine-upload is not set; Python artifacts will not be uploaded to PyPI",
+        ));
+    }
+
+    if tree.has_filename(Path::new("package.json"))
+        && cfg.npm_publish.is_none()
+        && !crate::npm::is_private(tree).unwrap_or(true)
+    {
+        findings.push(Finding::warning(
+            "npm-publish is not set; the package will not be published to npm",
+        ));
+    }
+
+    if cfg.rollout.is_some() && !tree.has_filename(Path::new("package.json")) {
+        findings.push(Finding::warning(
+            "rollout is configured, but promotion is only supported for npm packages",
+        ));
+    }
+
+    if crate::verify::determine_verify_command(cfg, tree).is_none() {
+        findings.push(Finding::info(
+            "No verify-command configured and none could be detected",
+        ));
+    }
+
+    findings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_settings() {
+        let cfg = ProjectConfig {
+            tag_name: Some("v$VERSION".to_string()),
+            news_file: Some("NEWS".into()),
+            ..Default::default()
+        };
+        assert_eq!(check_settings(&cfg), vec![]);
+
+        let cfg = ProjectConfig {
+            tag_name: Some("release".to_string()),
+            ..Default::default()
+        };
+        let findings = check_settings(&cfg);
+        assert_eq!(
+            findings.iter().map(|f| f.severity).collect::<Vec<_>>(),
+            vec![Severity::Warning, Severity::Info]
+        );
+        assert!(findings[0].message.contains("$VERSION"));
+    }
+
+    #[test]
+    fn test_check_toml() {
+        assert_eq!(check_toml("tag-name = \"v$VERSION\"\n"), vec![]);
+
+        let findings = check_toml("news-file = \"NEWS\"\ntagname = \"v$VERSION\"\n");
+        assert_eq!(
+            findings,
+            vec![Finding::error("line 2, column 1: unknown key tagname")]
+        );
+
+        let findings = check_toml("[debian]\n  uplaod = \"ppa\"\n");
+        assert_eq!(
+            findings,
+            vec![Finding::error(
+                "line 2, column 3: unknown key debian.uplaod"
+            )]
+        );
+
+        let findings = check_toml("twine-upload = \"yes\"\n");
+        assert_eq!(findings.len(), 1);
+        assert!(findings[0]
+            .message
+            .starts_with("line 1, column 16: twine-upload: "));
+
+        let findings =
+            check_toml("[[project]]\nname = \"a\"\npath = \"a\"\ntagname = \"a-$VERSION\"\n");
+        assert_eq!(
+            findings,
+            vec![Finding::error(
+                "line 4, column 1: unknown key project.tagname"
+            )]
+        );
+
+        // Deprecated keys are renamed by "disperse migrate", not rejected.
+        assert_eq!(check_toml("tag_name = \"v$VERSION\"\n"), vec![]);
+    }
+
+    #[test]
+    fn test_finding_json() {
+        assert_eq!(
+            serde_json::to_string(&Finding::warning("foo")).unwrap(),
+            r#"{"severity":"warning","message":"foo"}"#
+        );
+    }
+}
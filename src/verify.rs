@@ -0,0 +1,178 @@
+use crate::project_config::ProjectConfig;
+use breezyshim::tree::Tree;
+use std::path::Path;
+
+/// The placeholder test script that `npm init` generates.
+const NPM_DEFAULT_TEST_SCRIPT: &str = "echo \"Error: no test specified\" && exit 1";
+
+/// A command to verify a project, along with why it was picked.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VerifyCommand {
+    pub command: String,
+    pub reason: String,
+}
+
+impl VerifyCommand {
+    fn new(command: &str, reason: &str) -> Self {
+        Self {
+            command: command.to_string(),
+            reason: reason.to_string(),
+        }
+    }
+}
+
+/// Check whether a pyproject.toml file has pytest configuration.
+pub fn pyproject_has_pytest(text: &str) -> bool {
+    let parsed: toml_edit::DocumentMut = match text.parse() {
+        Ok(parsed) => parsed,
+        Err(e) => {
+            log::warn!("Unable to parse pyproject.toml: {}", e);
+            return false;
+        }
+    };
+    parsed.get("tool").and_then(|t| t.get("pytest")).is_some()
+}
+
+/// Check whether a package.json file has a test script, ignoring the placeholder npm generates.
+pub fn package_json_has_test_script(package_json: &serde_json::Value) -> bool {
+    match package_json
+        .get("scripts")
+        .and_then(|s| s.get("test"))
+        .and_then(|t| t.as_str())
+    {
+        Some(script) => script.trim() != NPM_DEFAULT_TEST_SCRIPT,
+        None => false,
+    }
+}
+
+/// Check whether a Makefile defines a particular target.
+pub fn makefile_has_target(text: &str, target: &str) -> bool {
+    text.lines().any(|line| {
+        if line.starts_with(char::is_whitespace) {
+            return false;
+        }
+        match line.split_once(':') {
+            // Skip variable assignments like "FOO := bar"
+            Some((targets, rest)) if !rest.starts_with('=') => {
+                targets.split_whitespace().any(|t| t == target)
+            }
+            _ => false,
+        }
+    })
+}
+
+fn read_file(tree: &dyn Tree, path: &Path) -> Option<Vec<u8>> {
+    match tree.get_file_text(path) {
+        Ok(text) => Some(text),
+        Err(e) => {
+            log::warn!("Unable to read {}: {}", path.display(), e);
+            None
+        }
+    }
+}
+
+/// Determine the command to run to verify a project before it is released.
+///
+/// An explicitly configured command always wins; otherwise the command is
+/// guessed from the files present in the tree.
+pub fn determine_verify_command(cfg: &ProjectConfig, tree: &dyn Tree) -> Option<VerifyCommand> {
+    if let Some(verify_command) = cfg.verify_command.as_ref() {
+        return Some(VerifyCommand::new(
+            verify_command,
+            "verify-command is set in the project configuration",
+        ));
+    }
+
+    if tree.has_filename(Path::new("tox.ini")) {
+        return Some(VerifyCommand::new("tox", "tox.ini is present"));
+    }
+
+    if tree.has_filename(Path::new("Cargo.toml")) {
+        return Some(VerifyCommand::new(
+            "cargo test --all",
+            "Cargo.toml is present",
+        ));
+    }
+
+    if tree.has_filename(Path::new("pyproject.toml")) {
+        if let Some(text) = read_file(tree, Path::new("pyproject.toml")) {
+            if pyproject_has_pytest(&String::from_utf8_lossy(&text)) {
+                return Some(VerifyCommand::new(
+                    "pytest",
+                    "pyproject.toml has a [tool.pytest] section",
+                ));
+            }
+        }
+    }
+
+    if tree.has_filename(Path::new("package.json")) {
+        if let Some(text) = read_file(tree, Path::new("package.json")) {
+            match serde_json::from_slice(&text) {
+                Ok(package_json) => {
+                    if package_json_has_test_script(&package_json) {
+                        return Some(VerifyCommand::new(
+                            "npm test",
+                            "package.json defines a test script",
+                        ));
+                    }
+                }
+                Err(e) => log::warn!("Unable to parse package.json: {}", e),
+            }
+        }
+    }
+
+    if tree.has_filename(Path::new("go.mod")) {
+        return Some(VerifyCommand::new("go test ./...", "go.mod is present"));
+    }
+
+    if tree.has_filename(Path::new("Makefile")) {
+        if let Some(text) = read_file(tree, Path::new("Makefile")) {
+            if makefile_has_target(&String::from_utf8_lossy(&text), "check") {
+                return Some(VerifyCommand::new(
+                    "make check",
+                    "Makefile has a check target",
+                ));
+            }
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pyproject_has_pytest() {
+        assert!(pyproject_has_pytest(
+            "[project]\nname = \"foo\"\n\n[tool.pytest.ini_options]\ntestpaths = [\"tests\"]\n"
+        ));
+        assert!(!pyproject_has_pytest(
+            "[project]\nname = \"foo\"\n\n[tool.ruff]\n"
+        ));
+        assert!(!pyproject_has_pytest("[project"));
+    }
+
+    #[test]
+    fn test_package_json_has_test_script() {
+        assert!(package_json_has_test_script(
+            &serde_json::json!({"scripts": {"test": "jest"}})
+        ));
+        assert!(!package_json_has_test_script(
+            &serde_json::json!({"scripts": {"test": NPM_DEFAULT_TEST_SCRIPT}})
+        ));
+        assert!(!package_json_has_test_script(
+            &serde_json::json!({"name": "foo"})
+        ));
+    }
+
+    #[test]
+    fn test_makefile_has_target() {
+        let text = "CHECK := foo\n\nall: build\n\ncheck test: all\n\tpython -m unittest\n";
+        assert!(makefile_has_target(text, "check"));
+        assert!(makefile_has_target(text, "test"));
+        assert!(!makefile_has_target(text, "CHECK"));
+        assert!(!makefile_has_target(text, "unittest"));
+    }
+}
@@ -1,42 +1,352 @@
+use crate::project_config::{Bump, VersionScheme};
+use chrono::Datelike;
 use std::str::FromStr;
 
 #[cfg(feature = "pyo3")]
 use pyo3::prelude::*;
 
+/// The kind of a pre-release.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum PreReleaseKind {
+    Alpha,
+    Beta,
+    Rc,
+}
+
+/// How a pre-release segment is spelled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum PreReleaseStyle {
+    /// PEP 440 style, e.g. "1.2.0rc1".
+    Pep440,
+    /// Dash-separated style, e.g. "1.2.0-rc.1".
+    Dashed,
+}
+
+/// A dot-separated identifier in a semver pre-release, e.g. "alpha" or "1"
+/// in "1.0.0-alpha.1".
+///
+/// The derived ordering matches semver precedence: numeric identifiers
+/// compare numerically and sort before alphanumeric ones.
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Identifier {
+    Numeric(u64),
+    AlphaNumeric(String),
+}
+
+impl std::fmt::Display for Identifier {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Identifier::Numeric(n) => write!(f, "{}", n),
+            Identifier::AlphaNumeric(s) => write!(f, "{}", s),
+        }
+    }
+}
+
+impl std::str::FromStr for Identifier {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if !lazy_regex::regex_is_match!(r"^[0-9A-Za-z-]+$", s) {
+            return Err(format!("invalid identifier: {:?}", s));
+        }
+        if s.bytes().all(|b| b.is_ascii_digit()) {
+            if s.len() > 1 && s.starts_with('0') {
+                return Err(format!("numeric identifier with leading zero: {}", s));
+            }
+            s.parse::<u64>()
+                .map(Identifier::Numeric)
+                .map_err(|e| format!("invalid identifier {}: {}", s, e))
+        } else {
+            Ok(Identifier::AlphaNumeric(s.to_string()))
+        }
+    }
+}
+
+/// A pre-release segment of a version, e.g. "rc1" in "1.2.0rc1".
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PreRelease {
+    /// An alpha, beta or release candidate, e.g. "rc1" or "-beta.2".
+    Named {
+        kind: PreReleaseKind,
+        number: Option<i32>,
+        style: PreReleaseStyle,
+    },
+    /// Arbitrary semver identifiers, e.g. "-x.7.z.92".
+    Identifiers(Vec<Identifier>),
+}
+
+impl PreRelease {
+    /// The identifiers that determine precedence, as defined by semver.
+    pub fn identifiers(&self) -> Vec<Identifier> {
+        match self {
+            PreRelease::Named { kind, number, .. } => {
+                let kind = match kind {
+                    PreReleaseKind::Alpha => "alpha",
+                    PreReleaseKind::Beta => "beta",
+                    PreReleaseKind::Rc => "rc",
+                };
+                let mut ret = vec![Identifier::AlphaNumeric(kind.to_string())];
+                if let Some(number) = number {
+                    ret.push(Identifier::Numeric(*number as u64));
+                }
+                ret
+            }
+            PreRelease::Identifiers(ids) => ids.clone(),
+        }
+    }
+
+    /// Whether this pre-release is spelled in a way that is valid in semver.
+    pub fn is_semver(&self) -> bool {
+        !matches!(
+            self,
+            PreRelease::Named {
+                style: PreReleaseStyle::Pep440,
+                ..
+            }
+        )
+    }
+}
+
+impl Ord for PreRelease {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.identifiers()
+            .cmp(&other.identifiers())
+            // Only differences in spelling are left; keep the order total.
+            .then_with(|| self.to_string().cmp(&other.to_string()))
+    }
+}
+
+impl PartialOrd for PreRelease {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl std::fmt::Display for PreRelease {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            PreRelease::Named {
+                kind,
+                number,
+                style: PreReleaseStyle::Pep440,
+            } => {
+                let kind = match kind {
+                    PreReleaseKind::Alpha => "a",
+                    PreReleaseKind::Beta => "b",
+                    PreReleaseKind::Rc => "rc",
+                };
+                write!(f, "{}", kind)?;
+                if let Some(number) = number {
+                    write!(f, "{}", number)?;
+                }
+            }
+            PreRelease::Named {
+                style: PreReleaseStyle::Dashed,
+                ..
+            }
+            | PreRelease::Identifiers(_) => {
+                let ids = self
+                    .identifiers()
+                    .iter()
+                    .map(|i| i.to_string())
+                    .collect::<Vec<_>>();
+                write!(f, "-{}", ids.join("."))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl std::str::FromStr for PreRelease {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some((_, dash, kind, number)) =
+            lazy_regex::regex_captures!(r"^(-)?(a|alpha|b|beta|c|rc)(?:\.?([0-9]+))?$", s)
+        {
+            let kind = match kind {
+                "a" | "alpha" => PreReleaseKind::Alpha,
+                "b" | "beta" => PreReleaseKind::Beta,
+                _ => PreReleaseKind::Rc,
+            };
+            let number = if number.is_empty() {
+                None
+            } else {
+                Some(
+                    number
+                        .parse::<i32>()
+                        .map_err(|e| format!("invalid pre-release number: {}", e))?,
+                )
+            };
+            return Ok(PreRelease::Named {
+                kind,
+                number,
+                style: if dash.is_empty() {
+                    PreReleaseStyle::Pep440
+                } else {
+                    PreReleaseStyle::Dashed
+                },
+            });
+        }
+        match s.strip_prefix('-') {
+            Some(ids) => Ok(PreRelease::Identifiers(
+                ids.split('.')
+                    .map(|i| i.parse::<Identifier>())
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(|e| format!("invalid pre-release {}: {}", s, e))?,
+            )),
+            None => Err(format!("invalid pre-release: {}", s)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Version {
     pub major: i32,
     pub minor: Option<i32>,
     pub micro: Option<i32>,
+    pub pre: Option<PreRelease>,
+    /// Build metadata, e.g. "build.5" in "1.0.0+build.5"; for PEP 440
+    /// versions, this is the local version label.
+    pub build: Option<String>,
+    /// PEP 440 epoch, e.g. 1 in "1!2.0".
+    pub epoch: Option<u32>,
+    /// PEP 440 post-release number, e.g. 1 in "1.0.post1".
+    pub post: Option<u32>,
+    /// PEP 440 development release number, e.g. 0 in "1.0.dev0".
+    pub dev: Option<u32>,
+}
+
+impl Version {
+    /// Where the version sorts relative to the other releases of the same
+    /// release segment, following PEP 440: development releases of the
+    /// final release first, then pre-releases, then the final release.
+    fn pre_key(&self) -> (u8, Option<&PreRelease>) {
+        match (&self.pre, self.post, self.dev) {
+            (None, None, Some(_)) => (0, None),
+            (Some(pre), _, _) => (1, Some(pre)),
+            (None, _, _) => (2, None),
+        }
+    }
+}
+
+impl Ord for Version {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.epoch
+            .unwrap_or(0)
+            .cmp(&other.epoch.unwrap_or(0))
+            .then_with(|| {
+                (self.major, self.minor, self.micro).cmp(&(other.major, other.minor, other.micro))
+            })
+            .then_with(|| self.pre_key().cmp(&other.pre_key()))
+            .then_with(|| self.post.cmp(&other.post))
+            // A development release sorts before the release it leads up to.
+            .then_with(|| (self.dev.is_none(), self.dev).cmp(&(other.dev.is_none(), other.dev)))
+            // Build metadata doesn't affect precedence; this only keeps the order total.
+            .then_with(|| self.build.cmp(&other.build))
+    }
+}
+
+impl PartialOrd for Version {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
 }
 
 impl std::str::FromStr for Version {
     type Err = String;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let parts: Vec<&str> = s.split('.').collect();
+        let (s, build) = match s.split_once('+') {
+            Some((s, build)) => {
+                if !lazy_regex::regex_is_match!(r"^[0-9A-Za-z-]+(\.[0-9A-Za-z-]+)*$", build) {
+                    return Err(format!("invalid build metadata: {}", build));
+                }
+                (s, Some(build.to_string()))
+            }
+            None => (s, None),
+        };
+        let (epoch, s) = match s.split_once('!') {
+            Some((epoch, s)) => (
+                Some(
+                    epoch
+                        .parse::<u32>()
+                        .map_err(|e| format!("invalid epoch: {}", e))?,
+                ),
+                s,
+            ),
+            None => (None, s),
+        };
+        let (_, release, pre, post, dev) = lazy_regex::regex_captures!(
+            r"^([0-9]+(?:\.[0-9]+)*)(.*?)(?:\.post([0-9]+))?(?:\.dev([0-9]+))?$",
+            s
+        )
+        .ok_or_else(|| format!("invalid version: {}", s))?;
+        let pre = if pre.is_empty() {
+            None
+        } else {
+            Some(pre.parse::<PreRelease>()?)
+        };
+        let number = |n: &str, what: &str| {
+            if n.is_empty() {
+                Ok(None)
+            } else {
+                n.parse::<u32>()
+                    .map(Some)
+                    .map_err(|e| format!("invalid {} number: {}", what, e))
+            }
+        };
+        let parts: Vec<&str> = release.split('.').collect();
         let major = parts[0]
             .parse::<i32>()
             .map_err(|e| format!("invalid major version: {}", e))?;
-        let minor = parts.get(1).map(|x| x.parse::<i32>().unwrap());
-        let micro = parts.get(2).map(|x| x.parse::<i32>().unwrap());
+        let minor = parts
+            .get(1)
+            .map(|x| x.parse::<i32>())
+            .transpose()
+            .map_err(|e| format!("invalid minor version: {}", e))?;
+        let micro = parts
+            .get(2)
+            .map(|x| x.parse::<i32>())
+            .transpose()
+            .map_err(|e| format!("invalid micro version: {}", e))?;
         Ok(Version {
             major,
             minor,
             micro,
+            pre,
+            build,
+            epoch,
+            post: number(post, "post-release")?,
+            dev: number(dev, "development release")?,
         })
     }
 }
 
 impl ToString for Version {
     fn to_string(&self) -> String {
-        let mut s = self.major.to_string();
+        let mut s = match self.epoch {
+            Some(epoch) => format!("{}!{}", epoch, self.major),
+            None => self.major.to_string(),
+        };
         if let Some(minor) = self.minor {
             s.push_str(format!(".{}", minor).as_str());
         }
         if let Some(micro) = self.micro {
             s.push_str(format!(".{}", micro).as_str());
         }
+        if let Some(pre) = self.pre.as_ref() {
+            s.push_str(pre.to_string().as_str());
+        }
+        if let Some(post) = self.post {
+            s.push_str(format!(".post{}", post).as_str());
+        }
+        if let Some(dev) = self.dev {
+            s.push_str(format!(".dev{}", dev).as_str());
+        }
+        if let Some(build) = self.build.as_ref() {
+            s.push_str(format!("+{}", build).as_str());
+        }
         s
     }
 }
@@ -54,6 +364,103 @@ impl Version {
         self.micro
     }
 
+    pub fn is_prerelease(&self) -> bool {
+        self.pre.is_some()
+    }
+
+    /// Turn a pre-release into the corresponding final release, e.g. 1.2.0rc2 into 1.2.0.
+    pub fn finalize(&mut self) {
+        self.pre = None;
+        self.dev = None;
+        self.build = None;
+    }
+
+    /// Parse a version, requiring it to be valid semver.
+    pub fn parse_semver(s: &str) -> Result<Self, String> {
+        if !lazy_regex::regex_is_match!(
+            r"^(0|[1-9][0-9]*)\.(0|[1-9][0-9]*)\.(0|[1-9][0-9]*)(-|\+|$)",
+            s
+        ) {
+            return Err(format!("{} is not a valid semver version", s));
+        }
+        let version = s.parse::<Version>()?;
+        if version.pre.as_ref().is_some_and(|p| !p.is_semver())
+            || version.epoch.is_some()
+            || version.post.is_some()
+            || version.dev.is_some()
+        {
+            return Err(format!("{} is not a valid semver version", s));
+        }
+        Ok(version)
+    }
+
+    /// Parse a PEP 440 version, normalizing alternative spellings.
+    ///
+    /// For example, "v1.0-Alpha-1" becomes "1.0a1" and "1.0-1" becomes "1.0.post1".
+    pub fn parse_pep440(s: &str) -> Result<Self, String> {
+        let normalized = s.trim().to_lowercase();
+        let (_, epoch, release, pre_l, pre_n, post_n1, post_l, post_n2, dev_l, dev_n, local) =
+            lazy_regex::regex_captures!(
+                r"^v?(?:([0-9]+)!)?([0-9]+(?:\.[0-9]+)*)(?:[-_\.]?(alpha|a|beta|b|preview|pre|c|rc)[-_\.]?([0-9]+)?)?(?:-([0-9]+)|[-_\.]?(post|rev|r)[-_\.]?([0-9]+)?)?(?:[-_\.]?(dev)[-_\.]?([0-9]+)?)?(?:\+([a-z0-9]+(?:[-_\.][a-z0-9]+)*))?$",
+                &normalized
+            )
+            .ok_or_else(|| format!("{} is not a valid PEP 440 version", s))?;
+        let release = release
+            .split('.')
+            .map(|p| p.parse::<i32>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("invalid release segment in {}: {}", s, e))?;
+        if release.len() > 3 {
+            return Err(format!(
+                "{} has more than three release components, which is not supported",
+                s
+            ));
+        }
+        let number = |n: &str| n.parse::<u32>().unwrap_or(0);
+        let pre = if pre_l.is_empty() {
+            None
+        } else {
+            Some(PreRelease::Named {
+                kind: match pre_l {
+                    "a" | "alpha" => PreReleaseKind::Alpha,
+                    "b" | "beta" => PreReleaseKind::Beta,
+                    _ => PreReleaseKind::Rc,
+                },
+                number: Some(number(pre_n) as i32),
+                style: PreReleaseStyle::Pep440,
+            })
+        };
+        let post = if !post_n1.is_empty() {
+            Some(number(post_n1))
+        } else if !post_l.is_empty() {
+            Some(number(post_n2))
+        } else {
+            None
+        };
+        Ok(Version {
+            major: release[0],
+            minor: release.get(1).copied(),
+            micro: release.get(2).copied(),
+            pre,
+            build: Some(local)
+                .filter(|l| !l.is_empty())
+                .map(|l| l.replace(['-', '_'], ".")),
+            epoch: Some(epoch).filter(|e| !e.is_empty()).map(number),
+            post,
+            dev: Some(dev_l).filter(|d| !d.is_empty()).map(|_| number(dev_n)),
+        })
+    }
+
+    /// Parse a version according to a versioning scheme.
+    pub fn parse_with_scheme(s: &str, scheme: VersionScheme) -> Result<Self, String> {
+        match scheme {
+            VersionScheme::Simple => s.parse(),
+            VersionScheme::Pep440 => Self::parse_pep440(s),
+            VersionScheme::Semver => Self::parse_semver(s),
+            VersionScheme::Calver => s.parse(),
+        }
+    }
+
     pub fn from_tupled(text: &str) -> Result<(Self, Option<crate::Status>), Error> {
         if text.starts_with('(') && text.ends_with(')') {
             return Self::from_tupled(&text[1..text.len() - 1]);
@@ -92,6 +499,11 @@ impl Version {
                 major,
                 minor,
                 micro,
+                pre: None,
+                build: None,
+                epoch: None,
+                post: None,
+                dev: None,
             },
             status,
         ))
@@ -101,6 +513,202 @@ impl Version {
 #[cfg(test)]
 mod tests {
     use super::*;
+    #[test]
+    fn test_pep440() {
+        let v = |s: &str| Version::parse_pep440(s).unwrap().to_string();
+        assert_eq!(v("1.0.post1"), "1.0.post1");
+        assert_eq!(v("1.0.dev0"), "1.0.dev0");
+        assert_eq!(v("1!2.0"), "1!2.0");
+        assert_eq!(v("v1.0-Alpha-1"), "1.0a1");
+        assert_eq!(v("1.0-1"), "1.0.post1");
+        assert_eq!(v("1.0.0-rc.2"), "1.0.0rc2");
+        assert_eq!(v("1.0a"), "1.0a0");
+        assert_eq!(v("1.0rev"), "1.0.post0");
+        assert_eq!(v("1.0+Ubuntu-1"), "1.0+ubuntu.1");
+        assert_eq!(v("1.0rc1.post2.dev3"), "1.0rc1.post2.dev3");
+        assert!(Version::parse_pep440("1.2.3.4").is_err());
+        assert!(Version::parse_pep440("1.0-x.7").is_err());
+
+        // Every version here round-trips through FromStr as well.
+        assert_eq!(
+            "1!1.0rc1.post2.dev3+local"
+                .parse::<Version>()
+                .unwrap()
+                .to_string(),
+            "1!1.0rc1.post2.dev3+local"
+        );
+
+        // Ordering example from PEP 440.
+        let ordered = [
+            "1.0.dev456",
+            "1.0a1",
+            "1.0a2.dev456",
+            "1.0a12.dev456",
+            "1.0a12",
+            "1.0b1.dev456",
+            "1.0b2",
+            "1.0b2.post345.dev456",
+            "1.0b2.post345",
+            "1.0rc1.dev456",
+            "1.0rc1",
+            "1.0",
+            "1.0+abc.5",
+            "1.0.post456.dev34",
+            "1.0.post456",
+            "1.1.dev1",
+            "1!0.1",
+        ];
+        let p = |s: &str| Version::parse_pep440(s).unwrap();
+        for pair in ordered.windows(2) {
+            assert!(p(pair[0]) < p(pair[1]), "{} < {}", pair[0], pair[1]);
+        }
+
+        let mut x = p("1.0.post1");
+        increase_version(&mut x, -1);
+        assert_eq!(x.to_string(), "1.0.post2");
+        let mut x = p("1.1.dev0");
+        increase_version(&mut x, -1);
+        assert_eq!(x.to_string(), "1.1.dev1");
+        x.finalize();
+        assert_eq!(x.to_string(), "1.1");
+    }
+
+    #[test]
+    fn test_semver() {
+        let v = |s: &str| Version::parse_semver(s).unwrap();
+        assert_eq!(
+            v("1.0.0-x.7.z.92+build.5").to_string(),
+            "1.0.0-x.7.z.92+build.5"
+        );
+        assert_eq!(
+            v("1.0.0+20130313144700").build.as_deref(),
+            Some("20130313144700")
+        );
+        for invalid in ["1.0", "01.0.0", "1.0.0rc1", "1.0.0-01", "1.0.0-", "1.0.0+"] {
+            assert!(Version::parse_semver(invalid).is_err(), "{}", invalid);
+        }
+        // The precedence example from the semver specification.
+        let ordered = [
+            "1.0.0-alpha",
+            "1.0.0-alpha.1",
+            "1.0.0-alpha.beta",
+            "1.0.0-beta",
+            "1.0.0-beta.2",
+            "1.0.0-beta.11",
+            "1.0.0-rc.1",
+            "1.0.0",
+            "1.9.0",
+            "1.10.0",
+        ];
+        for pair in ordered.windows(2) {
+            assert!(v(pair[0]) < v(pair[1]), "{} < {}", pair[0], pair[1]);
+        }
+
+        let mut x = v("1.0.0-x.7+build");
+        increase_version(&mut x, -1);
+        assert_eq!(x.to_string(), "1.0.0-x.8");
+        assert_eq!(
+            Version::parse_with_scheme("1.0.0rc1", VersionScheme::Simple).unwrap(),
+            "1.0.0rc1".parse().unwrap()
+        );
+        assert!(Version::parse_with_scheme("1.0.0rc1", VersionScheme::Semver).is_err());
+    }
+
+    #[test]
+    fn test_prerelease_roundtrip() {
+        for v in [
+            "1.2.0rc1",
+            "1.2.0a3",
+            "1.2b1",
+            "1.2.0-beta.2",
+            "1.2.0-rc",
+            "1.2.0",
+        ] {
+            assert_eq!(v.parse::<Version>().unwrap().to_string(), v);
+        }
+        let v = "1.2.0-beta.2".parse::<Version>().unwrap();
+        assert_eq!(
+            v.pre,
+            Some(PreRelease::Named {
+                kind: PreReleaseKind::Beta,
+                number: Some(2),
+                style: PreReleaseStyle::Dashed,
+            })
+        );
+        assert!("1.2.0foo".parse::<Version>().is_err());
+    }
+
+    #[test]
+    fn test_prerelease_ordering() {
+        let v = |s: &str| s.parse::<Version>().unwrap();
+        assert!(v("1.2.0a1") < v("1.2.0b1"));
+        assert!(v("1.2.0b2") < v("1.2.0rc1"));
+        assert!(v("1.2.0rc1") < v("1.2.0rc2"));
+        assert!(v("1.2.0rc2") < v("1.2.0"));
+        assert!(v("1.1.9") < v("1.2.0rc1"));
+    }
+
+    #[test]
+    fn test_increase_prerelease() {
+        let mut v = "1.2.0rc1".parse::<Version>().unwrap();
+        increase_version(&mut v, -1);
+        assert_eq!(v.to_string(), "1.2.0rc2");
+        v.finalize();
+        assert_eq!(v.to_string(), "1.2.0");
+
+        let mut v = "1.2.0-rc.1".parse::<Version>().unwrap();
+        increase_version(&mut v, 1);
+        assert_eq!(v.to_string(), "1.3.0");
+        assert_eq!(
+            expand_tag("v$VERSION", &"1.2.0rc1".parse().unwrap()),
+            "v1.2.0rc1"
+        );
+        assert_eq!(
+            unexpand_tag("v$VERSION", "v1.2.0rc1").unwrap(),
+            "1.2.0rc1".parse().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_tag_templates() {
+        let version: Version = "1.2.3".parse().unwrap();
+        let context = TagContext {
+            project: Some("foo"),
+            date: chrono::NaiveDate::from_ymd_opt(2024, 10, 6),
+        };
+        assert_eq!(
+            expand_tag_with("$PROJECT-$MAJOR.$MINOR.$MICRO", &version, &context),
+            "foo-1.2.3"
+        );
+        assert_eq!(
+            expand_tag_with("release-$DATE-$VERSION", &version, &context),
+            "release-2024-10-06-1.2.3"
+        );
+        assert_eq!(
+            expand_tag_with("$YEAR$MONTH$DAY", &version, &context),
+            "20241006"
+        );
+        assert_eq!(expand_tag("$MAJOR_$MINOR", &version), "1_2");
+
+        assert_eq!(
+            unexpand_tag("foo+bar-$VERSION", "foo+bar-1.2.3"),
+            Ok(version.clone())
+        );
+        assert!(unexpand_tag("foo+bar-$VERSION", "foooobar-1.2.3").is_err());
+        // Templates are anchored.
+        assert!(unexpand_tag("v$VERSION", "xv1.2.3").is_err());
+        assert_eq!(
+            unexpand_tag("$PROJECT-v$MAJOR_$MINOR_$MICRO", "foo-v1_2_3"),
+            Ok(version)
+        );
+        assert_eq!(
+            unexpand_tag("release-$DATE-$VERSION", "release-2024-10-06-1.2.3"),
+            Ok("1.2.3".parse().unwrap())
+        );
+        assert!(tag_has_date("release-$DATE-$VERSION"));
+        assert!(!tag_has_date("v$VERSION"));
+    }
+
     #[test]
     fn test_from_tupled() {
         assert_eq!(
@@ -110,6 +718,11 @@ mod tests {
                     major: 1,
                     minor: Some(2),
                     micro: Some(3),
+                    pre: None,
+                    build: None,
+                    epoch: None,
+                    post: None,
+                    dev: None,
                 },
                 Some(crate::Status::Dev)
             )
@@ -121,6 +734,11 @@ mod tests {
                     major: 1,
                     minor: Some(2),
                     micro: Some(3),
+                    pre: None,
+                    build: None,
+                    epoch: None,
+                    post: None,
+                    dev: None,
                 },
                 None
             )
@@ -132,6 +750,11 @@ mod tests {
                     major: 1,
                     minor: Some(2),
                     micro: None,
+                    pre: None,
+                    build: None,
+                    epoch: None,
+                    post: None,
+                    dev: None,
                 },
                 None
             )
@@ -143,6 +766,11 @@ mod tests {
                     major: 1,
                     minor: None,
                     micro: None,
+                    pre: None,
+                    build: None,
+                    epoch: None,
+                    post: None,
+                    dev: None,
                 },
                 None
             )
@@ -154,59 +782,348 @@ mod tests {
                     major: 1,
                     minor: None,
                     micro: None,
+                    pre: None,
+                    build: None,
+                    epoch: None,
+                    post: None,
+                    dev: None,
                 },
                 None
             )
         );
     }
+
+    #[test]
+    fn test_bump_version() {
+        let bump = |v: &str, b: Bump| {
+            let mut v: Version = v.parse().unwrap();
+            bump_version(&mut v, b);
+            v.to_string()
+        };
+        assert_eq!(bump("1.2.3", Bump::Major), "2.0.0");
+        assert_eq!(bump("1.2.3", Bump::Minor), "1.3.0");
+        assert_eq!(bump("1.2.3", Bump::Patch), "1.2.4");
+        assert_eq!(bump("1.2", Bump::Major), "2.0");
+        assert_eq!(bump("1.2", Bump::Minor), "1.3");
+        assert_eq!(bump("1.2", Bump::Patch), "1.2.1");
+        assert_eq!(bump("1.2.3rc1", Bump::Minor), "1.3.0");
+
+        let increase = |v: &str, b: Bump| {
+            let mut v: Version = v.parse().unwrap();
+            increase_version(&mut v, bump_index(b));
+            v.to_string()
+        };
+        assert_eq!(increase("1.2.3", Bump::Major), "2.2.3");
+        assert_eq!(increase("1.2.3", Bump::Minor), "1.3.3");
+        assert_eq!(increase("1.2.3", Bump::Patch), "1.2.4");
+    }
+
+    #[test]
+    fn test_next_calver() {
+        let today = chrono::NaiveDate::from_ymd_opt(2024, 10, 16).unwrap();
+        let next = |format: &str, last: Option<&str>| {
+            next_calver(format, today, last.map(|l| l.parse().unwrap()).as_ref())
+                .map(|v| v.to_string())
+        };
+        assert_eq!(next("YYYY.MM.MICRO", None), Ok("2024.10.0".to_string()));
+        assert_eq!(
+            next("YYYY.MM.MICRO", Some("2024.9.3")),
+            Ok("2024.10.0".to_string())
+        );
+        assert_eq!(
+            next("YYYY.MM.MICRO", Some("2024.10.0")),
+            Ok("2024.10.1".to_string())
+        );
+        assert_eq!(next("YY.MM", Some("24.9")), Ok("24.10".to_string()));
+        assert_eq!(next("YYYY.WW", None), Ok("2024.42".to_string()));
+        assert!(next("YY.MM", Some("24.10")).is_err());
+        assert!(next("MICRO.YYYY", None).is_err());
+        assert!(next("YYYY.0M", None).is_err());
+        assert!(next("YYYY.MM.DD.MICRO", None).is_err());
+
+        // Tags expand and unexpand like any other version.
+        let version = next_calver("YYYY.MM.MICRO", today, None).unwrap();
+        assert_eq!(expand_tag("v$VERSION", &version), "v2024.10.0");
+        assert_eq!(unexpand_tag("v$VERSION", "v2024.10.0"), Ok(version));
+    }
 }
 
 #[cfg(feature = "pyo3")]
 impl ToPyObject for Version {
-    fn to_object(&self, py: pyo3::Python) -> pyo3::PyObject {
+    fn to_object(&self, py: Python) -> PyObject {
         self.to_string().to_object(py)
     }
 }
 
 #[cfg(feature = "pyo3")]
-impl IntoPy<pyo3::PyObject> for Version {
-    fn into_py(self, py: pyo3::Python) -> pyo3::PyObject {
-        self.to_string().into_py(py)
+impl IntoPy<PyObject> for Version {
+    fn into_py(self, py: Python) -> PyObject {
+        self.to_object(py)
     }
 }
 
 #[cfg(feature = "pyo3")]
 impl FromPyObject<'_> for Version {
-    fn extract_bound(ob: &pyo3::Bound<pyo3::PyAny>) -> pyo3::PyResult<Self> {
-        use pyo3::prelude::*;
+    fn extract_bound(ob: &Bound<PyAny>) -> PyResult<Self> {
         let s = ob.extract::<String>()?;
         Version::from_str(s.as_str())
             .map_err(|e| pyo3::exceptions::PyValueError::new_err(format!("Invalid version: {}", e)))
     }
 }
 
+/// Variables in tag templates, e.g. "$VERSION".
+const TAG_VARIABLE: &str = r"\$(VERSION|MAJOR|MINOR|MICRO|PROJECT|YEAR|MONTH|DAY|DATE)";
+
+/// Values for the variables in a tag template that don't come from the version.
+#[derive(Debug, Clone, Default)]
+pub struct TagContext<'a> {
+    /// Name of the project, for $PROJECT.
+    pub project: Option<&'a str>,
+    /// Release date, for $YEAR, $MONTH, $DAY and $DATE; defaults to today.
+    pub date: Option<chrono::NaiveDate>,
+}
+
+/// Check whether a tag template has variables that depend on the release date.
+pub fn tag_has_date(tag_template: &str) -> bool {
+    regex::Regex::new(TAG_VARIABLE)
+        .unwrap()
+        .captures_iter(tag_template)
+        .any(|c| matches!(&c[1], "YEAR" | "MONTH" | "DAY" | "DATE"))
+}
+
+/// Expand a tag template for a version.
+///
+/// Supported variables are $VERSION, $MAJOR, $MINOR, $MICRO, $PROJECT and
+/// the release date as $YEAR, $MONTH, $DAY and $DATE (YYYY-MM-DD).
+pub fn expand_tag_with(tag_template: &str, version: &Version, context: &TagContext) -> String {
+    let date = context
+        .date
+        .unwrap_or_else(|| chrono::Utc::now().date_naive());
+    regex::Regex::new(TAG_VARIABLE)
+        .unwrap()
+        .replace_all(tag_template, |c: &regex::Captures| match &c[1] {
+            "VERSION" => version.to_string(),
+            "MAJOR" => version.major.to_string(),
+            "MINOR" => version.minor.unwrap_or(0).to_string(),
+            "MICRO" => version.micro.unwrap_or(0).to_string(),
+            "PROJECT" => context
+                .project
+                .map_or_else(|| c[0].to_string(), str::to_string),
+            "YEAR" => date.format("%Y").to_string(),
+            "MONTH" => date.format("%m").to_string(),
+            "DAY" => date.format("%d").to_string(),
+            "DATE" => date.format("%Y-%m-%d").to_string(),
+            _ => unreachable!(),
+        })
+        .into_owned()
+}
+
 pub fn expand_tag(tag_template: &str, version: &Version) -> String {
-    tag_template.replace("$VERSION", version.to_string().as_str())
+    expand_tag_with(tag_template, version, &TagContext::default())
+}
+
+/// Build a regex that matches the tags generated from a template.
+fn tag_regex(tag_template: &str) -> regex::Regex {
+    let variable = regex::Regex::new(TAG_VARIABLE).unwrap();
+    let mut pattern = "^".to_string();
+    let mut last = 0;
+    for c in variable.captures_iter(tag_template) {
+        let m = c.get(0).unwrap();
+        pattern.push_str(&regex::escape(&tag_template[last..m.start()]));
+        pattern.push_str(match &c[1] {
+            "VERSION" => "(?P<version>.+)",
+            "MAJOR" => "(?P<major>[0-9]+)",
+            "MINOR" => "(?P<minor>[0-9]+)",
+            "MICRO" => "(?P<micro>[0-9]+)",
+            "PROJECT" => ".+?",
+            "YEAR" => "[0-9]{4}",
+            "MONTH" | "DAY" => "[0-9]{2}",
+            "DATE" => "[0-9]{4}-[0-9]{2}-[0-9]{2}",
+            _ => unreachable!(),
+        });
+        last = m.end();
+    }
+    pattern.push_str(&regex::escape(&tag_template[last..]));
+    pattern.push('$');
+    regex::Regex::new(&pattern).unwrap()
 }
 
 pub fn unexpand_tag(tag_template: &str, tag: &str) -> Result<Version, String> {
-    let tag_re = regex::Regex::new(tag_template.replace("$VERSION", "(.*)").as_str()).unwrap();
-    if let Some(m) = tag_re.captures(tag) {
-        Ok(Version::from_str(m.get(1).unwrap().as_str()).map_err(|e| {
-            format!(
-                "Tag {} does not match template {}: {}",
-                tag, tag_template, e
-            )
-        })?)
-    } else {
-        Err(format!(
-            "Tag {} does not match template {}",
-            tag, tag_template
-        ))
+    let m = tag_regex(tag_template)
+        .captures(tag)
+        .ok_or_else(|| format!("Tag {} does not match template {}", tag, tag_template))?;
+    let component = |name: &str| m.name(name).and_then(|c| c.as_str().parse::<i32>().ok());
+    let version = match (m.name("version"), component("major")) {
+        (Some(version), _) => Version::from_str(version.as_str()),
+        (None, Some(major)) => Ok(Version {
+            major,
+            minor: component("minor"),
+            micro: component("micro"),
+            pre: None,
+            build: None,
+            epoch: None,
+            post: None,
+            dev: None,
+        }),
+        (None, None) => {
+            return Err(format!(
+                "Template {} does not contain a version",
+                tag_template
+            ))
+        }
+    };
+    version.map_err(|e| {
+        format!(
+            "Tag {} does not match template {}: {}",
+            tag, tag_template, e
+        )
+    })
+}
+
+/// Format of calendar versions, if none is configured.
+pub const DEFAULT_CALVER_FORMAT: &str = "YYYY.MM.MICRO";
+
+/// Work out the next calendar version, e.g. for the format "YYYY.MM.MICRO".
+///
+/// Supported components are YYYY, YY (years since 2000), MM, WW (ISO week),
+/// DD and, as the last component, MICRO. The date components come from
+/// `today`; MICRO starts at 0 and is increased for further releases in the
+/// same period.
+pub fn next_calver(
+    format: &str,
+    today: chrono::NaiveDate,
+    last: Option<&Version>,
+) -> Result<Version, String> {
+    let parts = format.split('.').collect::<Vec<_>>();
+    if parts.len() > 3 {
+        return Err(format!(
+            "calver format {} has more than three components, which is not supported",
+            format
+        ));
+    }
+    let mut components = vec![];
+    let mut has_micro = false;
+    for (i, part) in parts.iter().enumerate() {
+        components.push(match *part {
+            "YYYY" => today.year(),
+            "YY" => today.year() - 2000,
+            "MM" => today.month() as i32,
+            "WW" => today.iso_week().week() as i32,
+            "DD" => today.day() as i32,
+            "MICRO" if i > 0 && i == parts.len() - 1 => {
+                has_micro = true;
+                0
+            }
+            _ => {
+                return Err(format!(
+                    "unsupported component {:?} in calver format {}",
+                    part, format
+                ))
+            }
+        });
+    }
+    let period = components.len() - usize::from(has_micro);
+    if let Some(last) = last {
+        let last_components = [Some(last.major), last.minor, last.micro];
+        let same_period = components[..period]
+            .iter()
+            .zip(last_components.iter())
+            .all(|(c, l)| Some(*c) == *l);
+        if same_period {
+            if !has_micro {
+                return Err(format!(
+                    "{} has already been released; add MICRO to the calver format to allow more than one release per period",
+                    last.to_string()
+                ));
+            }
+            components[period] = last_components[period].map_or(0, |m| m + 1);
+        }
     }
+    Ok(Version {
+        major: components[0],
+        minor: components.get(1).copied(),
+        micro: components.get(2).copied(),
+        pre: None,
+        build: None,
+        epoch: None,
+        post: None,
+        dev: None,
+    })
 }
 
+/// Increase the major, minor or patch component of a version.
+///
+/// Less significant components are reset to 0, and any pre-release, post-release
+/// or development segment is dropped; e.g. bumping the minor component of
+/// 1.2.3 gives 1.3.0.
+pub fn bump_version(version: &mut Version, bump: Bump) {
+    version.pre = None;
+    version.post = None;
+    version.dev = None;
+    version.build = None;
+    match bump {
+        Bump::Major => {
+            version.major += 1;
+            version.minor = version.minor.map(|_| 0);
+            version.micro = version.micro.map(|_| 0);
+        }
+        Bump::Minor => {
+            version.minor = Some(version.minor.map_or(1, |m| m + 1));
+            version.micro = version.micro.map(|_| 0);
+        }
+        Bump::Patch => {
+            version.minor = Some(version.minor.unwrap_or(0));
+            version.micro = Some(version.micro.map_or(1, |m| m + 1));
+        }
+    }
+}
+
+/// Index of the component that a bump increases, for use with
+/// [`increase_version`].
+pub fn bump_index(bump: Bump) -> isize {
+    match bump {
+        Bump::Major => 0,
+        Bump::Minor => 1,
+        Bump::Patch => 2,
+    }
+}
+
+/// Increase a component of a version.
+///
+/// Unlike [`bump_version`], less significant components are left alone, so
+/// increasing the major component of 1.2.3 gives 2.2.3. An index of -1 increases the last component; for a pre-release, that is
+/// the pre-release number (1.2.0rc1 becomes 1.2.0rc2). Increasing any of the
+/// other components drops the pre-release segment.
 pub fn increase_version(version: &mut Version, idx: isize) {
+    version.build = None;
+    if idx == -1 {
+        if let Some(dev) = version.dev.as_mut() {
+            *dev += 1;
+            return;
+        }
+        if let Some(post) = version.post.as_mut() {
+            *post += 1;
+            return;
+        }
+        match version.pre.as_mut() {
+            Some(PreRelease::Named { number, .. }) => {
+                *number = Some(number.map_or(1, |n| n + 1));
+                return;
+            }
+            Some(PreRelease::Identifiers(ids)) => {
+                match ids.last_mut() {
+                    Some(Identifier::Numeric(n)) => *n += 1,
+                    _ => ids.push(Identifier::Numeric(1)),
+                }
+                return;
+            }
+            None => {}
+        }
+    } else {
+        version.pre = None;
+        version.post = None;
+        version.dev = None;
+    }
     match idx {
         0 => version.major += 1,
         1 => {